@@ -0,0 +1,7 @@
+-- SHA-256 becomes the authoritative deduplication key; xxh3 is kept for fast lookups.
+-- Existing rows are filled in by the background hash backfill.
+ALTER TABLE storage_files ADD COLUMN IF NOT EXISTS sha256 TEXT;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_storage_files_sha256 ON storage_files(sha256);
+
+ALTER TABLE storage_files DROP CONSTRAINT IF EXISTS storage_files_hash_key;
+CREATE INDEX IF NOT EXISTS idx_storage_files_hash ON storage_files(hash);
@@ -0,0 +1,6 @@
+-- Trash bin: items keep their storage reference until purged.
+-- trash_root_id groups every row trashed together; original_path lets a
+-- restore recreate missing parent folders.
+ALTER TABLE user_files ADD COLUMN IF NOT EXISTS trash_root_id TEXT;
+ALTER TABLE user_files ADD COLUMN IF NOT EXISTS original_path TEXT;
+CREATE INDEX IF NOT EXISTS idx_user_files_trash_root_id ON user_files(trash_root_id);
@@ -0,0 +1,15 @@
+-- Earlier content of user files, kept on overwrite. Each row holds one
+-- reference on the storage file's ref_count.
+CREATE TABLE IF NOT EXISTS file_versions (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_file_id TEXT NOT NULL,
+    storage_file_id TEXT NOT NULL,
+    version_number INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    archived_at TIMESTAMPTZ NOT NULL,
+    FOREIGN KEY (user_file_id) REFERENCES user_files(id) ON DELETE CASCADE,
+    FOREIGN KEY (storage_file_id) REFERENCES storage_files(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_file_versions_user_file_id ON file_versions(user_file_id);
+CREATE INDEX IF NOT EXISTS idx_file_versions_storage_file_id ON file_versions(storage_file_id);
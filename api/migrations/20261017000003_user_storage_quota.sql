@@ -0,0 +1,2 @@
+-- Per-user storage quota in bytes. NULL means the configured default applies.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS storage_quota BIGINT;
@@ -0,0 +1,11 @@
+-- Sliding-window log for per-user rate limits, shared by every API instance.
+-- Rows older than the window are swept by the background worker.
+CREATE TABLE IF NOT EXISTS rate_limit_events (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL,
+    bucket TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_rate_limit_events_window ON rate_limit_events(user_id, bucket, created_at);
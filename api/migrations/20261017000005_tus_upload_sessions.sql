@@ -0,0 +1,8 @@
+-- tus uploads share upload_sessions with the chunked API. upload_offset
+-- tracks received bytes; bytes past the last full multipart part live in a
+-- tail object until the part fills up.
+ALTER TABLE upload_sessions ADD COLUMN IF NOT EXISTS protocol TEXT NOT NULL DEFAULT 'chunked';
+ALTER TABLE upload_sessions ADD COLUMN IF NOT EXISTS upload_offset BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE upload_sessions ADD COLUMN IF NOT EXISTS upload_metadata TEXT;
+ALTER TABLE upload_sessions ADD COLUMN IF NOT EXISTS parent_id TEXT;
+CREATE INDEX IF NOT EXISTS idx_upload_sessions_expires_at ON upload_sessions(expires_at);
@@ -0,0 +1,16 @@
+-- Download tickets, previously held in API process memory.
+-- Expired and used-up tickets are swept by the background worker.
+CREATE TABLE IF NOT EXISTS download_tickets (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL,
+    user_file_id TEXT NOT NULL,
+    max_downloads INTEGER,
+    download_count INTEGER NOT NULL DEFAULT 0,
+    bound_ip TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_file_id) REFERENCES user_files(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_download_tickets_expires_at ON download_tickets(expires_at);
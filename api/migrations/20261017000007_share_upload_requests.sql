@@ -0,0 +1,6 @@
+-- "upload" shares let anonymous people add files to a shared folder.
+-- NULL limits mean no limit beyond the server-wide ones.
+ALTER TABLE share_links ADD COLUMN IF NOT EXISTS max_uploads INTEGER;
+ALTER TABLE share_links ADD COLUMN IF NOT EXISTS max_upload_size BIGINT;
+ALTER TABLE share_links ADD COLUMN IF NOT EXISTS allowed_upload_types TEXT;
+ALTER TABLE share_links ADD COLUMN IF NOT EXISTS upload_count INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,6 @@
+-- Optional download and view limits on share links. NULL means unlimited;
+-- a link stops working once its download limit is reached.
+ALTER TABLE share_links ADD COLUMN IF NOT EXISTS max_downloads INTEGER;
+ALTER TABLE share_links ADD COLUMN IF NOT EXISTS download_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE share_links ADD COLUMN IF NOT EXISTS max_views INTEGER;
+ALTER TABLE share_links ADD COLUMN IF NOT EXISTS view_count INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,13 @@
+-- Failed share password attempts, per client IP and per share (client_ip NULL),
+-- driving the exponential lockout on /share/:token/verify.
+CREATE TABLE IF NOT EXISTS share_password_attempts (
+    id TEXT PRIMARY KEY NOT NULL,
+    share_link_id TEXT NOT NULL,
+    client_ip TEXT,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    last_attempt TIMESTAMPTZ NOT NULL,
+    locked_until TIMESTAMPTZ,
+    FOREIGN KEY (share_link_id) REFERENCES share_links(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_share_password_attempts_share ON share_password_attempts(share_link_id);
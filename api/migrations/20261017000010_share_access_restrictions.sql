@@ -0,0 +1,5 @@
+-- Optional network restrictions on share links, stored comma-separated.
+-- NULL means unrestricted.
+ALTER TABLE share_links ADD COLUMN IF NOT EXISTS allowed_ips TEXT;
+ALTER TABLE share_links ADD COLUMN IF NOT EXISTS denied_ips TEXT;
+ALTER TABLE share_links ADD COLUMN IF NOT EXISTS allowed_referrers TEXT;
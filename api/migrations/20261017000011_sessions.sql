@@ -0,0 +1,21 @@
+-- Each row of `tokens` is now a sign-in session holding the SHA-256 of its
+-- current refresh token. Earlier rows held access tokens and are dropped.
+DELETE FROM tokens;
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS previous_token TEXT;
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS device TEXT;
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS ip_address TEXT;
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ;
+ALTER TABLE tokens ADD COLUMN IF NOT EXISTS last_used_at TIMESTAMPTZ;
+CREATE INDEX IF NOT EXISTS idx_tokens_user_id ON tokens(user_id);
+CREATE INDEX IF NOT EXISTS idx_tokens_previous_token ON tokens(previous_token);
+
+-- Denylist of access token IDs (jti), or session IDs covering every access
+-- token of a session, kept until those tokens expire.
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+    jti TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_revoked_tokens_expires_at ON revoked_tokens(expires_at);
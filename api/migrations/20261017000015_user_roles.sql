@@ -0,0 +1,6 @@
+-- Roles for the /admin API: 'user', 'admin' or 'auditor' (read-only admin).
+-- Disabled accounts keep their data but can no longer sign in.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS role TEXT NOT NULL DEFAULT 'user';
+ALTER TABLE users ADD COLUMN IF NOT EXISTS disabled_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_users_role ON users(role);
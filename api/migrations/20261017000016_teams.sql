@@ -0,0 +1,29 @@
+-- Teams and their shared drives. A file with a team_id lives in that team's
+-- drive: members reach it through their role ('owner', 'editor' or 'viewer')
+-- and it counts against the team's quota. user_id stays the member who added it.
+CREATE TABLE IF NOT EXISTS teams (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    storage_quota BIGINT,
+    created_by TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE TABLE IF NOT EXISTS team_members (
+    team_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    role TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (team_id, user_id),
+    FOREIGN KEY (team_id) REFERENCES teams(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_team_members_user_id ON team_members(user_id);
+
+ALTER TABLE user_files ADD COLUMN IF NOT EXISTS team_id TEXT REFERENCES teams(id) ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS idx_user_files_team_id ON user_files(team_id);
+
+-- Resumable uploads remember the team drive they finish in
+ALTER TABLE upload_sessions ADD COLUMN IF NOT EXISTS team_id TEXT;
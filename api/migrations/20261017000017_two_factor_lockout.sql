@@ -0,0 +1,4 @@
+-- Second-factor codes tried since the last correct one, across all login
+-- challenges, and when the resulting lockout ends
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_failed_attempts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_locked_until TIMESTAMPTZ;
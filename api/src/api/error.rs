@@ -1,6 +1,6 @@
 use axum::{
     Json,
-    http::StatusCode,
+    http::{HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
 };
 use serde_json::json;
@@ -23,6 +23,9 @@ pub enum AppError {
     #[error("Not Found: {0}")]
     NotFound(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("Internal Server Error: {0}")]
     Internal(String),
 
@@ -32,12 +35,32 @@ pub enum AppError {
     #[error("Payload Too Large: {0}")]
     PayloadTooLarge(String),
 
+    #[error("Insufficient Storage: {0}")]
+    InsufficientStorage(String),
+
+    /// tus checksum extension: the request body did not match `Upload-Checksum`
+    #[error("Checksum Mismatch: {0}")]
+    ChecksumMismatch(String),
+
+    #[error("Too Many Requests: {message}")]
+    TooManyRequests {
+        message: String,
+        retry_after_secs: u64,
+    },
+
     #[error("Anyhow error: {0}")]
     Anyhow(#[from] anyhow::Error),
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let retry_after = match &self {
+            AppError::TooManyRequests {
+                retry_after_secs, ..
+            } => Some(*retry_after_secs),
+            _ => None,
+        };
+
         let (status, message) = match self {
             AppError::Database(e) => {
                 tracing::error!("Database error: {:?}", e);
@@ -50,6 +73,7 @@ impl IntoResponse for AppError {
             AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, msg),
             AppError::Internal(msg) => {
                 tracing::error!("Internal error: {}", msg);
                 (
@@ -59,6 +83,12 @@ impl IntoResponse for AppError {
             }
             AppError::Gone(msg) => (StatusCode::GONE, msg),
             AppError::PayloadTooLarge(msg) => (StatusCode::PAYLOAD_TOO_LARGE, msg),
+            AppError::InsufficientStorage(msg) => (StatusCode::INSUFFICIENT_STORAGE, msg),
+            AppError::ChecksumMismatch(msg) => (
+                StatusCode::from_u16(460).expect("460 is a valid status code"),
+                msg,
+            ),
+            AppError::TooManyRequests { message, .. } => (StatusCode::TOO_MANY_REQUESTS, message),
             AppError::Anyhow(e) => {
                 tracing::error!("Anyhow error: {:?}", e);
                 (
@@ -72,6 +102,12 @@ impl IntoResponse for AppError {
             "error": message
         }));
 
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
     }
 }
@@ -0,0 +1,165 @@
+use crate::api::error::AppError;
+use crate::api::handlers::auth::hash_password;
+use crate::entities::prelude::*;
+use crate::services::account_email::AccountEmailService;
+use crate::services::audit::{AuditEventType, AuditService};
+use crate::services::sessions::{ClientInfo, SessionService};
+use crate::utils::auth::Claims;
+use axum::{
+    Extension, Json,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+};
+use sea_orm::EntityTrait;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Deserialize, ToSchema)]
+pub struct EmailTokenRequest {
+    /// Token from the emailed link
+    pub token: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct PasswordResetConfirmRequest {
+    /// Token from the emailed link
+    pub token: String,
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    pub password: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/me/email/verify",
+    responses(
+        (status = 202, description = "Verification email sent"),
+        (status = 400, description = "No email address on the account"),
+        (status = 401, description = "Unauthorized"),
+        (status = 409, description = "Email address is already verified")
+    ),
+    security(("jwt" = []))
+)]
+pub async fn send_verification_email(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode, AppError> {
+    let user = Users::find_by_id(&claims.sub)
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::Unauthorized("User not found".to_string()))?;
+    AccountEmailService::send_verification(&state.db, state.mailer.as_ref(), &user).await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/verify-email",
+    request_body = EmailTokenRequest,
+    responses(
+        (status = 204, description = "Email address verified"),
+        (status = 400, description = "Link invalid, used or expired")
+    ),
+    tag = "auth"
+)]
+pub async fn verify_email(
+    State(state): State<crate::AppState>,
+    headers: HeaderMap,
+    Json(req): Json<EmailTokenRequest>,
+) -> Result<StatusCode, AppError> {
+    let user = AccountEmailService::verify_email(&state.db, &req.token).await?;
+
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::EmailVerify,
+            Some(user.id),
+            None,
+            "email_verify",
+            "success",
+            Some(serde_json::json!({ "email": user.email })),
+            ClientInfo::from_headers(&headers).ip_address,
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/password-reset",
+    request_body = PasswordResetRequest,
+    responses(
+        (status = 202, description = "A reset link was emailed if the address belongs to a verified account")
+    ),
+    tag = "auth"
+)]
+pub async fn request_password_reset(
+    State(state): State<crate::AppState>,
+    headers: HeaderMap,
+    Json(req): Json<PasswordResetRequest>,
+) -> Result<StatusCode, AppError> {
+    let emailed =
+        AccountEmailService::request_password_reset(&state.db, &state.mailer, &req.email).await?;
+
+    let audit = AuditService::new(state.db.clone());
+    let ip = ClientInfo::from_headers(&headers).ip_address;
+    for user_id in emailed {
+        audit
+            .log(
+                AuditEventType::PasswordReset,
+                Some(user_id),
+                None,
+                "password_reset_request",
+                "success",
+                None,
+                ip.clone(),
+            )
+            .await;
+    }
+
+    // Same answer whether or not the address is known
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/password-reset/confirm",
+    request_body = PasswordResetConfirmRequest,
+    responses(
+        (status = 204, description = "Password changed and every session ended"),
+        (status = 400, description = "Link invalid, used or expired, or password too short")
+    ),
+    tag = "auth"
+)]
+pub async fn confirm_password_reset(
+    State(state): State<crate::AppState>,
+    headers: HeaderMap,
+    Json(req): Json<PasswordResetConfirmRequest>,
+) -> Result<StatusCode, AppError> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let password_hash = hash_password(&req.password)?;
+    let user = AccountEmailService::reset_password(&state.db, &req.token, password_hash).await?;
+    // Whoever knew the old password is signed out
+    let sessions = SessionService::revoke_all(&state.db, &state.config, &user.id).await?;
+
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::PasswordReset,
+            Some(user.id),
+            None,
+            "password_reset",
+            "success",
+            Some(serde_json::json!({ "sessions_ended": sessions })),
+            ClientInfo::from_headers(&headers).ip_address,
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
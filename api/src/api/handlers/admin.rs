@@ -0,0 +1,642 @@
+use crate::api::error::AppError;
+use crate::api::handlers::auth::hash_password;
+use crate::api::handlers::users::QuotaResponse;
+use crate::entities::{audit_logs, prelude::*, users};
+use crate::services::audit::{AuditEventType, AuditService};
+use crate::services::facts_service::FactsService;
+use crate::services::quota::QuotaService;
+use crate::services::two_factor::TwoFactorService;
+use crate::services::user_admin::{StorageStats, UserAdminService, UserFilter};
+use crate::utils::auth::{Claims, Role};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+// Access to every route here is checked by `admin_middleware`: admins may
+// call all of them, auditors only the GET routes.
+
+#[derive(Deserialize, ToSchema)]
+pub struct SetQuotaRequest {
+    /// Quota in bytes; `null` reverts the user to the default quota
+    pub storage_quota: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct ListUsersQuery {
+    /// Matched against username, email and name
+    pub q: Option<String>,
+    pub role: Option<Role>,
+    pub disabled: Option<bool>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AdminUserResponse {
+    pub id: String,
+    pub username: String,
+    pub email: Option<String>,
+    pub name: Option<String>,
+    pub role: Role,
+    pub email_verified: bool,
+    pub two_factor_enabled: bool,
+    pub oidc: bool,
+    pub created_at: Option<DateTime<Utc>>,
+    /// Set while the account is disabled
+    pub disabled_at: Option<DateTime<Utc>>,
+    /// The user's own quota in bytes; `null` when the default applies
+    pub storage_quota: Option<i64>,
+}
+
+impl From<users::Model> for AdminUserResponse {
+    fn from(user: users::Model) -> Self {
+        Self {
+            role: Role::of(&user),
+            email_verified: user.email_verified_at.is_some(),
+            two_factor_enabled: TwoFactorService::is_enabled(&user),
+            oidc: user.oidc_sub.is_some(),
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            name: user.name,
+            created_at: user.created_at,
+            disabled_at: user.disabled_at,
+            storage_quota: user.storage_quota,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SetRoleRequest {
+    pub role: Role,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct AdminPasswordResetRequest {
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    pub password: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UserUsageResponse {
+    pub user_id: String,
+    /// Live files, from `user_file_facts`
+    pub total_files: i64,
+    /// Bytes in live files, from `user_file_facts`
+    pub total_size: i64,
+    pub video_count: i64,
+    pub audio_count: i64,
+    pub document_count: i64,
+    pub image_count: i64,
+    pub others_count: i64,
+    pub facts_updated_at: DateTime<Utc>,
+    /// Usage counted against the quota, including trash and old versions
+    pub quota: QuotaResponse,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct StorageStatsResponse {
+    pub users: u64,
+    pub disabled_users: u64,
+    /// Distinct objects in storage
+    pub storage_objects: u64,
+    /// Bytes actually stored
+    pub physical_bytes: i64,
+    /// Bytes that would be stored without deduplication
+    pub logical_bytes: i64,
+    /// `logical_bytes - physical_bytes`
+    pub dedup_savings_bytes: i64,
+    /// `logical_bytes / physical_bytes`; 1.0 when nothing is shared
+    pub dedup_ratio: f64,
+}
+
+impl From<StorageStats> for StorageStatsResponse {
+    fn from(stats: StorageStats) -> Self {
+        Self {
+            users: stats.users,
+            disabled_users: stats.disabled_users,
+            storage_objects: stats.storage_objects,
+            physical_bytes: stats.physical_bytes,
+            logical_bytes: stats.logical_bytes,
+            dedup_savings_bytes: stats.dedup_savings(),
+            dedup_ratio: if stats.physical_bytes > 0 {
+                stats.logical_bytes as f64 / stats.physical_bytes as f64
+            } else {
+                1.0
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ListAuditLogsQuery {
+    pub user_id: Option<String>,
+    pub event_type: Option<String>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AuditLogResponse {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub event_type: String,
+    pub user_id: Option<String>,
+    pub resource_id: Option<String>,
+    pub action: String,
+    pub status: String,
+    pub details: Option<serde_json::Value>,
+    pub ip_address: Option<String>,
+}
+
+impl From<audit_logs::Model> for AuditLogResponse {
+    fn from(log: audit_logs::Model) -> Self {
+        Self {
+            details: log
+                .details
+                .as_deref()
+                .and_then(|d| serde_json::from_str(d).ok()),
+            id: log.id,
+            timestamp: log.timestamp,
+            event_type: log.event_type,
+            user_id: log.user_id,
+            resource_id: log.resource_id,
+            action: log.action,
+            status: log.status,
+            ip_address: log.ip_address,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users",
+    params(
+        ("q" = Option<String>, Query, description = "Search username, email and name"),
+        ("role" = Option<Role>, Query, description = "Only users with this role"),
+        ("disabled" = Option<bool>, Query, description = "Only disabled (true) or active (false) users"),
+        ("limit" = Option<u64>, Query, description = "Page size (default 50, at most 200)"),
+        ("offset" = Option<u64>, Query, description = "Users to skip")
+    ),
+    responses(
+        (status = 200, description = "Matching users, oldest first", body = Vec<AdminUserResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin or auditor")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn list_users(
+    State(state): State<crate::AppState>,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<Json<Vec<AdminUserResponse>>, AppError> {
+    let filter = UserFilter {
+        search: query.q,
+        role: query.role,
+        disabled: query.disabled,
+        limit: query.limit,
+        offset: query.offset,
+    };
+    let users = UserAdminService::list(&state.db, &filter).await?;
+    Ok(Json(users.into_iter().map(Into::into).collect()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users/{id}",
+    params(
+        ("id" = String, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "The user", body = AdminUserResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin or auditor"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn get_user(
+    State(state): State<crate::AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    Ok(Json(UserAdminService::get(&state.db, &id).await?.into()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/users/{id}/role",
+    request_body = SetRoleRequest,
+    params(
+        ("id" = String, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "Role changed", body = AdminUserResponse),
+        (status = 400, description = "Admins cannot remove their own admin role"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn set_user_role(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(req): Json<SetRoleRequest>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    let user = UserAdminService::set_role(&state.db, &claims.sub, &id, req.role).await?;
+
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::UserRoleChange,
+            Some(claims.sub),
+            Some(id),
+            "set_role",
+            "success",
+            Some(serde_json::json!({ "role": req.role.as_str() })),
+            None,
+        )
+        .await;
+
+    Ok(Json(user.into()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/disable",
+    params(
+        ("id" = String, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "Account disabled and its sessions ended", body = AdminUserResponse),
+        (status = 400, description = "Admins cannot disable themselves"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn disable_user(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    set_disabled(state, claims, id, true).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/enable",
+    params(
+        ("id" = String, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "Account enabled", body = AdminUserResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn enable_user(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    set_disabled(state, claims, id, false).await
+}
+
+async fn set_disabled(
+    state: crate::AppState,
+    claims: Claims,
+    id: String,
+    disabled: bool,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    let user = UserAdminService::set_disabled(&state.db, &state.config, &claims.sub, &id, disabled)
+        .await?;
+
+    let (event, action) = if disabled {
+        (AuditEventType::UserDisable, "disable_user")
+    } else {
+        (AuditEventType::UserEnable, "enable_user")
+    };
+    AuditService::new(state.db.clone())
+        .log(
+            event,
+            Some(claims.sub),
+            Some(id),
+            action,
+            "success",
+            None,
+            None,
+        )
+        .await;
+
+    Ok(Json(user.into()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/users/{id}",
+    params(
+        ("id" = String, Path, description = "User ID")
+    ),
+    responses(
+        (status = 204, description = "User and all their files deleted"),
+        (status = 400, description = "Admins cannot delete themselves"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn delete_user(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let user = UserAdminService::get(&state.db, &id).await?;
+    let purged = UserAdminService::delete(
+        &state.db,
+        state.storage.as_ref(),
+        &state.config,
+        &claims.sub,
+        &id,
+    )
+    .await?;
+
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::UserDelete,
+            Some(claims.sub),
+            Some(id),
+            "delete_user",
+            "success",
+            Some(serde_json::json!({ "username": user.username, "items_deleted": purged })),
+            None,
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/password",
+    request_body = AdminPasswordResetRequest,
+    params(
+        ("id" = String, Path, description = "User ID")
+    ),
+    responses(
+        (status = 204, description = "Password set and every session of the user ended"),
+        (status = 400, description = "Password too short"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn reset_user_password(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(req): Json<AdminPasswordResetRequest>,
+) -> Result<StatusCode, AppError> {
+    req.validate()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    let password_hash = hash_password(&req.password)?;
+    let sessions =
+        UserAdminService::reset_password(&state.db, &state.config, &id, password_hash).await?;
+
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::PasswordReset,
+            Some(claims.sub),
+            Some(id),
+            "admin_password_reset",
+            "success",
+            Some(serde_json::json!({ "sessions_ended": sessions })),
+            None,
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users/{id}/usage",
+    params(
+        ("id" = String, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "The user's file counts and storage usage", body = UserUsageResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin or auditor"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn get_user_usage(
+    State(state): State<crate::AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<UserUsageResponse>, AppError> {
+    let user = UserAdminService::get(&state.db, &id).await?;
+    FactsService::update_user_facts(&state.db, &user.id).await?;
+    let facts = UserFileFacts::find_by_id(&user.id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::Internal("Failed to generate facts".to_string()))?;
+    let quota = QuotaService::usage(&state.db, &state.config, &user.id).await?;
+
+    Ok(Json(UserUsageResponse {
+        user_id: user.id,
+        total_files: facts.total_files,
+        total_size: facts.total_size,
+        video_count: facts.video_count,
+        audio_count: facts.audio_count,
+        document_count: facts.document_count,
+        image_count: facts.image_count,
+        others_count: facts.others_count,
+        facts_updated_at: facts.updated_at,
+        quota: quota.into(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/stats",
+    responses(
+        (status = 200, description = "System-wide users, storage and deduplication savings", body = StorageStatsResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin or auditor")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn get_stats(
+    State(state): State<crate::AppState>,
+) -> Result<Json<StorageStatsResponse>, AppError> {
+    Ok(Json(
+        UserAdminService::storage_stats(&state.db).await?.into(),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/audit-logs",
+    params(
+        ("user_id" = Option<String>, Query, description = "Only events by this user"),
+        ("event_type" = Option<String>, Query, description = "Only events of this type, e.g. UserLogin"),
+        ("limit" = Option<u64>, Query, description = "Page size (default 50, at most 200)"),
+        ("offset" = Option<u64>, Query, description = "Events to skip")
+    ),
+    responses(
+        (status = 200, description = "Audit events, newest first", body = Vec<AuditLogResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin or auditor")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn list_audit_logs(
+    State(state): State<crate::AppState>,
+    Query(query): Query<ListAuditLogsQuery>,
+) -> Result<Json<Vec<AuditLogResponse>>, AppError> {
+    let mut select = AuditLogs::find();
+    if let Some(user_id) = query.user_id {
+        select = select.filter(audit_logs::Column::UserId.eq(user_id));
+    }
+    if let Some(event_type) = query.event_type {
+        select = select.filter(audit_logs::Column::EventType.eq(event_type));
+    }
+    let logs = select
+        .order_by_desc(audit_logs::Column::Timestamp)
+        .limit(
+            query
+                .limit
+                .unwrap_or(50)
+                .min(crate::services::user_admin::MAX_PAGE_SIZE),
+        )
+        .offset(query.offset.unwrap_or(0))
+        .all(&state.db)
+        .await?;
+    Ok(Json(logs.into_iter().map(Into::into).collect()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users/{id}/quota",
+    params(
+        ("id" = String, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "The user's quota and usage", body = QuotaResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin or auditor"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn get_user_quota(
+    State(state): State<crate::AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<QuotaResponse>, AppError> {
+    let usage = QuotaService::usage(&state.db, &state.config, &id).await?;
+    Ok(Json(usage.into()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/users/{id}/quota",
+    request_body = SetQuotaRequest,
+    params(
+        ("id" = String, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "Quota updated", body = QuotaResponse),
+        (status = 400, description = "Invalid quota"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn set_user_quota(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(req): Json<SetQuotaRequest>,
+) -> Result<Json<QuotaResponse>, AppError> {
+    if req.storage_quota.is_some_and(|quota| quota < 0) {
+        return Err(AppError::BadRequest(
+            "storage_quota must not be negative".to_string(),
+        ));
+    }
+
+    let user = Users::find_by_id(&id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    let mut active: users::ActiveModel = user.into();
+    active.storage_quota = Set(req.storage_quota);
+    active.update(&state.db).await?;
+
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::QuotaUpdate,
+            Some(claims.sub),
+            Some(id.clone()),
+            "set_quota",
+            "success",
+            Some(serde_json::json!({ "storage_quota": req.storage_quota })),
+            None,
+        )
+        .await;
+
+    let usage = QuotaService::usage(&state.db, &state.config, &id).await?;
+    Ok(Json(usage.into()))
+}
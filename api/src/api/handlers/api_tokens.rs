@@ -0,0 +1,174 @@
+use crate::api::error::AppError;
+use crate::entities::{api_tokens, prelude::*};
+use crate::services::api_tokens::ApiTokenService;
+use crate::services::audit::{AuditEventType, AuditService};
+use crate::utils::auth::{Claims, Role, Scope};
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use chrono::Utc;
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateApiTokenRequest {
+    pub name: String,
+    /// Any of "files:read", "files:write", "shares:manage" and "admin"
+    pub scopes: Vec<Scope>,
+    /// Days until the token expires; never when absent
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ApiTokenResponse {
+    pub id: String,
+    pub name: String,
+    /// First characters of the token
+    pub token_prefix: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: chrono::DateTime<Utc>,
+    pub expires_at: Option<chrono::DateTime<Utc>>,
+    pub last_used_at: Option<chrono::DateTime<Utc>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreatedApiTokenResponse {
+    /// The token for `Authorization: Bearer`; it is not shown again
+    pub token: String,
+    #[serde(flatten)]
+    pub details: ApiTokenResponse,
+}
+
+impl From<api_tokens::Model> for ApiTokenResponse {
+    fn from(model: api_tokens::Model) -> Self {
+        Self {
+            scopes: ApiTokenService::scopes(&model),
+            id: model.id,
+            name: model.name,
+            token_prefix: model.token_prefix,
+            created_at: model.created_at,
+            expires_at: model.expires_at,
+            last_used_at: model.last_used_at,
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/tokens",
+    request_body = CreateApiTokenRequest,
+    responses(
+        (status = 201, description = "Token created", body = CreatedApiTokenResponse),
+        (status = 400, description = "Invalid name, scopes or lifetime"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Only admins can create admin tokens")
+    ),
+    security(("jwt" = [])),
+    tag = "auth"
+)]
+pub async fn create_api_token(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<CreateApiTokenRequest>,
+) -> Result<(StatusCode, Json<CreatedApiTokenResponse>), AppError> {
+    if req.scopes.contains(&Scope::Admin) {
+        let user = Users::find_by_id(&claims.sub)
+            .one(&state.db)
+            .await?
+            .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
+        if Role::of(&user) != Role::Admin {
+            return Err(AppError::Forbidden(
+                "Only admins can create admin tokens".to_string(),
+            ));
+        }
+    }
+
+    let (model, token) = ApiTokenService::create(
+        &state.db,
+        &claims.sub,
+        &req.name,
+        &req.scopes,
+        req.expires_in_days,
+    )
+    .await?;
+
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::ApiTokenCreate,
+            Some(claims.sub),
+            None,
+            "api_token_create",
+            "success",
+            Some(serde_json::json!({
+                "token_id": model.id,
+                "name": model.name,
+                "scopes": model.scopes,
+                "expires_at": model.expires_at.map(|t| t.to_rfc3339()),
+            })),
+            None,
+        )
+        .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiTokenResponse {
+            token,
+            details: model.into(),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/tokens",
+    responses(
+        (status = 200, description = "Your personal access tokens", body = Vec<ApiTokenResponse>),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("jwt" = [])),
+    tag = "auth"
+)]
+pub async fn list_api_tokens(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<ApiTokenResponse>>, AppError> {
+    let tokens = ApiTokenService::list(&state.db, &claims.sub).await?;
+    Ok(Json(tokens.into_iter().map(Into::into).collect()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/tokens/{id}",
+    params(("id" = String, Path, description = "Token ID")),
+    responses(
+        (status = 204, description = "Token revoked"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Token not found")
+    ),
+    security(("jwt" = [])),
+    tag = "auth"
+)]
+pub async fn revoke_api_token(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    ApiTokenService::revoke(&state.db, &claims.sub, &id).await?;
+
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::ApiTokenRevoke,
+            Some(claims.sub),
+            None,
+            "api_token_revoke",
+            "success",
+            Some(serde_json::json!({ "token_id": id })),
+            None,
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,7 +1,9 @@
 use crate::api::error::AppError;
 use crate::entities::{prelude::*, *};
 use crate::services::audit::{AuditEventType, AuditService};
-use crate::utils::auth::create_jwt;
+use crate::services::sessions::{ClientInfo, IssuedTokens, SessionService};
+use crate::services::two_factor::TwoFactorService;
+use crate::utils::auth::Role;
 use argon2::{
     Argon2,
     password_hash::{PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
@@ -22,7 +24,6 @@ use openidconnect::{
 };
 use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
 use serde::{Deserialize, Serialize};
-use std::env;
 use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::Validate;
@@ -43,7 +44,41 @@ pub struct AuthRequest {
 
 #[derive(Serialize, ToSchema)]
 pub struct AuthResponse {
+    /// Access token for the `Authorization: Bearer` header
     pub token: String,
+    /// Single-use token for `POST /auth/refresh`
+    pub refresh_token: String,
+    /// Seconds until `token` expires
+    pub expires_in: i64,
+}
+
+/// Returned by `POST /login` when the account uses two-factor authentication
+#[derive(Serialize, ToSchema)]
+pub struct TwoFactorChallengeResponse {
+    /// Always `true`; the login continues at `POST /login/2fa`
+    pub two_factor_required: bool,
+    /// Token to send with the code to `POST /login/2fa`
+    pub challenge_token: String,
+    /// Seconds until the challenge expires
+    pub expires_in: i64,
+}
+
+/// Either tokens, or a challenge for the second factor
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Tokens(AuthResponse),
+    TwoFactorRequired(TwoFactorChallengeResponse),
+}
+
+impl From<IssuedTokens> for AuthResponse {
+    fn from(tokens: IssuedTokens) -> Self {
+        Self {
+            token: tokens.access_token,
+            refresh_token: tokens.refresh_token,
+            expires_in: tokens.expires_in,
+        }
+    }
 }
 
 #[utoipa::path(
@@ -74,17 +109,13 @@ pub async fn register(
         .validate()
         .map_err(|e| AppError::BadRequest(e.to_string()))?;
 
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    let password_hash = argon2
-        .hash_password(payload.password.as_bytes(), &salt)
-        .map_err(|e| AppError::Internal(e.to_string()))?
-        .to_string();
+    let password_hash = hash_password(&payload.password)?;
 
     let id = Uuid::new_v4().to_string();
 
     let user = users::ActiveModel {
         id: Set(id.clone()),
+        role: Set(Role::User.as_str().to_string()),
         username: Set(payload.username),
         password_hash: Set(Some(password_hash)),
         ..Default::default()
@@ -108,23 +139,16 @@ pub async fn register(
         )
         .await;
 
-    // Auto-login: generate JWT token for the new user
-    let secret = env::var("JWT_SECRET").unwrap_or_else(|_| "secret".to_string());
-    let token_str = create_jwt(&id, &secret).map_err(|e| AppError::Internal(e.to_string()))?;
-
-    let token_id = Uuid::new_v4().to_string();
-    let expires_at = chrono::Utc::now() + chrono::Duration::hours(24);
-
-    let token_model = tokens::ActiveModel {
-        id: Set(token_id),
-        user_id: Set(id),
-        token: Set(token_str.clone()),
-        expires_at: Set(expires_at),
-    };
-
-    token_model.insert(&state.db).await?;
+    // Auto-login: open a session for the new user
+    let tokens = SessionService::start(
+        &state.db,
+        &state.config,
+        &id,
+        ClientInfo::from_headers(&headers),
+    )
+    .await?;
 
-    Ok((StatusCode::CREATED, Json(AuthResponse { token: token_str })))
+    Ok((StatusCode::CREATED, Json(tokens.into())))
 }
 
 #[utoipa::path(
@@ -132,15 +156,17 @@ pub async fn register(
     path = "/login",
     request_body = AuthRequest,
     responses(
-        (status = 200, description = "Login successful", body = AuthResponse),
-        (status = 401, description = "Invalid credentials")
+        (status = 200, description = "Login successful, or a two-factor challenge", body = LoginResponse),
+        (status = 401, description = "Invalid credentials"),
+        (status = 403, description = "Account is disabled"),
+        (status = 429, description = "Two-factor logins are locked out after too many invalid codes")
     )
 )]
 pub async fn login(
     State(state): State<crate::AppState>,
     headers: HeaderMap,
     Json(payload): Json<AuthRequest>,
-) -> Result<Json<AuthResponse>, AppError> {
+) -> Result<Json<LoginResponse>, AppError> {
     // Validate CAPTCHA first
     let ip = crate::api::handlers::captcha::extract_client_ip(&headers);
     crate::api::handlers::captcha::validate_captcha(
@@ -157,6 +183,82 @@ pub async fn login(
         .await?
         .ok_or(AppError::Unauthorized("Invalid credentials".to_string()))?;
 
+    let audit = AuditService::new(state.db.clone());
+    let client = ClientInfo::from_headers(&headers);
+
+    if let Err(e) = check_password(&user, &payload.password) {
+        audit
+            .log(
+                AuditEventType::UserLogin,
+                Some(user.id),
+                None,
+                "login",
+                "failure",
+                Some(serde_json::json!({ "reason": "invalid_password" })),
+                client.ip_address,
+            )
+            .await;
+        return Err(e);
+    }
+    check_enabled(&user)?;
+
+    if TwoFactorService::is_enabled(&user) {
+        if let Some(retry_after_secs) = TwoFactorService::lockout_remaining(&user) {
+            return Err(AppError::TooManyRequests {
+                message: "Too many invalid two-factor codes, try again later".to_string(),
+                retry_after_secs,
+            });
+        }
+        let (challenge_token, expires_in) =
+            TwoFactorService::start_challenge(&state.db, &user.id).await?;
+        audit
+            .log(
+                AuditEventType::UserLogin,
+                Some(user.id),
+                None,
+                "login",
+                "pending",
+                Some(serde_json::json!({ "two_factor": "challenge_issued" })),
+                client.ip_address,
+            )
+            .await;
+        return Ok(Json(LoginResponse::TwoFactorRequired(
+            TwoFactorChallengeResponse {
+                two_factor_required: true,
+                challenge_token,
+                expires_in,
+            },
+        )));
+    }
+
+    audit
+        .log(
+            AuditEventType::UserLogin,
+            Some(user.id.clone()),
+            None,
+            "login",
+            "success",
+            Some(serde_json::json!({ "two_factor": "not_enabled" })),
+            client.ip_address.clone(),
+        )
+        .await;
+
+    let tokens = SessionService::start(&state.db, &state.config, &user.id, client).await?;
+
+    Ok(Json(LoginResponse::Tokens(tokens.into())))
+}
+
+/// Hash a password for `users.password_hash`
+pub(crate) fn hash_password(password: &str) -> Result<String, AppError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AppError::Internal(e.to_string()))
+}
+
+/// Check `password` against a user's stored hash
+pub(crate) fn check_password(user: &users::Model, password: &str) -> Result<(), AppError> {
     let password_hash = user
         .password_hash
         .as_ref()
@@ -167,26 +269,16 @@ pub async fn login(
         argon2::PasswordHash::new(password_hash).map_err(|e| AppError::Internal(e.to_string()))?;
 
     argon2
-        .verify_password(payload.password.as_bytes(), &parsed_hash)
-        .map_err(|_| AppError::Unauthorized("Invalid credentials".to_string()))?;
-
-    let token_str = create_jwt(&user.id, &state.config.jwt_secret)
-        .map_err(|e| AppError::Internal(e.to_string()))?;
-
-    // Store token in DB for expiration/revocation tracking
-    let token_id = Uuid::new_v4().to_string();
-    let expires_at = chrono::Utc::now() + chrono::Duration::hours(24);
-
-    let token_model = tokens::ActiveModel {
-        id: Set(token_id),
-        user_id: Set(user.id),
-        token: Set(token_str.clone()),
-        expires_at: Set(expires_at),
-    };
-
-    token_model.insert(&state.db).await?;
+        .verify_password(password.as_bytes(), &parsed_hash)
+        .map_err(|_| AppError::Unauthorized("Invalid credentials".to_string()))
+}
 
-    Ok(Json(AuthResponse { token: token_str }))
+/// Refuse sign-in to accounts an admin has disabled
+pub(crate) fn check_enabled(user: &users::Model) -> Result<(), AppError> {
+    if user.disabled_at.is_some() {
+        return Err(AppError::Forbidden("Account is disabled".to_string()));
+    }
+    Ok(())
 }
 
 #[derive(Deserialize)]
@@ -339,6 +431,7 @@ pub async fn login_oidc(
 
 pub async fn callback_oidc(
     State(state): State<crate::AppState>,
+    headers: HeaderMap,
     Query(params): Query<AuthCallbackParams>,
 ) -> Result<impl IntoResponse, AppError> {
     let issuer_url = state
@@ -467,6 +560,7 @@ pub async fn callback_oidc(
             // Create new user
             let user = users::ActiveModel {
                 id: Set(id.clone()),
+                role: Set(Role::User.as_str().to_string()),
                 username: Set(username),
                 oidc_sub: Set(Some(oidc_sub)),
                 email: Set(email),
@@ -494,31 +588,24 @@ pub async fn callback_oidc(
             u
         }
     };
+    check_enabled(&user)?;
 
     // Ensure existing users have keys (Migration/Backfill)
     // No Key Backfill needed
 
-    let secret = env::var("JWT_SECRET").unwrap_or_else(|_| "secret".to_string());
-    let token_str = create_jwt(&user.id, &secret).map_err(|e| AppError::Internal(e.to_string()))?;
-
-    // Store token in DB
-    let token_id = Uuid::new_v4().to_string();
-    let expires_at = chrono::Utc::now() + chrono::Duration::hours(24);
-
-    let token_model = tokens::ActiveModel {
-        id: Set(token_id),
-        user_id: Set(user.id),
-        token: Set(token_str.clone()),
-        expires_at: Set(expires_at),
-    };
-
-    token_model.insert(&state.db).await?;
+    let tokens = SessionService::start(
+        &state.db,
+        &state.config,
+        &user.id,
+        ClientInfo::from_headers(&headers),
+    )
+    .await?;
 
-    // Redirect to frontend with token
+    // Redirect to frontend with the session's tokens
     let frontend_url =
         std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:5173".to_string());
     Ok(Redirect::to(&format!(
-        "{}/?token={}",
-        frontend_url, token_str
+        "{}/?token={}&refresh_token={}",
+        frontend_url, tokens.access_token, tokens.refresh_token
     )))
 }
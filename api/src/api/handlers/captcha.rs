@@ -34,6 +34,9 @@ pub struct CaptchaVerifyRequest {
 }
 
 /// Extract client IP from headers (supports proxies)
+///
+/// Behind `TRUSTED_PROXIES` the client_ip middleware has already reduced
+/// `X-Forwarded-For` to the verified client address.
 pub fn extract_client_ip(headers: &HeaderMap) -> String {
     // Check X-Forwarded-For first (proxy)
     if let Some(forwarded) = headers.get("x-forwarded-for")
@@ -1,11 +1,12 @@
 use crate::api::error::AppError;
-use crate::entities::{prelude::*, *};
+use crate::entities::prelude::*;
+use crate::services::drives::{DriveAccess, Permission};
 use crate::utils::auth::Claims;
 use axum::{
     Extension, Json,
     extract::{Path, State},
 };
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use sea_orm::EntityTrait;
 use tokio_util::io::StreamReader;
 
 use super::types::*;
@@ -32,10 +33,7 @@ pub async fn get_zip_contents(
     Path(id): Path<String>,
 ) -> Result<Json<Vec<ZipEntry>>, AppError> {
     // 1. Verify file ownership and existence
-    let user_file = UserFiles::find_by_id(id)
-        .filter(user_files::Column::UserId.eq(&claims.sub))
-        .filter(user_files::Column::DeletedAt.is_null())
-        .one(&state.db)
+    let user_file = DriveAccess::find_item(&state.db, &claims.sub, &id, Permission::Read)
         .await?
         .ok_or(AppError::NotFound("File not found".to_string()))?;
 
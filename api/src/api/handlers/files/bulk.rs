@@ -60,7 +60,9 @@ pub async fn bulk_delete(
     responses(
         (status = 200, description = "Items moved", body = BulkMoveResponse),
         (status = 401, description = "Unauthorized"),
-        (status = 400, description = "Bad request")
+        (status = 400, description = "Bad request"),
+        (status = 403, description = "Team viewers cannot move team files"),
+        (status = 507, description = "Storage quota of the target drive exceeded")
     ),
     security(
         ("jwt" = [])
@@ -77,7 +79,7 @@ pub async fn bulk_move(
 
     let moved_count = state
         .file_service
-        .bulk_move(&claims.sub, req.item_ids, req.parent_id)
+        .bulk_move(&claims.sub, req.item_ids, req.parent_id, req.team_id)
         .await?;
 
     Ok(Json(BulkMoveResponse { moved_count }))
@@ -90,7 +92,8 @@ pub async fn bulk_move(
     responses(
         (status = 200, description = "Items copied", body = BulkCopyResponse),
         (status = 401, description = "Unauthorized"),
-        (status = 400, description = "Bad request")
+        (status = 400, description = "Bad request"),
+        (status = 507, description = "Storage quota exceeded")
     ),
     security(
         ("jwt" = [])
@@ -107,7 +110,7 @@ pub async fn bulk_copy(
 
     let copied_count = state
         .file_service
-        .bulk_copy(&claims.sub, req.item_ids, req.parent_id)
+        .bulk_copy(&claims.sub, req.item_ids, req.parent_id, req.team_id)
         .await?;
 
     Ok(Json(BulkCopyResponse { copied_count }))
@@ -0,0 +1,355 @@
+use crate::api::error::AppError;
+use crate::entities::storage_files;
+use axum::{
+    body::Body,
+    http::{HeaderMap, StatusCode, header},
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_util::io::ReaderStream;
+
+/// `DOWNLOAD_MODE` that makes the API serve file bytes itself
+pub const STREAM_MODE: &str = "stream";
+
+/// A stored object about to be sent to a client
+pub(crate) struct Download<'a> {
+    pub storage_file: &'a storage_files::Model,
+    pub content_type: String,
+    pub content_disposition: String,
+    pub cache_control: &'static str,
+    /// When the content last changed, for `Last-Modified`
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// Send `download` the way `DOWNLOAD_MODE` says: an nginx `X-Accel-Redirect`
+/// to the presigned object, or the bytes streamed from storage by the API
+pub(crate) async fn serve(
+    state: &crate::AppState,
+    request_headers: &HeaderMap,
+    download: Download<'_>,
+) -> Result<Response, AppError> {
+    if state.config.download_mode == STREAM_MODE {
+        stream(state, request_headers, download).await
+    } else {
+        accel_redirect(state, download).await
+    }
+}
+
+async fn accel_redirect(
+    state: &crate::AppState,
+    download: Download<'_>,
+) -> Result<Response, AppError> {
+    let presigned_url = state
+        .storage
+        .generate_presigned_url_raw(
+            &download.storage_file.s3_key,
+            43200, // 12 hours
+            &download.content_type,
+            &download.content_disposition,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to generate presigned URL: {}", e);
+            AppError::Internal("Failed to generate download URL".to_string())
+        })?;
+
+    let url = url::Url::parse(&presigned_url).map_err(|e| {
+        tracing::error!("Failed to parse presigned URL: {}", e);
+        AppError::Internal("Failed to generate download URL".to_string())
+    })?;
+
+    // Extract path and query for X-Accel-Redirect
+    // Should be /bucket/key?Signature=...
+    let internal_redirect_uri = format!(
+        "/minio_protected{}?{}",
+        url.path(),
+        url.query().unwrap_or("")
+    );
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        // Nginx internal redirect
+        .header("X-Accel-Redirect", internal_redirect_uri)
+        // Content headers for the client
+        .header(header::CONTENT_TYPE, download.content_type)
+        .header(header::CONTENT_DISPOSITION, download.content_disposition)
+        .header(header::CACHE_CONTROL, download.cache_control)
+        .body(Body::empty())
+        .unwrap())
+}
+
+async fn stream(
+    state: &crate::AppState,
+    request_headers: &HeaderMap,
+    download: Download<'_>,
+) -> Result<Response, AppError> {
+    let storage_file = download.storage_file;
+    let size = storage_file.size as u64;
+    let (etag, modified) = validators(storage_file, download.modified);
+
+    let mut builder = Response::builder()
+        .header(header::ETAG, &etag)
+        .header(header::CACHE_CONTROL, download.cache_control)
+        .header(header::ACCEPT_RANGES, "bytes");
+    if let Some(modified) = modified {
+        builder = builder.header(header::LAST_MODIFIED, httpdate::fmt_http_date(modified));
+    }
+
+    if not_modified(request_headers, &etag, modified) {
+        return Ok(builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap());
+    }
+
+    builder = builder
+        .header(header::CONTENT_TYPE, download.content_type)
+        .header(header::CONTENT_DISPOSITION, download.content_disposition);
+
+    let range = request_headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        // Multiple ranges are answered with the whole object, as RFC 9110 allows
+        .filter(|r| r.trim_start().starts_with("bytes=") && !r.contains(','))
+        .filter(|_| if_range_matches(request_headers, &etag, modified));
+
+    let (builder, object) = match range {
+        Some(raw) => {
+            let Some((start, end)) = crate::services::local_storage::parse_byte_range(raw, size)
+            else {
+                return Ok(builder
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, format!("bytes */{}", size))
+                    .body(Body::empty())
+                    .unwrap());
+            };
+            let builder = builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, size),
+                )
+                .header(header::CONTENT_LENGTH, end - start + 1);
+            let object = state
+                .storage
+                .get_object_range(&storage_file.s3_key, &format!("bytes={}-{}", start, end))
+                .await;
+            (builder, object)
+        }
+        None => {
+            let builder = builder
+                .status(StatusCode::OK)
+                .header(header::CONTENT_LENGTH, size);
+            let object = state.storage.get_object_stream(&storage_file.s3_key).await;
+            (builder, object)
+        }
+    };
+
+    let object = object.map_err(|e| {
+        tracing::error!("Failed to read object {}: {}", storage_file.s3_key, e);
+        AppError::Internal("Failed to read file".to_string())
+    })?;
+    let stream = ReaderStream::new(object.body.into_async_read());
+    Ok(builder.body(Body::from_stream(stream)).unwrap())
+}
+
+/// The `ETag` and `Last-Modified` of `storage_file` as served by `stream`
+fn validators(
+    storage_file: &storage_files::Model,
+    modified: Option<DateTime<Utc>>,
+) -> (String, Option<SystemTime>) {
+    // Content is immutable per storage file, so its hash is a strong validator
+    let etag = format!(
+        "\"{}\"",
+        storage_file.sha256.as_deref().unwrap_or(&storage_file.hash)
+    );
+    // HTTP dates have one-second resolution
+    let modified =
+        modified.map(|at| UNIX_EPOCH + Duration::from_secs(at.timestamp().max(0) as u64));
+    (etag, modified)
+}
+
+/// Whether the request only asks for the rest of `storage_file`: a single
+/// `bytes=N-` or `bytes=N-M` range starting past the first byte, which
+/// `If-Range`, if present, still allows
+///
+/// Suffix ranges, multiple ranges and ranges a failed `If-Range` turns into
+/// a full response all fetch the file from the start.
+pub(crate) fn resumes_download(
+    headers: &HeaderMap,
+    storage_file: &storage_files::Model,
+    modified: Option<DateTime<Utc>>,
+) -> bool {
+    let Some(spec) = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|r| r.trim().strip_prefix("bytes="))
+        .filter(|spec| !spec.contains(','))
+    else {
+        return false;
+    };
+    let starts_late = spec
+        .split('-')
+        .next()
+        .and_then(|start| start.trim().parse::<u64>().ok())
+        .is_some_and(|start| start > 0 && start < storage_file.size as u64);
+    let (etag, modified) = validators(storage_file, modified);
+    starts_late && if_range_matches(headers, &etag, modified)
+}
+
+/// `If-None-Match`, or failing that `If-Modified-Since`, says the client's copy is current
+fn not_modified(headers: &HeaderMap, etag: &str, modified: Option<SystemTime>) -> bool {
+    if let Some(if_none_match) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+    {
+        // Weak comparison: W/ prefixes are ignored
+        return if_none_match
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag);
+    }
+    match (
+        modified,
+        headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| httpdate::parse_http_date(v).ok()),
+    ) {
+        (Some(modified), Some(since)) => modified <= since,
+        _ => false,
+    }
+}
+
+/// `If-Range` is absent or still matches, so a `Range` may be honoured
+fn if_range_matches(headers: &HeaderMap, etag: &str, modified: Option<SystemTime>) -> bool {
+    let Some(if_range) = headers.get(header::IF_RANGE).and_then(|v| v.to_str().ok()) else {
+        return true;
+    };
+    let if_range = if_range.trim();
+    if if_range.starts_with('"') {
+        // Strong comparison only
+        return if_range == etag;
+    }
+    match (modified, httpdate::parse_http_date(if_range)) {
+        (Some(modified), Ok(date)) => modified == date,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(pairs: &[(header::HeaderName, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_str(value).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_resumes_download() {
+        let file = storage_files::Model {
+            id: "file".to_string(),
+            hash: "abc".to_string(),
+            sha256: None,
+            s3_key: "key".to_string(),
+            size: 1000,
+            ref_count: 1,
+            scan_status: None,
+            scan_result: None,
+            scanned_at: None,
+            mime_type: None,
+            content_type: None,
+            has_thumbnail: false,
+            is_encrypted: false,
+        };
+        let resumes =
+            |pairs: &[(header::HeaderName, &str)]| resumes_download(&headers(pairs), &file, None);
+
+        assert!(!resumes(&[]));
+        assert!(!resumes(&[(header::RANGE, "bytes=0-")]));
+        assert!(!resumes(&[(header::RANGE, "bytes=0-99")]));
+        assert!(!resumes(&[(header::RANGE, "bytes=500-, 0-10")]));
+        assert!(!resumes(&[(header::RANGE, "items=5-")]));
+        // Suffix ranges can cover the whole file
+        assert!(!resumes(&[(header::RANGE, "bytes=-500")]));
+        assert!(!resumes(&[(header::RANGE, "bytes=-999999999")]));
+        assert!(!resumes(&[(header::RANGE, "bytes=1000-")]));
+        assert!(resumes(&[(header::RANGE, "bytes=100-")]));
+        assert!(resumes(&[(header::RANGE, "bytes=100-199")]));
+
+        // A failed If-Range is answered with the whole file
+        assert!(resumes(&[
+            (header::RANGE, "bytes=100-"),
+            (header::IF_RANGE, "\"abc\"")
+        ]));
+        assert!(!resumes(&[
+            (header::RANGE, "bytes=100-"),
+            (header::IF_RANGE, "\"old\"")
+        ]));
+    }
+
+    #[test]
+    fn test_conditional_headers() {
+        let etag = "\"abc\"";
+        let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let date = httpdate::fmt_http_date(modified);
+        let earlier = httpdate::fmt_http_date(modified - Duration::from_secs(60));
+
+        assert!(not_modified(
+            &headers(&[(header::IF_NONE_MATCH, "W/\"abc\", \"x\"")]),
+            etag,
+            Some(modified)
+        ));
+        assert!(!not_modified(
+            &headers(&[(header::IF_NONE_MATCH, "\"x\"")]),
+            etag,
+            Some(modified)
+        ));
+        assert!(not_modified(
+            &headers(&[(header::IF_MODIFIED_SINCE, &date)]),
+            etag,
+            Some(modified)
+        ));
+        assert!(!not_modified(
+            &headers(&[(header::IF_MODIFIED_SINCE, &earlier)]),
+            etag,
+            Some(modified)
+        ));
+        // If-None-Match takes precedence over If-Modified-Since
+        assert!(!not_modified(
+            &headers(&[
+                (header::IF_NONE_MATCH, "\"x\""),
+                (header::IF_MODIFIED_SINCE, &date)
+            ]),
+            etag,
+            Some(modified)
+        ));
+
+        assert!(if_range_matches(&HeaderMap::new(), etag, Some(modified)));
+        assert!(if_range_matches(
+            &headers(&[(header::IF_RANGE, etag)]),
+            etag,
+            Some(modified)
+        ));
+        assert!(!if_range_matches(
+            &headers(&[(header::IF_RANGE, "W/\"abc\"")]),
+            etag,
+            Some(modified)
+        ));
+        assert!(if_range_matches(
+            &headers(&[(header::IF_RANGE, &date)]),
+            etag,
+            Some(modified)
+        ));
+        assert!(!if_range_matches(
+            &headers(&[(header::IF_RANGE, &earlier)]),
+            etag,
+            Some(modified)
+        ));
+    }
+}
@@ -1,17 +1,24 @@
 use crate::api::error::AppError;
+use crate::api::handlers::captcha::extract_client_ip;
+use crate::api::handlers::files::delivery::{self, Download};
+use crate::api::handlers::files::{
+    BulkDownloadRequest, DownloadTicketRequest, DownloadTicketResponse,
+};
 use crate::entities::{prelude::*, *};
+use crate::services::download_tickets::{DownloadTicketService, TicketOptions};
+use crate::services::drives::{DriveAccess, Permission};
+use crate::services::zip_stream::{ArchiveEntry, ZipStreamService};
 use crate::utils::auth::Claims;
 use axum::{
     Extension, Json,
     body::Body,
     extract::{Path, State},
-    http::{StatusCode, header},
+    http::{HeaderMap, StatusCode, header},
     response::Response,
 };
 use chrono::Utc;
 use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
-use uuid::Uuid;
 
 #[utoipa::path(
     get,
@@ -21,6 +28,8 @@ use uuid::Uuid;
     ),
     responses(
         (status = 200, description = "File download stream"),
+        (status = 206, description = "Partial content (DOWNLOAD_MODE=stream)"),
+        (status = 304, description = "Not modified (DOWNLOAD_MODE=stream)"),
         (status = 401, description = "Unauthorized"),
         (status = 404, description = "File not found"),
         (status = 410, description = "File expired")
@@ -33,12 +42,10 @@ pub async fn download_file(
     State(state): State<crate::AppState>,
     Extension(claims): Extension<Claims>,
     Path(file_id): Path<String>,
+    headers: HeaderMap,
 ) -> Result<Response, AppError> {
     // 1. Verify file ownership and existence
-    let user_file = UserFiles::find_by_id(file_id.clone())
-        .filter(user_files::Column::UserId.eq(&claims.sub))
-        .filter(user_files::Column::DeletedAt.is_null())
-        .one(&state.db)
+    let user_file = DriveAccess::find_item(&state.db, &claims.sub, &file_id, Permission::Read)
         .await?
         .ok_or(AppError::NotFound(
             "File not found, access denied, or already deleted".to_string(),
@@ -79,57 +86,24 @@ pub async fn download_file(
         )));
     }
 
-    // 5. Generate presigned URL and redirect (no data through backend memory)
+    // 5. Hand off to nginx, or stream when DOWNLOAD_MODE=stream
     let (content_type, content_disposition) =
         resolve_file_headers(&user_file.filename, &storage_file);
 
-    let presigned_url = state
-        .storage
-        .generate_presigned_url_raw(
-            &storage_file.s3_key,
-            43200, // 12 hours
-            &content_type,
-            &content_disposition,
-        )
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to generate presigned URL: {}", e);
-            AppError::Internal("Failed to generate download URL".to_string())
-        })?;
-
-    tracing::info!(
-        "📎 Presigned redirect for file_id={} user={}",
-        file_id,
-        claims.sub
-    );
-
-    let url = url::Url::parse(&presigned_url).map_err(|e| {
-        tracing::error!("Failed to parse presigned URL: {}", e);
-        AppError::Internal("Failed to generate download URL".to_string())
-    })?;
-
-    // Extract path and query for X-Accel-Redirect
-    // Should be /bucket/key?Signature=...
-    let path = url.path();
-    let query = url.query().unwrap_or("");
-    let internal_redirect_uri = format!("/minio_protected{}?{}", path, query);
-
-    tracing::info!(
-        "📎 X-Accel-Redirect for file_id={} user={}",
-        file_id,
-        claims.sub
-    );
-
-    Ok(Response::builder()
-        .status(StatusCode::OK)
-        // Nginx internal redirect
-        .header("X-Accel-Redirect", internal_redirect_uri)
-        // Content headers for the client
-        .header(header::CONTENT_TYPE, content_type)
-        .header(header::CONTENT_DISPOSITION, content_disposition)
-        .header(header::CACHE_CONTROL, "private, max-age=31536000") // 1 year cache since it's immutable
-        .body(Body::empty())
-        .unwrap())
+    tracing::info!("📎 Download for file_id={} user={}", file_id, claims.sub);
+
+    delivery::serve(
+        &state,
+        &headers,
+        Download {
+            storage_file: &storage_file,
+            content_type,
+            content_disposition,
+            cache_control: "private, max-age=31536000", // 1 year cache since it's immutable
+            modified: user_file.created_at,
+        },
+    )
+    .await
 }
 
 #[utoipa::path(
@@ -153,10 +127,7 @@ pub async fn get_thumbnail(
     Path(file_id): Path<String>,
 ) -> Result<Response, AppError> {
     // 1. Verify file ownership and existence
-    let user_file = UserFiles::find_by_id(file_id.clone())
-        .filter(user_files::Column::UserId.eq(&claims.sub))
-        .filter(user_files::Column::DeletedAt.is_null())
-        .one(&state.db)
+    let user_file = DriveAccess::find_item(&state.db, &claims.sub, &file_id, Permission::Read)
         .await?
         .ok_or(AppError::NotFound(
             "File not found or access denied".to_string(),
@@ -224,8 +195,10 @@ pub async fn get_thumbnail(
     params(
         ("id" = String, Path, description = "File ID")
     ),
+    request_body(content = Option<DownloadTicketRequest>, description = "Ticket restrictions; all optional"),
     responses(
-        (status = 200, description = "Ticket generated"),
+        (status = 200, description = "Ticket generated", body = DownloadTicketResponse),
+        (status = 400, description = "Invalid ticket options"),
         (status = 401, description = "Unauthorized"),
         (status = 404, description = "File not found")
     ),
@@ -237,23 +210,15 @@ pub async fn generate_download_ticket(
     State(state): State<crate::AppState>,
     Extension(claims): Extension<Claims>,
     Path(file_id): Path<String>,
-) -> Result<Json<serde_json::Value>, AppError> {
-    let user_file = UserFiles::find_by_id(file_id.clone())
-        .filter(user_files::Column::UserId.eq(&claims.sub))
-        .filter(user_files::Column::DeletedAt.is_null())
-        .one(&state.db)
+    headers: HeaderMap,
+    payload: Option<Json<DownloadTicketRequest>>,
+) -> Result<Json<DownloadTicketResponse>, AppError> {
+    let req = payload.map(|Json(req)| req).unwrap_or_default();
+
+    let user_file = DriveAccess::find_item(&state.db, &claims.sub, &file_id, Permission::Read)
         .await?
         .ok_or(AppError::NotFound("File not found".to_string()))?;
 
-    // Generate ticket for backward compat
-    let ticket = Uuid::new_v4().to_string();
-    let expiry = Utc::now() + chrono::Duration::hours(12);
-
-    state
-        .download_tickets
-        .insert(ticket.clone(), (file_id.clone(), expiry));
-
-    // Generate presigned URL (12 hours)
     let storage_file_id = user_file
         .storage_file_id
         .clone()
@@ -275,11 +240,38 @@ pub async fn generate_download_ticket(
         )));
     }
 
-    let (_content_type, _content_disposition) =
-        resolve_file_headers(&user_file.filename, &storage_file);
+    let max_downloads = match (req.single_use, req.max_downloads) {
+        (true, Some(max)) if max != 1 => {
+            return Err(AppError::BadRequest(
+                "single_use conflicts with max_downloads".to_string(),
+            ));
+        }
+        (true, _) => Some(1),
+        (false, max) => max,
+    };
+    let bound_ip = if req.bind_ip {
+        let ip = extract_client_ip(&headers);
+        if ip == "unknown" {
+            return Err(AppError::BadRequest(
+                "Cannot bind ticket: client IP is unknown".to_string(),
+            ));
+        }
+        Some(ip)
+    } else {
+        None
+    };
 
-    // Generate a public URL pointing to the download endpoint with ticket
-    let public_url = format!("/api/download/{}", ticket);
+    let ticket = DownloadTicketService::issue(
+        &state.db,
+        &claims.sub,
+        &file_id,
+        TicketOptions {
+            ttl_secs: req.expires_in_seconds,
+            max_downloads,
+            bound_ip,
+        },
+    )
+    .await?;
 
     tracing::info!(
         "📎 Ticket generated for file_id={} user={}",
@@ -287,11 +279,38 @@ pub async fn generate_download_ticket(
         claims.sub
     );
 
-    Ok(Json(serde_json::json!({
-        "ticket": ticket,
-        "url": public_url,
-        "expires_at": expiry
-    })))
+    Ok(Json(DownloadTicketResponse {
+        // A public URL pointing to the download endpoint with ticket
+        url: format!("/api/download/{}", ticket.id),
+        ticket: ticket.id,
+        expires_at: ticket.expires_at,
+        max_downloads: ticket.max_downloads,
+        ip_bound: ticket.bound_ip.is_some(),
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/tickets/{ticket}",
+    params(
+        ("ticket" = String, Path, description = "Download Ticket")
+    ),
+    responses(
+        (status = 204, description = "Ticket revoked"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Ticket not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn revoke_download_ticket(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(ticket): Path<String>,
+) -> Result<StatusCode, AppError> {
+    DownloadTicketService::revoke(&state.db, &claims.sub, &ticket).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
 
 #[utoipa::path(
@@ -302,24 +321,19 @@ pub async fn generate_download_ticket(
     ),
     responses(
         (status = 200, description = "File stream"),
-        (status = 403, description = "Invalid/Expired ticket")
+        (status = 206, description = "Partial content (DOWNLOAD_MODE=stream)"),
+        (status = 304, description = "Not modified (DOWNLOAD_MODE=stream)"),
+        (status = 403, description = "Invalid, expired, used up or IP-bound ticket, or its creator lost access")
     )
 )]
 pub async fn download_file_with_ticket(
     State(state): State<crate::AppState>,
     Path(ticket): Path<String>,
+    headers: HeaderMap,
 ) -> Result<Response, AppError> {
-    let (file_id, _) = {
-        if let Some(entry) = state.download_tickets.get(&ticket) {
-            let (fid, exp) = entry.value();
-            if *exp < Utc::now() {
-                return Err(AppError::Forbidden("Ticket expired".to_string()));
-            }
-            (fid.clone(), *exp)
-        } else {
-            return Err(AppError::Forbidden("Invalid ticket".to_string()));
-        }
-    };
+    let file_id = DownloadTicketService::redeem(&state.db, &ticket, &extract_client_ip(&headers))
+        .await?
+        .user_file_id;
 
     let user_file = UserFiles::find_by_id(file_id.clone())
         .filter(user_files::Column::DeletedAt.is_null())
@@ -358,47 +372,130 @@ pub async fn download_file_with_ticket(
         )));
     }
 
-    // Generate presigned URL and redirect
     let (content_type, content_disposition) =
         resolve_file_headers(&user_file.filename, &storage_file);
 
-    let presigned_url = state
-        .storage
-        .generate_presigned_url_raw(
-            &storage_file.s3_key,
-            43200, // 12 hours
-            &content_type,
-            &content_disposition,
-        )
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to generate presigned URL: {}", e);
-            AppError::Internal("Failed to generate download URL".to_string())
-        })?;
+    delivery::serve(
+        &state,
+        &headers,
+        Download {
+            storage_file: &storage_file,
+            content_type,
+            content_disposition,
+            cache_control: "private, max-age=3600",
+            modified: user_file.created_at,
+        },
+    )
+    .await
+}
 
-    let url = url::Url::parse(&presigned_url).map_err(|e| {
-        tracing::error!("Failed to parse presigned URL: {}", e);
-        AppError::Internal("Failed to generate download URL".to_string())
-    })?;
+#[utoipa::path(
+    get,
+    path = "/files/{id}/zip",
+    params(
+        ("id" = String, Path, description = "Folder ID")
+    ),
+    responses(
+        (status = 200, description = "ZIP archive of the folder, streamed", content_type = "application/zip"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Folder not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn download_folder_zip(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(folder_id): Path<String>,
+) -> Result<Response, AppError> {
+    let folder = DriveAccess::find_item(&state.db, &claims.sub, &folder_id, Permission::Read)
+        .await?
+        .filter(|f| f.is_folder)
+        .ok_or(AppError::NotFound("Folder not found".to_string()))?;
 
-    // Extract path and query for X-Accel-Redirect
-    // Should be /bucket/key?Signature=...
-    let path = url.path();
-    let query = url.query().unwrap_or("");
-    let internal_redirect_uri = format!("/minio_protected{}?{}", path, query);
+    let archive_name = format!("{}.zip", folder.filename);
+    let entries = ZipStreamService::collect(&state.db, vec![(folder, None)]).await?;
+    Ok(zip_response(&state, &archive_name, entries))
+}
 
-    Ok(Response::builder()
+#[utoipa::path(
+    post,
+    path = "/files/bulk-download",
+    request_body = BulkDownloadRequest,
+    responses(
+        (status = 200, description = "ZIP archive of the selected items, streamed", content_type = "application/zip"),
+        (status = 400, description = "No items selected"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "None of the items were found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn bulk_download(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<BulkDownloadRequest>,
+) -> Result<Response, AppError> {
+    if req.item_ids.is_empty() {
+        return Err(AppError::BadRequest("No items selected".to_string()));
+    }
+
+    let candidates = UserFiles::find()
+        .filter(user_files::Column::Id.is_in(req.item_ids.clone()))
+        .filter(user_files::Column::DeletedAt.is_null())
+        .find_also_related(StorageFiles)
+        .all(&state.db)
+        .await?;
+    let mut items = Vec::with_capacity(candidates.len());
+    for (item, storage_file) in candidates {
+        if let Some(item) =
+            DriveAccess::authorize(&state.db, &claims.sub, Some(item), Permission::Read).await?
+        {
+            items.push((item, storage_file));
+        }
+    }
+    if items.is_empty() {
+        return Err(AppError::NotFound("Items not found".to_string()));
+    }
+    // Keep the order the client selected them in
+    items.sort_by_key(|(item, _)| req.item_ids.iter().position(|id| id == &item.id));
+
+    let archive_name = match items.as_slice() {
+        [(item, _)] if item.is_folder => format!("{}.zip", item.filename),
+        _ => "download.zip".to_string(),
+    };
+    let entries = ZipStreamService::collect(&state.db, items).await?;
+    Ok(zip_response(&state, &archive_name, entries))
+}
+
+/// Stream `entries` as an attachment named `archive_name`
+pub(crate) fn zip_response(
+    state: &crate::AppState,
+    archive_name: &str,
+    entries: Vec<ArchiveEntry>,
+) -> Response {
+    let ascii_name = archive_name
+        .chars()
+        .filter(|c| c.is_ascii() && !c.is_control() && *c != '"' && *c != '\\' && *c != ';')
+        .collect::<String>();
+    let content_disposition = format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        ascii_name,
+        utf8_percent_encode(archive_name, NON_ALPHANUMERIC)
+    );
+
+    Response::builder()
         .status(StatusCode::OK)
-        // Nginx internal redirect
-        .header("X-Accel-Redirect", internal_redirect_uri)
-        // Content headers for the client
-        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_TYPE, "application/zip")
         .header(header::CONTENT_DISPOSITION, content_disposition)
-        // Cache for ticket duration approx? Or strict validation.
-        // We'll trust the browser cache for a bit if needed.
-        .header(header::CACHE_CONTROL, "private, max-age=3600")
-        .body(Body::empty())
-        .unwrap())
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Body::from_stream(ZipStreamService::stream(
+            state.storage.clone(),
+            entries,
+        )))
+        .unwrap()
 }
 
 /// Resolve content-type and content-disposition for a file.
@@ -1,5 +1,6 @@
 use crate::api::error::AppError;
 use crate::entities::{prelude::*, *};
+use crate::services::drives::{Drive, DriveAccess, Permission};
 use crate::utils::auth::Claims;
 use axum::{
     Extension, Json,
@@ -19,11 +20,13 @@ use super::types::*;
     path = "/files",
     params(
         ("parent_id" = Option<String>, Query, description = "Parent Folder ID"),
-        ("search" = Option<String>, Query, description = "Search query")
+        ("search" = Option<String>, Query, description = "Search query"),
+        ("team_id" = Option<String>, Query, description = "List this team's drive instead of the personal one")
     ),
     responses(
         (status = 200, description = "List of user files", body = Vec<FileMetadata>),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Team not found")
     ),
     security(
         ("jwt" = [])
@@ -34,8 +37,27 @@ pub async fn list_files(
     Extension(claims): Extension<Claims>,
     Query(query): Query<ListFilesQuery>,
 ) -> Result<Json<Vec<FileMetadataResponse>>, AppError> {
+    // A folder decides the drive; otherwise list the drive `team_id` names
+    let drive = match query.parent_id.as_deref() {
+        Some(parent) if parent != "root" => {
+            match DriveAccess::find_item(&state.db, &claims.sub, parent, Permission::Read).await? {
+                Some(folder) => Drive::of(&folder),
+                None => return Ok(Json(Vec::new())),
+            }
+        }
+        _ => {
+            DriveAccess::drive(
+                &state.db,
+                &claims.sub,
+                query.team_id.as_deref(),
+                Permission::Read,
+            )
+            .await?
+        }
+    };
+
     let mut cond = Condition::all()
-        .add(user_files::Column::UserId.eq(&claims.sub))
+        .add(drive.condition())
         .add(user_files::Column::DeletedAt.is_null()); // Exclude soft-deleted items
 
     // Basic filters
@@ -219,6 +241,7 @@ pub async fn list_files(
                 .map(|s| s.is_encrypted)
                 .unwrap_or(false),
             is_shared,
+            team_id: user_file.team_id,
         });
     }
 
@@ -253,10 +276,7 @@ pub async fn get_folder_path(
             break;
         }
 
-        let folder = UserFiles::find_by_id(id_str)
-            .filter(user_files::Column::UserId.eq(&claims.sub))
-            .filter(user_files::Column::DeletedAt.is_null())
-            .one(&state.db)
+        let folder = DriveAccess::find_item(&state.db, &claims.sub, &id_str, Permission::Read)
             .await?
             .ok_or(AppError::NotFound("Folder not found".to_string()))?;
 
@@ -285,6 +305,7 @@ pub async fn get_folder_path(
                 has_thumbnail: false,
                 is_encrypted: false,
                 is_shared: false,
+                team_id: folder.team_id.clone(),
             },
         );
 
@@ -297,9 +318,13 @@ pub async fn get_folder_path(
 #[utoipa::path(
     get,
     path = "/folders/tree",
+    params(
+        ("team_id" = Option<String>, Query, description = "Folders of this team's drive instead of the personal one")
+    ),
     responses(
         (status = 200, description = "All folders for navigation tree", body = Vec<FolderTreeEntry>),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Team not found")
     ),
     security(
         ("jwt" = [])
@@ -308,11 +333,19 @@ pub async fn get_folder_path(
 pub async fn folder_tree(
     State(state): State<crate::AppState>,
     Extension(claims): Extension<Claims>,
+    Query(query): Query<DriveQuery>,
 ) -> Result<Json<Vec<FolderTreeEntry>>, AppError> {
+    let drive = DriveAccess::drive(
+        &state.db,
+        &claims.sub,
+        query.team_id.as_deref(),
+        Permission::Read,
+    )
+    .await?;
     let folders = UserFiles::find()
         .filter(
             Condition::all()
-                .add(user_files::Column::UserId.eq(&claims.sub))
+                .add(drive.condition())
                 .add(user_files::Column::IsFolder.eq(true))
                 .add(user_files::Column::DeletedAt.is_null()),
         )
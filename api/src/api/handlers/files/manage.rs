@@ -1,6 +1,9 @@
 use crate::api::error::AppError;
 use crate::entities::{prelude::*, *};
 use crate::services::audit::{AuditEventType, AuditService};
+use crate::services::drives::{Drive, DriveAccess, Permission};
+use crate::services::file_versions::FileVersionService;
+use crate::services::storage_lifecycle::StorageLifecycleService;
 use crate::utils::auth::Claims;
 use crate::utils::validation::sanitize_filename;
 use axum::{
@@ -11,7 +14,7 @@ use axum::{
 use chrono::Utc;
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, QuerySelect,
-    RelationTrait, Set,
+    RelationTrait, Set, TransactionTrait,
 };
 use uuid::Uuid;
 
@@ -23,7 +26,9 @@ use super::types::*;
     request_body = CreateFolderRequest,
     responses(
         (status = 200, description = "Folder created"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Team viewers cannot create folders"),
+        (status = 404, description = "Parent folder or team not found")
     ),
     security(
         ("jwt" = [])
@@ -34,26 +39,37 @@ pub async fn create_folder(
     Extension(claims): Extension<Claims>,
     Json(req): Json<CreateFolderRequest>,
 ) -> Result<Json<FileMetadataResponse>, AppError> {
-    let id = Uuid::new_v4().to_string();
+    insert_folder(&state, &claims.sub, req).await
+}
 
-    let rules = crate::utils::validation::ValidationRules::load(
+/// Create a folder as `user_id`, in the drive of its parent or of `req.team_id`
+pub(crate) async fn insert_folder(
+    state: &crate::AppState,
+    user_id: &str,
+    req: CreateFolderRequest,
+) -> Result<Json<FileMetadataResponse>, AppError> {
+    let id = Uuid::new_v4().to_string();
+    let destination = DriveAccess::destination(
         &state.db,
-        state.config.max_file_size,
-        state.config.chunk_size,
+        user_id,
+        req.parent_id.as_deref(),
+        req.team_id.as_deref(),
     )
-    .await
-    .map_err(|e| AppError::Internal(format!("Failed to load validation rules: {}", e)))?;
+    .await?;
+
+    let rules = state.file_service.validation_rules().await?;
 
     let sanitized_name =
         sanitize_filename(&req.name, &rules).map_err(|e| AppError::BadRequest(e.to_string()))?;
 
     let new_folder = user_files::ActiveModel {
         id: Set(id.clone()),
-        user_id: Set(claims.sub),
+        user_id: Set(user_id.to_string()),
+        team_id: Set(destination.drive.team_id()),
         storage_file_id: Set(None),
         filename: Set(sanitized_name.clone()),
         is_folder: Set(true),
-        parent_id: Set(req.parent_id.clone()),
+        parent_id: Set(destination.parent_id),
         created_at: Set(Some(Utc::now())),
         is_favorite: Set(false),
         ..Default::default()
@@ -80,6 +96,7 @@ pub async fn create_folder(
         has_thumbnail: false,
         is_encrypted: false,
         is_shared: false,
+        team_id: res.team_id,
     }))
 }
 
@@ -142,10 +159,7 @@ pub async fn toggle_favorite(
     Extension(claims): Extension<Claims>,
     Path(id): Path<String>,
 ) -> Result<Json<FileMetadataResponse>, AppError> {
-    let item = UserFiles::find_by_id(id)
-        .filter(user_files::Column::UserId.eq(&claims.sub))
-        .filter(user_files::Column::DeletedAt.is_null())
-        .one(&state.db)
+    let item = DriveAccess::find_item(&state.db, &claims.sub, &id, Permission::Write)
         .await?
         .ok_or(AppError::NotFound("Item not found".to_string()))?;
 
@@ -202,6 +216,7 @@ pub async fn toggle_favorite(
             .map(|s| s.is_encrypted)
             .unwrap_or(false),
         is_shared: false,
+        team_id: res.team_id,
     }))
 }
 
@@ -215,7 +230,9 @@ pub async fn toggle_favorite(
     responses(
         (status = 200, description = "Item renamed"),
         (status = 401, description = "Unauthorized"),
-        (status = 404, description = "Item not found")
+        (status = 403, description = "Team viewers cannot rename team files"),
+        (status = 404, description = "Item not found"),
+        (status = 507, description = "Storage quota of the target drive exceeded")
     ),
     security(
         ("jwt" = [])
@@ -227,23 +244,26 @@ pub async fn rename_item(
     Path(id): Path<String>,
     Json(req): Json<RenameRequest>,
 ) -> Result<Json<FileMetadataResponse>, AppError> {
-    let item = UserFiles::find_by_id(id)
-        .filter(user_files::Column::UserId.eq(&claims.sub))
-        .filter(user_files::Column::DeletedAt.is_null())
-        .one(&state.db)
-        .await
-        .map_err(|e| AppError::Internal(e.to_string()))?
+    rename_user_item(state, &claims.sub, id, req).await
+}
+
+/// Rename and/or move an item `user_id` may write
+///
+/// Moving into a folder of another drive takes the item's contents along.
+pub(crate) async fn rename_user_item(
+    state: crate::AppState,
+    user_id: &str,
+    id: String,
+    req: RenameRequest,
+) -> Result<Json<FileMetadataResponse>, AppError> {
+    let item = DriveAccess::find_item(&state.db, user_id, &id, Permission::Write)
+        .await?
         .ok_or(AppError::NotFound(
             "Item not found or already deleted".to_string(),
         ))?;
+    let mut target_drive = Drive::of(&item);
 
-    let rules = crate::utils::validation::ValidationRules::load(
-        &state.db,
-        state.config.max_file_size,
-        state.config.chunk_size,
-    )
-    .await
-    .map_err(|e| AppError::Internal(format!("Failed to load validation rules: {}", e)))?;
+    let rules = state.file_service.validation_rules().await?;
 
     let target_filename = if let Some(name) = req.name.clone() {
         sanitize_filename(&name, &rules).map_err(|e| AppError::BadRequest(e.to_string()))?
@@ -264,12 +284,9 @@ pub async fn rename_item(
                     ));
                 }
 
-                // Verify the parent folder exists and belongs to the user
-                let parent_folder = UserFiles::find_by_id(&p)
-                    .filter(user_files::Column::UserId.eq(&claims.sub))
-                    .filter(user_files::Column::DeletedAt.is_null())
-                    .one(&state.db)
-                    .await?;
+                // Verify the parent folder exists and the user may write to it
+                let parent_folder =
+                    DriveAccess::find_item(&state.db, user_id, &p, Permission::Write).await?;
 
                 if parent_folder.is_none() {
                     return Err(AppError::NotFound(
@@ -285,6 +302,9 @@ pub async fn rename_item(
                         "Parent ID must refer to a folder, not a file".to_string(),
                     ));
                 }
+                if let Some(ref parent) = parent_folder {
+                    target_drive = Drive::of(parent);
+                }
             }
             Some(p)
         }
@@ -304,7 +324,7 @@ pub async fn rename_item(
         let mut current_check_id = target_id.clone();
         // Traverse up from target parent to root
         while let Some(parent) = UserFiles::find_by_id(current_check_id)
-            .filter(user_files::Column::UserId.eq(&claims.sub))
+            .filter(target_drive.condition())
             .one(&state.db)
             .await?
         {
@@ -321,44 +341,46 @@ pub async fn rename_item(
         }
     }
 
+    // Moves into another drive check its quota under a lock in `move_to_drive`
+    let crosses_drives = target_drive != Drive::of(&item);
+
     // Check if target already exists (only for files)
     if !item.is_folder {
         let existing = UserFiles::find()
-            .filter(user_files::Column::UserId.eq(&claims.sub))
+            .filter(target_drive.condition())
             .filter(user_files::Column::Filename.eq(&target_filename))
-            .filter(user_files::Column::ParentId.eq(target_parent_id.clone()))
+            .filter(StorageLifecycleService::in_folder(&target_parent_id))
             .filter(user_files::Column::IsFolder.eq(false))
             .filter(user_files::Column::DeletedAt.is_null())
             .filter(user_files::Column::Id.ne(&item.id))
             .one(&state.db)
             .await?;
 
-        if let Some(existing_file) = existing {
-            // Merge logic: Update existing_file to use item's storage_file_id
-            let old_storage_file_id = existing_file.storage_file_id.clone();
-            let new_storage_file_id = item.storage_file_id.clone();
-
-            let mut active_existing: user_files::ActiveModel = existing_file.clone().into();
-            active_existing.storage_file_id = Set(new_storage_file_id.clone());
-            active_existing.created_at = Set(Some(Utc::now()));
-            let updated = active_existing.update(&state.db).await?;
+        if let Some(existing_file) = existing
+            && let Some(new_storage_file_id) = item.storage_file_id.clone()
+        {
+            // Merge logic: existing_file takes item's content (and the reference
+            // item held); its previous content is kept as a version
+            let txn = state.db.begin().await?;
+            if crosses_drives {
+                state
+                    .file_service
+                    .move_to_drive(&txn, user_id, &item, &target_drive)
+                    .await?;
+            }
+            let updated =
+                FileVersionService::replace_content(&txn, &existing_file, &new_storage_file_id)
+                    .await
+                    .map_err(|e| AppError::Internal(e.to_string()))?;
+            FileVersionService::adopt(&txn, &item.id, &existing_file.id)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
 
             // Soft delete the original item (the one being renamed/moved)
             let mut active_item: user_files::ActiveModel = item.clone().into();
             active_item.deleted_at = Set(Some(Utc::now()));
-            active_item.update(&state.db).await?;
-
-            // Decrement ref count of the OVERWRITTEN storage file
-            if let Some(old_id) = old_storage_file_id
-                && Some(old_id.clone()) != new_storage_file_id
-            {
-                let _ = crate::services::storage_lifecycle::StorageLifecycleService::decrement_ref_count(
-                        &state.db,
-                        state.storage.as_ref(),
-                        &old_id,
-                    )
-                    .await;
-            }
+            active_item.update(&txn).await?;
+            txn.commit().await?;
 
             // Return the updated existing file metadata
             return return_file_metadata(state, updated).await;
@@ -367,13 +389,7 @@ pub async fn rename_item(
 
     let mut active: user_files::ActiveModel = item.clone().into();
     if let Some(name) = req.name {
-        let rules = crate::utils::validation::ValidationRules::load(
-            &state.db,
-            state.config.max_file_size,
-            state.config.chunk_size,
-        )
-        .await
-        .map_err(|e| AppError::Internal(format!("Failed to load validation rules: {}", e)))?;
+        let rules = state.file_service.validation_rules().await?;
 
         let sanitized_name =
             sanitize_filename(&name, &rules).map_err(|e| AppError::BadRequest(e.to_string()))?;
@@ -387,7 +403,22 @@ pub async fn rename_item(
         }
     }
 
-    let updated = active.update(&state.db).await?;
+    let updated = if crosses_drives {
+        let txn = state.db.begin().await?;
+        state
+            .file_service
+            .move_to_drive(&txn, user_id, &item, &target_drive)
+            .await?;
+        active.team_id = Set(target_drive.team_id());
+        if let Drive::Personal(owner) = &target_drive {
+            active.user_id = Set(owner.clone());
+        }
+        let updated = active.update(&txn).await?;
+        txn.commit().await?;
+        updated
+    } else {
+        active.update(&state.db).await?
+    };
     return_file_metadata(state, updated).await
 }
 
@@ -452,5 +483,6 @@ pub(crate) async fn return_file_metadata(
             .map(|s| s.is_encrypted)
             .unwrap_or(false),
         is_shared: false,
+        team_id: updated.team_id,
     }))
 }
@@ -1,10 +1,13 @@
 pub mod archive;
 pub mod bulk;
+pub mod delivery;
 pub mod download;
 pub mod list;
 pub mod manage;
+pub mod trash;
 pub mod types;
 pub mod upload;
+pub mod versions;
 
 // Re-export all types
 pub use types::*;
@@ -13,8 +16,11 @@ pub use types::*;
 pub use archive::get_zip_contents;
 pub use bulk::{bulk_copy, bulk_delete, bulk_move};
 pub use download::{
-    download_file, download_file_with_ticket, generate_download_ticket, get_thumbnail,
+    bulk_download, download_file, download_file_with_ticket, download_folder_zip,
+    generate_download_ticket, get_thumbnail, revoke_download_ticket,
 };
 pub use list::{folder_tree, get_folder_path, list_files};
 pub use manage::{create_folder, delete_item, rename_item, toggle_favorite};
+pub use trash::{empty_trash, list_trash, purge_item, restore_item};
 pub use upload::{link_file, pre_check_dedup, upload_file};
+pub use versions::{download_version, list_versions, prune_versions, restore_version};
@@ -0,0 +1,187 @@
+use crate::api::error::AppError;
+use crate::services::audit::{AuditEventType, AuditService};
+use crate::services::drives::{DriveAccess, Permission};
+use crate::utils::auth::Claims;
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use chrono::{Duration, Utc};
+
+use super::manage::return_file_metadata;
+use super::types::*;
+
+#[utoipa::path(
+    get,
+    path = "/trash",
+    params(
+        ("team_id" = Option<String>, Query, description = "List this team's trash instead of the personal one")
+    ),
+    responses(
+        (status = 200, description = "Trashed items", body = Vec<TrashItemResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Team not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn list_trash(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<DriveQuery>,
+) -> Result<Json<Vec<TrashItemResponse>>, AppError> {
+    let retention = Duration::days(state.config.trash_retention_days as i64);
+    let drive = DriveAccess::drive(
+        &state.db,
+        &claims.sub,
+        query.team_id.as_deref(),
+        Permission::Read,
+    )
+    .await?;
+    let items = state.file_service.list_trash(&drive).await?;
+
+    Ok(Json(
+        items
+            .into_iter()
+            .map(|(item, storage_file)| {
+                let deleted_at = item.deleted_at.unwrap_or_else(Utc::now);
+                TrashItemResponse {
+                    id: item.id,
+                    filename: item.filename,
+                    is_folder: item.is_folder,
+                    size: storage_file.map(|s| s.size),
+                    original_path: item.original_path,
+                    deleted_at,
+                    purge_at: deleted_at + retention,
+                }
+            })
+            .collect(),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/trash/{id}/restore",
+    params(
+        ("id" = String, Path, description = "Trashed File/Folder ID")
+    ),
+    responses(
+        (status = 200, description = "Item restored", body = FileMetadataResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Item not found in trash")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn restore_item(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<FileMetadataResponse>, AppError> {
+    let restored = state.file_service.restore_item(&claims.sub, &id).await?;
+
+    let audit = AuditService::new(state.db.clone());
+    audit
+        .log(
+            AuditEventType::FileRestore,
+            Some(claims.sub),
+            Some(id),
+            "restore_item",
+            "success",
+            None,
+            None,
+        )
+        .await;
+
+    return_file_metadata(state, restored).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/trash/{id}",
+    params(
+        ("id" = String, Path, description = "Trashed File/Folder ID")
+    ),
+    responses(
+        (status = 204, description = "Item permanently deleted"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Item not found in trash")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn purge_item(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    state.file_service.purge_item(&claims.sub, &id).await?;
+
+    let audit = AuditService::new(state.db.clone());
+    audit
+        .log(
+            AuditEventType::FilePurge,
+            Some(claims.sub),
+            Some(id),
+            "purge_item",
+            "success",
+            None,
+            None,
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/trash",
+    params(
+        ("team_id" = Option<String>, Query, description = "Empty this team's trash instead of the personal one")
+    ),
+    responses(
+        (status = 200, description = "Trash emptied", body = EmptyTrashResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Team viewers cannot empty the trash"),
+        (status = 404, description = "Team not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn empty_trash(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<DriveQuery>,
+) -> Result<Json<EmptyTrashResponse>, AppError> {
+    let drive = DriveAccess::drive(
+        &state.db,
+        &claims.sub,
+        query.team_id.as_deref(),
+        Permission::Write,
+    )
+    .await?;
+    let purged_count = state.file_service.empty_trash(&claims.sub, &drive).await?;
+
+    let audit = AuditService::new(state.db.clone());
+    audit
+        .log(
+            AuditEventType::FilePurge,
+            Some(claims.sub),
+            None,
+            "empty_trash",
+            "success",
+            Some(serde_json::json!({
+                "purged_count": purged_count,
+                "team_id": query.team_id
+            })),
+            None,
+        )
+        .await;
+
+    Ok(Json(EmptyTrashResponse { purged_count }))
+}
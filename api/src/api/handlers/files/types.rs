@@ -30,6 +30,8 @@ pub struct FileMetadataResponse {
     pub has_thumbnail: bool,
     pub is_encrypted: bool,
     pub is_shared: bool,
+    /// Team drive the item lives in; `null` for personal files
+    pub team_id: Option<String>,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -54,6 +56,8 @@ pub struct PreCheckResponse {
     pub exists: bool,
     pub upload_token: Option<String>,
     pub file_id: Option<String>,
+    /// Present when `exists` is true; must be answered in `/files/link`
+    pub challenge: Option<crate::services::file_service::PossessionChallenge>,
 }
 
 #[derive(Deserialize, Serialize, ToSchema, Clone)]
@@ -79,6 +83,14 @@ pub struct ListFilesQuery {
     pub limit: Option<u64>,
     pub offset: Option<u64>,
     pub is_favorite: Option<bool>,
+    /// List this team's drive instead of the personal one
+    pub team_id: Option<String>,
+}
+
+/// Selects a team drive; the personal drive when `team_id` is absent
+#[derive(Deserialize)]
+pub struct DriveQuery {
+    pub team_id: Option<String>,
 }
 
 #[derive(Deserialize, ToSchema, Validate)]
@@ -90,6 +102,8 @@ pub struct CreateFolderRequest {
     ))]
     pub name: String,
     pub parent_id: Option<String>,
+    /// Team drive to create a root folder in; implied by `parent_id` when set
+    pub team_id: Option<String>,
 }
 
 #[derive(Deserialize, ToSchema)]
@@ -103,10 +117,19 @@ pub struct BulkDeleteRequest {
     pub item_ids: Vec<String>,
 }
 
+#[derive(Deserialize, ToSchema)]
+pub struct BulkDownloadRequest {
+    /// Files and folders to include; folders are added with their contents
+    pub item_ids: Vec<String>,
+}
+
 #[derive(Deserialize, ToSchema)]
 pub struct BulkMoveRequest {
     pub item_ids: Vec<String>,
     pub parent_id: Option<String>,
+    /// Team drive whose root receives the items when `parent_id` is absent;
+    /// the personal drive when both are absent
+    pub team_id: Option<String>,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -119,7 +142,13 @@ pub struct LinkFileRequest {
     pub storage_file_id: String,
     pub filename: String,
     pub parent_id: Option<String>,
+    /// Team drive to link into when `parent_id` is absent
+    pub team_id: Option<String>,
     pub expiration_hours: Option<i64>,
+    /// Token from the `/pre-check` challenge
+    pub challenge_token: Option<String>,
+    /// Hex SHA-256 of the challenged byte ranges, concatenated in order
+    pub challenge_response: Option<String>,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -139,3 +168,69 @@ pub struct BulkDeleteResponse {
 pub struct BulkCopyResponse {
     pub copied_count: usize,
 }
+
+#[derive(Serialize, ToSchema)]
+pub struct TrashItemResponse {
+    pub id: String,
+    pub filename: String,
+    pub is_folder: bool,
+    pub size: Option<i64>,
+    /// Folder the item was in when trashed, e.g. `Documents/Reports`
+    pub original_path: Option<String>,
+    pub deleted_at: chrono::DateTime<Utc>,
+    /// When the item will be permanently deleted
+    pub purge_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct EmptyTrashResponse {
+    pub purged_count: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct FileVersionResponse {
+    pub id: String,
+    pub version_number: i32,
+    pub size: Option<i64>,
+    pub hash: Option<String>,
+    /// When this content was uploaded
+    pub created_at: chrono::DateTime<Utc>,
+    /// When newer content replaced it
+    pub archived_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct PruneVersionsRequest {
+    /// Keep only this many of the most recent versions
+    pub keep_latest: Option<usize>,
+    /// Remove versions archived more than this many days ago
+    pub older_than_days: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PruneVersionsResponse {
+    pub pruned_count: usize,
+}
+
+#[derive(Deserialize, ToSchema, Default)]
+pub struct DownloadTicketRequest {
+    /// Seconds until the ticket expires (default 12 hours, at most 7 days)
+    pub expires_in_seconds: Option<i64>,
+    /// Allow only this many downloads
+    pub max_downloads: Option<i32>,
+    /// Shorthand for `max_downloads: 1`
+    #[serde(default)]
+    pub single_use: bool,
+    /// Only accept the ticket from the IP address that requested it
+    #[serde(default)]
+    pub bind_ip: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DownloadTicketResponse {
+    pub ticket: String,
+    pub url: String,
+    pub expires_at: chrono::DateTime<Utc>,
+    pub max_downloads: Option<i32>,
+    pub ip_bound: bool,
+}
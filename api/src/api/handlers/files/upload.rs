@@ -1,5 +1,7 @@
 use crate::api::error::AppError;
 use crate::entities::{prelude::*, *};
+use crate::services::drives::DriveAccess;
+use crate::services::file_service::PossessionProof;
 use crate::utils::auth::Claims;
 use crate::utils::validation::sanitize_filename;
 use axum::{
@@ -20,7 +22,8 @@ use super::types::*;
     request_body = PreCheckRequest,
     responses(
         (status = 200, description = "Pre-check successful", body = PreCheckResponse),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 429, description = "Too many pre-checks; see Retry-After")
     ),
     security(
         ("jwt" = [])
@@ -28,7 +31,7 @@ use super::types::*;
 )]
 pub async fn pre_check_dedup(
     State(state): State<crate::AppState>,
-    Extension(_claims): Extension<Claims>,
+    Extension(claims): Extension<Claims>,
     Json(req): Json<PreCheckRequest>,
 ) -> Result<Json<PreCheckResponse>, AppError> {
     req.validate()
@@ -41,16 +44,21 @@ pub async fn pre_check_dedup(
         .await?;
 
     if let Some(file) = existing {
+        let challenge = state
+            .file_service
+            .create_possession_challenge(&claims.sub, &file)?;
         Ok(Json(PreCheckResponse {
             exists: true,
             upload_token: None,
             file_id: Some(file.id),
+            challenge: Some(challenge),
         }))
     } else {
         Ok(Json(PreCheckResponse {
             exists: false,
             upload_token: Some(Uuid::new_v4().to_string()),
             file_id: None,
+            challenge: None,
         }))
     }
 }
@@ -62,7 +70,10 @@ pub async fn pre_check_dedup(
     responses(
         (status = 200, description = "File linked successfully", body = UploadResponse),
         (status = 401, description = "Unauthorized"),
-        (status = 404, description = "Storage file not found")
+        (status = 403, description = "Missing or invalid proof of possession, or team viewer"),
+        (status = 404, description = "Storage file, target folder or team not found"),
+        (status = 429, description = "Upload rate limit exceeded; see Retry-After"),
+        (status = 507, description = "Storage quota exceeded")
     ),
     security(
         ("jwt" = [])
@@ -73,25 +84,37 @@ pub async fn link_file(
     Extension(claims): Extension<Claims>,
     Json(req): Json<LinkFileRequest>,
 ) -> Result<Json<UploadResponse>, AppError> {
-    let rules = crate::utils::validation::ValidationRules::load(
-        &state.db,
-        state.config.max_file_size,
-        state.config.chunk_size,
-    )
-    .await
-    .map_err(|e| AppError::Internal(format!("Failed to load validation rules: {}", e)))?;
+    let rules = state.file_service.validation_rules().await?;
 
     let sanitized_filename = crate::utils::validation::sanitize_filename(&req.filename, &rules)
         .map_err(|e| AppError::BadRequest(e.to_string()))?;
 
+    let proof = match (req.challenge_token, req.challenge_response) {
+        (Some(token), Some(response)) => PossessionProof { token, response },
+        _ => {
+            return Err(AppError::Forbidden(
+                "Proof of possession required: answer the /pre-check challenge".to_string(),
+            ));
+        }
+    };
+
+    let destination = DriveAccess::destination(
+        &state.db,
+        &claims.sub,
+        req.parent_id.as_deref(),
+        req.team_id.as_deref(),
+    )
+    .await?;
+
     let (user_file_id, expires_at) = state
         .file_service
         .link_existing_file(
             req.storage_file_id,
             sanitized_filename.clone(),
             claims.sub,
-            req.parent_id,
+            destination,
             req.expiration_hours,
+            &proof,
         )
         .await?;
 
@@ -108,7 +131,11 @@ pub async fn link_file(
     request_body(content = Multipart, description = "File upload"),
     responses(
         (status = 200, description = "File uploaded successfully", body = UploadResponse),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Team viewers cannot upload"),
+        (status = 404, description = "Target folder or team not found"),
+        (status = 429, description = "Upload rate limit exceeded; see Retry-After"),
+        (status = 507, description = "Storage quota exceeded")
     ),
     security(
         ("jwt" = [])
@@ -122,6 +149,7 @@ pub async fn upload_file(
     let mut filename = String::new();
     let mut expiration_hours: Option<i64> = None;
     let mut parent_id: Option<String> = None;
+    let mut team_id: Option<String> = None;
     let mut total_size: Option<u64> = None;
     let mut staged_file: Option<crate::services::file_service::StagedFile> = None;
 
@@ -144,15 +172,7 @@ pub async fn upload_file(
                 let content_type = field.content_type().map(|s| s.to_string());
 
                 // 0. Load Validation Rules
-                let rules = crate::utils::validation::ValidationRules::load(
-                    &state.db,
-                    state.config.max_file_size,
-                    state.config.chunk_size,
-                )
-                .await
-                .map_err(|e| {
-                    AppError::Internal(format!("Failed to load validation rules: {}", e))
-                })?;
+                let rules = state.file_service.validation_rules().await?;
 
                 // 1. Sanitize filename
                 filename = sanitize_filename(&original_filename, &rules)
@@ -162,11 +182,24 @@ pub async fn upload_file(
                 let body_with_io_error = field.map_err(std::io::Error::other);
                 let reader = StreamReader::new(body_with_io_error);
 
-                // 3. Upload to Staging
+                // 3. Upload to Staging, against the quota of the drive named by
+                // the fields sent so far
+                let destination = DriveAccess::destination(
+                    &state.db,
+                    &claims.sub,
+                    parent_id.as_deref(),
+                    team_id.as_deref(),
+                )
+                .await?;
                 staged_file = Some(
                     state
                         .file_service
-                        .upload_to_staging(&filename, content_type.as_deref(), reader)
+                        .upload_to_staging(
+                            &destination.drive,
+                            &filename,
+                            content_type.as_deref(),
+                            reader,
+                        )
                         .await?,
                 );
             } else if name == "expiration_hours" {
@@ -177,6 +210,11 @@ pub async fn upload_file(
                 if !text.is_empty() && text != "null" {
                     parent_id = Some(text);
                 }
+            } else if name == "team_id" {
+                let text = field.text().await.unwrap_or_default();
+                if !text.is_empty() && text != "null" {
+                    team_id = Some(text);
+                }
             } else if name == "total_size" {
                 let text = field.text().await.unwrap_or_default();
                 total_size = text.parse().ok();
@@ -186,13 +224,27 @@ pub async fn upload_file(
         let staged = staged_file.ok_or(AppError::BadRequest("No file provided".to_string()))?;
 
         // 4. Process Upload
+        let destination = match DriveAccess::destination(
+            &state.db,
+            &claims.sub,
+            parent_id.as_deref(),
+            team_id.as_deref(),
+        )
+        .await
+        {
+            Ok(destination) => destination,
+            Err(e) => {
+                let _ = state.storage.delete_file(&staged.s3_key).await;
+                return Err(e);
+            }
+        };
         let (user_file_id, expires_at) = state
             .file_service
             .process_upload(
                 staged,
                 filename.clone(),
                 claims.sub,
-                parent_id,
+                destination,
                 expiration_hours,
                 total_size,
             )
@@ -0,0 +1,222 @@
+use crate::api::error::AppError;
+use crate::entities::{prelude::*, *};
+use crate::services::drives::{DriveAccess, Permission};
+use crate::services::file_versions::FileVersionService;
+use crate::services::storage_lifecycle::ReleasedObjects;
+use crate::utils::auth::Claims;
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::HeaderMap,
+    response::Response,
+};
+use chrono::{Duration, Utc};
+use sea_orm::{EntityTrait, TransactionTrait};
+
+use super::delivery::{self, Download};
+use super::download::resolve_file_headers;
+use super::manage::return_file_metadata;
+use super::types::*;
+
+/// Load a live file owned by the caller
+async fn find_owned_file(
+    state: &crate::AppState,
+    user_id: &str,
+    file_id: &str,
+    permission: Permission,
+) -> Result<user_files::Model, AppError> {
+    DriveAccess::find_item(&state.db, user_id, file_id, permission)
+        .await?
+        .filter(|f| !f.is_folder)
+        .ok_or_else(|| AppError::NotFound("File not found".to_string()))
+}
+
+async fn find_version(
+    state: &crate::AppState,
+    user_file_id: &str,
+    version_id: &str,
+) -> Result<file_versions::Model, AppError> {
+    FileVersionService::find(&state.db, user_file_id, version_id)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+        .ok_or_else(|| AppError::NotFound("Version not found".to_string()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/files/{id}/versions",
+    params(
+        ("id" = String, Path, description = "User File ID")
+    ),
+    responses(
+        (status = 200, description = "Previous versions, newest first", body = Vec<FileVersionResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "File not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn list_versions(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<FileVersionResponse>>, AppError> {
+    let user_file = find_owned_file(&state, &claims.sub, &id, Permission::Read).await?;
+    let versions = FileVersionService::list(&state.db, &user_file.id)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok(Json(
+        versions
+            .into_iter()
+            .map(|(version, storage_file)| FileVersionResponse {
+                id: version.id,
+                version_number: version.version_number,
+                size: storage_file.as_ref().map(|s| s.size),
+                hash: storage_file.map(|s| s.hash),
+                created_at: version.created_at,
+                archived_at: version.archived_at,
+            })
+            .collect(),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/files/{id}/versions/{version_id}",
+    params(
+        ("id" = String, Path, description = "User File ID"),
+        ("version_id" = String, Path, description = "Version ID")
+    ),
+    responses(
+        (status = 200, description = "Version download stream"),
+        (status = 206, description = "Partial content (DOWNLOAD_MODE=stream)"),
+        (status = 304, description = "Not modified (DOWNLOAD_MODE=stream)"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Version is infected"),
+        (status = 404, description = "File or version not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn download_version(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((id, version_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let user_file = find_owned_file(&state, &claims.sub, &id, Permission::Read).await?;
+    let version = find_version(&state, &user_file.id, &version_id).await?;
+
+    let storage_file = StorageFiles::find_by_id(&version.storage_file_id)
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound("Storage file not found".to_string()))?;
+
+    if matches!(storage_file.scan_status.as_deref(), Some("infected")) {
+        return Err(AppError::Forbidden(
+            "File is infected with malware".to_string(),
+        ));
+    }
+
+    let (content_type, content_disposition) =
+        resolve_file_headers(&user_file.filename, &storage_file);
+
+    delivery::serve(
+        &state,
+        &headers,
+        Download {
+            storage_file: &storage_file,
+            content_type,
+            content_disposition,
+            cache_control: "private, max-age=31536000",
+            modified: Some(version.created_at),
+        },
+    )
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/files/{id}/versions/{version_id}/restore",
+    params(
+        ("id" = String, Path, description = "User File ID"),
+        ("version_id" = String, Path, description = "Version ID")
+    ),
+    responses(
+        (status = 200, description = "Version restored; the replaced content becomes a new version", body = FileMetadataResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "File or version not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn restore_version(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((id, version_id)): Path<(String, String)>,
+) -> Result<Json<FileMetadataResponse>, AppError> {
+    let user_file = find_owned_file(&state, &claims.sub, &id, Permission::Write).await?;
+    let version = find_version(&state, &user_file.id, &version_id).await?;
+
+    let txn = state.db.begin().await?;
+    let restored = FileVersionService::restore(&txn, &user_file, &version)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    txn.commit().await?;
+
+    return_file_metadata(state, restored).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/files/{id}/versions/prune",
+    params(
+        ("id" = String, Path, description = "User File ID")
+    ),
+    request_body = PruneVersionsRequest,
+    responses(
+        (status = 200, description = "Versions pruned", body = PruneVersionsResponse),
+        (status = 400, description = "No pruning criteria given"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "File not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn prune_versions(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(req): Json<PruneVersionsRequest>,
+) -> Result<Json<PruneVersionsResponse>, AppError> {
+    if req.keep_latest.is_none() && req.older_than_days.is_none() {
+        return Err(AppError::BadRequest(
+            "Specify keep_latest and/or older_than_days".to_string(),
+        ));
+    }
+    let user_file = find_owned_file(&state, &claims.sub, &id, Permission::Write).await?;
+    let older_than = req
+        .older_than_days
+        .map(|days| Utc::now() - Duration::days(days.max(0)));
+
+    let txn = state.db.begin().await?;
+    let mut released = ReleasedObjects::default();
+    let pruned_count = FileVersionService::prune(
+        &txn,
+        &user_file.id,
+        req.keep_latest,
+        older_than,
+        &mut released,
+    )
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))?;
+    txn.commit().await?;
+    released.delete_objects(state.storage.as_ref()).await;
+
+    Ok(Json(PruneVersionsResponse { pruned_count }))
+}
@@ -51,13 +51,7 @@ pub async fn health_check(State(state): State<AppState>) -> impl IntoResponse {
 pub async fn get_validation_rules(
     State(state): State<AppState>,
 ) -> Result<Json<crate::utils::validation::ValidationRules>, crate::api::error::AppError> {
-    let rules = crate::utils::validation::ValidationRules::load(
-        &state.db,
-        state.config.max_file_size,
-        state.config.chunk_size,
-    )
-    .await
-    .map_err(|e| crate::api::error::AppError::Internal(e.to_string()))?;
+    let rules = state.file_service.validation_rules().await?;
 
-    Ok(Json(rules))
+    Ok(Json(rules.as_ref().clone()))
 }
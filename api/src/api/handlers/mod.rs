@@ -1,8 +1,17 @@
+pub mod account_email;
+pub mod admin;
+pub mod api_tokens;
 pub mod auth;
 pub mod captcha;
 pub mod files;
 pub mod health;
+pub mod sessions;
 pub mod shares;
+pub mod storage;
+pub mod teams;
+pub mod tus;
+pub mod two_factor;
 pub mod upload;
 pub mod user_settings;
 pub mod users;
+pub mod validation_rules;
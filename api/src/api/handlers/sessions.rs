@@ -0,0 +1,207 @@
+use crate::api::error::AppError;
+use crate::api::handlers::auth::AuthResponse;
+use crate::services::audit::{AuditEventType, AuditService};
+use crate::services::sessions::{ClientInfo, SessionService};
+use crate::utils::auth::Claims;
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SessionResponse {
+    pub id: String,
+    /// User agent of the client that last used the session
+    pub device: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: chrono::DateTime<Utc>,
+    /// Last sign-in or token refresh
+    pub last_used_at: chrono::DateTime<Utc>,
+    pub expires_at: chrono::DateTime<Utc>,
+    /// The session of the token making this request
+    pub current: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/refresh",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "New access and refresh tokens", body = AuthResponse),
+        (status = 401, description = "Refresh token invalid, already used or expired")
+    ),
+    tag = "auth"
+)]
+pub async fn refresh(
+    State(state): State<crate::AppState>,
+    headers: HeaderMap,
+    Json(req): Json<RefreshRequest>,
+) -> Result<Json<AuthResponse>, AppError> {
+    let tokens = SessionService::refresh(
+        &state.db,
+        &state.config,
+        &req.refresh_token,
+        ClientInfo::from_headers(&headers),
+    )
+    .await?;
+    Ok(Json(tokens.into()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/logout",
+    responses(
+        (status = 204, description = "Session ended"),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("jwt" = [])),
+    tag = "auth"
+)]
+pub async fn logout(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+) -> Result<StatusCode, AppError> {
+    SessionService::revoke_access_token(&state.db, &claims).await?;
+    if let Some(sid) = &claims.sid {
+        match SessionService::revoke(&state.db, &state.config, &claims.sub, sid).await {
+            Ok(()) | Err(AppError::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::UserLogout,
+            Some(claims.sub),
+            None,
+            "logout",
+            "success",
+            claims
+                .sid
+                .map(|sid| serde_json::json!({ "session_id": sid })),
+            ClientInfo::from_headers(&headers).ip_address,
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/sessions",
+    responses(
+        (status = 200, description = "Active sessions", body = Vec<SessionResponse>),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("jwt" = [])),
+    tag = "auth"
+)]
+pub async fn list_sessions(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<SessionResponse>>, AppError> {
+    let sessions = SessionService::list(&state.db, &claims.sub).await?;
+    Ok(Json(
+        sessions
+            .into_iter()
+            .map(|s| {
+                let created_at = s.created_at.unwrap_or(s.expires_at);
+                SessionResponse {
+                    current: claims.sid.as_deref() == Some(s.id.as_str()),
+                    id: s.id,
+                    device: s.device,
+                    ip_address: s.ip_address,
+                    created_at,
+                    last_used_at: s.last_used_at.unwrap_or(created_at),
+                    expires_at: s.expires_at,
+                }
+            })
+            .collect(),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/sessions/{id}",
+    params(("id" = String, Path, description = "Session ID")),
+    responses(
+        (status = 204, description = "Session ended"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Session not found")
+    ),
+    security(("jwt" = [])),
+    tag = "auth"
+)]
+pub async fn revoke_session(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<StatusCode, AppError> {
+    SessionService::revoke(&state.db, &state.config, &claims.sub, &id).await?;
+    audit_revoke(
+        &state,
+        claims.sub,
+        serde_json::json!({ "session_id": id }),
+        &headers,
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/sessions",
+    responses(
+        (status = 204, description = "Every session ended, including the current one"),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("jwt" = [])),
+    tag = "auth"
+)]
+pub async fn revoke_all_sessions(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+) -> Result<StatusCode, AppError> {
+    let ended = SessionService::revoke_all(&state.db, &state.config, &claims.sub).await?;
+    // Also covers a current token that belongs to no session
+    SessionService::revoke_access_token(&state.db, &claims).await?;
+    audit_revoke(
+        &state,
+        claims.sub,
+        serde_json::json!({ "sessions": ended }),
+        &headers,
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn audit_revoke(
+    state: &crate::AppState,
+    user_id: String,
+    details: serde_json::Value,
+    headers: &HeaderMap,
+) {
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::SessionRevoke,
+            Some(user_id),
+            None,
+            "session_revoke",
+            "success",
+            Some(details),
+            ClientInfo::from_headers(headers).ip_address,
+        )
+        .await;
+}
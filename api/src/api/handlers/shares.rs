@@ -1,19 +1,33 @@
 use crate::api::error::AppError;
+use crate::api::handlers::captcha::{extract_client_ip, validate_captcha};
+use crate::api::handlers::files::delivery::{self, Download};
+use crate::api::handlers::files::manage::{insert_folder, rename_user_item};
+use crate::api::handlers::files::{
+    CreateFolderRequest, FileMetadataResponse, RenameRequest, UploadResponse,
+};
 use crate::entities::{prelude::*, *};
 use crate::services::audit::{AuditEventType, AuditService};
+use crate::services::drives::{Destination, DriveAccess};
+use crate::services::rate_limiter::{RateLimitService, UPLOAD_BUCKET};
+use crate::services::share_lockout::ShareLockoutService;
 use crate::services::share_service::ShareService;
+use crate::services::zip_stream::ZipStreamService;
 use crate::utils::auth::Claims;
+use crate::utils::validation::sanitize_filename;
 use axum::{
     Extension, Json,
-    body::Body,
-    extract::{Path, Query, State},
+    extract::{Multipart, Path, Query, State},
     http::{HeaderMap, StatusCode, header},
     response::Response,
 };
 use chrono::Utc;
+use futures::TryStreamExt;
 use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio_util::io::StreamReader;
 use utoipa::ToSchema;
 
 // ── Request / Response Types ──────────────────────────────────────────
@@ -24,8 +38,27 @@ pub struct CreateShareRequest {
     pub share_type: String, // "public" or "user"
     pub shared_with_user_id: Option<String>,
     pub password: Option<String>,
-    pub permission: String,    // "view" or "download"
+    pub permission: String,    // "view", "download", "upload" or "edit"
     pub expires_in_hours: i64, // Must be > 0
+    /// Upload shares: total number of files accepted
+    pub max_uploads: Option<i32>,
+    /// Upload shares: largest accepted file, in bytes
+    pub max_upload_size: Option<i64>,
+    /// Upload shares: accepted extensions or MIME types, e.g. ["pdf", "image/*"]
+    pub allowed_upload_types: Option<Vec<String>>,
+    /// Downloads allowed in total; unlimited when absent
+    pub max_downloads: Option<i32>,
+    /// Times the share page may be opened; unlimited when absent
+    pub max_views: Option<i32>,
+    /// Stop working after the first download; same as `max_downloads: 1`
+    #[serde(default)]
+    pub burn_after_download: bool,
+    /// Public shares: IP addresses or CIDR ranges the link works from, e.g. ["10.0.0.0/8"]
+    pub allowed_ips: Option<Vec<String>>,
+    /// Public shares: IP addresses or CIDR ranges refused even if allowed
+    pub denied_ips: Option<Vec<String>>,
+    /// Public shares: sites (subdomains included) that may link to the share
+    pub allowed_referrers: Option<Vec<String>>,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -42,6 +75,21 @@ pub struct ShareResponse {
     pub filename: Option<String>,
     pub is_folder: Option<bool>,
     pub parent_id: Option<String>,
+    pub max_uploads: Option<i32>,
+    pub max_upload_size: Option<i64>,
+    pub allowed_upload_types: Option<Vec<String>>,
+    pub upload_count: i32,
+    pub max_downloads: Option<i32>,
+    pub download_count: i32,
+    /// Downloads left before the link stops working; absent when unlimited
+    pub remaining_downloads: Option<i32>,
+    pub max_views: Option<i32>,
+    pub view_count: i32,
+    /// Views left; absent when unlimited
+    pub remaining_views: Option<i32>,
+    pub allowed_ips: Option<Vec<String>>,
+    pub denied_ips: Option<Vec<String>>,
+    pub allowed_referrers: Option<Vec<String>>,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -63,16 +111,45 @@ pub struct PublicShareInfoResponse {
     pub permission: String,
     pub requires_password: bool,
     pub expires_at: chrono::DateTime<Utc>,
+    /// What an upload share still accepts; absent for other shares
+    pub upload: Option<ShareUploadInfo>,
+    /// Downloads left before the link stops working; absent when unlimited
+    pub remaining_downloads: Option<i32>,
+    /// Views left after this one; absent when unlimited
+    pub remaining_views: Option<i32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ShareUploadInfo {
+    /// Files that can still be uploaded; absent when unlimited
+    pub remaining_uploads: Option<i32>,
+    pub max_upload_size: i64,
+    pub allowed_upload_types: Option<Vec<String>>,
 }
 
 #[derive(Deserialize, ToSchema)]
 pub struct VerifySharePasswordRequest {
     pub password: String,
+    /// Required once the client has failed `SHARE_PASSWORD_CAPTCHA_AFTER` times
+    pub captcha_id: Option<String>,
+    pub captcha_answer: Option<i32>,
 }
 
 #[derive(Serialize, ToSchema)]
 pub struct VerifySharePasswordResponse {
     pub verified: bool,
+    /// The next attempt must include a solved CAPTCHA
+    pub captcha_required: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ShareLockoutResponse {
+    /// The client the counter tracks; absent for the share-wide counter
+    pub client_ip: Option<String>,
+    pub failed_attempts: i32,
+    pub last_attempt: chrono::DateTime<Utc>,
+    /// Attempts are refused until then; absent when not locked
+    pub locked_until: Option<chrono::DateTime<Utc>>,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -90,6 +167,76 @@ pub struct SharesForFileQuery {
     pub user_file_id: Option<String>,
 }
 
+#[derive(Serialize, ToSchema)]
+pub struct IncomingShareResponse {
+    pub id: String,
+    pub user_file_id: String,
+    pub permission: String,
+    pub expires_at: chrono::DateTime<Utc>,
+    pub created_at: chrono::DateTime<Utc>,
+    pub owner_id: String,
+    pub owner_username: Option<String>,
+    pub filename: String,
+    pub is_folder: bool,
+    pub size: Option<i64>,
+    pub mime_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct IncomingFolderQuery {
+    /// Folder inside the share; the shared folder itself when absent
+    pub folder_id: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AddSharedItemRequest {
+    /// File inside the share to add; the shared file itself when absent
+    pub file_id: Option<String>,
+    /// Destination folder in the recipient's drive; the root when absent
+    pub parent_id: Option<String>,
+    /// Team drive whose root receives the file when `parent_id` is absent
+    pub team_id: Option<String>,
+}
+
+/// Header carrying the password of a protected upload share
+pub const SHARE_PASSWORD_HEADER: &str = "x-share-password";
+
+/// Split a comma-separated share column into its entries
+fn comma_list(list: &Option<String>) -> Option<Vec<String>> {
+    list.as_ref()
+        .map(|entries| entries.split(',').map(str::to_string).collect())
+}
+
+fn share_response(share: share_links::Model, uf: Option<&user_files::Model>) -> ShareResponse {
+    ShareResponse {
+        allowed_upload_types: comma_list(&share.allowed_upload_types),
+        max_uploads: share.max_uploads,
+        max_upload_size: share.max_upload_size,
+        upload_count: share.upload_count,
+        remaining_downloads: ShareService::remaining(share.max_downloads, share.download_count),
+        max_downloads: share.max_downloads,
+        download_count: share.download_count,
+        remaining_views: ShareService::remaining(share.max_views, share.view_count),
+        max_views: share.max_views,
+        view_count: share.view_count,
+        allowed_ips: comma_list(&share.allowed_ips),
+        denied_ips: comma_list(&share.denied_ips),
+        allowed_referrers: comma_list(&share.allowed_referrers),
+        id: share.id,
+        user_file_id: share.user_file_id,
+        share_token: share.share_token,
+        share_type: share.share_type,
+        shared_with_user_id: share.shared_with_user_id,
+        has_password: share.password_hash.is_some(),
+        permission: share.permission,
+        expires_at: share.expires_at,
+        created_at: share.created_at.unwrap_or_else(Utc::now),
+        filename: uf.map(|f| f.filename.clone()),
+        is_folder: uf.map(|f| f.is_folder),
+        parent_id: uf.and_then(|f| f.parent_id.clone()),
+    }
+}
+
 // ── Authenticated Endpoints ───────────────────────────────────────────
 
 /// Create a share link
@@ -124,11 +271,68 @@ pub async fn create_share(
             "share_type must be 'public' or 'user'".to_string(),
         ));
     }
-    if !["view", "download"].contains(&req.permission.as_str()) {
+    if !["view", "download", "upload", "edit"].contains(&req.permission.as_str()) {
+        return Err(AppError::BadRequest(
+            "permission must be 'view', 'download', 'upload' or 'edit'".to_string(),
+        ));
+    }
+    if req.max_uploads.is_some_and(|max| max < 1) {
+        return Err(AppError::BadRequest(
+            "max_uploads must be at least 1".to_string(),
+        ));
+    }
+    if req.max_upload_size.is_some_and(|max| max < 1) {
+        return Err(AppError::BadRequest(
+            "max_upload_size must be positive".to_string(),
+        ));
+    }
+    if req.max_downloads.is_some_and(|max| max < 1) {
         return Err(AppError::BadRequest(
-            "permission must be 'view' or 'download'".to_string(),
+            "max_downloads must be at least 1".to_string(),
         ));
     }
+    if req.max_views.is_some_and(|max| max < 1) {
+        return Err(AppError::BadRequest(
+            "max_views must be at least 1".to_string(),
+        ));
+    }
+    let max_downloads = match (req.burn_after_download, req.max_downloads) {
+        (true, Some(_)) => {
+            return Err(AppError::BadRequest(
+                "burn_after_download cannot be combined with max_downloads".to_string(),
+            ));
+        }
+        (true, None) => Some(1),
+        (false, max) => max,
+    };
+    let allowed_upload_types = match req.allowed_upload_types {
+        Some(types) => {
+            let types: Vec<String> = types
+                .iter()
+                .map(|t| t.trim().to_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect();
+            if types.is_empty() || types.iter().any(|t| t.contains(',')) {
+                return Err(AppError::BadRequest(
+                    "allowed_upload_types must list extensions or MIME types".to_string(),
+                ));
+            }
+            Some(types.join(","))
+        }
+        None => None,
+    };
+    let allowed_ips = req
+        .allowed_ips
+        .map(|ips| ShareService::normalize_networks("allowed_ips", &ips))
+        .transpose()?;
+    let denied_ips = req
+        .denied_ips
+        .map(|ips| ShareService::normalize_networks("denied_ips", &ips))
+        .transpose()?;
+    let allowed_referrers = req
+        .allowed_referrers
+        .map(|hosts| ShareService::normalize_referrers(&hosts))
+        .transpose()?;
     if req.share_type == "user" && req.shared_with_user_id.is_none() {
         return Err(AppError::BadRequest(
             "shared_with_user_id required for user share".to_string(),
@@ -147,6 +351,14 @@ pub async fn create_share(
             password: req.password,
             permission: req.permission,
             expires_at,
+            max_uploads: req.max_uploads,
+            max_upload_size: req.max_upload_size,
+            allowed_upload_types,
+            max_downloads,
+            max_views: req.max_views,
+            allowed_ips,
+            denied_ips,
+            allowed_referrers,
         },
     )
     .await?;
@@ -165,6 +377,10 @@ pub async fn create_share(
                 "share_type": share.share_type,
                 "permission": share.permission,
                 "has_password": share.password_hash.is_some(),
+                "max_downloads": share.max_downloads,
+                "max_views": share.max_views,
+                "ip_restricted": share.allowed_ips.is_some() || share.denied_ips.is_some(),
+                "referrer_restricted": share.allowed_referrers.is_some(),
                 "expires_at": share.expires_at.to_rfc3339()
             })),
             None,
@@ -178,20 +394,7 @@ pub async fn create_share(
 
     Ok((
         StatusCode::CREATED,
-        Json(ShareResponse {
-            id: share.id,
-            user_file_id: share.user_file_id,
-            share_token: share.share_token,
-            share_type: share.share_type,
-            shared_with_user_id: share.shared_with_user_id,
-            has_password: share.password_hash.is_some(),
-            permission: share.permission,
-            expires_at: share.expires_at,
-            created_at: share.created_at.unwrap_or_else(Utc::now),
-            filename: user_file.as_ref().map(|f| f.filename.clone()),
-            is_folder: user_file.as_ref().map(|f| f.is_folder),
-            parent_id: user_file.as_ref().and_then(|f| f.parent_id.clone()),
-        }),
+        Json(share_response(share, user_file.as_ref())),
     ))
 }
 
@@ -233,23 +436,7 @@ pub async fn list_shares(
 
     let result: Vec<ShareResponse> = shares
         .into_iter()
-        .map(|(share, user_file)| {
-            let uf = user_file.as_ref().or(file_info.as_ref());
-            ShareResponse {
-                id: share.id,
-                user_file_id: share.user_file_id,
-                share_token: share.share_token,
-                share_type: share.share_type,
-                shared_with_user_id: share.shared_with_user_id,
-                has_password: share.password_hash.is_some(),
-                permission: share.permission,
-                expires_at: share.expires_at,
-                created_at: share.created_at.unwrap_or_else(Utc::now),
-                filename: uf.map(|f| f.filename.clone()),
-                is_folder: uf.map(|f| f.is_folder),
-                parent_id: uf.and_then(|f| f.parent_id.clone()),
-            }
-        })
+        .map(|(share, user_file)| share_response(share, user_file.as_ref().or(file_info.as_ref())))
         .collect();
 
     Ok(Json(result))
@@ -325,19 +512,78 @@ pub async fn get_share_logs(
     Ok(Json(result))
 }
 
+/// Password lockouts of a share link
+#[utoipa::path(
+    get,
+    path = "/shares/{id}/lockouts",
+    params(("id" = String, Path, description = "Share ID")),
+    responses(
+        (status = 200, description = "Failed password attempt counters", body = Vec<ShareLockoutResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Share not found")
+    ),
+    security(("jwt" = []))
+)]
+pub async fn list_share_lockouts(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(share_id): Path<String>,
+) -> Result<Json<Vec<ShareLockoutResponse>>, AppError> {
+    let counters = ShareLockoutService::list(&state.db, &share_id, &claims.sub).await?;
+    let now = Utc::now();
+
+    Ok(Json(
+        counters
+            .into_iter()
+            .map(|c| ShareLockoutResponse {
+                client_ip: c.client_ip,
+                failed_attempts: c.failed_attempts,
+                last_attempt: c.last_attempt,
+                locked_until: c.locked_until.filter(|until| *until > now),
+            })
+            .collect(),
+    ))
+}
+
+/// Lift all password lockouts of a share link
+#[utoipa::path(
+    delete,
+    path = "/shares/{id}/lockouts",
+    params(("id" = String, Path, description = "Share ID")),
+    responses(
+        (status = 204, description = "Lockouts cleared"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Share not found")
+    ),
+    security(("jwt" = []))
+)]
+pub async fn reset_share_lockouts(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(share_id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let cleared = ShareLockoutService::reset(&state.db, &share_id, &claims.sub).await?;
+
+    let audit = AuditService::new(state.db.clone());
+    audit
+        .log(
+            AuditEventType::ShareAccess,
+            Some(claims.sub),
+            Some(share_id.clone()),
+            "share_lockout_reset",
+            "success",
+            Some(serde_json::json!({ "share_id": share_id, "cleared": cleared })),
+            None,
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
 // ── Public Endpoints ──────────────────────────────────────────────────
 
 fn extract_ip(headers: &HeaderMap) -> Option<String> {
-    headers
-        .get("x-forwarded-for")
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.split(',').next().unwrap_or("").trim().to_string())
-        .or_else(|| {
-            headers
-                .get("x-real-ip")
-                .and_then(|v| v.to_str().ok())
-                .map(|s| s.to_string())
-        })
+    Some(extract_client_ip(headers)).filter(|ip| ip != "unknown")
 }
 
 fn extract_user_agent(headers: &HeaderMap) -> Option<String> {
@@ -347,6 +593,45 @@ fn extract_user_agent(headers: &HeaderMap) -> Option<String> {
         .map(|s| s.to_string())
 }
 
+/// Refuse clients outside the share's IP ranges or referrer list, logging
+/// the attempt as "access_denied"
+async fn enforce_restrictions(
+    state: &crate::AppState,
+    share: &share_links::Model,
+    headers: &HeaderMap,
+) -> Result<(), AppError> {
+    let ip = extract_ip(headers);
+    let referer = headers.get(header::REFERER).and_then(|v| v.to_str().ok());
+    let Err(e) = ShareService::check_restrictions(share, ip.as_deref(), referer) else {
+        return Ok(());
+    };
+
+    tracing::warn!(
+        "Share {} refused for {}: {}",
+        share.id,
+        ip.as_deref().unwrap_or("unknown client"),
+        e
+    );
+    let ua = extract_user_agent(headers);
+    ShareService::log_access(&state.db, &share.id, None, ip.clone(), ua, "access_denied").await;
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::ShareAccess,
+            None,
+            Some(share.user_file_id.clone()),
+            "share_access_denied",
+            "failure",
+            Some(serde_json::json!({
+                "share_id": share.id,
+                "referer": referer,
+                "reason": e.to_string(),
+            })),
+            ip,
+        )
+        .await;
+    Err(e)
+}
+
 /// Get shared item info (public)
 #[utoipa::path(
     get,
@@ -354,8 +639,9 @@ fn extract_user_agent(headers: &HeaderMap) -> Option<String> {
     params(("token" = String, Path, description = "Share token")),
     responses(
         (status = 200, description = "Share info", body = PublicShareInfoResponse),
+        (status = 403, description = "Client outside the share's IP or referrer restrictions"),
         (status = 404, description = "Share not found"),
-        (status = 410, description = "Share expired")
+        (status = 410, description = "Share expired, or its view or download limit reached")
     )
 )]
 pub async fn get_public_share(
@@ -363,7 +649,8 @@ pub async fn get_public_share(
     Path(token): Path<String>,
     headers: HeaderMap,
 ) -> Result<Json<PublicShareInfoResponse>, AppError> {
-    let share = ShareService::get_share_by_token(&state.db, &token).await?;
+    let mut share = ShareService::get_share_by_token(&state.db, &token).await?;
+    enforce_restrictions(&state, &share, &headers).await?;
 
     let user_file = UserFiles::find_by_id(&share.user_file_id)
         .filter(user_files::Column::DeletedAt.is_null())
@@ -379,6 +666,8 @@ pub async fn get_public_share(
         None
     };
 
+    ShareService::record_view(&state.db, &mut share).await?;
+
     // Log the view access
     let ip = extract_ip(&headers);
     let ua = extract_user_agent(&headers);
@@ -402,7 +691,19 @@ pub async fn get_public_share(
         )
         .await;
 
+    let upload = (share.permission == "upload").then(|| ShareUploadInfo {
+        remaining_uploads: ShareService::remaining(share.max_uploads, share.upload_count),
+        max_upload_size: share
+            .max_upload_size
+            .unwrap_or(state.config.max_file_size as i64)
+            .min(state.config.max_file_size as i64),
+        allowed_upload_types: comma_list(&share.allowed_upload_types),
+    });
+
     Ok(Json(PublicShareInfoResponse {
+        upload,
+        remaining_downloads: ShareService::remaining(share.max_downloads, share.download_count),
+        remaining_views: ShareService::remaining(share.max_views, share.view_count),
         filename: user_file.filename,
         is_folder: user_file.is_folder,
         size: storage_file.as_ref().map(|s| s.size),
@@ -421,8 +722,11 @@ pub async fn get_public_share(
     request_body = VerifySharePasswordRequest,
     responses(
         (status = 200, description = "Password verification result", body = VerifySharePasswordResponse),
+        (status = 400, description = "CAPTCHA missing or wrong after repeated failures"),
+        (status = 403, description = "Client outside the share's IP or referrer restrictions"),
         (status = 404, description = "Share not found"),
-        (status = 410, description = "Share expired")
+        (status = 410, description = "Share expired"),
+        (status = 429, description = "Locked out after too many failed attempts")
     )
 )]
 pub async fn verify_share_password(
@@ -432,12 +736,39 @@ pub async fn verify_share_password(
     Json(req): Json<VerifySharePasswordRequest>,
 ) -> Result<Json<VerifySharePasswordResponse>, AppError> {
     let share = ShareService::get_share_by_token(&state.db, &token).await?;
+    enforce_restrictions(&state, &share, &headers).await?;
 
     let ip = extract_ip(&headers);
     let ua = extract_user_agent(&headers);
 
-    let verified = match &share.password_hash {
+    let captcha_after = state.config.share_password_captcha_after;
+    let (verified, captcha_required) = match &share.password_hash {
         Some(hash) => {
+            let failures = ShareLockoutService::check(&state.db, &share.id, ip.as_deref()).await?;
+            if captcha_after > 0 && failures >= captcha_after {
+                let (Some(captcha_id), Some(captcha_answer)) =
+                    (&req.captcha_id, req.captcha_answer)
+                else {
+                    return Err(AppError::BadRequest(
+                        "CAPTCHA required after repeated failures".to_string(),
+                    ));
+                };
+                validate_captcha(
+                    &state.captchas,
+                    &state.cooldowns,
+                    captcha_id,
+                    captcha_answer,
+                    &extract_client_ip(&headers),
+                )?;
+            }
+
+            ShareLockoutService::reserve_attempt(
+                &state.db,
+                &share,
+                ip.as_deref(),
+                &state.config,
+            )
+            .await?;
             let result = ShareService::verify_password(&req.password, hash)?;
             ShareService::log_access(
                 &state.db,
@@ -452,9 +783,15 @@ pub async fn verify_share_password(
                 },
             )
             .await;
-            result
+            if result {
+                ShareLockoutService::clear(&state.db, &share.id, ip.as_deref(), &state.config)
+                    .await?;
+                (true, false)
+            } else {
+                (false, captcha_after > 0 && failures + 1 >= captcha_after)
+            }
         }
-        None => true, // No password required
+        None => (true, false), // No password required
     };
 
     // Audit log
@@ -478,12 +815,20 @@ pub async fn verify_share_password(
         )
         .await;
 
-    Ok(Json(VerifySharePasswordResponse { verified }))
+    Ok(Json(VerifySharePasswordResponse {
+        verified,
+        captcha_required,
+    }))
 }
 
+/// Response header carrying the token that resumes a counted share download
+pub const RESUME_TOKEN_HEADER: &str = "x-resume-token";
+
 #[derive(Deserialize)]
 pub struct DownloadSharedFileQuery {
     pub file_id: Option<String>,
+    /// `X-Resume-Token` of an earlier download of the same file
+    pub resume: Option<String>,
 }
 
 /// Download shared file (public)
@@ -492,13 +837,16 @@ pub struct DownloadSharedFileQuery {
     path = "/share/{token}/download",
     params(
         ("token" = String, Path, description = "Share token"),
-        ("file_id" = Option<String>, Query, description = "Child file ID for folder shares")
+        ("file_id" = Option<String>, Query, description = "Child file ID for folder shares"),
+        ("resume" = Option<String>, Query, description = "X-Resume-Token of an earlier download, so a Range request for the rest of the file is not counted again")
     ),
     responses(
         (status = 200, description = "File download redirect"),
-        (status = 403, description = "Download not permitted"),
+        (status = 206, description = "Partial content (DOWNLOAD_MODE=stream)"),
+        (status = 304, description = "Not modified (DOWNLOAD_MODE=stream)"),
+        (status = 403, description = "Download not permitted, or client outside the share's IP or referrer restrictions"),
         (status = 404, description = "Share not found"),
-        (status = 410, description = "Share expired")
+        (status = 410, description = "Share expired, or its download limit reached")
     )
 )]
 pub async fn download_shared_file(
@@ -507,7 +855,14 @@ pub async fn download_shared_file(
     Query(query): Query<DownloadSharedFileQuery>,
     headers: HeaderMap,
 ) -> Result<Response, AppError> {
-    let share = ShareService::get_share_by_token(&state.db, &token).await?;
+    // A resume token may finish its download after the limit is hit; whether
+    // the request really resumes it is decided once the file is known
+    let mut share = if query.resume.is_some() {
+        ShareService::get_share_to_resume(&state.db, &token).await?
+    } else {
+        ShareService::get_share_by_token(&state.db, &token).await?
+    };
+    enforce_restrictions(&state, &share, &headers).await?;
 
     // Password-protected shares are verified via /verify endpoint first.
     // The share token itself is the security gate; no password in URLs.
@@ -566,6 +921,25 @@ pub async fn download_shared_file(
         return Err(AppError::Forbidden("File is infected".to_string()));
     }
 
+    // Seeking and download managers fetch the rest of a download they were
+    // counted for; anything that may return the file from the start counts
+    let resuming = query.resume.as_deref().is_some_and(|resume| {
+        ShareService::resume_token_matches(
+            &state.config.jwt_secret,
+            resume,
+            &share.id,
+            &user_file.id,
+        )
+    }) && delivery::resumes_download(&headers, &storage_file, user_file.created_at);
+
+    if resuming {
+        return serve_shared_file(&state, &headers, &share, &user_file, &storage_file).await;
+    }
+
+    ShareService::record_download(&state.db, &mut share).await?;
+    let resume_token =
+        ShareService::issue_resume_token(&state.config.jwt_secret, &share.id, &user_file.id)?;
+
     // Log download
     let ip = extract_ip(&headers);
     let ua = extract_user_agent(&headers);
@@ -589,7 +963,23 @@ pub async fn download_shared_file(
         )
         .await;
 
-    // Generate presigned URL (same approach as files.rs download_file)
+    let mut response =
+        serve_shared_file(&state, &headers, &share, &user_file, &storage_file).await?;
+    response.headers_mut().insert(
+        RESUME_TOKEN_HEADER,
+        header::HeaderValue::from_str(&resume_token).unwrap(),
+    );
+    Ok(response)
+}
+
+/// Send a shared file: inline for `view` shares, as an attachment otherwise
+async fn serve_shared_file(
+    state: &crate::AppState,
+    headers: &HeaderMap,
+    share: &share_links::Model,
+    user_file: &user_files::Model,
+    storage_file: &storage_files::Model,
+) -> Result<Response, AppError> {
     let content_type = storage_file
         .mime_type
         .clone()
@@ -618,37 +1008,93 @@ pub async fn download_shared_file(
         disposition_type, fallback_filename, encoded_filename
     );
 
-    let presigned_url = state
-        .storage
-        .generate_presigned_url_raw(
-            &storage_file.s3_key,
-            43200,
-            &content_type,
-            &content_disposition,
-        )
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to generate presigned URL: {}", e);
-            AppError::Internal("Failed to generate download URL".to_string())
-        })?;
-
-    let url = url::Url::parse(&presigned_url).map_err(|e| {
-        tracing::error!("Failed to parse presigned URL: {}", e);
-        AppError::Internal("Failed to generate download URL".to_string())
-    })?;
+    delivery::serve(
+        state,
+        headers,
+        Download {
+            storage_file,
+            content_type,
+            content_disposition,
+            cache_control: "no-cache",
+            modified: user_file.created_at,
+        },
+    )
+    .await
+}
+
+/// Download a shared folder as a ZIP archive (public)
+#[utoipa::path(
+    get,
+    path = "/share/{token}/zip",
+    params(("token" = String, Path, description = "Share token")),
+    responses(
+        (status = 200, description = "ZIP archive of the shared folder, streamed", content_type = "application/zip"),
+        (status = 400, description = "Not a folder share"),
+        (status = 403, description = "Download not permitted, or client outside the share's IP or referrer restrictions"),
+        (status = 404, description = "Share not found"),
+        (status = 410, description = "Share expired, or its download limit reached")
+    )
+)]
+pub async fn download_shared_folder_zip(
+    State(state): State<crate::AppState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let mut share = ShareService::get_share_by_token(&state.db, &token).await?;
+    enforce_restrictions(&state, &share, &headers).await?;
+
+    if share.permission != "download" {
+        return Err(AppError::Forbidden(
+            "Download is not permitted for this share".to_string(),
+        ));
+    }
+
+    let folder = UserFiles::find_by_id(&share.user_file_id)
+        .filter(user_files::Column::DeletedAt.is_null())
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound(
+            "Shared folder no longer exists".to_string(),
+        ))?;
+
+    if !folder.is_folder {
+        return Err(AppError::BadRequest(
+            "Only folders can be downloaded as ZIP".to_string(),
+        ));
+    }
+
+    ShareService::record_download(&state.db, &mut share).await?;
+
+    // Log download
+    let ip = extract_ip(&headers);
+    let ua = extract_user_agent(&headers);
+    ShareService::log_access(&state.db, &share.id, None, ip.clone(), ua, "download").await;
 
-    let path = url.path();
-    let query = url.query().unwrap_or("");
-    let internal_redirect_uri = format!("/minio_protected{}?{}", path, query);
+    // Audit log
+    let audit = AuditService::new(state.db.clone());
+    audit
+        .log(
+            AuditEventType::ShareAccess,
+            None,
+            Some(folder.id.clone()),
+            "share_download_zip",
+            "success",
+            Some(serde_json::json!({
+                "share_id": share.id,
+                "folder_name": folder.filename,
+                "action": "download"
+            })),
+            ip,
+        )
+        .await;
 
-    Ok(Response::builder()
-        .status(StatusCode::OK)
-        .header("X-Accel-Redirect", internal_redirect_uri)
-        .header(header::CONTENT_TYPE, content_type)
-        .header(header::CONTENT_DISPOSITION, content_disposition)
-        .header(header::CACHE_CONTROL, "no-cache")
-        .body(Body::empty())
-        .unwrap())
+    let archive_name = format!("{}.zip", folder.filename);
+    let entries = ZipStreamService::collect(&state.db, vec![(folder, None)]).await?;
+    Ok(crate::api::handlers::files::download::zip_response(
+        &state,
+        &archive_name,
+        entries,
+    ))
 }
 
 /// List shared folder contents (public)
@@ -658,6 +1104,7 @@ pub async fn download_shared_file(
     params(("token" = String, Path, description = "Share token")),
     responses(
         (status = 200, description = "Folder contents", body = Vec<PublicFileEntry>),
+        (status = 403, description = "Upload shares cannot be listed, or client outside the share's IP or referrer restrictions"),
         (status = 404, description = "Share not found"),
         (status = 410, description = "Share expired")
     )
@@ -668,6 +1115,14 @@ pub async fn list_shared_folder(
     headers: HeaderMap,
 ) -> Result<Json<Vec<PublicFileEntry>>, AppError> {
     let share = ShareService::get_share_by_token(&state.db, &token).await?;
+    enforce_restrictions(&state, &share, &headers).await?;
+
+    // Uploaders must not see what others have sent
+    if share.permission == "upload" {
+        return Err(AppError::Forbidden(
+            "Listing is not permitted for this share".to_string(),
+        ));
+    }
 
     // Validate that it's a folder
     let user_file = UserFiles::find_by_id(&share.user_file_id)
@@ -729,3 +1184,823 @@ pub async fn list_shared_folder(
 
     Ok(Json(result))
 }
+
+/// Upload a file into a shared folder (public)
+#[utoipa::path(
+    post,
+    path = "/share/{token}/upload",
+    params(
+        ("token" = String, Path, description = "Share token"),
+        ("X-Share-Password" = Option<String>, Header, description = "Password of a protected share")
+    ),
+    request_body(content = Multipart, description = "One `file` field"),
+    responses(
+        (status = 200, description = "File received", body = UploadResponse),
+        (status = 400, description = "File rejected by validation or the share's allowed types"),
+        (status = 401, description = "Missing or wrong share password"),
+        (status = 403, description = "Not an upload share, no uploads left, or client outside the share's IP or referrer restrictions"),
+        (status = 404, description = "Share not found"),
+        (status = 410, description = "Share expired"),
+        (status = 413, description = "File larger than the share allows"),
+        (status = 429, description = "Upload rate limit of the share owner exceeded"),
+        (status = 507, description = "Owner's storage quota exceeded")
+    )
+)]
+pub async fn upload_to_share(
+    State(state): State<crate::AppState>,
+    Path(token): Path<String>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<Json<UploadResponse>, AppError> {
+    let ip = extract_ip(&headers);
+    let ua = extract_user_agent(&headers);
+
+    let result: Result<Json<UploadResponse>, AppError> = async {
+        let share = ShareService::get_share_by_token(&state.db, &token).await?;
+        enforce_restrictions(&state, &share, &headers).await?;
+
+        if share.permission != "upload" {
+            return Err(AppError::Forbidden(
+                "Upload is not permitted for this share".to_string(),
+            ));
+        }
+
+        // Refuse a body that cannot fit before reading any of it
+        let content_length = headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<i64>().ok());
+        if let (Some(max), Some(len)) = (share.max_upload_size, content_length)
+            && len > max.saturating_add(MULTIPART_OVERHEAD)
+        {
+            return Err(share_upload_too_large());
+        }
+
+        if let Some(hash) = &share.password_hash {
+            let password = headers
+                .get(SHARE_PASSWORD_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .ok_or(AppError::Unauthorized(
+                    "Share password required".to_string(),
+                ))?;
+            ShareLockoutService::reserve_attempt(
+                &state.db,
+                &share,
+                ip.as_deref(),
+                &state.config,
+            )
+            .await?;
+            if !ShareService::verify_password(password, hash)? {
+                ShareService::log_access(
+                    &state.db,
+                    &share.id,
+                    None,
+                    ip.clone(),
+                    ua.clone(),
+                    "password_attempt",
+                )
+                .await;
+                return Err(AppError::Unauthorized("Invalid share password".to_string()));
+            }
+            ShareLockoutService::clear(&state.db, &share.id, ip.as_deref(), &state.config).await?;
+        }
+
+        let folder = UserFiles::find_by_id(&share.user_file_id)
+            .filter(user_files::Column::DeletedAt.is_null())
+            .filter(user_files::Column::IsFolder.eq(true))
+            .one(&state.db)
+            .await?
+            .ok_or(AppError::NotFound(
+                "Shared folder no longer exists".to_string(),
+            ))?;
+
+        // Anonymous uploads count against the owner's UPLOADS_PER_HOUR
+        RateLimitService::hit(
+            &state.db,
+            &share.created_by,
+            UPLOAD_BUCKET,
+            state.config.uploads_per_hour,
+            chrono::Duration::hours(1),
+        )
+        .await?;
+
+        ShareService::reserve_upload(&state.db, &share).await?;
+        let uploaded = receive_share_upload(&state, &share, &folder, &mut multipart).await;
+        if uploaded.is_err() {
+            ShareService::release_upload(&state.db, &share.id).await;
+        }
+        let (file_id, filename) = uploaded?;
+
+        ShareService::log_access(&state.db, &share.id, None, ip.clone(), ua.clone(), "upload")
+            .await;
+
+        let audit = AuditService::new(state.db.clone());
+        audit
+            .log(
+                AuditEventType::ShareAccess,
+                None,
+                Some(file_id.clone()),
+                "share_upload",
+                "success",
+                Some(serde_json::json!({
+                    "share_id": share.id,
+                    "folder_id": folder.id,
+                    "filename": filename,
+                    "action": "upload"
+                })),
+                ip.clone(),
+            )
+            .await;
+
+        Ok(Json(UploadResponse {
+            file_id,
+            filename,
+            expires_at: None,
+        }))
+    }
+    .await;
+
+    if let Err(e) = &result
+        && !matches!(e, AppError::PayloadTooLarge(_))
+    {
+        // Drain the rest of the body so the client sees the error rather than a reset
+        tracing::warn!("Share upload failed: {}. Consuming remaining stream...", e);
+        while let Ok(Some(mut field)) = multipart.next_field().await {
+            while let Ok(Some(_)) = field.chunk().await {}
+        }
+    }
+    result
+}
+
+/// Room a multipart body needs besides its file: boundaries and part headers
+const MULTIPART_OVERHEAD: i64 = 16 * 1024;
+
+fn share_upload_too_large() -> AppError {
+    AppError::PayloadTooLarge("File is larger than this share accepts".to_string())
+}
+
+/// Stage the multipart `file` field and store it in the share's folder, owned by the share creator
+async fn receive_share_upload(
+    state: &crate::AppState,
+    share: &share_links::Model,
+    folder: &user_files::Model,
+    multipart: &mut Multipart,
+) -> Result<(String, String), AppError> {
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        if e.to_string().contains("length limit exceeded") {
+            AppError::PayloadTooLarge("Request body exceeds the maximum allowed limit".to_string())
+        } else {
+            AppError::BadRequest(e.to_string())
+        }
+    })? {
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let original_filename = field.file_name().unwrap_or("unnamed").to_string();
+        let content_type = field.content_type().map(|s| s.to_string());
+
+        let rules = state.file_service.validation_rules().await?;
+        let filename = sanitize_filename(&original_filename, &rules)
+            .map_err(|e| AppError::BadRequest(e.to_string()))?;
+        ShareService::check_upload_type(share, &filename, content_type.as_deref())?;
+
+        // Cut the stream off as soon as it passes the share's limit
+        let exceeded = Arc::new(AtomicBool::new(false));
+        let mut received: i64 = 0;
+        let limited = field.map_err(std::io::Error::other).and_then({
+            let (max, exceeded) = (share.max_upload_size, exceeded.clone());
+            move |chunk| {
+                received += chunk.len() as i64;
+                let too_large = max.is_some_and(|max| received > max);
+                exceeded.store(too_large, Ordering::Relaxed);
+                std::future::ready(if too_large {
+                    Err(std::io::Error::other("File is larger than this share accepts"))
+                } else {
+                    Ok(chunk)
+                })
+            }
+        });
+        let destination = Destination::folder(folder);
+        let staged = state
+            .file_service
+            .upload_to_staging(
+                &destination.drive,
+                &filename,
+                content_type.as_deref(),
+                StreamReader::new(limited),
+            )
+            .await
+            .map_err(|e| {
+                if exceeded.load(Ordering::Relaxed) {
+                    share_upload_too_large()
+                } else {
+                    e
+                }
+            })?;
+
+        let filename = ShareService::available_name(&state.db, &destination, &filename).await?;
+        let (file_id, _) = state
+            .file_service
+            .process_upload(
+                staged,
+                filename.clone(),
+                share.created_by.clone(),
+                destination,
+                None,
+                None,
+            )
+            .await?;
+        return Ok((file_id, filename));
+    }
+
+    Err(AppError::BadRequest("No file provided".to_string()))
+}
+
+// ── Shared With Me ────────────────────────────────────────────────────
+
+/// List shares addressed to the current user
+#[utoipa::path(
+    get,
+    path = "/shares/incoming",
+    responses(
+        (status = 200, description = "Shares addressed to the caller", body = Vec<IncomingShareResponse>),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("jwt" = []))
+)]
+pub async fn list_incoming_shares(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<IncomingShareResponse>>, AppError> {
+    let shares = ShareService::list_incoming_shares(&state.db, &claims.sub).await?;
+
+    let mut result = Vec::with_capacity(shares.len());
+    for (share, user_file, owner) in shares {
+        let storage_file = match &user_file.storage_file_id {
+            Some(sid) => StorageFiles::find_by_id(sid).one(&state.db).await?,
+            None => None,
+        };
+        result.push(IncomingShareResponse {
+            id: share.id,
+            user_file_id: share.user_file_id,
+            permission: share.permission,
+            expires_at: share.expires_at,
+            created_at: share.created_at.unwrap_or_else(Utc::now),
+            owner_id: share.created_by,
+            owner_username: owner.map(|o| o.username),
+            filename: user_file.filename,
+            is_folder: user_file.is_folder,
+            size: storage_file.as_ref().map(|s| s.size),
+            mime_type: storage_file.and_then(|s| s.mime_type),
+        });
+    }
+
+    Ok(Json(result))
+}
+
+/// List a folder inside a share addressed to the current user
+#[utoipa::path(
+    get,
+    path = "/shares/incoming/{id}/list",
+    params(
+        ("id" = String, Path, description = "Share ID"),
+        ("folder_id" = Option<String>, Query, description = "Subfolder of the shared folder")
+    ),
+    responses(
+        (status = 200, description = "Folder contents", body = Vec<PublicFileEntry>),
+        (status = 400, description = "Not a folder"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Folder does not belong to this share"),
+        (status = 404, description = "Share not found"),
+        (status = 410, description = "Share expired")
+    ),
+    security(("jwt" = []))
+)]
+pub async fn list_incoming_folder(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(share_id): Path<String>,
+    Query(query): Query<IncomingFolderQuery>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<PublicFileEntry>>, AppError> {
+    let share = ShareService::get_incoming_share(&state.db, &share_id, &claims.sub).await?;
+    let folder =
+        ShareService::resolve_shared_item(&state.db, &share, query.folder_id.as_deref()).await?;
+
+    if !folder.is_folder {
+        return Err(AppError::BadRequest(
+            "Only folders can be listed".to_string(),
+        ));
+    }
+
+    let children = UserFiles::find()
+        .filter(user_files::Column::ParentId.eq(&folder.id))
+        .filter(user_files::Column::DeletedAt.is_null())
+        .find_also_related(StorageFiles)
+        .all(&state.db)
+        .await?;
+
+    ShareService::log_access(
+        &state.db,
+        &share.id,
+        Some(claims.sub),
+        extract_ip(&headers),
+        extract_user_agent(&headers),
+        "view",
+    )
+    .await;
+
+    Ok(Json(
+        children
+            .into_iter()
+            .map(|(child, storage)| PublicFileEntry {
+                id: child.id,
+                filename: child.filename,
+                is_folder: child.is_folder,
+                size: storage.as_ref().map(|s| s.size),
+                mime_type: storage.and_then(|s| s.mime_type.clone()),
+                created_at: child.created_at.unwrap_or_else(Utc::now),
+            })
+            .collect(),
+    ))
+}
+
+/// Download or preview a file from a share addressed to the current user
+#[utoipa::path(
+    get,
+    path = "/shares/incoming/{id}/download",
+    params(
+        ("id" = String, Path, description = "Share ID"),
+        ("file_id" = Option<String>, Query, description = "File inside a shared folder")
+    ),
+    responses(
+        (status = 200, description = "File download; inline for `view` shares"),
+        (status = 206, description = "Partial content (DOWNLOAD_MODE=stream)"),
+        (status = 304, description = "Not modified (DOWNLOAD_MODE=stream)"),
+        (status = 400, description = "Cannot download a folder"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "File does not belong to this share, or is infected"),
+        (status = 404, description = "Share not found"),
+        (status = 410, description = "Share expired")
+    ),
+    security(("jwt" = []))
+)]
+pub async fn download_incoming_file(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(share_id): Path<String>,
+    Query(query): Query<DownloadSharedFileQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let share = ShareService::get_incoming_share(&state.db, &share_id, &claims.sub).await?;
+    let (user_file, storage_file) =
+        shared_file_content(&state, &share, query.file_id.as_deref()).await?;
+
+    let ip = extract_ip(&headers);
+    ShareService::log_access(
+        &state.db,
+        &share.id,
+        Some(claims.sub.clone()),
+        ip.clone(),
+        extract_user_agent(&headers),
+        "download",
+    )
+    .await;
+
+    let audit = AuditService::new(state.db.clone());
+    audit
+        .log(
+            AuditEventType::ShareAccess,
+            Some(claims.sub),
+            Some(user_file.id.clone()),
+            "share_download",
+            "success",
+            Some(serde_json::json!({
+                "share_id": share.id,
+                "filename": user_file.filename,
+                "action": "download"
+            })),
+            ip,
+        )
+        .await;
+
+    serve_shared_file(&state, &headers, &share, &user_file, &storage_file).await
+}
+
+/// Add a file from a share addressed to the current user to their own drive
+///
+/// The copy links to the same stored content, so it takes no extra space on
+/// the server, but it counts against the recipient's quota.
+#[utoipa::path(
+    post,
+    path = "/shares/incoming/{id}/add",
+    params(("id" = String, Path, description = "Share ID")),
+    request_body = AddSharedItemRequest,
+    responses(
+        (status = 200, description = "File added to the caller's drive", body = UploadResponse),
+        (status = 400, description = "Folders cannot be added, or bad destination"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Share only permits viewing"),
+        (status = 404, description = "Share not found"),
+        (status = 410, description = "Share expired"),
+        (status = 507, description = "Storage quota exceeded")
+    ),
+    security(("jwt" = []))
+)]
+pub async fn add_incoming_to_drive(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(share_id): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<AddSharedItemRequest>,
+) -> Result<Json<UploadResponse>, AppError> {
+    let share = ShareService::get_incoming_share(&state.db, &share_id, &claims.sub).await?;
+    if share.permission != "download" && share.permission != "edit" {
+        return Err(AppError::Forbidden(
+            "Adding to your drive requires download permission".to_string(),
+        ));
+    }
+
+    let (user_file, storage_file) =
+        shared_file_content(&state, &share, req.file_id.as_deref()).await?;
+
+    let destination = DriveAccess::destination(
+        &state.db,
+        &claims.sub,
+        req.parent_id.as_deref(),
+        req.team_id.as_deref(),
+    )
+    .await
+    .map_err(|e| match e {
+        AppError::NotFound(_) if req.parent_id.is_some() => {
+            AppError::BadRequest("Destination folder not found".to_string())
+        }
+        e => e,
+    })?;
+
+    let filename =
+        ShareService::available_name(&state.db, &destination, &user_file.filename).await?;
+    let (file_id, _) = state
+        .file_service
+        .link_storage_file(
+            storage_file,
+            filename.clone(),
+            claims.sub.clone(),
+            destination,
+            None,
+        )
+        .await?;
+
+    let ip = extract_ip(&headers);
+    ShareService::log_access(
+        &state.db,
+        &share.id,
+        Some(claims.sub.clone()),
+        ip.clone(),
+        extract_user_agent(&headers),
+        "add_to_drive",
+    )
+    .await;
+
+    let audit = AuditService::new(state.db.clone());
+    audit
+        .log(
+            AuditEventType::ShareAccess,
+            Some(claims.sub),
+            Some(file_id.clone()),
+            "share_add_to_drive",
+            "success",
+            Some(serde_json::json!({
+                "share_id": share.id,
+                "source_file_id": user_file.id,
+                "filename": filename,
+            })),
+            ip,
+        )
+        .await;
+
+    Ok(Json(UploadResponse {
+        file_id,
+        filename,
+        expires_at: None,
+    }))
+}
+
+// ── Collaborative Editing ─────────────────────────────────────────────
+//
+// Recipients of an `edit` share act on the owner's files: everything they
+// create belongs to, and is charged against, the share owner, while the
+// audit trail records who actually made the change.
+
+/// Record a change made through an edit share in the access log and the audit trail
+async fn record_share_edit(
+    state: &crate::AppState,
+    share: &share_links::Model,
+    actor: &str,
+    item_id: &str,
+    action: &str,
+    details: serde_json::Value,
+    headers: &HeaderMap,
+) {
+    let ip = extract_ip(headers);
+    ShareService::log_access(
+        &state.db,
+        &share.id,
+        Some(actor.to_string()),
+        ip.clone(),
+        extract_user_agent(headers),
+        action,
+    )
+    .await;
+
+    let mut details = details;
+    if let Some(map) = details.as_object_mut() {
+        map.insert("share_id".to_string(), share.id.clone().into());
+        map.insert("owner_id".to_string(), share.created_by.clone().into());
+    }
+    let audit = AuditService::new(state.db.clone());
+    audit
+        .log(
+            AuditEventType::ShareEdit,
+            Some(actor.to_string()),
+            Some(item_id.to_string()),
+            &format!("share_{}", action),
+            "success",
+            Some(details),
+            ip,
+        )
+        .await;
+}
+
+/// Upload a file into a folder shared with the current user for editing
+///
+/// The file belongs to the share owner and counts against their quota. A
+/// name already taken in the folder gets a numbered suffix.
+#[utoipa::path(
+    post,
+    path = "/shares/incoming/{id}/upload",
+    params(
+        ("id" = String, Path, description = "Share ID"),
+        ("folder_id" = Option<String>, Query, description = "Subfolder of the shared folder")
+    ),
+    request_body(content = String, description = "File to upload", content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "File uploaded", body = UploadResponse),
+        (status = 400, description = "File rejected by validation, or not a folder"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Share does not permit changes, or folder is outside it"),
+        (status = 404, description = "Share not found"),
+        (status = 410, description = "Share expired"),
+        (status = 507, description = "Owner's storage quota exceeded")
+    ),
+    security(("jwt" = []))
+)]
+pub async fn upload_to_incoming_share(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(share_id): Path<String>,
+    Query(query): Query<IncomingFolderQuery>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<Json<UploadResponse>, AppError> {
+    let result: Result<Json<UploadResponse>, AppError> = async {
+        let share = ShareService::get_incoming_share(&state.db, &share_id, &claims.sub).await?;
+        ShareService::require_edit(&share)?;
+        let folder =
+            ShareService::resolve_shared_folder(&state.db, &share, query.folder_id.as_deref())
+                .await?;
+
+        let (file_id, filename) =
+            receive_share_upload(&state, &share, &folder, &mut multipart).await?;
+
+        record_share_edit(
+            &state,
+            &share,
+            &claims.sub,
+            &file_id,
+            "upload",
+            serde_json::json!({ "folder_id": folder.id, "filename": filename }),
+            &headers,
+        )
+        .await;
+
+        Ok(Json(UploadResponse {
+            file_id,
+            filename,
+            expires_at: None,
+        }))
+    }
+    .await;
+
+    if let Err(e) = &result {
+        tracing::warn!("Share upload failed: {}. Consuming remaining stream...", e);
+        while let Ok(Some(mut field)) = multipart.next_field().await {
+            while let Ok(Some(_)) = field.chunk().await {}
+        }
+    }
+    result
+}
+
+/// Create a folder inside a folder shared with the current user for editing
+#[utoipa::path(
+    post,
+    path = "/shares/incoming/{id}/folders",
+    params(("id" = String, Path, description = "Share ID")),
+    request_body = CreateFolderRequest,
+    responses(
+        (status = 200, description = "Folder created", body = FileMetadataResponse),
+        (status = 400, description = "Invalid name, or parent is not a folder"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Share does not permit changes, or parent is outside it"),
+        (status = 404, description = "Share not found"),
+        (status = 410, description = "Share expired")
+    ),
+    security(("jwt" = []))
+)]
+pub async fn create_incoming_folder(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(share_id): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<CreateFolderRequest>,
+) -> Result<Json<FileMetadataResponse>, AppError> {
+    let share = ShareService::get_incoming_share(&state.db, &share_id, &claims.sub).await?;
+    ShareService::require_edit(&share)?;
+    let parent =
+        ShareService::resolve_shared_folder(&state.db, &share, req.parent_id.as_deref()).await?;
+
+    let folder = insert_folder(
+        &state,
+        &share.created_by,
+        CreateFolderRequest {
+            name: req.name,
+            parent_id: Some(parent.id.clone()),
+            team_id: None,
+        },
+    )
+    .await?;
+
+    record_share_edit(
+        &state,
+        &share,
+        &claims.sub,
+        &folder.id,
+        "create_folder",
+        serde_json::json!({ "parent_id": parent.id, "filename": folder.filename }),
+        &headers,
+    )
+    .await;
+
+    Ok(folder)
+}
+
+/// Rename or move an item inside a folder shared with the current user for editing
+///
+/// Items can only be moved between folders of the same share.
+#[utoipa::path(
+    put,
+    path = "/shares/incoming/{id}/items/{item_id}",
+    params(
+        ("id" = String, Path, description = "Share ID"),
+        ("item_id" = String, Path, description = "File or folder inside the share")
+    ),
+    request_body = RenameRequest,
+    responses(
+        (status = 200, description = "Item updated", body = FileMetadataResponse),
+        (status = 400, description = "Invalid name or destination"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Share does not permit changes, or item or destination is outside it"),
+        (status = 404, description = "Share or item not found"),
+        (status = 410, description = "Share expired")
+    ),
+    security(("jwt" = []))
+)]
+pub async fn update_incoming_item(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((share_id, item_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    Json(req): Json<RenameRequest>,
+) -> Result<Json<FileMetadataResponse>, AppError> {
+    let share = ShareService::get_incoming_share(&state.db, &share_id, &claims.sub).await?;
+    ShareService::require_edit(&share)?;
+    let item = ShareService::resolve_shared_child(&state.db, &share, &item_id).await?;
+
+    if let Some(parent_id) = &req.parent_id {
+        if parent_id == "root" || parent_id == "0" {
+            return Err(AppError::Forbidden(
+                "Items cannot be moved out of the share".to_string(),
+            ));
+        }
+        ShareService::resolve_shared_folder(&state.db, &share, Some(parent_id)).await?;
+    }
+
+    let details = serde_json::json!({
+        "from_name": item.filename,
+        "from_parent_id": item.parent_id,
+        "name": req.name,
+        "parent_id": req.parent_id,
+    });
+    let updated = rename_user_item(state.clone(), &share.created_by, item.id, req).await?;
+
+    let action = if details["parent_id"].is_null() {
+        "rename"
+    } else {
+        "move"
+    };
+    record_share_edit(
+        &state,
+        &share,
+        &claims.sub,
+        &updated.id,
+        action,
+        details,
+        &headers,
+    )
+    .await;
+
+    Ok(updated)
+}
+
+/// Delete an item inside a folder shared with the current user for editing
+///
+/// The item goes to the owner's trash.
+#[utoipa::path(
+    delete,
+    path = "/shares/incoming/{id}/items/{item_id}",
+    params(
+        ("id" = String, Path, description = "Share ID"),
+        ("item_id" = String, Path, description = "File or folder inside the share")
+    ),
+    responses(
+        (status = 204, description = "Item moved to the owner's trash"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Share does not permit changes, or item is outside it"),
+        (status = 404, description = "Share or item not found"),
+        (status = 410, description = "Share expired")
+    ),
+    security(("jwt" = []))
+)]
+pub async fn delete_incoming_item(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((share_id, item_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<StatusCode, AppError> {
+    let share = ShareService::get_incoming_share(&state.db, &share_id, &claims.sub).await?;
+    ShareService::require_edit(&share)?;
+    let item = ShareService::resolve_shared_child(&state.db, &share, &item_id).await?;
+
+    state
+        .file_service
+        .delete_item(&share.created_by, &item.id)
+        .await?;
+
+    record_share_edit(
+        &state,
+        &share,
+        &claims.sub,
+        &item.id,
+        "delete",
+        serde_json::json!({ "filename": item.filename, "is_folder": item.is_folder }),
+        &headers,
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// A clean, downloadable file inside `share`
+async fn shared_file_content(
+    state: &crate::AppState,
+    share: &share_links::Model,
+    file_id: Option<&str>,
+) -> Result<(user_files::Model, storage_files::Model), AppError> {
+    let user_file = ShareService::resolve_shared_item(&state.db, share, file_id).await?;
+
+    if user_file.is_folder {
+        return Err(AppError::BadRequest("Cannot download a folder".to_string()));
+    }
+    if user_file
+        .expires_at
+        .is_some_and(|expires| Utc::now() > expires)
+    {
+        return Err(AppError::Gone("File has expired".to_string()));
+    }
+
+    let storage_file_id = user_file
+        .storage_file_id
+        .clone()
+        .ok_or(AppError::NotFound("Storage file missing".to_string()))?;
+    let storage_file = StorageFiles::find_by_id(&storage_file_id)
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound("Storage file not found".to_string()))?;
+
+    if matches!(storage_file.scan_status.as_deref(), Some("infected")) {
+        return Err(AppError::Forbidden("File is infected".to_string()));
+    }
+
+    Ok((user_file, storage_file))
+}
@@ -0,0 +1,99 @@
+use crate::api::error::AppError;
+use crate::services::local_storage::{parse_byte_range, verify_object_url};
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::Response,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use tokio_util::io::ReaderStream;
+
+#[derive(Deserialize)]
+pub struct SignedObjectQuery {
+    pub expires: i64,
+    #[serde(default)]
+    pub content_type: String,
+    #[serde(default)]
+    pub content_disposition: String,
+    pub signature: String,
+}
+
+/// Serve an object referenced by a signed URL from the local storage backend.
+///
+/// This is the local-filesystem counterpart of an S3 presigned GET: nginx's
+/// X-Accel-Redirect location (or the client directly) fetches the URL produced
+/// by `LocalFsStorageService::generate_presigned_url_raw`.
+pub async fn serve_signed_object(
+    State(state): State<crate::AppState>,
+    Path(key): Path<String>,
+    Query(query): Query<SignedObjectQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    if !verify_object_url(
+        &state.config.jwt_secret,
+        &key,
+        query.expires,
+        &query.content_type,
+        &query.content_disposition,
+        &query.signature,
+    ) {
+        return Err(AppError::Forbidden("Invalid signature".to_string()));
+    }
+
+    if Utc::now().timestamp() > query.expires {
+        return Err(AppError::Forbidden("URL has expired".to_string()));
+    }
+
+    let metadata = state
+        .storage
+        .get_object_metadata(&key)
+        .await
+        .map_err(|_| AppError::NotFound("Object not found".to_string()))?;
+    let size = metadata.size as u64;
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .map(|r| (r, parse_byte_range(r, size)));
+
+    let (status, object) = match range {
+        Some((_, None)) => {
+            return Ok(Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", size))
+                .body(Body::empty())
+                .unwrap());
+        }
+        Some((raw, Some(_))) => (
+            StatusCode::PARTIAL_CONTENT,
+            state.storage.get_object_range(&key, raw).await,
+        ),
+        None => (StatusCode::OK, state.storage.get_object_stream(&key).await),
+    };
+
+    let object = object.map_err(|e| {
+        tracing::error!("Failed to read local object {}: {}", key, e);
+        AppError::Internal("Failed to read object".to_string())
+    })?;
+
+    let mut builder = Response::builder()
+        .status(status)
+        .header(header::ACCEPT_RANGES, "bytes");
+    if let Some(len) = object.content_length() {
+        builder = builder.header(header::CONTENT_LENGTH, len);
+    }
+    if let Some(content_range) = object.content_range() {
+        builder = builder.header(header::CONTENT_RANGE, content_range);
+    }
+    if !query.content_type.is_empty() {
+        builder = builder.header(header::CONTENT_TYPE, &query.content_type);
+    }
+    if !query.content_disposition.is_empty() {
+        builder = builder.header(header::CONTENT_DISPOSITION, &query.content_disposition);
+    }
+
+    let stream = ReaderStream::new(object.body.into_async_read());
+    Ok(builder.body(Body::from_stream(stream)).unwrap())
+}
@@ -0,0 +1,445 @@
+use crate::api::error::AppError;
+use crate::api::handlers::admin::SetQuotaRequest;
+use crate::api::handlers::users::QuotaResponse;
+use crate::entities::{team_members, users};
+use crate::services::audit::{AuditEventType, AuditService};
+use crate::services::drives::Drive;
+use crate::services::quota::QuotaService;
+use crate::services::teams::{Membership, TeamRole, TeamService};
+use crate::utils::auth::Claims;
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+// Team files are reached through the regular file routes with a `team_id`;
+// these routes manage the teams themselves.
+
+#[derive(Deserialize, ToSchema)]
+pub struct TeamNameRequest {
+    pub name: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AddTeamMemberRequest {
+    pub username: String,
+    pub role: TeamRole,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateTeamMemberRequest {
+    pub role: TeamRole,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TeamResponse {
+    pub id: String,
+    pub name: String,
+    /// The caller's role in the team
+    pub role: TeamRole,
+    /// The team's own quota in bytes; `null` when the default applies
+    pub storage_quota: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Membership> for TeamResponse {
+    fn from(membership: Membership) -> Self {
+        Self {
+            id: membership.team.id,
+            name: membership.team.name,
+            role: membership.role,
+            storage_quota: membership.team.storage_quota,
+            created_at: membership.team.created_at,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TeamMemberResponse {
+    pub user_id: String,
+    pub username: String,
+    pub name: Option<String>,
+    pub role: TeamRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+impl From<(team_members::Model, users::Model)> for TeamMemberResponse {
+    fn from((member, user): (team_members::Model, users::Model)) -> Self {
+        Self {
+            role: TeamRole::parse(&member.role).unwrap_or(TeamRole::Viewer),
+            user_id: member.user_id,
+            username: user.username,
+            name: user.name,
+            joined_at: member.created_at,
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/teams",
+    request_body = TeamNameRequest,
+    responses(
+        (status = 201, description = "Team created with you as its owner", body = TeamResponse),
+        (status = 400, description = "Invalid name"),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("jwt" = [])),
+    tag = "teams"
+)]
+pub async fn create_team(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<TeamNameRequest>,
+) -> Result<(StatusCode, Json<TeamResponse>), AppError> {
+    let team = TeamService::create(&state.db, &claims.sub, &req.name).await?;
+
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::TeamCreate,
+            Some(claims.sub),
+            Some(team.id.clone()),
+            "team_create",
+            "success",
+            Some(serde_json::json!({ "name": team.name })),
+            None,
+        )
+        .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(
+            Membership {
+                team,
+                role: TeamRole::Owner,
+            }
+            .into(),
+        ),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/teams",
+    responses(
+        (status = 200, description = "Teams you belong to, by name", body = Vec<TeamResponse>),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("jwt" = [])),
+    tag = "teams"
+)]
+pub async fn list_teams(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<TeamResponse>>, AppError> {
+    let teams = TeamService::list_for(&state.db, &claims.sub).await?;
+    Ok(Json(teams.into_iter().map(Into::into).collect()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/teams/{id}",
+    params(("id" = String, Path, description = "Team ID")),
+    responses(
+        (status = 200, description = "The team", body = TeamResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Team not found")
+    ),
+    security(("jwt" = [])),
+    tag = "teams"
+)]
+pub async fn get_team(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<TeamResponse>, AppError> {
+    let membership = TeamService::get(&state.db, &claims.sub, &id).await?;
+    Ok(Json(membership.into()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/teams/{id}",
+    request_body = TeamNameRequest,
+    params(("id" = String, Path, description = "Team ID")),
+    responses(
+        (status = 200, description = "Team renamed", body = TeamResponse),
+        (status = 400, description = "Invalid name"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a team owner"),
+        (status = 404, description = "Team not found")
+    ),
+    security(("jwt" = [])),
+    tag = "teams"
+)]
+pub async fn rename_team(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(req): Json<TeamNameRequest>,
+) -> Result<Json<TeamResponse>, AppError> {
+    let team = TeamService::rename(&state.db, &claims.sub, &id, &req.name).await?;
+
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::TeamUpdate,
+            Some(claims.sub),
+            Some(id),
+            "team_rename",
+            "success",
+            Some(serde_json::json!({ "name": team.name })),
+            None,
+        )
+        .await;
+
+    Ok(Json(
+        Membership {
+            team,
+            role: TeamRole::Owner,
+        }
+        .into(),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/teams/{id}",
+    params(("id" = String, Path, description = "Team ID")),
+    responses(
+        (status = 204, description = "Team and all its files deleted"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a team owner"),
+        (status = 404, description = "Team not found")
+    ),
+    security(("jwt" = [])),
+    tag = "teams"
+)]
+pub async fn delete_team(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let purged = TeamService::delete(&state.db, state.storage.as_ref(), &claims.sub, &id).await?;
+
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::TeamDelete,
+            Some(claims.sub),
+            Some(id),
+            "team_delete",
+            "success",
+            Some(serde_json::json!({ "items_deleted": purged })),
+            None,
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/teams/{id}/usage",
+    params(("id" = String, Path, description = "Team ID")),
+    responses(
+        (status = 200, description = "Team drive quota and usage", body = QuotaResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Team not found")
+    ),
+    security(("jwt" = [])),
+    tag = "teams"
+)]
+pub async fn get_team_usage(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<QuotaResponse>, AppError> {
+    let membership = TeamService::get(&state.db, &claims.sub, &id).await?;
+    let usage =
+        QuotaService::drive_usage(&state.db, &state.config, &Drive::Team(membership.team.id))
+            .await?;
+    Ok(Json(usage.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/teams/{id}/members",
+    params(("id" = String, Path, description = "Team ID")),
+    responses(
+        (status = 200, description = "Members, oldest first", body = Vec<TeamMemberResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Team not found")
+    ),
+    security(("jwt" = [])),
+    tag = "teams"
+)]
+pub async fn list_team_members(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<TeamMemberResponse>>, AppError> {
+    TeamService::get(&state.db, &claims.sub, &id).await?;
+    let members = TeamService::members(&state.db, &id).await?;
+    Ok(Json(members.into_iter().map(Into::into).collect()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/teams/{id}/members",
+    request_body = AddTeamMemberRequest,
+    params(("id" = String, Path, description = "Team ID")),
+    responses(
+        (status = 201, description = "Member added", body = TeamMemberResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a team owner"),
+        (status = 404, description = "Team or user not found"),
+        (status = 409, description = "Already a member")
+    ),
+    security(("jwt" = [])),
+    tag = "teams"
+)]
+pub async fn add_team_member(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(req): Json<AddTeamMemberRequest>,
+) -> Result<(StatusCode, Json<TeamMemberResponse>), AppError> {
+    let (member, user) =
+        TeamService::add_member(&state.db, &claims.sub, &id, &req.username, req.role).await?;
+
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::TeamMemberChange,
+            Some(claims.sub),
+            Some(id),
+            "team_member_add",
+            "success",
+            Some(serde_json::json!({ "member_id": user.id, "role": member.role })),
+            None,
+        )
+        .await;
+
+    Ok((StatusCode::CREATED, Json((member, user).into())))
+}
+
+#[utoipa::path(
+    put,
+    path = "/teams/{id}/members/{user_id}",
+    request_body = UpdateTeamMemberRequest,
+    params(
+        ("id" = String, Path, description = "Team ID"),
+        ("user_id" = String, Path, description = "Member's user ID")
+    ),
+    responses(
+        (status = 204, description = "Role changed"),
+        (status = 400, description = "The team would be left without an owner"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not a team owner"),
+        (status = 404, description = "Team or member not found")
+    ),
+    security(("jwt" = [])),
+    tag = "teams"
+)]
+pub async fn update_team_member(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((id, user_id)): Path<(String, String)>,
+    Json(req): Json<UpdateTeamMemberRequest>,
+) -> Result<StatusCode, AppError> {
+    TeamService::set_role(&state.db, &claims.sub, &id, &user_id, req.role).await?;
+
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::TeamMemberChange,
+            Some(claims.sub),
+            Some(id),
+            "team_member_role",
+            "success",
+            Some(serde_json::json!({ "member_id": user_id, "role": req.role.as_str() })),
+            None,
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/teams/{id}/members/{user_id}",
+    params(
+        ("id" = String, Path, description = "Team ID"),
+        ("user_id" = String, Path, description = "Member's user ID; your own to leave the team")
+    ),
+    responses(
+        (status = 204, description = "Member removed"),
+        (status = 400, description = "The team would be left without an owner"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Only owners can remove other members"),
+        (status = 404, description = "Team or member not found")
+    ),
+    security(("jwt" = [])),
+    tag = "teams"
+)]
+pub async fn remove_team_member(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((id, user_id)): Path<(String, String)>,
+) -> Result<StatusCode, AppError> {
+    TeamService::remove_member(&state.db, &claims.sub, &id, &user_id).await?;
+
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::TeamMemberChange,
+            Some(claims.sub),
+            Some(id),
+            "team_member_remove",
+            "success",
+            Some(serde_json::json!({ "member_id": user_id })),
+            None,
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/teams/{id}/quota",
+    request_body = SetQuotaRequest,
+    params(("id" = String, Path, description = "Team ID")),
+    responses(
+        (status = 200, description = "Quota updated", body = QuotaResponse),
+        (status = 400, description = "Invalid quota"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "Team not found")
+    ),
+    security(("jwt" = [])),
+    tag = "admin"
+)]
+pub async fn set_team_quota(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(req): Json<SetQuotaRequest>,
+) -> Result<Json<QuotaResponse>, AppError> {
+    let team = TeamService::set_quota(&state.db, &id, req.storage_quota).await?;
+
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::QuotaUpdate,
+            Some(claims.sub),
+            Some(id),
+            "set_team_quota",
+            "success",
+            Some(serde_json::json!({ "storage_quota": req.storage_quota })),
+            None,
+        )
+        .await;
+
+    let usage = QuotaService::drive_usage(&state.db, &state.config, &Drive::Team(team.id)).await?;
+    Ok(Json(usage.into()))
+}
@@ -0,0 +1,324 @@
+use crate::api::error::AppError;
+use crate::api::middleware::tus::TUS_VERSION;
+use crate::services::upload_service::tus::{
+    APPENDING, CHECKSUM_ALGORITHMS, TusAppend, UploadChecksum,
+};
+use crate::utils::auth::Claims;
+use axum::{
+    Extension,
+    body::Body,
+    extract::{Path, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, FixedOffset};
+
+const OFFSET_OCTET_STREAM: &str = "application/offset+octet-stream";
+const TUS_EXTENSIONS: &str = "creation,creation-with-upload,termination,checksum,expiration";
+
+fn upload_expires(expires_at: DateTime<FixedOffset>) -> String {
+    httpdate::fmt_http_date(expires_at.into())
+}
+
+fn header_i64(headers: &HeaderMap, name: &str) -> Result<Option<i64>, AppError> {
+    headers
+        .get(name)
+        .map(|v| {
+            v.to_str()
+                .ok()
+                .and_then(|s| s.trim().parse::<i64>().ok())
+                .filter(|n| *n >= 0)
+                .ok_or_else(|| AppError::BadRequest(format!("Invalid {}", name)))
+        })
+        .transpose()
+}
+
+fn is_offset_octet_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.eq_ignore_ascii_case(OFFSET_OCTET_STREAM))
+}
+
+fn checksum(headers: &HeaderMap) -> Result<Option<UploadChecksum>, AppError> {
+    headers
+        .get("upload-checksum")
+        .map(|v| {
+            v.to_str()
+                .map_err(|_| AppError::BadRequest("Malformed Upload-Checksum".to_string()))
+                .and_then(UploadChecksum::parse)
+        })
+        .transpose()
+}
+
+/// Headers describing the upload after a successful append
+fn append_headers(append: &TusAppend) -> Vec<(HeaderName, HeaderValue)> {
+    let mut headers = vec![(
+        HeaderName::from_static("upload-offset"),
+        HeaderValue::from(append.offset),
+    )];
+    match &append.file {
+        Some(file) => {
+            if let Ok(id) = HeaderValue::from_str(&file.id) {
+                headers.push((HeaderName::from_static("x-file-id"), id));
+            }
+        }
+        None => {
+            if let Ok(expires) = HeaderValue::from_str(&upload_expires(append.expires_at)) {
+                headers.push((HeaderName::from_static("upload-expires"), expires));
+            }
+        }
+    }
+    headers
+}
+
+fn with_headers(
+    status: StatusCode,
+    headers: impl IntoIterator<Item = (HeaderName, HeaderValue)>,
+) -> Response {
+    let mut response = status.into_response();
+    response.headers_mut().extend(headers);
+    response
+}
+
+#[utoipa::path(
+    options,
+    path = "/files/tus",
+    responses(
+        (status = 204, description = "Supported tus version, extensions and limits")
+    ),
+    tag = "files"
+)]
+pub async fn tus_options(State(state): State<crate::AppState>) -> Response {
+    with_headers(
+        StatusCode::NO_CONTENT,
+        [
+            (
+                HeaderName::from_static("tus-version"),
+                HeaderValue::from_static(TUS_VERSION),
+            ),
+            (
+                HeaderName::from_static("tus-extension"),
+                HeaderValue::from_static(TUS_EXTENSIONS),
+            ),
+            (
+                HeaderName::from_static("tus-max-size"),
+                HeaderValue::from(state.config.max_file_size),
+            ),
+            (
+                HeaderName::from_static("tus-checksum-algorithm"),
+                HeaderValue::from_static(CHECKSUM_ALGORITHMS),
+            ),
+        ],
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/files/tus",
+    params(
+        ("Tus-Resumable" = String, Header, description = "Must be 1.0.0"),
+        ("Upload-Length" = i64, Header, description = "Total upload size in bytes"),
+        ("Upload-Metadata" = Option<String>, Header, description = "Comma-separated `key base64value` pairs; `filename`, `filetype`, `parent_id` and `team_id` are used")
+    ),
+    request_body(content = Vec<u8>, description = "Optional first bytes (creation-with-upload)", content_type = "application/offset+octet-stream"),
+    responses(
+        (status = 201, description = "Upload created; see Location"),
+        (status = 400, description = "Missing or invalid Upload-Length or Upload-Metadata"),
+        (status = 401, description = "Unauthorized"),
+        (status = 412, description = "Unsupported Tus-Resumable"),
+        (status = 413, description = "Upload-Length exceeds Tus-Max-Size"),
+        (status = 429, description = "Upload rate limit exceeded; see Retry-After"),
+        (status = 460, description = "Upload-Checksum mismatch"),
+        (status = 507, description = "Storage quota exceeded")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "files"
+)]
+pub async fn tus_create(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, AppError> {
+    if headers.contains_key("upload-defer-length") {
+        return Err(AppError::BadRequest(
+            "Upload-Defer-Length is not supported".to_string(),
+        ));
+    }
+    let length = header_i64(&headers, "upload-length")?
+        .ok_or_else(|| AppError::BadRequest("Upload-Length is required".to_string()))?;
+    let metadata = headers
+        .get("upload-metadata")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let session = state
+        .upload_service
+        .tus_create(&claims.sub, length, metadata)
+        .await?;
+
+    // Relative, so it resolves against whatever prefix a reverse proxy
+    // strips (`/api/files/tus` becomes `/api/files/tus/{id}`)
+    let mut response_headers = vec![(
+        header::LOCATION,
+        HeaderValue::from_str(&format!("tus/{}", session.id))
+            .map_err(|e| AppError::Internal(e.to_string()))?,
+    )];
+    let has_body = header_i64(&headers, "content-length")?.is_none_or(|len| len > 0);
+    if is_offset_octet_stream(&headers) && has_body {
+        let append = state
+            .upload_service
+            .tus_append(
+                &claims.sub,
+                &session.id,
+                0,
+                checksum(&headers)?,
+                body.into_data_stream(),
+            )
+            .await?;
+        response_headers.extend(append_headers(&append));
+    } else if let Ok(expires) = HeaderValue::from_str(&upload_expires(session.expires_at)) {
+        response_headers.push((HeaderName::from_static("upload-expires"), expires));
+    }
+
+    Ok(with_headers(StatusCode::CREATED, response_headers))
+}
+
+#[utoipa::path(
+    head,
+    path = "/files/tus/{id}",
+    params(
+        ("id" = String, Path, description = "Upload ID"),
+        ("Tus-Resumable" = String, Header, description = "Must be 1.0.0")
+    ),
+    responses(
+        (status = 200, description = "Current Upload-Offset and Upload-Length"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Upload not found"),
+        (status = 410, description = "Upload expired")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "files"
+)]
+pub async fn tus_head(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let session = state.upload_service.tus_status(&claims.sub, &id).await?;
+
+    let mut headers = vec![
+        (
+            HeaderName::from_static("upload-offset"),
+            HeaderValue::from(session.upload_offset),
+        ),
+        (
+            HeaderName::from_static("upload-length"),
+            HeaderValue::from(session.total_size),
+        ),
+        (header::CACHE_CONTROL, HeaderValue::from_static("no-store")),
+    ];
+    if let Some(metadata) = session
+        .upload_metadata
+        .as_deref()
+        .and_then(|m| HeaderValue::from_str(m).ok())
+    {
+        headers.push((HeaderName::from_static("upload-metadata"), metadata));
+    }
+    if matches!(session.status.as_str(), "pending" | APPENDING)
+        && let Ok(expires) = HeaderValue::from_str(&upload_expires(session.expires_at))
+    {
+        headers.push((HeaderName::from_static("upload-expires"), expires));
+    }
+
+    Ok(with_headers(StatusCode::OK, headers))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/files/tus/{id}",
+    params(
+        ("id" = String, Path, description = "Upload ID"),
+        ("Tus-Resumable" = String, Header, description = "Must be 1.0.0"),
+        ("Upload-Offset" = i64, Header, description = "Offset the body starts at"),
+        ("Upload-Checksum" = Option<String>, Header, description = "`sha1` or `sha256` digest of the body, base64-encoded")
+    ),
+    request_body(content = Vec<u8>, description = "Upload bytes", content_type = "application/offset+octet-stream"),
+    responses(
+        (status = 204, description = "Bytes appended; X-File-Id is set once the upload completes"),
+        (status = 400, description = "Missing Upload-Offset"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Upload not found"),
+        (status = 409, description = "Upload-Offset does not match the current offset"),
+        (status = 410, description = "Upload expired"),
+        (status = 413, description = "Body exceeds Upload-Length"),
+        (status = 415, description = "Content-Type is not application/offset+octet-stream"),
+        (status = 460, description = "Upload-Checksum mismatch"),
+        (status = 507, description = "Storage quota exceeded")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "files"
+)]
+pub async fn tus_patch(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, AppError> {
+    if !is_offset_octet_stream(&headers) {
+        return Ok(StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response());
+    }
+    let offset = header_i64(&headers, "upload-offset")?
+        .ok_or_else(|| AppError::BadRequest("Upload-Offset is required".to_string()))?;
+
+    let append = state
+        .upload_service
+        .tus_append(
+            &claims.sub,
+            &id,
+            offset,
+            checksum(&headers)?,
+            body.into_data_stream(),
+        )
+        .await?;
+
+    Ok(with_headers(
+        StatusCode::NO_CONTENT,
+        append_headers(&append),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/files/tus/{id}",
+    params(
+        ("id" = String, Path, description = "Upload ID"),
+        ("Tus-Resumable" = String, Header, description = "Must be 1.0.0")
+    ),
+    responses(
+        (status = 204, description = "Upload terminated"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Upload not found"),
+        (status = 409, description = "Upload already finished")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "files"
+)]
+pub async fn tus_terminate(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    state.upload_service.tus_terminate(&claims.sub, &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
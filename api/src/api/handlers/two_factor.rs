@@ -0,0 +1,386 @@
+use crate::api::error::AppError;
+use crate::api::handlers::auth::{AuthResponse, check_enabled, check_password};
+use crate::entities::{prelude::*, users};
+use crate::services::audit::{AuditEventType, AuditService};
+use crate::services::sessions::{ClientInfo, SessionService};
+use crate::services::two_factor::{ChallengeError, SecondFactor, TwoFactorService};
+use crate::utils::auth::Claims;
+use axum::{
+    Extension, Json,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+};
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Deserialize, ToSchema)]
+pub struct TwoFactorLoginRequest {
+    /// Token returned by `POST /login`
+    pub challenge_token: String,
+    /// Code from the authenticator app, or an unused recovery code
+    pub code: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct DisableTwoFactorRequest {
+    pub password: String,
+    /// Code from the authenticator app, or an unused recovery code
+    pub code: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TwoFactorStatusResponse {
+    pub enabled: bool,
+    /// Enrollment started but not yet confirmed
+    pub pending: bool,
+    pub recovery_codes_remaining: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TwoFactorEnrollResponse {
+    /// Base32 secret for manual entry
+    pub secret: String,
+    /// `otpauth://` URI to show as a QR code
+    pub otpauth_uri: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RecoveryCodesResponse {
+    /// Single-use codes; they are not shown again
+    pub recovery_codes: Vec<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/login/2fa",
+    request_body = TwoFactorLoginRequest,
+    responses(
+        (status = 200, description = "Login successful", body = AuthResponse),
+        (status = 401, description = "Invalid code, or challenge unknown or expired"),
+        (status = 403, description = "Account is disabled"),
+        (status = 429, description = "Too many invalid codes; retry after the lockout")
+    )
+)]
+pub async fn login_2fa(
+    State(state): State<crate::AppState>,
+    headers: HeaderMap,
+    Json(req): Json<TwoFactorLoginRequest>,
+) -> Result<Json<AuthResponse>, AppError> {
+    let audit = AuditService::new(state.db.clone());
+    let client = ClientInfo::from_headers(&headers);
+
+    let (user, factor) = match TwoFactorService::complete_challenge(
+        &state.db,
+        &req.challenge_token,
+        &req.code,
+    )
+    .await
+    {
+        Ok(done) => done,
+        Err(ChallengeError::Invalid) => {
+            return Err(AppError::Unauthorized(
+                "Two-factor challenge is invalid or has expired".to_string(),
+            ));
+        }
+        Err(ChallengeError::WrongCode(user_id)) => {
+            audit
+                .log(
+                    AuditEventType::UserLogin,
+                    Some(user_id),
+                    None,
+                    "login_2fa",
+                    "failure",
+                    Some(serde_json::json!({ "two_factor": "invalid_code" })),
+                    client.ip_address,
+                )
+                .await;
+            return Err(AppError::Unauthorized(
+                "Invalid two-factor code".to_string(),
+            ));
+        }
+        Err(ChallengeError::LockedOut {
+            user_id,
+            retry_after_secs,
+        }) => {
+            audit
+                .log(
+                    AuditEventType::UserLogin,
+                    Some(user_id),
+                    None,
+                    "login_2fa",
+                    "failure",
+                    Some(serde_json::json!({ "two_factor": "locked_out" })),
+                    client.ip_address,
+                )
+                .await;
+            return Err(AppError::TooManyRequests {
+                message: "Too many invalid two-factor codes, try again later".to_string(),
+                retry_after_secs,
+            });
+        }
+        Err(ChallengeError::Other(e)) => return Err(e),
+    };
+    check_enabled(&user)?;
+
+    let mut details = serde_json::json!({ "two_factor": factor.as_str() });
+    if factor == SecondFactor::RecoveryCode {
+        details["recovery_codes_remaining"] =
+            TwoFactorService::recovery_codes_remaining(&state.db, &user.id)
+                .await?
+                .into();
+    }
+    audit
+        .log(
+            AuditEventType::UserLogin,
+            Some(user.id.clone()),
+            None,
+            "login_2fa",
+            "success",
+            Some(details),
+            client.ip_address.clone(),
+        )
+        .await;
+
+    let tokens = SessionService::start(&state.db, &state.config, &user.id, client).await?;
+    Ok(Json(tokens.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/2fa",
+    responses(
+        (status = 200, description = "Two-factor authentication status", body = TwoFactorStatusResponse),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("jwt" = [])),
+    tag = "auth"
+)]
+pub async fn get_two_factor_status(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<TwoFactorStatusResponse>, AppError> {
+    let user = current_user(&state, &claims).await?;
+    let enabled = TwoFactorService::is_enabled(&user);
+    Ok(Json(TwoFactorStatusResponse {
+        enabled,
+        pending: !enabled && user.totp_secret.is_some(),
+        recovery_codes_remaining: TwoFactorService::recovery_codes_remaining(&state.db, &user.id)
+            .await?,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/2fa/enroll",
+    responses(
+        (status = 200, description = "Secret to add to an authenticator app", body = TwoFactorEnrollResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 409, description = "Two-factor authentication is already enabled")
+    ),
+    security(("jwt" = [])),
+    tag = "auth"
+)]
+pub async fn enroll_two_factor(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+) -> Result<Json<TwoFactorEnrollResponse>, AppError> {
+    let user = current_user(&state, &claims).await?;
+    let (secret, otpauth_uri) = TwoFactorService::enroll(&state.db, user).await?;
+    audit_change(
+        &state,
+        AuditEventType::TwoFactorEnroll,
+        claims.sub,
+        "2fa_enroll",
+        "success",
+        &headers,
+    )
+    .await;
+    Ok(Json(TwoFactorEnrollResponse {
+        secret,
+        otpauth_uri,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/2fa/confirm",
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor authentication enabled", body = RecoveryCodesResponse),
+        (status = 400, description = "Invalid code or no enrollment in progress"),
+        (status = 401, description = "Unauthorized"),
+        (status = 409, description = "Two-factor authentication is already enabled")
+    ),
+    security(("jwt" = [])),
+    tag = "auth"
+)]
+pub async fn confirm_two_factor(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(req): Json<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    let user = current_user(&state, &claims).await?;
+    let result = TwoFactorService::confirm(&state.db, user, &req.code).await;
+    audit_change(
+        &state,
+        AuditEventType::TwoFactorEnable,
+        claims.sub,
+        "2fa_confirm",
+        if result.is_ok() { "success" } else { "failure" },
+        &headers,
+    )
+    .await;
+    Ok(Json(RecoveryCodesResponse {
+        recovery_codes: result?,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/2fa/recovery-codes",
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 200, description = "New recovery codes; the old ones stop working", body = RecoveryCodesResponse),
+        (status = 400, description = "Two-factor authentication is not enabled"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Invalid code"),
+        (status = 429, description = "Too many invalid two-factor codes")
+    ),
+    security(("jwt" = [])),
+    tag = "auth"
+)]
+pub async fn regenerate_recovery_codes(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(req): Json<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    let user = require_enabled(current_user(&state, &claims).await?)?;
+    let verified = TwoFactorService::verify(&state.db, &user, &req.code).await;
+    if !matches!(verified, Ok(Some(_))) {
+        audit_change(
+            &state,
+            AuditEventType::TwoFactorEnable,
+            claims.sub,
+            "2fa_recovery_codes",
+            "failure",
+            &headers,
+        )
+        .await;
+        verified?;
+        return Err(AppError::Forbidden("Invalid two-factor code".to_string()));
+    }
+
+    let recovery_codes = TwoFactorService::regenerate_recovery_codes(&state.db, &user.id).await?;
+    audit_change(
+        &state,
+        AuditEventType::TwoFactorEnable,
+        claims.sub,
+        "2fa_recovery_codes",
+        "success",
+        &headers,
+    )
+    .await;
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/2fa/disable",
+    request_body = DisableTwoFactorRequest,
+    responses(
+        (status = 204, description = "Two-factor authentication disabled"),
+        (status = 400, description = "Two-factor authentication is not enabled"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Invalid password or code"),
+        (status = 429, description = "Too many invalid two-factor codes")
+    ),
+    security(("jwt" = [])),
+    tag = "auth"
+)]
+pub async fn disable_two_factor(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Json(req): Json<DisableTwoFactorRequest>,
+) -> Result<StatusCode, AppError> {
+    let user = require_enabled(current_user(&state, &claims).await?)?;
+
+    // Re-authenticate: a stolen session alone must not turn 2FA off
+    let verified = match check_password(&user, &req.password) {
+        Ok(()) => TwoFactorService::verify(&state.db, &user, &req.code).await,
+        Err(_) => Ok(None),
+    };
+    if !matches!(verified, Ok(Some(_))) {
+        audit_change(
+            &state,
+            AuditEventType::TwoFactorDisable,
+            claims.sub,
+            "2fa_disable",
+            "failure",
+            &headers,
+        )
+        .await;
+        verified?;
+        return Err(AppError::Forbidden(
+            "Invalid password or two-factor code".to_string(),
+        ));
+    }
+
+    TwoFactorService::disable(&state.db, user).await?;
+    audit_change(
+        &state,
+        AuditEventType::TwoFactorDisable,
+        claims.sub,
+        "2fa_disable",
+        "success",
+        &headers,
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn current_user(state: &crate::AppState, claims: &Claims) -> Result<users::Model, AppError> {
+    Users::find_by_id(&claims.sub)
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::Unauthorized("User not found".to_string()))
+}
+
+fn require_enabled(user: users::Model) -> Result<users::Model, AppError> {
+    if !TwoFactorService::is_enabled(&user) {
+        return Err(AppError::BadRequest(
+            "Two-factor authentication is not enabled".to_string(),
+        ));
+    }
+    Ok(user)
+}
+
+async fn audit_change(
+    state: &crate::AppState,
+    event: AuditEventType,
+    user_id: String,
+    action: &str,
+    status: &str,
+    headers: &HeaderMap,
+) {
+    AuditService::new(state.db.clone())
+        .log(
+            event,
+            Some(user_id),
+            None,
+            action,
+            status,
+            None,
+            ClientInfo::from_headers(headers).ip_address,
+        )
+        .await;
+}
@@ -10,13 +10,23 @@ use axum::{
 };
 use uuid::Uuid;
 
+/// Keep quota errors raised by the upload service; anything else is the client's fault
+fn upload_error(e: anyhow::Error) -> AppError {
+    match e.downcast::<AppError>() {
+        Ok(app_error) => app_error,
+        Err(e) => AppError::BadRequest(e.to_string()),
+    }
+}
+
 #[utoipa::path(
     post,
     path = "/files/upload/init",
     request_body = InitUploadRequest,
     responses(
         (status = 200, description = "Upload initiated", body = InitUploadResponse),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 429, description = "Upload rate limit exceeded; see Retry-After"),
+        (status = 507, description = "Storage quota exceeded")
     ),
     security(
         ("jwt" = [])
@@ -32,7 +42,7 @@ pub async fn init_upload_handler(
         .upload_service
         .init_upload(claims.sub, req)
         .await
-        .map_err(|e: anyhow::Error| AppError::BadRequest(e.to_string()))?;
+        .map_err(upload_error)?;
     Ok(Json(res))
 }
 
@@ -111,7 +121,8 @@ pub async fn upload_chunk_handler(
     ),
     responses(
         (status = 200, description = "Upload completed", body = FileResponse),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 507, description = "Storage quota exceeded")
     ),
     security(
         ("jwt" = [])
@@ -131,7 +142,7 @@ pub async fn complete_upload_handler(
         .upload_service
         .complete_upload(claims.sub, session_id.to_string(), req)
         .await
-        .map_err(|e: anyhow::Error| AppError::BadRequest(e.to_string()))?;
+        .map_err(upload_error)?;
 
     Ok(Json(res))
 }
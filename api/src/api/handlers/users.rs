@@ -1,5 +1,6 @@
 use crate::api::error::AppError;
 use crate::entities::{prelude::*, user_file_facts, users};
+use crate::services::quota::{QuotaService, QuotaUsage};
 use crate::utils::auth::Claims;
 use argon2::{
     Argon2,
@@ -24,6 +25,8 @@ pub struct UserProfileResponse {
     pub email: Option<String>,
     pub name: Option<String>,
     pub avatar_url: Option<String>,
+    /// Whether `email` has been confirmed through a verification link
+    pub email_verified: bool,
 }
 
 #[derive(Deserialize, ToSchema, Validate)]
@@ -41,6 +44,25 @@ pub struct AvatarResponse {
     pub url: String,
 }
 
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct QuotaResponse {
+    /// Bytes counted against the quota, including trash and old versions
+    pub used: i64,
+    /// Quota in bytes; absent when unlimited
+    pub limit: Option<i64>,
+    pub remaining: Option<i64>,
+}
+
+impl From<QuotaUsage> for QuotaResponse {
+    fn from(usage: QuotaUsage) -> Self {
+        Self {
+            used: usage.used,
+            limit: usage.limit,
+            remaining: usage.remaining(),
+        }
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/users/me",
@@ -76,6 +98,7 @@ pub async fn get_profile(
         email: user.email,
         name: user.name,
         avatar_url,
+        email_verified: user.email_verified_at.is_some(),
     }))
 }
 
@@ -107,9 +130,14 @@ pub async fn update_profile(
         .map_err(|e| AppError::Internal(e.to_string()))?
         .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
 
+    let previous_email = user.email.clone();
     let mut active: users::ActiveModel = user.into();
 
     if let Some(email) = payload.email {
+        // A new address has to be verified again
+        if previous_email.as_deref() != Some(email.as_str()) {
+            active.email_verified_at = Set(None);
+        }
         active.email = Set(Some(email));
     }
     if let Some(name) = payload.name {
@@ -146,6 +174,7 @@ pub async fn update_profile(
         email: updated.email,
         name: updated.name,
         avatar_url,
+        email_verified: updated.email_verified_at.is_some(),
     }))
 }
 
@@ -278,3 +307,22 @@ pub async fn get_user_facts(
         Ok(Json(facts.unwrap()))
     }
 }
+
+#[utoipa::path(
+    get,
+    path = "/users/me/quota",
+    responses(
+        (status = 200, description = "Storage quota and current usage", body = QuotaResponse),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_quota(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<QuotaResponse>, AppError> {
+    let usage = QuotaService::usage(&state.db, &state.config, &claims.sub).await?;
+    Ok(Json(usage.into()))
+}
@@ -0,0 +1,629 @@
+use crate::api::error::AppError;
+use crate::entities::{allowed_mimes, blocked_extensions, magic_signatures};
+use crate::services::audit::{AuditEventType, AuditService};
+use crate::services::validation_rules::ValidationRuleService;
+use crate::utils::auth::Claims;
+use crate::utils::validation::{detect_mime, dry_run_upload};
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use utoipa::ToSchema;
+
+// Upload validation rules, under `/admin/validation`. Every change drops the
+// cached rules of this instance, so the next upload is checked against it.
+
+/// Largest sample accepted by the dry-run tester, in bytes
+const MAX_SAMPLE_BYTES: usize = 64 * 1024;
+
+#[derive(Serialize, ToSchema)]
+pub struct AllowedMimeResponse {
+    pub id: i32,
+    pub mime_type: String,
+    pub category: String,
+    pub description: Option<String>,
+}
+
+impl From<allowed_mimes::Model> for AllowedMimeResponse {
+    fn from(m: allowed_mimes::Model) -> Self {
+        Self {
+            id: m.id,
+            mime_type: m.mime_type,
+            category: m.category,
+            description: m.description,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AllowedMimeRequest {
+    /// e.g. `image/webp`
+    pub mime_type: String,
+    /// Grouping shown to admins, e.g. `image` or `document`
+    pub category: String,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct MagicSignatureResponse {
+    pub id: i32,
+    /// Leading bytes of the file, in upper-case hex
+    pub signature: String,
+    pub mime_type: String,
+    pub description: Option<String>,
+}
+
+impl From<magic_signatures::Model> for MagicSignatureResponse {
+    fn from(s: magic_signatures::Model) -> Self {
+        Self {
+            id: s.id,
+            signature: hex::encode_upper(&s.signature),
+            mime_type: s.mime_type,
+            description: s.description,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MagicSignatureRequest {
+    /// Leading bytes in hex, e.g. `25504446`; spaces are ignored
+    pub signature: String,
+    pub mime_type: String,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BlockedExtensionResponse {
+    pub id: i32,
+    /// Without the leading dot
+    pub extension: String,
+    pub description: Option<String>,
+}
+
+impl From<blocked_extensions::Model> for BlockedExtensionResponse {
+    fn from(e: blocked_extensions::Model) -> Self {
+        Self {
+            id: e.id,
+            extension: e.extension,
+            description: e.description,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct BlockedExtensionRequest {
+    /// e.g. `exe` or `.exe`
+    pub extension: String,
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ValidationTestRequest {
+    pub filename: String,
+    /// Content type the client would send; defaults to `application/octet-stream`
+    pub content_type: Option<String>,
+    /// Base64 of the first bytes of the file, at most 64 KiB
+    pub sample: String,
+    /// Full file size in bytes; defaults to the sample length
+    pub size: Option<u64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ValidationCheckResult {
+    /// `size`, `filename`, `mime_type`, `magic_bytes` or `content`
+    pub check: String,
+    pub passed: bool,
+    /// Error code an upload would fail with, e.g. `BLOCKED_EXTENSION`
+    pub code: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ValidationTestResponse {
+    /// Whether an upload of this file would be accepted
+    pub passed: bool,
+    /// Name the file would be stored under; `null` when the name is rejected
+    pub sanitized_filename: Option<String>,
+    /// MIME type of the first matching magic signature
+    pub detected_mime: Option<String>,
+    pub checks: Vec<ValidationCheckResult>,
+}
+
+/// Drop the cached rules and record the change
+async fn rules_changed(
+    state: &crate::AppState,
+    claims: Claims,
+    resource: String,
+    action: &str,
+    details: serde_json::Value,
+) {
+    state.file_service.invalidate_validation_rules();
+    AuditService::new(state.db.clone())
+        .log(
+            AuditEventType::ValidationRuleChange,
+            Some(claims.sub),
+            Some(resource),
+            action,
+            "success",
+            Some(details),
+            None,
+        )
+        .await;
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/validation/mimes",
+    responses(
+        (status = 200, description = "MIME types uploads may have", body = Vec<AllowedMimeResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin or auditor")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn list_mimes(
+    State(state): State<crate::AppState>,
+) -> Result<Json<Vec<AllowedMimeResponse>>, AppError> {
+    let mimes = ValidationRuleService::list_mimes(&state.db).await?;
+    Ok(Json(mimes.into_iter().map(Into::into).collect()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/validation/mimes",
+    request_body = AllowedMimeRequest,
+    responses(
+        (status = 201, description = "MIME type allowed", body = AllowedMimeResponse),
+        (status = 400, description = "Invalid MIME type or category"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 409, description = "MIME type already allowed")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn create_mime(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<AllowedMimeRequest>,
+) -> Result<(StatusCode, Json<AllowedMimeResponse>), AppError> {
+    let mime = ValidationRuleService::create_mime(
+        &state.db,
+        &req.mime_type,
+        &req.category,
+        req.description,
+    )
+    .await?;
+    rules_changed(
+        &state,
+        claims,
+        format!("mime:{}", mime.id),
+        "allow_mime",
+        json!({ "mime_type": mime.mime_type }),
+    )
+    .await;
+    Ok((StatusCode::CREATED, Json(mime.into())))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/validation/mimes/{id}",
+    request_body = AllowedMimeRequest,
+    params(
+        ("id" = i32, Path, description = "Allowed MIME type ID")
+    ),
+    responses(
+        (status = 200, description = "MIME type updated", body = AllowedMimeResponse),
+        (status = 400, description = "Invalid MIME type or category"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "MIME type not found"),
+        (status = 409, description = "MIME type already allowed")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn update_mime(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Json(req): Json<AllowedMimeRequest>,
+) -> Result<Json<AllowedMimeResponse>, AppError> {
+    let mime = ValidationRuleService::update_mime(
+        &state.db,
+        id,
+        &req.mime_type,
+        &req.category,
+        req.description,
+    )
+    .await?;
+    rules_changed(
+        &state,
+        claims,
+        format!("mime:{}", id),
+        "update_mime",
+        json!({ "mime_type": mime.mime_type }),
+    )
+    .await;
+    Ok(Json(mime.into()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/validation/mimes/{id}",
+    params(
+        ("id" = i32, Path, description = "Allowed MIME type ID")
+    ),
+    responses(
+        (status = 204, description = "MIME type no longer allowed"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "MIME type not found")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn delete_mime(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    ValidationRuleService::delete_mime(&state.db, id).await?;
+    rules_changed(
+        &state,
+        claims,
+        format!("mime:{}", id),
+        "delete_mime",
+        json!({}),
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/validation/signatures",
+    responses(
+        (status = 200, description = "Magic signatures used to check file contents", body = Vec<MagicSignatureResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin or auditor")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn list_signatures(
+    State(state): State<crate::AppState>,
+) -> Result<Json<Vec<MagicSignatureResponse>>, AppError> {
+    let signatures = ValidationRuleService::list_signatures(&state.db).await?;
+    Ok(Json(signatures.into_iter().map(Into::into).collect()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/validation/signatures",
+    request_body = MagicSignatureRequest,
+    responses(
+        (status = 201, description = "Signature added", body = MagicSignatureResponse),
+        (status = 400, description = "Invalid signature or MIME type"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 409, description = "Signature already registered for this MIME type")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn create_signature(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<MagicSignatureRequest>,
+) -> Result<(StatusCode, Json<MagicSignatureResponse>), AppError> {
+    let signature = ValidationRuleService::create_signature(
+        &state.db,
+        &req.signature,
+        &req.mime_type,
+        req.description,
+    )
+    .await?;
+    rules_changed(
+        &state,
+        claims,
+        format!("signature:{}", signature.id),
+        "add_signature",
+        json!({
+            "signature": hex::encode_upper(&signature.signature),
+            "mime_type": signature.mime_type,
+        }),
+    )
+    .await;
+    Ok((StatusCode::CREATED, Json(signature.into())))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/validation/signatures/{id}",
+    request_body = MagicSignatureRequest,
+    params(
+        ("id" = i32, Path, description = "Magic signature ID")
+    ),
+    responses(
+        (status = 200, description = "Signature updated", body = MagicSignatureResponse),
+        (status = 400, description = "Invalid signature or MIME type"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "Signature not found"),
+        (status = 409, description = "Signature already registered for this MIME type")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn update_signature(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Json(req): Json<MagicSignatureRequest>,
+) -> Result<Json<MagicSignatureResponse>, AppError> {
+    let signature = ValidationRuleService::update_signature(
+        &state.db,
+        id,
+        &req.signature,
+        &req.mime_type,
+        req.description,
+    )
+    .await?;
+    rules_changed(
+        &state,
+        claims,
+        format!("signature:{}", id),
+        "update_signature",
+        json!({
+            "signature": hex::encode_upper(&signature.signature),
+            "mime_type": signature.mime_type,
+        }),
+    )
+    .await;
+    Ok(Json(signature.into()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/validation/signatures/{id}",
+    params(
+        ("id" = i32, Path, description = "Magic signature ID")
+    ),
+    responses(
+        (status = 204, description = "Signature removed"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "Signature not found")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn delete_signature(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    ValidationRuleService::delete_signature(&state.db, id).await?;
+    rules_changed(
+        &state,
+        claims,
+        format!("signature:{}", id),
+        "delete_signature",
+        json!({}),
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/validation/extensions",
+    responses(
+        (status = 200, description = "File extensions uploads may not have", body = Vec<BlockedExtensionResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin or auditor")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn list_extensions(
+    State(state): State<crate::AppState>,
+) -> Result<Json<Vec<BlockedExtensionResponse>>, AppError> {
+    let extensions = ValidationRuleService::list_extensions(&state.db).await?;
+    Ok(Json(extensions.into_iter().map(Into::into).collect()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/validation/extensions",
+    request_body = BlockedExtensionRequest,
+    responses(
+        (status = 201, description = "Extension blocked", body = BlockedExtensionResponse),
+        (status = 400, description = "Invalid extension"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 409, description = "Extension already blocked")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn create_extension(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(req): Json<BlockedExtensionRequest>,
+) -> Result<(StatusCode, Json<BlockedExtensionResponse>), AppError> {
+    let extension =
+        ValidationRuleService::create_extension(&state.db, &req.extension, req.description).await?;
+    rules_changed(
+        &state,
+        claims,
+        format!("extension:{}", extension.id),
+        "block_extension",
+        json!({ "extension": extension.extension }),
+    )
+    .await;
+    Ok((StatusCode::CREATED, Json(extension.into())))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/validation/extensions/{id}",
+    request_body = BlockedExtensionRequest,
+    params(
+        ("id" = i32, Path, description = "Blocked extension ID")
+    ),
+    responses(
+        (status = 200, description = "Extension updated", body = BlockedExtensionResponse),
+        (status = 400, description = "Invalid extension"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "Extension not found"),
+        (status = 409, description = "Extension already blocked")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn update_extension(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+    Json(req): Json<BlockedExtensionRequest>,
+) -> Result<Json<BlockedExtensionResponse>, AppError> {
+    let extension =
+        ValidationRuleService::update_extension(&state.db, id, &req.extension, req.description)
+            .await?;
+    rules_changed(
+        &state,
+        claims,
+        format!("extension:{}", id),
+        "update_extension",
+        json!({ "extension": extension.extension }),
+    )
+    .await;
+    Ok(Json(extension.into()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/validation/extensions/{id}",
+    params(
+        ("id" = i32, Path, description = "Blocked extension ID")
+    ),
+    responses(
+        (status = 204, description = "Extension unblocked"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "Extension not found")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn delete_extension(
+    State(state): State<crate::AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    ValidationRuleService::delete_extension(&state.db, id).await?;
+    rules_changed(
+        &state,
+        claims,
+        format!("extension:{}", id),
+        "unblock_extension",
+        json!({}),
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Check a file against the current rules without uploading it
+///
+/// Every check runs, so one response shows all the reasons a file would be
+/// refused. Virus scanning and quota are not part of the test.
+#[utoipa::path(
+    post,
+    path = "/admin/validation/test",
+    request_body = ValidationTestRequest,
+    responses(
+        (status = 200, description = "Outcome of each upload check", body = ValidationTestResponse),
+        (status = 400, description = "Sample is not base64 or is too large"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin")
+    ),
+    security(
+        ("jwt" = [])
+    ),
+    tag = "admin"
+)]
+pub async fn test_validation(
+    State(state): State<crate::AppState>,
+    Json(req): Json<ValidationTestRequest>,
+) -> Result<Json<ValidationTestResponse>, AppError> {
+    let sample = base64::engine::general_purpose::STANDARD
+        .decode(req.sample.trim())
+        .map_err(|_| AppError::BadRequest("sample must be base64".to_string()))?;
+    if sample.len() > MAX_SAMPLE_BYTES {
+        return Err(AppError::BadRequest(format!(
+            "sample must be at most {} bytes",
+            MAX_SAMPLE_BYTES
+        )));
+    }
+    let size = req.size.map_or(sample.len(), |size| {
+        usize::try_from(size).unwrap_or(usize::MAX)
+    });
+
+    let rules = state.file_service.validation_rules().await?;
+    let (sanitized_filename, outcomes) = dry_run_upload(
+        &req.filename,
+        req.content_type.as_deref(),
+        size,
+        &sample,
+        rules.max_file_size,
+        &rules,
+    );
+    let checks: Vec<ValidationCheckResult> = outcomes
+        .into_iter()
+        .map(|outcome| ValidationCheckResult {
+            check: outcome.check.to_string(),
+            passed: outcome.error.is_none(),
+            code: outcome.error.as_ref().map(|e| e.code.to_string()),
+            message: outcome.error.map(|e| e.message),
+        })
+        .collect();
+
+    Ok(Json(ValidationTestResponse {
+        passed: checks.iter().all(|c| c.passed),
+        sanitized_filename,
+        detected_mime: detect_mime(&sample, &rules).map(str::to_string),
+        checks,
+    }))
+}
@@ -0,0 +1,34 @@
+use crate::utils::auth::{Claims, Role};
+use crate::{AppState, entities::prelude::Users};
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use sea_orm::EntityTrait;
+
+/// Keep the `/admin` API to admins, and auditors to its read-only routes
+///
+/// Runs after `auth_middleware`, which already refuses personal access
+/// tokens without the admin scope.
+pub async fn admin_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let claims = req
+        .extensions()
+        .get::<Claims>()
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    let user = Users::find_by_id(&claims.sub)
+        .one(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if !Role::of(&user).can_administer(req.method()) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(next.run(req).await)
+}
@@ -1,4 +1,6 @@
-use crate::utils::auth::validate_jwt;
+use crate::services::api_tokens::{API_TOKEN_PREFIX, ApiTokenService};
+use crate::services::sessions::SessionService;
+use crate::utils::auth::{Scope, validate_jwt};
 use crate::{AppState, entities::prelude::Users};
 use axum::{
     extract::{Request, State},
@@ -36,23 +38,49 @@ pub async fn auth_middleware(
             .and_then(|q| q.token)
     };
 
-    if let Some(token) = token {
-        let secret = &state.config.jwt_secret;
-
-        if let Ok(claims) = validate_jwt(&token, secret) {
-            // Check if user still exists in DB
-            let user_exists = Users::find_by_id(claims.sub.clone())
-                .one(&state.db)
-                .await
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-                .is_some();
+    let Some(token) = token else {
+        return Err(StatusCode::UNAUTHORIZED);
+    };
 
-            if user_exists {
-                req.extensions_mut().insert(claims);
-                return Ok(next.run(req).await);
+    let claims = if token.starts_with(API_TOKEN_PREFIX) {
+        ApiTokenService::authenticate(&state.db, &token)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    } else {
+        match validate_jwt(&token, &state.config.jwt_secret) {
+            Ok(claims) => {
+                // Refused if the token or its session was revoked
+                let revoked = SessionService::is_revoked(&state.db, &claims)
+                    .await
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                (!revoked).then_some(claims)
             }
+            Err(_) => None,
+        }
+    };
+    let Some(claims) = claims else {
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+
+    // Check if user still exists in DB and has not been disabled
+    let user_active = Users::find_by_id(claims.sub.clone())
+        .one(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .is_some_and(|user| user.disabled_at.is_none());
+    if !user_active {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    // Personal access tokens only reach routes their scopes cover
+    if claims.scopes.is_some() {
+        let permitted = Scope::required_for(req.method(), req.uri().path())
+            .is_some_and(|scope| claims.allows(scope));
+        if !permitted {
+            return Err(StatusCode::FORBIDDEN);
         }
     }
 
-    Err(StatusCode::UNAUTHORIZED)
+    req.extensions_mut().insert(claims);
+    Ok(next.run(req).await)
 }
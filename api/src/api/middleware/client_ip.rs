@@ -0,0 +1,52 @@
+use crate::AppState;
+use crate::utils::net::resolve_client_ip;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set once the missing `TRUSTED_PROXIES` warning has been logged
+static WARNED_UNTRUSTED_FORWARDING: AtomicBool = AtomicBool::new(false);
+
+/// Rewrite `X-Forwarded-For` to the single client address the proxies in
+/// `TRUSTED_PROXIES` vouch for, so handlers reading it cannot be fooled by
+/// a forged header
+///
+/// With an empty trusted-proxy list the header is replaced by the peer
+/// address. Only when the peer address is unknown, as for a router called
+/// without `ConnectInfo`, do the headers pass through untouched.
+pub async fn client_ip(State(state): State<AppState>, mut req: Request, next: Next) -> Response {
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    if let Some(peer) = peer {
+        let forwarded = req
+            .headers()
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok());
+        if forwarded.is_some()
+            && state.config.trusted_proxies.is_empty()
+            && !WARNED_UNTRUSTED_FORWARDING.swap(true, Ordering::Relaxed)
+        {
+            tracing::warn!(
+                "Ignoring X-Forwarded-For from {} because TRUSTED_PROXIES is empty; behind a \
+                 reverse proxy every client will appear as the proxy's address",
+                peer
+            );
+        }
+        let client = resolve_client_ip(peer, forwarded, &state.config.trusted_proxies);
+
+        let headers = req.headers_mut();
+        headers.remove("x-real-ip");
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_str(&client.to_string()).unwrap(),
+        );
+    }
+    next.run(req).await
+}
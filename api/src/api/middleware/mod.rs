@@ -1,4 +1,8 @@
+pub mod admin;
 pub mod auth;
+pub mod client_ip;
 pub mod metrics;
+pub mod rate_limit;
 pub mod request_id;
 pub mod security;
+pub mod tus;
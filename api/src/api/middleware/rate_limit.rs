@@ -0,0 +1,49 @@
+use crate::AppState;
+use crate::api::error::AppError;
+use crate::services::rate_limiter::{PRE_CHECK_BUCKET, RateLimitService, UPLOAD_BUCKET};
+use crate::utils::auth::Claims;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use chrono::Duration;
+
+/// Enforce `UPLOADS_PER_HOUR` on routes that store new content
+pub async fn upload_rate_limit(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    hit(&state, user_id(&req)?, UPLOAD_BUCKET).await?;
+    Ok(next.run(req).await)
+}
+
+/// Enforce `UPLOADS_PER_HOUR` on dedup pre-checks
+pub async fn pre_check_rate_limit(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    hit(&state, user_id(&req)?, PRE_CHECK_BUCKET).await?;
+    Ok(next.run(req).await)
+}
+
+/// The caller's user ID, from the claims auth_middleware inserted
+fn user_id(req: &Request) -> Result<String, AppError> {
+    req.extensions()
+        .get::<Claims>()
+        .map(|claims| claims.sub.clone())
+        .ok_or_else(|| AppError::Unauthorized("Missing credentials".to_string()))
+}
+
+async fn hit(state: &AppState, user_id: String, bucket: &str) -> Result<(), AppError> {
+    RateLimitService::hit(
+        &state.db,
+        &user_id,
+        bucket,
+        state.config.uploads_per_hour,
+        Duration::hours(1),
+    )
+    .await
+}
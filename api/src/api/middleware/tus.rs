@@ -0,0 +1,54 @@
+use crate::AppState;
+use axum::{
+    extract::{Request, State},
+    http::{HeaderValue, Method, StatusCode, header, header::HeaderName},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// The only tus protocol version the server speaks
+pub const TUS_VERSION: &str = "1.0.0";
+
+/// Require `Tus-Resumable: 1.0.0` on tus requests and echo it on every response
+pub async fn tus_resumable(req: Request, next: Next) -> Response {
+    let resumable = HeaderName::from_static("tus-resumable");
+    let supported = req
+        .headers()
+        .get(&resumable)
+        .is_some_and(|v| v.as_bytes() == TUS_VERSION.as_bytes());
+
+    let mut response = if supported {
+        next.run(req).await
+    } else {
+        (
+            StatusCode::PRECONDITION_FAILED,
+            [(HeaderName::from_static("tus-version"), TUS_VERSION)],
+        )
+            .into_response()
+    };
+    response
+        .headers_mut()
+        .insert(resumable, HeaderValue::from_static(TUS_VERSION));
+    response
+}
+
+/// Answer tus discovery (`OPTIONS /files/tus`) without authentication
+///
+/// CORS preflights, which carry `Access-Control-Request-Method`, pass through.
+pub async fn tus_discovery(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let discovery = req.method() == Method::OPTIONS
+        && req.uri().path().starts_with("/files/tus")
+        && !req
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+    if !discovery {
+        return next.run(req).await;
+    }
+
+    let mut response = crate::api::handlers::tus::tus_options(State(state)).await;
+    response.headers_mut().insert(
+        HeaderName::from_static("tus-resumable"),
+        HeaderValue::from_static(TUS_VERSION),
+    );
+    response
+}
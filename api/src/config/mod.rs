@@ -38,6 +38,42 @@ pub struct SecurityConfig {
     /// Staging file cleanup age in hours (default: 24)
     pub staging_cleanup_age_hours: u64,
 
+    /// Days a trashed item is kept before it is purged (default: 30)
+    pub trash_retention_days: u64,
+
+    /// Storage quota in bytes for users without their own (default: none, unlimited)
+    pub default_storage_quota: Option<i64>,
+
+    /// How downloads are delivered: "x-accel" hands them to nginx via
+    /// X-Accel-Redirect, "stream" serves the bytes from the API (default: "x-accel")
+    pub download_mode: String,
+
+    /// Failed share password attempts from one IP before it is locked out of
+    /// that share (default: 5)
+    pub share_password_ip_attempts: u32,
+
+    /// Failed password attempts on one share, from any IP, before the share
+    /// itself is locked (default: 20)
+    pub share_password_share_attempts: u32,
+
+    /// Failed attempts from one IP after which share password verification
+    /// also needs a CAPTCHA; 0 disables (default: 3)
+    pub share_password_captcha_after: u32,
+
+    /// Reverse proxies (CIDR ranges) whose `X-Forwarded-For` is believed.
+    /// Empty keeps the legacy behaviour of trusting the header from anyone
+    pub trusted_proxies: Vec<ipnet::IpNet>,
+
+    /// Lifetime of access tokens, in minutes (default: 15)
+    pub access_token_ttl_minutes: i64,
+
+    /// Days a session survives without being refreshed (default: 30)
+    pub refresh_token_ttl_days: i64,
+
+    /// How emails are sent: "smtp", "file" (one .eml per message in
+    /// `MAIL_SINK_DIR`) or "log" (default: "log")
+    pub mail_transport: String,
+
     /// JWT Secret Key (Required)
     pub jwt_secret: String,
 
@@ -61,6 +97,16 @@ impl Default for SecurityConfig {
             oidc_redirect_url: None,
             oidc_skip_discovery: false,
             staging_cleanup_age_hours: 24,
+            trash_retention_days: 30,
+            default_storage_quota: None,
+            download_mode: "x-accel".to_string(),
+            share_password_ip_attempts: 5,
+            share_password_share_attempts: 20,
+            share_password_captcha_after: 3,
+            trusted_proxies: Vec::new(),
+            access_token_ttl_minutes: 15,
+            refresh_token_ttl_days: 30,
+            mail_transport: "log".to_string(),
             jwt_secret: "secret".to_string(),
             // More secure default: localhost only instead of wildcard
             allowed_origins: vec![
@@ -120,6 +166,47 @@ impl SecurityConfig {
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(default.staging_cleanup_age_hours),
 
+            trash_retention_days: env::var("TRASH_RETENTION_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.trash_retention_days),
+
+            default_storage_quota: env::var("DEFAULT_STORAGE_QUOTA")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&quota: &i64| quota > 0),
+
+            download_mode: env::var("DOWNLOAD_MODE").unwrap_or(default.download_mode),
+            share_password_ip_attempts: env::var("SHARE_PASSWORD_IP_ATTEMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&n: &u32| n > 0)
+                .unwrap_or(default.share_password_ip_attempts),
+            share_password_share_attempts: env::var("SHARE_PASSWORD_SHARE_ATTEMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&n: &u32| n > 0)
+                .unwrap_or(default.share_password_share_attempts),
+            share_password_captcha_after: env::var("SHARE_PASSWORD_CAPTCHA_AFTER")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.share_password_captcha_after),
+            trusted_proxies: env::var("TRUSTED_PROXIES")
+                .ok()
+                .map(|v| crate::utils::net::parse_networks(&v))
+                .unwrap_or(default.trusted_proxies),
+            access_token_ttl_minutes: env::var("ACCESS_TOKEN_TTL_MINUTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&n: &i64| n > 0)
+                .unwrap_or(default.access_token_ttl_minutes),
+            refresh_token_ttl_days: env::var("REFRESH_TOKEN_TTL_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&n: &i64| n > 0)
+                .unwrap_or(default.refresh_token_ttl_days),
+            mail_transport: env::var("MAIL_TRANSPORT").unwrap_or(default.mail_transport),
+
             jwt_secret: env::var("JWT_SECRET").unwrap_or_else(|_| "secret".to_string()), // Fallback for dev convenience, strictly enforced in production method
 
             allowed_origins: env::var("ALLOWED_ORIGINS")
@@ -145,6 +232,16 @@ impl SecurityConfig {
             oidc_redirect_url: None,
             oidc_skip_discovery: false,
             staging_cleanup_age_hours: 24,
+            trash_retention_days: 30,
+            default_storage_quota: None,
+            download_mode: "x-accel".to_string(),
+            share_password_ip_attempts: 5,
+            share_password_share_attempts: 20,
+            share_password_captcha_after: 3,
+            trusted_proxies: Vec::new(),
+            access_token_ttl_minutes: 15,
+            refresh_token_ttl_days: 30,
+            mail_transport: "log".to_string(),
             jwt_secret: "secret".to_string(),
             // Development: localhost origins only
             allowed_origins: vec![
@@ -192,6 +289,44 @@ impl SecurityConfig {
                 .ok()
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(24),
+            trash_retention_days: env::var("TRASH_RETENTION_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            default_storage_quota: env::var("DEFAULT_STORAGE_QUOTA")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&quota: &i64| quota > 0),
+            download_mode: env::var("DOWNLOAD_MODE").unwrap_or(default.download_mode),
+            share_password_ip_attempts: env::var("SHARE_PASSWORD_IP_ATTEMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&n: &u32| n > 0)
+                .unwrap_or(default.share_password_ip_attempts),
+            share_password_share_attempts: env::var("SHARE_PASSWORD_SHARE_ATTEMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&n: &u32| n > 0)
+                .unwrap_or(default.share_password_share_attempts),
+            share_password_captcha_after: env::var("SHARE_PASSWORD_CAPTCHA_AFTER")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.share_password_captcha_after),
+            trusted_proxies: env::var("TRUSTED_PROXIES")
+                .ok()
+                .map(|v| crate::utils::net::parse_networks(&v))
+                .unwrap_or(default.trusted_proxies),
+            access_token_ttl_minutes: env::var("ACCESS_TOKEN_TTL_MINUTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&n: &i64| n > 0)
+                .unwrap_or(default.access_token_ttl_minutes),
+            refresh_token_ttl_days: env::var("REFRESH_TOKEN_TTL_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&n: &i64| n > 0)
+                .unwrap_or(default.refresh_token_ttl_days),
+            mail_transport: env::var("MAIL_TRANSPORT").unwrap_or(default.mail_transport),
             jwt_secret: env::var("JWT_SECRET").expect("CRITICAL: JWT_SECRET must be set"),
             allowed_origins: env::var("ALLOWED_ORIGINS")
                 .ok()
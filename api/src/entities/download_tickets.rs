@@ -0,0 +1,54 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A bearer link to one file, handed out by `POST /files/:id/ticket`
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "download_tickets")]
+pub struct Model {
+    /// The ticket itself, as it appears in `/download/:ticket`
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: String,
+    pub user_id: String,
+    pub user_file_id: String,
+    /// Downloads allowed before the ticket is used up; unlimited when unset
+    pub max_downloads: Option<i32>,
+    pub download_count: i32,
+    /// Only requests from this client IP may redeem the ticket
+    pub bound_ip: Option<String>,
+    pub created_at: DateTimeUtc,
+    pub expires_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Users,
+    #[sea_orm(
+        belongs_to = "super::user_files::Entity",
+        from = "Column::UserFileId",
+        to = "super::user_files::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    UserFiles,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Users.def()
+    }
+}
+
+impl Related<super::user_files::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserFiles.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
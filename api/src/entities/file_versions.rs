@@ -0,0 +1,52 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Earlier content of a user file, kept when it is overwritten
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "file_versions")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: String,
+    pub user_file_id: String,
+    /// Holds one reference on the storage file's `ref_count`
+    pub storage_file_id: String,
+    pub version_number: i32,
+    /// When this content was uploaded
+    pub created_at: DateTimeUtc,
+    /// When newer content replaced it
+    pub archived_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user_files::Entity",
+        from = "Column::UserFileId",
+        to = "super::user_files::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    UserFiles,
+    #[sea_orm(
+        belongs_to = "super::storage_files::Entity",
+        from = "Column::StorageFileId",
+        to = "super::storage_files::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    StorageFiles,
+}
+
+impl Related<super::user_files::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserFiles.def()
+    }
+}
+
+impl Related<super::storage_files::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StorageFiles.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
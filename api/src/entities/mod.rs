@@ -1,11 +1,21 @@
 pub mod prelude;
 
+pub mod api_tokens;
 pub mod audit_logs;
+pub mod download_tickets;
+pub mod email_tokens;
 pub mod file_metadata;
 pub mod file_tags;
+pub mod file_versions;
+pub mod rate_limit_events;
+pub mod recovery_codes;
+pub mod revoked_tokens;
 pub mod storage_files;
 pub mod tags;
+pub mod team_members;
+pub mod teams;
 pub mod tokens;
+pub mod two_factor_challenges;
 pub mod user_file_facts;
 pub mod user_files;
 pub mod user_settings;
@@ -16,4 +26,5 @@ pub mod blocked_extensions;
 pub mod magic_signatures;
 pub mod share_access_logs;
 pub mod share_links;
+pub mod share_password_attempts;
 pub mod upload_sessions;
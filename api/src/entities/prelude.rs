@@ -1,14 +1,25 @@
 pub use super::allowed_mimes::Entity as AllowedMimes;
+pub use super::api_tokens::Entity as ApiTokens;
 pub use super::audit_logs::Entity as AuditLogs;
 pub use super::blocked_extensions::Entity as BlockedExtensions;
+pub use super::download_tickets::Entity as DownloadTickets;
+pub use super::email_tokens::Entity as EmailTokens;
 pub use super::file_metadata::Entity as FileMetadata;
 pub use super::file_tags::Entity as FileTags;
+pub use super::file_versions::Entity as FileVersions;
 pub use super::magic_signatures::Entity as MagicSignatures;
+pub use super::rate_limit_events::Entity as RateLimitEvents;
+pub use super::recovery_codes::Entity as RecoveryCodes;
+pub use super::revoked_tokens::Entity as RevokedTokens;
 pub use super::share_access_logs::Entity as ShareAccessLogs;
 pub use super::share_links::Entity as ShareLinks;
+pub use super::share_password_attempts::Entity as SharePasswordAttempts;
 pub use super::storage_files::Entity as StorageFiles;
 pub use super::tags::Entity as Tags;
+pub use super::team_members::Entity as TeamMembers;
+pub use super::teams::Entity as Teams;
 pub use super::tokens::Entity as Tokens;
+pub use super::two_factor_challenges::Entity as TwoFactorChallenges;
 pub use super::upload_sessions::Entity as UploadSessions;
 pub use super::user_file_facts::Entity as UserFileFacts;
 pub use super::user_files::Entity as UserFiles;
@@ -13,9 +13,28 @@ pub struct Model {
     pub share_type: String, // "public" or "user"
     pub shared_with_user_id: Option<String>,
     pub password_hash: Option<String>,
-    pub permission: String, // "view" or "download"
+    pub permission: String, // "view", "download", "upload" or "edit"
     pub expires_at: DateTimeUtc,
     pub created_at: Option<DateTimeUtc>,
+    /// Files an "upload" share accepts in total; unlimited when unset
+    pub max_uploads: Option<i32>,
+    /// Largest file an "upload" share accepts, in bytes
+    pub max_upload_size: Option<i64>,
+    /// Comma-separated extensions or MIME types an "upload" share accepts, e.g. "pdf,image/*"
+    pub allowed_upload_types: Option<String>,
+    pub upload_count: i32,
+    /// Downloads the link allows in total; unlimited when unset
+    pub max_downloads: Option<i32>,
+    pub download_count: i32,
+    /// Times the share page may be opened; unlimited when unset
+    pub max_views: Option<i32>,
+    pub view_count: i32,
+    /// Comma-separated CIDR ranges the link may be opened from; anywhere when unset
+    pub allowed_ips: Option<String>,
+    /// Comma-separated CIDR ranges refused even when otherwise allowed
+    pub denied_ips: Option<String>,
+    /// Comma-separated hosts whose pages may link to the share (subdomains included)
+    pub allowed_referrers: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,36 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Failed password attempts against a share, from one IP or from anywhere
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "share_password_attempts")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: String,
+    pub share_link_id: String,
+    /// The client this counter tracks; `None` for the share-wide counter
+    pub client_ip: Option<String>,
+    pub failed_attempts: i32,
+    pub last_attempt: DateTimeUtc,
+    pub locked_until: Option<DateTimeUtc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::share_links::Entity",
+        from = "Column::ShareLinkId",
+        to = "super::share_links::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    ShareLinks,
+}
+
+impl Related<super::share_links::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ShareLinks.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -6,8 +6,12 @@ use serde::{Deserialize, Serialize};
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: String,
-    #[sea_orm(unique)]
+    /// xxh3-128 of the content, used for fast pre-upload lookups
     pub hash: String,
+    /// SHA-256 of the content; the authoritative deduplication key.
+    /// `None` only for rows created before it was introduced (see `HashBackfillService`).
+    #[sea_orm(unique)]
+    pub sha256: Option<String>,
     pub s3_key: String,
     pub size: i64,
     pub ref_count: i32,
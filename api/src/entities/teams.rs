@@ -0,0 +1,37 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A group of users sharing a drive
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "teams")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: String,
+    pub name: String,
+    /// Quota of the team drive in bytes; `None` falls back to the default quota
+    pub storage_quota: Option<i64>,
+    pub created_by: Option<String>,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::team_members::Entity")]
+    TeamMembers,
+    #[sea_orm(has_many = "super::user_files::Entity")]
+    UserFiles,
+}
+
+impl Related<super::team_members::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TeamMembers.def()
+    }
+}
+
+impl Related<super::user_files::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserFiles.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
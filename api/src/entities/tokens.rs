@@ -1,14 +1,23 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
+/// A sign-in session, identified to its client by a rotating refresh token
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "tokens")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: String,
     pub user_id: String,
+    /// SHA-256 of the current refresh token
     #[sea_orm(unique)]
     pub token: String,
+    /// SHA-256 of the refresh token this one replaced, to spot replays
+    pub previous_token: Option<String>,
+    /// User agent of the client that last used the session
+    pub device: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: Option<DateTimeUtc>,
+    pub last_used_at: Option<DateTimeUtc>,
     pub expires_at: DateTimeUtc,
 }
 
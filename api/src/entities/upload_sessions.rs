@@ -21,6 +21,19 @@ pub struct Model {
     pub status: String,
     pub created_at: DateTimeWithTimeZone,
     pub expires_at: DateTimeWithTimeZone,
+    /// "chunked" for the numbered-chunk API, "tus" for tus uploads
+    #[sea_orm(default_value = "chunked")]
+    pub protocol: String,
+    /// Bytes received so far (tus only). Bytes past the last full part are
+    /// kept in a tail object until a part fills up.
+    #[sea_orm(default_value = 0)]
+    pub upload_offset: i64,
+    /// Raw tus `Upload-Metadata`, echoed back on HEAD
+    pub upload_metadata: Option<String>,
+    /// Folder the finished file is placed in (tus only)
+    pub parent_id: Option<String>,
+    /// Team drive the finished file is placed in when there is no `parent_id`
+    pub team_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
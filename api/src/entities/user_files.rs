@@ -6,7 +6,10 @@ use serde::{Deserialize, Serialize};
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: String,
+    /// The owner, or for team drive items the member who added it
     pub user_id: String,
+    /// Team drive the item lives in; `None` for the owner's personal files
+    pub team_id: Option<String>,
     pub storage_file_id: Option<String>,
     pub parent_id: Option<String>,
     pub is_folder: bool,
@@ -17,6 +20,11 @@ pub struct Model {
     pub created_at: Option<DateTimeUtc>,
     pub deleted_at: Option<DateTimeUtc>,
     pub file_signature: Option<String>,
+    /// Id of the item the user trashed; shared by every row trashed with it.
+    /// `None` for live rows and for internal tombstones that never reach the trash.
+    pub trash_root_id: Option<String>,
+    /// Folder names from the root to the trashed item's parent, joined by `/`
+    pub original_path: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -37,6 +45,14 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     Users,
+    #[sea_orm(
+        belongs_to = "super::teams::Entity",
+        from = "Column::TeamId",
+        to = "super::teams::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Teams,
     #[sea_orm(has_many = "super::file_tags::Entity")]
     FileTags,
 }
@@ -59,4 +75,10 @@ impl Related<super::users::Entity> for Entity {
     }
 }
 
+impl Related<super::teams::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Teams.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -12,9 +12,29 @@ pub struct Model {
     #[sea_orm(unique)]
     pub oidc_sub: Option<String>,
     pub email: Option<String>,
+    /// When `email` was confirmed; cleared whenever it changes
+    pub email_verified_at: Option<DateTimeUtc>,
     pub name: Option<String>,
     pub avatar_url: Option<String>,
     pub created_at: Option<DateTimeUtc>,
+    /// Storage quota in bytes; `None` falls back to the configured default
+    pub storage_quota: Option<i64>,
+    /// Base32 TOTP secret, set at enrollment
+    pub totp_secret: Option<String>,
+    /// When two-factor authentication was confirmed; `None` while off
+    pub totp_enabled_at: Option<DateTimeUtc>,
+    /// Time step of the last accepted TOTP code
+    pub totp_last_step: Option<i64>,
+    /// Second-factor codes tried since the last correct one
+    #[sea_orm(default_value = 0)]
+    pub totp_failed_attempts: i32,
+    /// Until when second-factor logins are refused after too many wrong codes
+    pub totp_locked_until: Option<DateTimeUtc>,
+    /// `user`, `admin` or `auditor`; see `utils::auth::Role`
+    #[sea_orm(default_value = "user")]
+    pub role: String,
+    /// When an admin disabled the account; disabled users cannot sign in
+    pub disabled_at: Option<DateTimeUtc>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -1,9 +1,11 @@
 use crate::entities::{
-    allowed_mimes, audit_logs, blocked_extensions, file_metadata, file_tags, magic_signatures,
-    share_access_logs, share_links, storage_files, tags, tokens, upload_sessions, user_file_facts,
+    allowed_mimes, api_tokens, audit_logs, blocked_extensions, download_tickets, email_tokens,
+    file_metadata, file_tags, file_versions, magic_signatures, rate_limit_events, recovery_codes,
+    revoked_tokens, share_access_logs, share_links, share_password_attempts, storage_files, tags,
+    team_members, teams, tokens, two_factor_challenges, upload_sessions, user_file_facts,
     user_files, user_settings, users,
 };
-use sea_orm::{ConnectOptions, ConnectionTrait, Database, DatabaseConnection, Schema};
+use sea_orm::{ConnectOptions, ConnectionTrait, Database, DatabaseConnection, DbBackend, Schema};
 use std::env;
 use std::time::Duration;
 use tracing::info;
@@ -36,10 +38,9 @@ pub async fn setup_database() -> anyhow::Result<DatabaseConnection> {
 }
 
 pub async fn run_migrations(db: &DatabaseConnection) -> anyhow::Result<()> {
-    let db_url = env::var("DATABASE_URL")?;
-
-    if db_url.starts_with("postgres://") {
+    if db.get_database_backend() == DbBackend::Postgres {
         info!("🔄 Running SQLx migrations for PostgreSQL...");
+        let db_url = env::var("DATABASE_URL")?;
         let pool = sqlx::PgPool::connect(&db_url).await?;
         match sqlx::migrate!("./migrations").run(&pool).await {
             Ok(_) => info!("✅ Migrations completed successfully"),
@@ -73,6 +74,34 @@ pub async fn run_migrations(db: &DatabaseConnection) -> anyhow::Result<()> {
                 .create_table_from_entity(tokens::Entity)
                 .if_not_exists()
                 .to_owned(),
+            schema
+                .create_table_from_entity(revoked_tokens::Entity)
+                .if_not_exists()
+                .to_owned(),
+            schema
+                .create_table_from_entity(api_tokens::Entity)
+                .if_not_exists()
+                .to_owned(),
+            schema
+                .create_table_from_entity(recovery_codes::Entity)
+                .if_not_exists()
+                .to_owned(),
+            schema
+                .create_table_from_entity(two_factor_challenges::Entity)
+                .if_not_exists()
+                .to_owned(),
+            schema
+                .create_table_from_entity(email_tokens::Entity)
+                .if_not_exists()
+                .to_owned(),
+            schema
+                .create_table_from_entity(teams::Entity)
+                .if_not_exists()
+                .to_owned(),
+            schema
+                .create_table_from_entity(team_members::Entity)
+                .if_not_exists()
+                .to_owned(),
             schema
                 .create_table_from_entity(storage_files::Entity)
                 .if_not_exists()
@@ -125,6 +154,22 @@ pub async fn run_migrations(db: &DatabaseConnection) -> anyhow::Result<()> {
                 .create_table_from_entity(share_access_logs::Entity)
                 .if_not_exists()
                 .to_owned(),
+            schema
+                .create_table_from_entity(file_versions::Entity)
+                .if_not_exists()
+                .to_owned(),
+            schema
+                .create_table_from_entity(rate_limit_events::Entity)
+                .if_not_exists()
+                .to_owned(),
+            schema
+                .create_table_from_entity(download_tickets::Entity)
+                .if_not_exists()
+                .to_owned(),
+            schema
+                .create_table_from_entity(share_password_attempts::Entity)
+                .if_not_exists()
+                .to_owned(),
         ];
 
         for stmt in stmts {
@@ -132,6 +177,65 @@ pub async fn run_migrations(db: &DatabaseConnection) -> anyhow::Result<()> {
             let _ = db.execute(stmt).await;
         }
 
+        // Columns added after the initial schema; errors mean they already exist
+        let alters = [
+            "ALTER TABLE storage_files ADD COLUMN sha256 TEXT",
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_storage_files_sha256 ON storage_files(sha256)",
+            "CREATE INDEX IF NOT EXISTS idx_storage_files_hash ON storage_files(hash)",
+            "ALTER TABLE user_files ADD COLUMN trash_root_id TEXT",
+            "ALTER TABLE user_files ADD COLUMN original_path TEXT",
+            "CREATE INDEX IF NOT EXISTS idx_user_files_trash_root_id ON user_files(trash_root_id)",
+            "CREATE INDEX IF NOT EXISTS idx_file_versions_user_file_id ON file_versions(user_file_id)",
+            "ALTER TABLE users ADD COLUMN storage_quota BIGINT",
+            "CREATE INDEX IF NOT EXISTS idx_rate_limit_events_window ON rate_limit_events(user_id, bucket, created_at)",
+            "ALTER TABLE upload_sessions ADD COLUMN protocol TEXT NOT NULL DEFAULT 'chunked'",
+            "ALTER TABLE upload_sessions ADD COLUMN upload_offset BIGINT NOT NULL DEFAULT 0",
+            "ALTER TABLE upload_sessions ADD COLUMN upload_metadata TEXT",
+            "ALTER TABLE upload_sessions ADD COLUMN parent_id TEXT",
+            "CREATE INDEX IF NOT EXISTS idx_download_tickets_expires_at ON download_tickets(expires_at)",
+            "ALTER TABLE share_links ADD COLUMN max_uploads INTEGER",
+            "ALTER TABLE share_links ADD COLUMN max_upload_size BIGINT",
+            "ALTER TABLE share_links ADD COLUMN allowed_upload_types TEXT",
+            "ALTER TABLE share_links ADD COLUMN upload_count INTEGER NOT NULL DEFAULT 0",
+            "ALTER TABLE share_links ADD COLUMN max_downloads INTEGER",
+            "ALTER TABLE share_links ADD COLUMN download_count INTEGER NOT NULL DEFAULT 0",
+            "ALTER TABLE share_links ADD COLUMN max_views INTEGER",
+            "ALTER TABLE share_links ADD COLUMN view_count INTEGER NOT NULL DEFAULT 0",
+            "ALTER TABLE share_links ADD COLUMN allowed_ips TEXT",
+            "ALTER TABLE share_links ADD COLUMN denied_ips TEXT",
+            "ALTER TABLE share_links ADD COLUMN allowed_referrers TEXT",
+            "CREATE INDEX IF NOT EXISTS idx_share_password_attempts_share ON share_password_attempts(share_link_id)",
+            "ALTER TABLE tokens ADD COLUMN previous_token TEXT",
+            "ALTER TABLE tokens ADD COLUMN device TEXT",
+            "ALTER TABLE tokens ADD COLUMN ip_address TEXT",
+            "ALTER TABLE tokens ADD COLUMN created_at TEXT",
+            "ALTER TABLE tokens ADD COLUMN last_used_at TEXT",
+            "CREATE INDEX IF NOT EXISTS idx_tokens_user_id ON tokens(user_id)",
+            "CREATE INDEX IF NOT EXISTS idx_tokens_previous_token ON tokens(previous_token)",
+            "CREATE INDEX IF NOT EXISTS idx_revoked_tokens_expires_at ON revoked_tokens(expires_at)",
+            "CREATE INDEX IF NOT EXISTS idx_api_tokens_user_id ON api_tokens(user_id)",
+            "ALTER TABLE users ADD COLUMN totp_secret TEXT",
+            "ALTER TABLE users ADD COLUMN totp_enabled_at TEXT",
+            "ALTER TABLE users ADD COLUMN totp_last_step BIGINT",
+            "ALTER TABLE users ADD COLUMN totp_failed_attempts INTEGER NOT NULL DEFAULT 0",
+            "ALTER TABLE users ADD COLUMN totp_locked_until TEXT",
+            "CREATE INDEX IF NOT EXISTS idx_recovery_codes_user_id ON recovery_codes(user_id)",
+            "CREATE INDEX IF NOT EXISTS idx_two_factor_challenges_expires_at ON two_factor_challenges(expires_at)",
+            "ALTER TABLE users ADD COLUMN email_verified_at TEXT",
+            "CREATE INDEX IF NOT EXISTS idx_email_tokens_user_purpose ON email_tokens(user_id, purpose)",
+            "CREATE INDEX IF NOT EXISTS idx_email_tokens_expires_at ON email_tokens(expires_at)",
+            "ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'user'",
+            "ALTER TABLE users ADD COLUMN disabled_at TEXT",
+            "CREATE INDEX IF NOT EXISTS idx_users_role ON users(role)",
+            "CREATE INDEX IF NOT EXISTS idx_team_members_user_id ON team_members(user_id)",
+            "ALTER TABLE user_files ADD COLUMN team_id TEXT REFERENCES teams(id) ON DELETE CASCADE",
+            "CREATE INDEX IF NOT EXISTS idx_user_files_team_id ON user_files(team_id)",
+            "ALTER TABLE upload_sessions ADD COLUMN team_id TEXT",
+        ];
+        for sql in alters {
+            let _ = db.execute_unprepared(sql).await;
+        }
+
         // Seed validation data for SQLite
         crate::infrastructure::seed::seed_validation_data_sqlite(db).await?;
     }
@@ -0,0 +1,22 @@
+use crate::config::SecurityConfig;
+use crate::services::mailer::Mailer;
+use std::sync::Arc;
+use tracing::info;
+
+pub async fn setup_mailer(security_config: &SecurityConfig) -> anyhow::Result<Arc<dyn Mailer>> {
+    let mailer = crate::services::mailer::create_mailer(&security_config.mail_transport)?;
+
+    if mailer.health_check().await {
+        info!(
+            "📧 Mail transport '{}' ready",
+            security_config.mail_transport
+        );
+    } else {
+        tracing::warn!(
+            "⚠️  Mail transport '{}' unreachable! Verification and password reset emails will fail.",
+            security_config.mail_transport
+        );
+    }
+
+    Ok(mailer.into())
+}
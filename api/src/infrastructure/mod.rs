@@ -1,4 +1,5 @@
 pub mod database;
+pub mod mailer;
 pub mod scanner;
 pub mod seed;
 pub mod storage;
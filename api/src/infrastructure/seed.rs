@@ -1,5 +1,6 @@
 use crate::entities::prelude::*;
 use crate::entities::{allowed_mimes, blocked_extensions, magic_signatures, users};
+use crate::utils::auth::Role;
 use argon2::PasswordHasher;
 use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
 use tracing::info;
@@ -31,6 +32,7 @@ pub async fn seed_initial_data(db: &DatabaseConnection) -> anyhow::Result<()> {
             username: Set("admin".to_string()),
             password_hash: Set(Some(password_hash)),
             name: Set(Some("Administrator".to_string())),
+            role: Set(Role::Admin.as_str().to_string()),
             ..Default::default()
         };
 
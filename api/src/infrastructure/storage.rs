@@ -1,10 +1,50 @@
-use crate::services::storage::S3StorageService;
+use crate::config::SecurityConfig;
+use crate::services::local_storage::LocalFsStorageService;
+use crate::services::storage::{S3StorageService, StorageService};
 use aws_sdk_s3::config::Region;
 use std::env;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tracing::info;
 
-pub async fn setup_storage() -> Arc<S3StorageService> {
+/// Build the storage backend selected by `STORAGE_BACKEND` (`s3` or `local`)
+pub async fn setup_storage(config: &SecurityConfig) -> Arc<dyn StorageService> {
+    let backend = env::var("STORAGE_BACKEND").unwrap_or_else(|_| "s3".to_string());
+    match backend.to_lowercase().as_str() {
+        "local" | "fs" => setup_local_storage(config).await,
+        "s3" | "minio" => setup_s3_storage().await,
+        other => panic!(
+            "Unknown STORAGE_BACKEND '{}': expected 's3' or 'local'",
+            other
+        ),
+    }
+}
+
+async fn setup_local_storage(config: &SecurityConfig) -> Arc<dyn StorageService> {
+    let root = PathBuf::from(
+        env::var("LOCAL_STORAGE_PATH").unwrap_or_else(|_| "./data/storage".to_string()),
+    );
+    let endpoint_url =
+        env::var("LOCAL_STORAGE_ENDPOINT").unwrap_or_else(|_| "http://localhost:3000".to_string());
+
+    if let Err(e) = tokio::fs::create_dir_all(&root).await {
+        panic!(
+            "❌ Failed to create local storage directory '{}': {}",
+            root.display(),
+            e
+        );
+    }
+
+    info!("💾 Local Storage: {}", root.display());
+
+    Arc::new(LocalFsStorageService::new(
+        root,
+        endpoint_url,
+        config.jwt_secret.clone(),
+    ))
+}
+
+async fn setup_s3_storage() -> Arc<dyn StorageService> {
     // Setup S3 client
     let endpoint_url = env::var("MINIO_ENDPOINT").expect("MINIO_ENDPOINT must be set");
     let access_key = env::var("MINIO_ACCESS_KEY").expect("MINIO_ACCESS_KEY must be set");
@@ -8,6 +8,7 @@ pub mod utils;
 
 use crate::config::SecurityConfig;
 use crate::services::file_service::FileService;
+use crate::services::mailer::Mailer;
 use crate::services::scanner::VirusScanner;
 use crate::services::storage::StorageService;
 use api::handlers::captcha::{CaptchaChallenge, CooldownEntry};
@@ -16,7 +17,6 @@ use axum::{
     middleware::{from_fn, from_fn_with_state},
     routing::{get, post},
 };
-use chrono::{DateTime, Utc};
 use dashmap::DashMap;
 use sea_orm::DatabaseConnection;
 use std::sync::Arc;
@@ -29,6 +29,24 @@ use utoipa_swagger_ui::SwaggerUi;
     paths(
         api::handlers::auth::register,
         api::handlers::auth::login,
+        api::handlers::sessions::refresh,
+        api::handlers::sessions::logout,
+        api::handlers::sessions::list_sessions,
+        api::handlers::sessions::revoke_session,
+        api::handlers::sessions::revoke_all_sessions,
+        api::handlers::api_tokens::create_api_token,
+        api::handlers::api_tokens::list_api_tokens,
+        api::handlers::api_tokens::revoke_api_token,
+        api::handlers::two_factor::login_2fa,
+        api::handlers::two_factor::get_two_factor_status,
+        api::handlers::two_factor::enroll_two_factor,
+        api::handlers::two_factor::confirm_two_factor,
+        api::handlers::two_factor::regenerate_recovery_codes,
+        api::handlers::two_factor::disable_two_factor,
+        api::handlers::account_email::send_verification_email,
+        api::handlers::account_email::verify_email,
+        api::handlers::account_email::request_password_reset,
+        api::handlers::account_email::confirm_password_reset,
         api::handlers::captcha::generate_captcha,
         api::handlers::files::upload::upload_file,
         api::handlers::files::upload::pre_check_dedup,
@@ -43,10 +61,21 @@ use utoipa_swagger_ui::SwaggerUi;
         api::handlers::files::manage::toggle_favorite,
         api::handlers::files::archive::get_zip_contents,
         api::handlers::files::bulk::bulk_delete,
+        api::handlers::files::trash::list_trash,
+        api::handlers::files::trash::restore_item,
+        api::handlers::files::trash::purge_item,
+        api::handlers::files::trash::empty_trash,
+        api::handlers::files::versions::list_versions,
+        api::handlers::files::versions::download_version,
+        api::handlers::files::versions::restore_version,
+        api::handlers::files::versions::prune_versions,
         api::handlers::files::bulk::bulk_move,
         api::handlers::files::bulk::bulk_copy,
         api::handlers::files::download::generate_download_ticket,
         api::handlers::files::download::download_file_with_ticket,
+        api::handlers::files::download::revoke_download_ticket,
+        api::handlers::files::download::download_folder_zip,
+        api::handlers::files::download::bulk_download,
         api::handlers::files::list::folder_tree,
         api::handlers::user_settings::get_settings,
         api::handlers::user_settings::update_settings,
@@ -57,22 +86,93 @@ use utoipa_swagger_ui::SwaggerUi;
         api::handlers::users::upload_avatar,
         api::handlers::users::get_avatar,
         api::handlers::users::get_user_facts,
+        api::handlers::users::get_quota,
+        api::handlers::admin::list_users,
+        api::handlers::admin::get_user,
+        api::handlers::admin::set_user_role,
+        api::handlers::admin::disable_user,
+        api::handlers::admin::enable_user,
+        api::handlers::admin::delete_user,
+        api::handlers::admin::reset_user_password,
+        api::handlers::admin::get_user_usage,
+        api::handlers::admin::get_stats,
+        api::handlers::admin::list_audit_logs,
+        api::handlers::admin::get_user_quota,
+        api::handlers::admin::set_user_quota,
+        api::handlers::teams::set_team_quota,
+        api::handlers::teams::create_team,
+        api::handlers::teams::list_teams,
+        api::handlers::teams::get_team,
+        api::handlers::teams::rename_team,
+        api::handlers::teams::delete_team,
+        api::handlers::teams::get_team_usage,
+        api::handlers::teams::list_team_members,
+        api::handlers::teams::add_team_member,
+        api::handlers::teams::update_team_member,
+        api::handlers::teams::remove_team_member,
+        api::handlers::validation_rules::list_mimes,
+        api::handlers::validation_rules::create_mime,
+        api::handlers::validation_rules::update_mime,
+        api::handlers::validation_rules::delete_mime,
+        api::handlers::validation_rules::list_signatures,
+        api::handlers::validation_rules::create_signature,
+        api::handlers::validation_rules::update_signature,
+        api::handlers::validation_rules::delete_signature,
+        api::handlers::validation_rules::list_extensions,
+        api::handlers::validation_rules::create_extension,
+        api::handlers::validation_rules::update_extension,
+        api::handlers::validation_rules::delete_extension,
+        api::handlers::validation_rules::test_validation,
         api::handlers::upload::init_upload_handler,
         api::handlers::upload::upload_chunk_handler,
         api::handlers::upload::complete_upload_handler,
         api::handlers::upload::abort_upload_handler,
+        api::handlers::tus::tus_options,
+        api::handlers::tus::tus_create,
+        api::handlers::tus::tus_head,
+        api::handlers::tus::tus_patch,
+        api::handlers::tus::tus_terminate,
         api::handlers::shares::create_share,
         api::handlers::shares::list_shares,
         api::handlers::shares::revoke_share,
         api::handlers::shares::get_share_logs,
+        api::handlers::shares::list_share_lockouts,
+        api::handlers::shares::reset_share_lockouts,
         api::handlers::shares::get_public_share,
         api::handlers::shares::verify_share_password,
         api::handlers::shares::download_shared_file,
+        api::handlers::shares::download_shared_folder_zip,
+        api::handlers::shares::upload_to_share,
+        api::handlers::shares::list_incoming_shares,
+        api::handlers::shares::list_incoming_folder,
+        api::handlers::shares::download_incoming_file,
+        api::handlers::shares::add_incoming_to_drive,
+        api::handlers::shares::upload_to_incoming_share,
+        api::handlers::shares::create_incoming_folder,
+        api::handlers::shares::update_incoming_item,
+        api::handlers::shares::delete_incoming_item,
     ),
     components(
         schemas(
             api::handlers::auth::AuthRequest,
             api::handlers::auth::AuthResponse,
+            api::handlers::sessions::RefreshRequest,
+            api::handlers::sessions::SessionResponse,
+            api::handlers::api_tokens::CreateApiTokenRequest,
+            api::handlers::api_tokens::ApiTokenResponse,
+            api::handlers::api_tokens::CreatedApiTokenResponse,
+            crate::utils::auth::Scope,
+            api::handlers::auth::LoginResponse,
+            api::handlers::auth::TwoFactorChallengeResponse,
+            api::handlers::two_factor::TwoFactorLoginRequest,
+            api::handlers::two_factor::TwoFactorCodeRequest,
+            api::handlers::two_factor::DisableTwoFactorRequest,
+            api::handlers::two_factor::TwoFactorStatusResponse,
+            api::handlers::two_factor::TwoFactorEnrollResponse,
+            api::handlers::two_factor::RecoveryCodesResponse,
+            api::handlers::account_email::EmailTokenRequest,
+            api::handlers::account_email::PasswordResetRequest,
+            api::handlers::account_email::PasswordResetConfirmRequest,
             api::handlers::captcha::CaptchaResponse,
             api::handlers::files::UploadResponse,
             api::handlers::files::PreCheckRequest,
@@ -82,8 +182,18 @@ use utoipa_swagger_ui::SwaggerUi;
             api::handlers::files::RenameRequest,
             api::handlers::files::BulkDeleteRequest,
             api::handlers::files::LinkFileRequest,
+            crate::services::file_service::ByteRange,
+            crate::services::file_service::PossessionChallenge,
             api::handlers::files::ZipEntry,
             api::handlers::files::BulkDeleteResponse,
+            api::handlers::files::TrashItemResponse,
+            api::handlers::files::EmptyTrashResponse,
+            api::handlers::files::FileVersionResponse,
+            api::handlers::files::PruneVersionsRequest,
+            api::handlers::files::PruneVersionsResponse,
+            api::handlers::files::BulkDownloadRequest,
+            api::handlers::files::DownloadTicketRequest,
+            api::handlers::files::DownloadTicketResponse,
             api::handlers::files::BulkMoveRequest,
             api::handlers::files::BulkMoveResponse,
             api::handlers::files::BulkCopyResponse,
@@ -95,6 +205,30 @@ use utoipa_swagger_ui::SwaggerUi;
             api::handlers::users::UserProfileResponse,
             api::handlers::users::UpdateProfileRequest,
             api::handlers::users::AvatarResponse,
+            api::handlers::users::QuotaResponse,
+            api::handlers::admin::SetQuotaRequest,
+            api::handlers::admin::AdminUserResponse,
+            api::handlers::admin::SetRoleRequest,
+            api::handlers::admin::AdminPasswordResetRequest,
+            api::handlers::admin::UserUsageResponse,
+            api::handlers::admin::StorageStatsResponse,
+            api::handlers::admin::AuditLogResponse,
+            api::handlers::validation_rules::AllowedMimeResponse,
+            api::handlers::validation_rules::AllowedMimeRequest,
+            api::handlers::validation_rules::MagicSignatureResponse,
+            api::handlers::validation_rules::MagicSignatureRequest,
+            api::handlers::validation_rules::BlockedExtensionResponse,
+            api::handlers::validation_rules::BlockedExtensionRequest,
+            api::handlers::validation_rules::ValidationTestRequest,
+            api::handlers::validation_rules::ValidationCheckResult,
+            api::handlers::validation_rules::ValidationTestResponse,
+            crate::utils::auth::Role,
+            api::handlers::teams::TeamNameRequest,
+            api::handlers::teams::AddTeamMemberRequest,
+            api::handlers::teams::UpdateTeamMemberRequest,
+            api::handlers::teams::TeamResponse,
+            api::handlers::teams::TeamMemberResponse,
+            crate::services::teams::TeamRole,
             crate::services::upload_service::InitUploadRequest,
             crate::services::upload_service::InitUploadResponse,
             crate::services::upload_service::UploadPartResponse,
@@ -104,8 +238,13 @@ use utoipa_swagger_ui::SwaggerUi;
             api::handlers::shares::ShareResponse,
             api::handlers::shares::ShareAccessLogResponse,
             api::handlers::shares::PublicShareInfoResponse,
+            api::handlers::shares::ShareUploadInfo,
+            api::handlers::shares::IncomingShareResponse,
+            api::handlers::shares::AddSharedItemRequest,
+            api::handlers::shares::PublicFileEntry,
             api::handlers::shares::VerifySharePasswordRequest,
             api::handlers::shares::VerifySharePasswordResponse,
+            api::handlers::shares::ShareLockoutResponse,
         )
     ),
     tags(
@@ -114,7 +253,9 @@ use utoipa_swagger_ui::SwaggerUi;
         (name = "users", description = "User profile endpoints"),
         (name = "settings", description = "User preferences endpoints"),
         (name = "system", description = "System health and status"),
-        (name = "shares", description = "File sharing endpoints")
+        (name = "shares", description = "File sharing endpoints"),
+        (name = "teams", description = "Team and shared drive endpoints"),
+        (name = "admin", description = "Administration endpoints")
     )
 )]
 pub struct ApiDoc;
@@ -124,10 +265,10 @@ pub struct AppState {
     pub db: DatabaseConnection,
     pub storage: Arc<dyn StorageService>,
     pub scanner: Arc<dyn VirusScanner>,
+    pub mailer: Arc<dyn Mailer>,
     pub file_service: Arc<FileService>,
     pub upload_service: Arc<crate::services::upload_service::UploadService>,
     pub config: SecurityConfig,
-    pub download_tickets: Arc<DashMap<String, (String, DateTime<Utc>)>>,
     pub captchas: Arc<DashMap<String, CaptchaChallenge>>,
     pub cooldowns: Arc<DashMap<String, CooldownEntry>>,
 }
@@ -149,6 +290,20 @@ pub fn create_app(state: AppState) -> Router {
         .route("/captcha", get(api::handlers::captcha::generate_captcha))
         .route("/register", post(api::handlers::auth::register))
         .route("/login", post(api::handlers::auth::login))
+        .route("/login/2fa", post(api::handlers::two_factor::login_2fa))
+        .route(
+            "/auth/verify-email",
+            post(api::handlers::account_email::verify_email),
+        )
+        .route(
+            "/auth/password-reset",
+            post(api::handlers::account_email::request_password_reset),
+        )
+        .route(
+            "/auth/password-reset/confirm",
+            post(api::handlers::account_email::confirm_password_reset),
+        )
+        .route("/auth/refresh", post(api::handlers::sessions::refresh))
         .route("/auth/oidc/login", get(api::handlers::auth::login_oidc))
         .route(
             "/auth/oidc/callback",
@@ -173,17 +328,64 @@ pub fn create_app(state: AppState) -> Router {
         .route(
             "/share/:token/download",
             get(api::handlers::shares::download_shared_file),
+        )
+        .route(
+            "/share/:token/zip",
+            get(api::handlers::shares::download_shared_folder_zip),
+        )
+        .route(
+            "/share/:token/upload",
+            post(api::handlers::shares::upload_to_share).layer(
+                axum::extract::DefaultBodyLimit::max(state.config.max_file_size + 10 * 1024 * 1024),
+            ),
+        )
+        .route(
+            "/storage/local/*key",
+            get(api::handlers::storage::serve_signed_object),
         );
 
+    // Per-user UPLOADS_PER_HOUR limits; these run after auth_middleware
+    let upload_rate_limit = from_fn_with_state(
+        state.clone(),
+        api::middleware::rate_limit::upload_rate_limit,
+    );
+    let pre_check_rate_limit = from_fn_with_state(
+        state.clone(),
+        api::middleware::rate_limit::pre_check_rate_limit,
+    );
+
+    // tus 1.0 resumable uploads; OPTIONS discovery is answered by tus_discovery below
+    let tus_routes = Router::new()
+        .route(
+            "/files/tus",
+            post(api::handlers::tus::tus_create).layer(upload_rate_limit.clone()),
+        )
+        .route(
+            "/files/tus/:id",
+            axum::routing::head(api::handlers::tus::tus_head)
+                .patch(api::handlers::tus::tus_patch)
+                .delete(api::handlers::tus::tus_terminate),
+        )
+        .layer(auth_middleware.clone())
+        .layer(from_fn(api::middleware::tus::tus_resumable));
+
     // Protected routes
     let protected_routes = Router::new()
-        .route("/pre-check", post(api::handlers::files::pre_check_dedup))
-        .route("/files/link", post(api::handlers::files::link_file))
+        .route(
+            "/pre-check",
+            post(api::handlers::files::pre_check_dedup).layer(pre_check_rate_limit),
+        )
+        .route(
+            "/files/link",
+            post(api::handlers::files::link_file).layer(upload_rate_limit.clone()),
+        )
         .route(
             "/upload",
-            post(api::handlers::files::upload_file).layer(axum::extract::DefaultBodyLimit::max(
-                state.config.max_file_size + 10 * 1024 * 1024,
-            )),
+            post(api::handlers::files::upload_file)
+                .layer::<_, std::convert::Infallible>(upload_rate_limit.clone())
+                .layer(axum::extract::DefaultBodyLimit::max(
+                    state.config.max_file_size + 10 * 1024 * 1024,
+                )),
         )
         .route(
             "/files/upload/sessions",
@@ -191,7 +393,7 @@ pub fn create_app(state: AppState) -> Router {
         )
         .route(
             "/files/upload/init",
-            post(api::handlers::upload::init_upload_handler),
+            post(api::handlers::upload::init_upload_handler).layer(upload_rate_limit.clone()),
         )
         .route(
             "/files/upload/:upload_id/chunk/:part_number",
@@ -221,6 +423,10 @@ pub fn create_app(state: AppState) -> Router {
             "/files/:id/ticket",
             post(api::handlers::files::generate_download_ticket),
         )
+        .route(
+            "/tickets/:ticket",
+            axum::routing::delete(api::handlers::files::revoke_download_ticket),
+        )
         .route(
             "/files/:id/rename",
             axum::routing::put(api::handlers::files::rename_item),
@@ -229,6 +435,26 @@ pub fn create_app(state: AppState) -> Router {
             "/files/:id/path",
             get(api::handlers::files::get_folder_path),
         )
+        .route(
+            "/files/:id/versions",
+            get(api::handlers::files::list_versions),
+        )
+        .route(
+            "/files/:id/versions/prune",
+            post(api::handlers::files::prune_versions),
+        )
+        .route(
+            "/files/:id/versions/:version_id",
+            get(api::handlers::files::download_version),
+        )
+        .route(
+            "/files/:id/versions/:version_id/restore",
+            post(api::handlers::files::restore_version),
+        )
+        .route(
+            "/files/:id/zip",
+            get(api::handlers::files::download_folder_zip),
+        )
         .route(
             "/files/:id/zip-contents",
             get(api::handlers::files::get_zip_contents),
@@ -241,6 +467,22 @@ pub fn create_app(state: AppState) -> Router {
             post(api::handlers::files::bulk_delete),
         )
         .route("/files/bulk-move", post(api::handlers::files::bulk_move))
+        .route(
+            "/files/bulk-download",
+            post(api::handlers::files::bulk_download),
+        )
+        .route(
+            "/trash",
+            get(api::handlers::files::list_trash).delete(api::handlers::files::empty_trash),
+        )
+        .route(
+            "/trash/:id",
+            axum::routing::delete(api::handlers::files::purge_item),
+        )
+        .route(
+            "/trash/:id/restore",
+            post(api::handlers::files::restore_item),
+        )
         .route("/files/bulk-copy", post(api::handlers::files::bulk_copy))
         .route(
             "/settings",
@@ -255,7 +497,51 @@ pub fn create_app(state: AppState) -> Router {
             "/users/me/avatar",
             post(api::handlers::users::upload_avatar),
         )
+        .route("/auth/logout", post(api::handlers::sessions::logout))
+        .route(
+            "/auth/sessions",
+            get(api::handlers::sessions::list_sessions)
+                .delete(api::handlers::sessions::revoke_all_sessions),
+        )
+        .route(
+            "/auth/sessions/:id",
+            axum::routing::delete(api::handlers::sessions::revoke_session),
+        )
+        .route(
+            "/auth/tokens",
+            get(api::handlers::api_tokens::list_api_tokens)
+                .post(api::handlers::api_tokens::create_api_token),
+        )
+        .route(
+            "/auth/tokens/:id",
+            axum::routing::delete(api::handlers::api_tokens::revoke_api_token),
+        )
+        .route(
+            "/auth/2fa",
+            get(api::handlers::two_factor::get_two_factor_status),
+        )
+        .route(
+            "/auth/2fa/enroll",
+            post(api::handlers::two_factor::enroll_two_factor),
+        )
+        .route(
+            "/auth/2fa/confirm",
+            post(api::handlers::two_factor::confirm_two_factor),
+        )
+        .route(
+            "/auth/2fa/recovery-codes",
+            post(api::handlers::two_factor::regenerate_recovery_codes),
+        )
+        .route(
+            "/auth/2fa/disable",
+            post(api::handlers::two_factor::disable_two_factor),
+        )
         .route("/users/me/facts", get(api::handlers::users::get_user_facts))
+        .route("/users/me/quota", get(api::handlers::users::get_quota))
+        .route(
+            "/users/me/email/verify",
+            post(api::handlers::account_email::send_verification_email),
+        )
         .route(
             "/shares",
             get(api::handlers::shares::list_shares).post(api::handlers::shares::create_share),
@@ -268,6 +554,148 @@ pub fn create_app(state: AppState) -> Router {
             "/shares/:id/logs",
             get(api::handlers::shares::get_share_logs),
         )
+        .route(
+            "/shares/:id/lockouts",
+            get(api::handlers::shares::list_share_lockouts)
+                .delete(api::handlers::shares::reset_share_lockouts),
+        )
+        .route(
+            "/shares/incoming",
+            get(api::handlers::shares::list_incoming_shares),
+        )
+        .route(
+            "/shares/incoming/:id/list",
+            get(api::handlers::shares::list_incoming_folder),
+        )
+        .route(
+            "/shares/incoming/:id/download",
+            get(api::handlers::shares::download_incoming_file),
+        )
+        .route(
+            "/shares/incoming/:id/add",
+            post(api::handlers::shares::add_incoming_to_drive),
+        )
+        .route(
+            "/shares/incoming/:id/upload",
+            post(api::handlers::shares::upload_to_incoming_share)
+                .layer::<_, std::convert::Infallible>(upload_rate_limit)
+                .layer(axum::extract::DefaultBodyLimit::max(
+                    state.config.max_file_size + 10 * 1024 * 1024,
+                )),
+        )
+        .route(
+            "/shares/incoming/:id/folders",
+            post(api::handlers::shares::create_incoming_folder),
+        )
+        .route(
+            "/shares/incoming/:id/items/:item_id",
+            axum::routing::put(api::handlers::shares::update_incoming_item)
+                .delete(api::handlers::shares::delete_incoming_item),
+        )
+        .route(
+            "/teams",
+            get(api::handlers::teams::list_teams).post(api::handlers::teams::create_team),
+        )
+        .route(
+            "/teams/:id",
+            get(api::handlers::teams::get_team)
+                .put(api::handlers::teams::rename_team)
+                .delete(api::handlers::teams::delete_team),
+        )
+        .route(
+            "/teams/:id/usage",
+            get(api::handlers::teams::get_team_usage),
+        )
+        .route(
+            "/teams/:id/members",
+            get(api::handlers::teams::list_team_members)
+                .post(api::handlers::teams::add_team_member),
+        )
+        .route(
+            "/teams/:id/members/:user_id",
+            axum::routing::put(api::handlers::teams::update_team_member)
+                .delete(api::handlers::teams::remove_team_member),
+        )
+        .layer(auth_middleware.clone());
+
+    // Administration; admin_middleware runs after auth_middleware
+    let admin_routes = Router::new()
+        .route("/admin/users", get(api::handlers::admin::list_users))
+        .route(
+            "/admin/users/:id",
+            get(api::handlers::admin::get_user).delete(api::handlers::admin::delete_user),
+        )
+        .route(
+            "/admin/users/:id/role",
+            axum::routing::put(api::handlers::admin::set_user_role),
+        )
+        .route(
+            "/admin/users/:id/disable",
+            post(api::handlers::admin::disable_user),
+        )
+        .route(
+            "/admin/users/:id/enable",
+            post(api::handlers::admin::enable_user),
+        )
+        .route(
+            "/admin/users/:id/password",
+            post(api::handlers::admin::reset_user_password),
+        )
+        .route(
+            "/admin/users/:id/usage",
+            get(api::handlers::admin::get_user_usage),
+        )
+        .route(
+            "/admin/users/:id/quota",
+            get(api::handlers::admin::get_user_quota).put(api::handlers::admin::set_user_quota),
+        )
+        .route(
+            "/admin/teams/:id/quota",
+            axum::routing::put(api::handlers::teams::set_team_quota),
+        )
+        .route("/admin/stats", get(api::handlers::admin::get_stats))
+        .route(
+            "/admin/audit-logs",
+            get(api::handlers::admin::list_audit_logs),
+        )
+        .route(
+            "/admin/validation/mimes",
+            get(api::handlers::validation_rules::list_mimes)
+                .post(api::handlers::validation_rules::create_mime),
+        )
+        .route(
+            "/admin/validation/mimes/:id",
+            axum::routing::put(api::handlers::validation_rules::update_mime)
+                .delete(api::handlers::validation_rules::delete_mime),
+        )
+        .route(
+            "/admin/validation/signatures",
+            get(api::handlers::validation_rules::list_signatures)
+                .post(api::handlers::validation_rules::create_signature),
+        )
+        .route(
+            "/admin/validation/signatures/:id",
+            axum::routing::put(api::handlers::validation_rules::update_signature)
+                .delete(api::handlers::validation_rules::delete_signature),
+        )
+        .route(
+            "/admin/validation/extensions",
+            get(api::handlers::validation_rules::list_extensions)
+                .post(api::handlers::validation_rules::create_extension),
+        )
+        .route(
+            "/admin/validation/extensions/:id",
+            axum::routing::put(api::handlers::validation_rules::update_extension)
+                .delete(api::handlers::validation_rules::delete_extension),
+        )
+        .route(
+            "/admin/validation/test",
+            post(api::handlers::validation_rules::test_validation),
+        )
+        .layer(from_fn_with_state(
+            state.clone(),
+            api::middleware::admin::admin_middleware,
+        ))
         .layer(auth_middleware);
 
     // Configure CORS based on allowed_origins
@@ -301,6 +729,8 @@ pub fn create_app(state: AppState) -> Router {
                 axum::http::Method::POST,
                 axum::http::Method::PUT,
                 axum::http::Method::DELETE,
+                axum::http::Method::PATCH,
+                axum::http::Method::HEAD,
                 axum::http::Method::OPTIONS,
             ])
             .allow_headers([
@@ -313,8 +743,19 @@ pub fn create_app(state: AppState) -> Router {
                 axum::http::header::PRAGMA,
                 axum::http::header::IF_NONE_MATCH,
                 axum::http::header::IF_MODIFIED_SINCE,
+                axum::http::header::RANGE,
+                axum::http::header::IF_RANGE,
                 axum::http::header::HeaderName::from_static("x-request-id"),
                 axum::http::header::HeaderName::from_static("x-requested-with"),
+                axum::http::header::HeaderName::from_static("tus-resumable"),
+                axum::http::header::HeaderName::from_static("upload-length"),
+                axum::http::header::HeaderName::from_static("upload-offset"),
+                axum::http::header::HeaderName::from_static("upload-metadata"),
+                axum::http::header::HeaderName::from_static("upload-checksum"),
+                axum::http::header::HeaderName::from_static("upload-defer-length"),
+                axum::http::header::HeaderName::from_static(
+                    api::handlers::shares::SHARE_PASSWORD_HEADER,
+                ),
             ])
             .expose_headers([
                 axum::http::header::CONTENT_LENGTH,
@@ -322,7 +763,23 @@ pub fn create_app(state: AppState) -> Router {
                 axum::http::header::CONTENT_DISPOSITION,
                 axum::http::header::ETAG,
                 axum::http::header::LAST_MODIFIED,
+                axum::http::header::ACCEPT_RANGES,
+                axum::http::header::CONTENT_RANGE,
+                axum::http::header::LOCATION,
                 axum::http::header::HeaderName::from_static("x-request-id"),
+                axum::http::header::HeaderName::from_static("tus-resumable"),
+                axum::http::header::HeaderName::from_static("tus-version"),
+                axum::http::header::HeaderName::from_static("tus-extension"),
+                axum::http::header::HeaderName::from_static("tus-max-size"),
+                axum::http::header::HeaderName::from_static("tus-checksum-algorithm"),
+                axum::http::header::HeaderName::from_static("upload-offset"),
+                axum::http::header::HeaderName::from_static("upload-length"),
+                axum::http::header::HeaderName::from_static("upload-metadata"),
+                axum::http::header::HeaderName::from_static("upload-expires"),
+                axum::http::header::HeaderName::from_static("x-file-id"),
+                axum::http::header::HeaderName::from_static(
+                    api::handlers::shares::RESUME_TOKEN_HEADER,
+                ),
             ])
             .allow_credentials(true)
     };
@@ -331,6 +788,8 @@ pub fn create_app(state: AppState) -> Router {
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .merge(public_routes)
         .merge(protected_routes)
+        .merge(admin_routes)
+        .merge(tus_routes)
         .layer(from_fn(api::middleware::metrics::metrics_middleware))
         .layer(from_fn(api::middleware::request_id::request_id_middleware))
         .layer(from_fn(api::middleware::security::security_headers))
@@ -338,5 +797,14 @@ pub fn create_app(state: AppState) -> Router {
             state.config.max_file_size + 10 * 1024 * 1024,
         ))
         .layer(cors_layer)
+        .layer(from_fn_with_state(
+            state.clone(),
+            api::middleware::client_ip::client_ip,
+        ))
+        // Outside CORS, which would otherwise treat every OPTIONS as a preflight
+        .layer(from_fn_with_state(
+            state.clone(),
+            api::middleware::tus::tus_discovery,
+        ))
         .with_state(state)
 }
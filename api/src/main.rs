@@ -2,7 +2,7 @@ use clap::Parser;
 use dashmap::DashMap;
 use dotenvy::dotenv;
 use rust_file_backend::api::handlers::captcha::{CaptchaChallenge, CooldownEntry, cleanup_expired};
-use rust_file_backend::infrastructure::{database, scanner, storage};
+use rust_file_backend::infrastructure::{database, mailer, scanner, storage};
 use rust_file_backend::services::file_service::FileService;
 use rust_file_backend::{AppState, create_app};
 use std::net::SocketAddr;
@@ -22,6 +22,10 @@ struct Args {
     /// Port for the API server
     #[arg(short, long, default_value_t = 3000)]
     port: u16,
+
+    /// Give the admin role to this user id, then exit
+    #[arg(long, value_name = "USER_ID")]
+    grant_admin: Option<String>,
 }
 
 #[tokio::main]
@@ -42,7 +46,6 @@ async fn main() -> anyhow::Result<()> {
 
     // 2. Setup Common Infrastructure
     let db = database::setup_database().await?;
-    let storage_service = storage::setup_storage().await;
 
     // Load security config
     let security_config = rust_file_backend::config::SecurityConfig::from_env();
@@ -53,8 +56,21 @@ async fn main() -> anyhow::Result<()> {
         security_config.virus_scanner_type
     );
 
+    if let Some(user_id) = args.grant_admin.as_deref() {
+        let user =
+            rust_file_backend::services::user_admin::UserAdminService::grant_admin(&db, user_id)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to grant the admin role: {}", e))?;
+        info!("👑 {} ({}) now has the admin role", user.username, user.id);
+        return Ok(());
+    }
+
+    let storage_service = storage::setup_storage(&security_config).await;
+
     let scanner_service = scanner::setup_scanner(&security_config).await;
 
+    let mailer_service = mailer::setup_mailer(&security_config).await?;
+
     // 3. Setup Graceful Shutdown Channel
     let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
     let mut handles = Vec::new();
@@ -163,10 +179,10 @@ async fn main() -> anyhow::Result<()> {
             db: db.clone(),
             storage: storage_service.clone(),
             scanner: scanner_service.clone(),
+            mailer: mailer_service.clone(),
             file_service,
             upload_service,
             config: security_config.clone(),
-            download_tickets: Arc::new(DashMap::new()),
             captchas: captchas.clone(),
             cooldowns: cooldowns.clone(),
         };
@@ -225,11 +241,14 @@ async fn main() -> anyhow::Result<()> {
         );
 
         let server_handle = tokio::spawn(async move {
-            if let Err(e) = axum::serve(listener, app)
-                .with_graceful_shutdown(async move {
-                    shutdown_signal().await;
-                })
-                .await
+            if let Err(e) = axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .with_graceful_shutdown(async move {
+                shutdown_signal().await;
+            })
+            .await
             {
                 error!("❌ Server runtime error: {}", e);
             }
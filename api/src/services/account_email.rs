@@ -0,0 +1,301 @@
+use crate::api::error::AppError;
+use crate::entities::{prelude::*, *};
+use crate::services::mailer::{Email, Mailer};
+use crate::services::rate_limiter::RateLimitService;
+use crate::utils::hash::calculate_hash;
+use base64::Engine;
+use chrono::{Duration, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter,
+    Set, sea_query::Expr,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// `email_tokens.purpose` of email verification links
+pub const VERIFY_EMAIL: &str = "verify_email";
+/// `email_tokens.purpose` of password reset links
+pub const PASSWORD_RESET: &str = "password_reset";
+/// Rate limit bucket for reset emails, counted against the target account
+pub const PASSWORD_RESET_BUCKET: &str = "password_reset";
+/// Reset emails one account may receive per hour
+const PASSWORD_RESET_EMAILS_PER_HOUR: u32 = 3;
+const VERIFY_EMAIL_TTL_HOURS: i64 = 24;
+const PASSWORD_RESET_TTL_MINUTES: i64 = 60;
+
+/// Email verification and password reset links
+///
+/// Each link carries a random token that works once and expires; only its
+/// SHA-256 is stored. A token is also tied to the address it was sent to,
+/// so changing the account's email invalidates outstanding links.
+pub struct AccountEmailService;
+
+impl AccountEmailService {
+    /// Email a verification link to the user's current address
+    pub async fn send_verification(
+        db: &DatabaseConnection,
+        mailer: &dyn Mailer,
+        user: &users::Model,
+    ) -> Result<(), AppError> {
+        let email = user
+            .email
+            .as_deref()
+            .filter(|e| !e.trim().is_empty())
+            .ok_or(AppError::BadRequest(
+                "Add an email address to your profile first".to_string(),
+            ))?;
+        if user.email_verified_at.is_some() {
+            return Err(AppError::Conflict(
+                "Email address is already verified".to_string(),
+            ));
+        }
+
+        let token = Self::issue(
+            db,
+            &user.id,
+            VERIFY_EMAIL,
+            email,
+            Duration::hours(VERIFY_EMAIL_TTL_HOURS),
+        )
+        .await?;
+        let body = format!(
+            "Hello {},\n\n\
+             Confirm your email address by opening this link:\n\n{}\n\n\
+             The link expires in {} hours. If you did not ask for this, ignore this email.\n",
+            user.username,
+            Self::link("verify-email", &token),
+            VERIFY_EMAIL_TTL_HOURS
+        );
+        Self::deliver(db, mailer, email, "Verify your email address", body, &token).await
+    }
+
+    /// Mark the address a verification token was sent to as verified
+    pub async fn verify_email(
+        db: &DatabaseConnection,
+        token: &str,
+    ) -> Result<users::Model, AppError> {
+        let (record, user) = Self::consume(db, token, VERIFY_EMAIL).await?;
+        let mut active = user.into_active_model();
+        active.email_verified_at = Set(Some(Utc::now()));
+        let user = active.update(db).await?;
+        Self::discard(db, &record.user_id, VERIFY_EMAIL).await?;
+        Ok(user)
+    }
+
+    /// Email a reset link to every account with this verified address
+    ///
+    /// Unknown addresses are not reported, so callers cannot probe which
+    /// addresses have accounts. For the same reason the emails are sent in
+    /// the background and delivery failures are only logged. Returns the IDs
+    /// of the accounts a link was issued to.
+    pub async fn request_password_reset(
+        db: &DatabaseConnection,
+        mailer: &Arc<dyn Mailer>,
+        email: &str,
+    ) -> Result<Vec<String>, AppError> {
+        let email = email.trim();
+        if email.is_empty() {
+            return Ok(Vec::new());
+        }
+        let users = Users::find()
+            .filter(users::Column::Email.eq(email))
+            .filter(users::Column::EmailVerifiedAt.is_not_null())
+            .all(db)
+            .await?;
+
+        let mut emailed = Vec::new();
+        for user in users {
+            match RateLimitService::hit(
+                db,
+                &user.id,
+                PASSWORD_RESET_BUCKET,
+                PASSWORD_RESET_EMAILS_PER_HOUR,
+                Duration::hours(1),
+            )
+            .await
+            {
+                Ok(()) => {}
+                Err(AppError::TooManyRequests { .. }) => {
+                    tracing::warn!("Password reset emails to user {} rate limited", user.id);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+
+            let token = Self::issue(
+                db,
+                &user.id,
+                PASSWORD_RESET,
+                email,
+                Duration::minutes(PASSWORD_RESET_TTL_MINUTES),
+            )
+            .await?;
+            let body = format!(
+                "Hello {},\n\n\
+                 Someone asked to reset the password of your account. Choose a new password here:\n\n{}\n\n\
+                 The link expires in {} minutes and works once. If you did not ask for this, ignore this email; your password is unchanged.\n",
+                user.username,
+                Self::link("reset-password", &token),
+                PASSWORD_RESET_TTL_MINUTES
+            );
+            let (db, mailer, to) = (db.clone(), mailer.clone(), email.to_string());
+            let user_id = user.id.clone();
+            tokio::spawn(async move {
+                let subject = "Reset your password";
+                if let Err(e) =
+                    Self::deliver(&db, mailer.as_ref(), &to, subject, body, &token).await
+                {
+                    tracing::warn!("Password reset email to user {} not sent: {}", user_id, e);
+                }
+            });
+            emailed.push(user.id);
+        }
+        Ok(emailed)
+    }
+
+    /// Set a new password hash with a reset token, voiding the account's
+    /// other reset links
+    pub async fn reset_password(
+        db: &DatabaseConnection,
+        token: &str,
+        password_hash: String,
+    ) -> Result<users::Model, AppError> {
+        let (record, user) = Self::consume(db, token, PASSWORD_RESET).await?;
+        let mut active = user.into_active_model();
+        active.password_hash = Set(Some(password_hash));
+        let user = active.update(db).await?;
+        Self::discard(db, &record.user_id, PASSWORD_RESET).await?;
+        Ok(user)
+    }
+
+    /// Delete expired and used tokens
+    pub async fn sweep(db: &DatabaseConnection) -> Result<u64, AppError> {
+        let res = EmailTokens::delete_many()
+            .filter(
+                sea_orm::Condition::any()
+                    .add(email_tokens::Column::ExpiresAt.lt(Utc::now()))
+                    .add(email_tokens::Column::UsedAt.is_not_null()),
+            )
+            .exec(db)
+            .await?;
+        Ok(res.rows_affected)
+    }
+
+    async fn issue(
+        db: &DatabaseConnection,
+        user_id: &str,
+        purpose: &str,
+        email: &str,
+        ttl: Duration,
+    ) -> Result<String, AppError> {
+        // Only the newest link of each kind works
+        Self::discard(db, user_id, purpose).await?;
+
+        use rand::Rng;
+        let bytes: [u8; 32] = rand::thread_rng().r#gen();
+        let token = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+        let now = Utc::now();
+        email_tokens::ActiveModel {
+            id: Set(Uuid::new_v4().to_string()),
+            user_id: Set(user_id.to_string()),
+            purpose: Set(purpose.to_string()),
+            token_hash: Set(calculate_hash(token.as_bytes())),
+            email: Set(email.to_string()),
+            created_at: Set(now),
+            expires_at: Set(now + ttl),
+            used_at: Set(None),
+        }
+        .insert(db)
+        .await?;
+        Ok(token)
+    }
+
+    /// Send a link email, withdrawing its token if delivery fails
+    async fn deliver(
+        db: &DatabaseConnection,
+        mailer: &dyn Mailer,
+        to: &str,
+        subject: &str,
+        body: String,
+        token: &str,
+    ) -> Result<(), AppError> {
+        let email = Email {
+            to: to.to_string(),
+            subject: subject.to_string(),
+            body,
+        };
+        if let Err(e) = mailer.send(&email).await {
+            tracing::error!("Failed to send '{}' email: {:#}", subject, e);
+            EmailTokens::delete_many()
+                .filter(email_tokens::Column::TokenHash.eq(calculate_hash(token.as_bytes())))
+                .exec(db)
+                .await?;
+            return Err(AppError::Internal("Could not send email".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Use up a token, returning it with its user
+    async fn consume(
+        db: &DatabaseConnection,
+        token: &str,
+        purpose: &str,
+    ) -> Result<(email_tokens::Model, users::Model), AppError> {
+        let invalid = || AppError::BadRequest("This link is invalid or has expired".to_string());
+        let hash = calculate_hash(token.as_bytes());
+        let now = Utc::now();
+
+        // Only one request can use a token
+        let res = EmailTokens::update_many()
+            .col_expr(email_tokens::Column::UsedAt, Expr::value(now))
+            .filter(email_tokens::Column::TokenHash.eq(&hash))
+            .filter(email_tokens::Column::Purpose.eq(purpose))
+            .filter(email_tokens::Column::UsedAt.is_null())
+            .filter(email_tokens::Column::ExpiresAt.gt(now))
+            .exec(db)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(invalid());
+        }
+
+        let record = EmailTokens::find()
+            .filter(email_tokens::Column::TokenHash.eq(&hash))
+            .one(db)
+            .await?
+            .ok_or_else(invalid)?;
+        let user = Users::find_by_id(&record.user_id)
+            .one(db)
+            .await?
+            .ok_or_else(invalid)?;
+        if user.email.as_deref() != Some(record.email.as_str()) {
+            return Err(invalid());
+        }
+        Ok((record, user))
+    }
+
+    /// Drop a user's unused tokens of one kind
+    async fn discard(
+        db: &DatabaseConnection,
+        user_id: &str,
+        purpose: &str,
+    ) -> Result<(), AppError> {
+        EmailTokens::delete_many()
+            .filter(email_tokens::Column::UserId.eq(user_id))
+            .filter(email_tokens::Column::Purpose.eq(purpose))
+            .filter(email_tokens::Column::UsedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    fn link(path: &str, token: &str) -> String {
+        let frontend_url =
+            std::env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:5173".to_string());
+        format!(
+            "{}/{}?token={}",
+            frontend_url.trim_end_matches('/'),
+            path,
+            token
+        )
+    }
+}
@@ -0,0 +1,167 @@
+use crate::api::error::AppError;
+use crate::entities::{prelude::*, *};
+use crate::utils::auth::{Claims, Scope};
+use crate::utils::hash::calculate_hash;
+use base64::Engine;
+use chrono::{Duration, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter,
+    QueryOrder, Set, sea_query::Expr,
+};
+use uuid::Uuid;
+
+/// Marks a bearer token as a personal access token rather than a JWT
+pub const API_TOKEN_PREFIX: &str = "rfb_pat_";
+/// Longest lifetime a token may be given
+pub const MAX_TOKEN_LIFETIME_DAYS: i64 = 365;
+/// Characters of the token kept in clear to recognise it by
+const DISPLAY_PREFIX_LEN: usize = API_TOKEN_PREFIX.len() + 4;
+/// `last_used_at` is only rewritten once this much time has passed
+const LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+/// Personal access tokens kept in `api_tokens`
+///
+/// The token itself is shown once, when created; the database keeps only
+/// its SHA-256, so a leaked database does not leak working tokens.
+pub struct ApiTokenService;
+
+impl ApiTokenService {
+    /// Create a token for `user_id`, returning its row and the token itself
+    pub async fn create(
+        db: &DatabaseConnection,
+        user_id: &str,
+        name: &str,
+        scopes: &[Scope],
+        expires_in_days: Option<i64>,
+    ) -> Result<(api_tokens::Model, String), AppError> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > 100 {
+            return Err(AppError::BadRequest(
+                "Token name must be between 1 and 100 characters".to_string(),
+            ));
+        }
+        if scopes.is_empty() {
+            return Err(AppError::BadRequest(
+                "A token needs at least one scope".to_string(),
+            ));
+        }
+        if expires_in_days.is_some_and(|days| !(1..=MAX_TOKEN_LIFETIME_DAYS).contains(&days)) {
+            return Err(AppError::BadRequest(format!(
+                "Token lifetime must be between 1 and {} days",
+                MAX_TOKEN_LIFETIME_DAYS
+            )));
+        }
+
+        let mut scope_names: Vec<&str> = Vec::new();
+        for scope in scopes {
+            if !scope_names.contains(&scope.as_str()) {
+                scope_names.push(scope.as_str());
+            }
+        }
+
+        let token = Self::generate_token();
+        let now = Utc::now();
+        let model = api_tokens::ActiveModel {
+            id: Set(Uuid::new_v4().to_string()),
+            user_id: Set(user_id.to_string()),
+            name: Set(name.to_string()),
+            token_hash: Set(calculate_hash(token.as_bytes())),
+            token_prefix: Set(token[..DISPLAY_PREFIX_LEN].to_string()),
+            scopes: Set(scope_names.join(",")),
+            created_at: Set(now),
+            expires_at: Set(expires_in_days.map(|days| now + Duration::days(days))),
+            last_used_at: Set(None),
+        }
+        .insert(db)
+        .await?;
+        Ok((model, token))
+    }
+
+    /// Tokens of `user_id`, newest first, including expired ones
+    pub async fn list(
+        db: &DatabaseConnection,
+        user_id: &str,
+    ) -> Result<Vec<api_tokens::Model>, AppError> {
+        let tokens = ApiTokens::find()
+            .filter(api_tokens::Column::UserId.eq(user_id))
+            .order_by_desc(api_tokens::Column::CreatedAt)
+            .all(db)
+            .await?;
+        Ok(tokens)
+    }
+
+    /// Delete one of `user_id`'s tokens; it stops working at once
+    pub async fn revoke(
+        db: &DatabaseConnection,
+        user_id: &str,
+        token_id: &str,
+    ) -> Result<(), AppError> {
+        let res = ApiTokens::delete_many()
+            .filter(api_tokens::Column::Id.eq(token_id))
+            .filter(api_tokens::Column::UserId.eq(user_id))
+            .exec(db)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(AppError::NotFound("Token not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Claims for a valid, unexpired token, recording that it was used
+    pub async fn authenticate(
+        db: &DatabaseConnection,
+        token: &str,
+    ) -> Result<Option<Claims>, AppError> {
+        let Some(model) = ApiTokens::find()
+            .filter(api_tokens::Column::TokenHash.eq(calculate_hash(token.as_bytes())))
+            .one(db)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let now = Utc::now();
+        if model.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Ok(None);
+        }
+
+        ApiTokens::update_many()
+            .col_expr(api_tokens::Column::LastUsedAt, Expr::value(now))
+            .filter(api_tokens::Column::Id.eq(&model.id))
+            .filter(
+                Condition::any()
+                    .add(api_tokens::Column::LastUsedAt.is_null())
+                    .add(
+                        api_tokens::Column::LastUsedAt
+                            .lt(now - Duration::seconds(LAST_USED_RESOLUTION_SECS)),
+                    ),
+            )
+            .exec(db)
+            .await?;
+
+        Ok(Some(Claims {
+            sub: model.user_id.clone(),
+            exp: model
+                .expires_at
+                .map_or(usize::MAX, |expires_at| expires_at.timestamp() as usize),
+            jti: model.id.clone(),
+            sid: None,
+            scopes: Some(Self::scopes(&model)),
+        }))
+    }
+
+    /// The scopes a token grants
+    pub fn scopes(model: &api_tokens::Model) -> Vec<Scope> {
+        model.scopes.split(',').filter_map(Scope::parse).collect()
+    }
+
+    fn generate_token() -> String {
+        use rand::Rng;
+        let bytes: [u8; 32] = rand::thread_rng().r#gen();
+        format!(
+            "{}{}",
+            API_TOKEN_PREFIX,
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+        )
+    }
+}
@@ -10,6 +10,15 @@ use uuid::Uuid;
 pub enum AuditEventType {
     UserLogin,
     UserRegister,
+    UserLogout,
+    SessionRevoke,
+    ApiTokenCreate,
+    ApiTokenRevoke,
+    TwoFactorEnroll,
+    TwoFactorEnable,
+    TwoFactorDisable,
+    EmailVerify,
+    PasswordReset,
     KeyGeneration,
     FileEncrypt,
     FileUpload,
@@ -17,9 +26,22 @@ pub enum AuditEventType {
     FileDecrypt,
     FileAccess,
     FileDelete,
+    FileRestore,
+    FilePurge,
+    QuotaUpdate,
+    UserRoleChange,
+    UserDisable,
+    UserEnable,
+    UserDelete,
+    ValidationRuleChange,
+    TeamCreate,
+    TeamUpdate,
+    TeamDelete,
+    TeamMemberChange,
     ShareCreate,
     ShareRevoke,
     ShareAccess,
+    ShareEdit,
     SystemError,
 }
 
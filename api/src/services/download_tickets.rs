@@ -0,0 +1,153 @@
+use crate::api::error::AppError;
+use crate::entities::{prelude::*, *};
+use crate::services::drives::{DriveAccess, Permission};
+use chrono::{Duration, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, Set,
+    sea_query::Expr,
+};
+use uuid::Uuid;
+
+/// Lifetime of a ticket when the caller does not choose one
+pub const DEFAULT_TICKET_TTL_SECS: i64 = 12 * 60 * 60;
+/// Longest lifetime a caller may ask for
+pub const MAX_TICKET_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Restrictions chosen when a ticket is issued
+#[derive(Debug, Default, Clone)]
+pub struct TicketOptions {
+    pub ttl_secs: Option<i64>,
+    pub max_downloads: Option<i32>,
+    /// Client IP the ticket is bound to
+    pub bound_ip: Option<String>,
+}
+
+/// Download tickets kept in `download_tickets`, so they survive restarts and
+/// work on every API instance sharing the database
+pub struct DownloadTicketService;
+
+impl DownloadTicketService {
+    pub async fn issue(
+        db: &DatabaseConnection,
+        user_id: &str,
+        user_file_id: &str,
+        options: TicketOptions,
+    ) -> Result<download_tickets::Model, AppError> {
+        let ttl_secs = options.ttl_secs.unwrap_or(DEFAULT_TICKET_TTL_SECS);
+        if !(1..=MAX_TICKET_TTL_SECS).contains(&ttl_secs) {
+            return Err(AppError::BadRequest(format!(
+                "Ticket lifetime must be between 1 and {} seconds",
+                MAX_TICKET_TTL_SECS
+            )));
+        }
+        if options.max_downloads.is_some_and(|max| max < 1) {
+            return Err(AppError::BadRequest(
+                "max_downloads must be at least 1".to_string(),
+            ));
+        }
+
+        let now = Utc::now();
+        let ticket = download_tickets::ActiveModel {
+            id: Set(Uuid::new_v4().to_string()),
+            user_id: Set(user_id.to_string()),
+            user_file_id: Set(user_file_id.to_string()),
+            max_downloads: Set(options.max_downloads),
+            download_count: Set(0),
+            bound_ip: Set(options.bound_ip),
+            created_at: Set(now),
+            expires_at: Set(now + Duration::seconds(ttl_secs)),
+        }
+        .insert(db)
+        .await?;
+        Ok(ticket)
+    }
+
+    /// Check `ticket` for a request from `client_ip` and count one download against it
+    ///
+    /// A ticket only works while its creator can still read the file.
+    pub async fn redeem(
+        db: &DatabaseConnection,
+        ticket: &str,
+        client_ip: &str,
+    ) -> Result<download_tickets::Model, AppError> {
+        let model = DownloadTickets::find_by_id(ticket)
+            .one(db)
+            .await?
+            .ok_or(AppError::Forbidden("Invalid ticket".to_string()))?;
+
+        if model.expires_at < Utc::now() {
+            return Err(AppError::Forbidden("Ticket expired".to_string()));
+        }
+        if model.bound_ip.as_deref().is_some_and(|ip| ip != client_ip) {
+            return Err(AppError::Forbidden(
+                "Ticket is not valid from this address".to_string(),
+            ));
+        }
+        // The creator may have lost access since, e.g. by leaving the team
+        if let Some(item) = UserFiles::find_by_id(&model.user_file_id).one(db).await?
+            && DriveAccess::authorize(db, &model.user_id, Some(item), Permission::Read)
+                .await?
+                .is_none()
+        {
+            return Err(AppError::Forbidden(
+                "Ticket creator can no longer access this file".to_string(),
+            ));
+        }
+
+        // Conditional increment, so concurrent requests cannot overspend the ticket
+        let res = DownloadTickets::update_many()
+            .col_expr(
+                download_tickets::Column::DownloadCount,
+                Expr::col(download_tickets::Column::DownloadCount).add(1),
+            )
+            .filter(download_tickets::Column::Id.eq(ticket))
+            .filter(
+                Condition::any()
+                    .add(download_tickets::Column::MaxDownloads.is_null())
+                    .add(
+                        Expr::col(download_tickets::Column::DownloadCount)
+                            .lt(Expr::col(download_tickets::Column::MaxDownloads)),
+                    ),
+            )
+            .exec(db)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(AppError::Forbidden("Ticket has been used up".to_string()));
+        }
+
+        Ok(model)
+    }
+
+    /// Delete one of `user_id`'s tickets
+    pub async fn revoke(
+        db: &DatabaseConnection,
+        user_id: &str,
+        ticket: &str,
+    ) -> Result<(), AppError> {
+        let res = DownloadTickets::delete_many()
+            .filter(download_tickets::Column::Id.eq(ticket))
+            .filter(download_tickets::Column::UserId.eq(user_id))
+            .exec(db)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(AppError::NotFound("Ticket not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Delete tickets that expired or ran out of downloads
+    pub async fn sweep(db: &DatabaseConnection) -> Result<u64, AppError> {
+        let res = DownloadTickets::delete_many()
+            .filter(
+                Condition::any()
+                    .add(download_tickets::Column::ExpiresAt.lt(Utc::now()))
+                    .add(
+                        Expr::col(download_tickets::Column::DownloadCount)
+                            .gte(Expr::col(download_tickets::Column::MaxDownloads)),
+                    ),
+            )
+            .exec(db)
+            .await?;
+        Ok(res.rows_affected)
+    }
+}
@@ -0,0 +1,207 @@
+use crate::api::error::AppError;
+use crate::entities::{prelude::*, *};
+use crate::services::teams::TeamRole;
+use sea_orm::{ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter};
+
+/// Where a file lives: a user's personal space or a team's shared drive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Drive {
+    /// Files of this user that belong to no team
+    Personal(String),
+    /// Files of this team, whoever added them
+    Team(String),
+}
+
+impl Drive {
+    pub fn of(item: &user_files::Model) -> Self {
+        match &item.team_id {
+            Some(team_id) => Drive::Team(team_id.clone()),
+            None => Drive::Personal(item.user_id.clone()),
+        }
+    }
+
+    /// Matches the rows of this drive
+    pub fn condition(&self) -> Condition {
+        match self {
+            Drive::Personal(user_id) => Condition::all()
+                .add(user_files::Column::UserId.eq(user_id.as_str()))
+                .add(user_files::Column::TeamId.is_null()),
+            Drive::Team(team_id) => {
+                Condition::all().add(user_files::Column::TeamId.eq(team_id.as_str()))
+            }
+        }
+    }
+
+    pub fn team_id(&self) -> Option<String> {
+        match self {
+            Drive::Personal(_) => None,
+            Drive::Team(team_id) => Some(team_id.clone()),
+        }
+    }
+
+    /// Key for `FileService::bulk_lock`, serializing writes that count against one quota
+    ///
+    /// Personal drives keep the bare user id that per-user locks already use.
+    pub fn lock_key(&self) -> String {
+        match self {
+            Drive::Personal(user_id) => user_id.clone(),
+            Drive::Team(team_id) => format!("team:{}", team_id),
+        }
+    }
+}
+
+/// Folder new items are placed in, and the drive it belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Destination {
+    pub drive: Drive,
+    /// `None` for the drive's root
+    pub parent_id: Option<String>,
+}
+
+impl Destination {
+    /// The root of `user_id`'s personal drive
+    pub fn personal_root(user_id: &str) -> Self {
+        Self {
+            drive: Drive::Personal(user_id.to_string()),
+            parent_id: None,
+        }
+    }
+
+    /// Inside `folder`, in whichever drive it lives
+    pub fn folder(folder: &user_files::Model) -> Self {
+        Self {
+            drive: Drive::of(folder),
+            parent_id: Some(folder.id.clone()),
+        }
+    }
+}
+
+/// What a caller wants to do with a drive's files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// List, download and inspect
+    Read,
+    /// Anything that adds, changes, moves or removes files
+    Write,
+}
+
+/// Resolves which files a user may reach
+///
+/// Personal files are visible to their owner only. Team files are visible to
+/// every member; writing them takes the owner or editor role. Items outside
+/// the caller's drives are reported as missing rather than forbidden, so ids
+/// of other users' files leak nothing.
+pub struct DriveAccess;
+
+impl DriveAccess {
+    /// `user_id`'s role in `team_id`, if they are a member
+    pub async fn role(
+        db: &impl ConnectionTrait,
+        user_id: &str,
+        team_id: &str,
+    ) -> Result<Option<TeamRole>, AppError> {
+        Ok(
+            TeamMembers::find_by_id((team_id.to_string(), user_id.to_string()))
+                .one(db)
+                .await?
+                .and_then(|m| TeamRole::parse(&m.role)),
+        )
+    }
+
+    /// Whether `user_id` can see `drive` at all, and fail with 403 if they
+    /// can but `permission` is beyond their role
+    async fn visible(
+        db: &impl ConnectionTrait,
+        user_id: &str,
+        drive: &Drive,
+        permission: Permission,
+    ) -> Result<bool, AppError> {
+        match drive {
+            Drive::Personal(owner) => Ok(owner == user_id),
+            Drive::Team(team_id) => match Self::role(db, user_id, team_id).await? {
+                None => Ok(false),
+                Some(role) if role.allows(permission) => Ok(true),
+                Some(_) => Err(AppError::Forbidden(
+                    "Viewers cannot change team files".to_string(),
+                )),
+            },
+        }
+    }
+
+    /// `item` if `user_id` may access it with `permission`
+    pub async fn authorize(
+        db: &impl ConnectionTrait,
+        user_id: &str,
+        item: Option<user_files::Model>,
+        permission: Permission,
+    ) -> Result<Option<user_files::Model>, AppError> {
+        let Some(item) = item else {
+            return Ok(None);
+        };
+        if Self::visible(db, user_id, &Drive::of(&item), permission).await? {
+            Ok(Some(item))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// The live item `id`, if `user_id` may access it with `permission`
+    pub async fn find_item(
+        db: &impl ConnectionTrait,
+        user_id: &str,
+        id: &str,
+        permission: Permission,
+    ) -> Result<Option<user_files::Model>, AppError> {
+        let item = UserFiles::find_by_id(id)
+            .filter(user_files::Column::DeletedAt.is_null())
+            .one(db)
+            .await?;
+        Self::authorize(db, user_id, item, permission).await
+    }
+
+    /// The drive `team_id` names, or the caller's personal drive when it is `None`
+    pub async fn drive(
+        db: &impl ConnectionTrait,
+        user_id: &str,
+        team_id: Option<&str>,
+        permission: Permission,
+    ) -> Result<Drive, AppError> {
+        let drive = match team_id {
+            Some(team_id) => Drive::Team(team_id.to_string()),
+            None => Drive::Personal(user_id.to_string()),
+        };
+        if !Self::visible(db, user_id, &drive, permission).await? {
+            return Err(AppError::NotFound("Team not found".to_string()));
+        }
+        Ok(drive)
+    }
+
+    /// Where new items under `parent_id` land, after checking write access
+    ///
+    /// A parent folder decides the drive by itself; `team_id`, if also given,
+    /// must agree with it. Without a parent the items go to the root of the
+    /// `team_id` drive, or of the caller's personal drive.
+    pub async fn destination(
+        db: &impl ConnectionTrait,
+        user_id: &str,
+        parent_id: Option<&str>,
+        team_id: Option<&str>,
+    ) -> Result<Destination, AppError> {
+        let Some(parent_id) = parent_id else {
+            return Ok(Destination {
+                drive: Self::drive(db, user_id, team_id, Permission::Write).await?,
+                parent_id: None,
+            });
+        };
+        let parent = Self::find_item(db, user_id, parent_id, Permission::Write)
+            .await?
+            .filter(|p| p.is_folder)
+            .ok_or_else(|| AppError::NotFound("Target folder not found".to_string()))?;
+        if team_id.is_some() && parent.team_id.as_deref() != team_id {
+            return Err(AppError::BadRequest(
+                "Target folder is not in the given team drive".to_string(),
+            ));
+        }
+        Ok(Destination::folder(&parent))
+    }
+}
@@ -1,11 +1,9 @@
 use crate::entities::{prelude::*, *};
-use crate::services::storage::StorageService;
 use chrono::Utc;
 use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect};
-use std::sync::Arc;
 use tokio::time::{Duration, sleep};
 
-pub async fn expiration_worker(db: DatabaseConnection, storage: Arc<dyn StorageService>) {
+pub async fn expiration_worker(db: DatabaseConnection) {
     loop {
         tracing::info!("Running expiration worker...");
 
@@ -19,21 +17,20 @@ pub async fn expiration_worker(db: DatabaseConnection, storage: Arc<dyn StorageS
             for file in files {
                 tracing::info!("Expiring file: {}", file.id);
 
-                if let Err(e) = crate::services::storage_lifecycle::StorageLifecycleService::soft_delete_user_file(
-                    &db,
-                    storage.as_ref(),
-                    &file,
-                ).await {
+                if let Err(e) =
+                    crate::services::storage_lifecycle::StorageLifecycleService::trash_user_file(
+                        &db, &file,
+                    )
+                    .await
+                {
                     tracing::error!("Failed to expire file {}: {}", file.id, e);
                 }
             }
         }
 
-        // Clean up expired tokens
-        let _ = Tokens::delete_many()
-            .filter(tokens::Column::ExpiresAt.lt(Utc::now()))
-            .exec(&db)
-            .await;
+        // Clean up expired sessions, revoked access tokens and email links
+        let _ = crate::services::sessions::SessionService::sweep(&db).await;
+        let _ = crate::services::account_email::AccountEmailService::sweep(&db).await;
 
         sleep(Duration::from_secs(3600)).await; // Run every hour
     }
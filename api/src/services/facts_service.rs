@@ -27,6 +27,7 @@ impl FactsService {
                     .into(),
             )
             .filter(user_files::Column::UserId.eq(user_id))
+            .filter(user_files::Column::TeamId.is_null())
             .filter(user_files::Column::DeletedAt.is_null())
             .filter(user_files::Column::IsFolder.eq(false))
             .into_model::<FileFactRow>()
@@ -1,22 +1,38 @@
 use crate::api::error::AppError;
 use crate::entities::{prelude::*, *};
+use crate::services::drives::{Drive, DriveAccess, Permission};
+use crate::services::quota::QuotaService;
 use chrono::Utc;
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set, sea_query::Expr};
 use uuid::Uuid;
 
 use super::FileService;
 
 impl FileService {
+    /// Move items under `new_parent_id`, or to the root of the `team_id` drive
+    /// (the personal drive when `None`)
+    ///
+    /// Items from another drive are moved there with everything under them.
     pub async fn bulk_move(
         &self,
         user_id: &str,
         item_ids: Vec<String>,
         new_parent_id: Option<String>,
+        team_id: Option<String>,
     ) -> Result<usize, AppError> {
         use sea_orm::TransactionTrait;
 
-        // Lock user scope
-        let _lock = self.bulk_lock.lock(user_id).await;
+        let target = DriveAccess::destination(
+            &self.db,
+            user_id,
+            new_parent_id.as_deref(),
+            team_id.as_deref(),
+        )
+        .await?
+        .drive;
+
+        // Lock the target drive, whose quota may grow
+        let _lock = self.lock_drive(&target).await;
         tracing::info!("🔒 Scoped lock acquired for bulk move by user {}", user_id);
 
         let txn = self.db.begin().await.map_err(AppError::Database)?;
@@ -24,19 +40,20 @@ impl FileService {
 
         for id in item_ids {
             // Reusing the logic from rename_item but in a bulk context
-            let item = UserFiles::find_by_id(&id)
-                .filter(user_files::Column::UserId.eq(user_id))
-                .one(&txn)
-                .await
-                .map_err(|e| AppError::Internal(e.to_string()))?
+            let item = DriveAccess::find_item(&txn, user_id, &id, Permission::Write)
+                .await?
                 .ok_or_else(|| AppError::NotFound(format!("Item {} not found", id)))?;
 
-            // Basic circularity check (simplified for bulk)
+            // Skip moves of a folder into itself or its own subfolders
             if let Some(ref target_id) = new_parent_id
                 && item.is_folder
-                && target_id == &item.id
+                && Self::is_within(&txn, target_id, &item.id).await?
             {
-                continue; // Skip invalid moves in bulk
+                continue;
+            }
+
+            if Drive::of(&item) != target {
+                self.move_to_drive(&txn, user_id, &item, &target).await?;
             }
 
             let mut active: user_files::ActiveModel = item.into();
@@ -61,27 +78,41 @@ impl FileService {
         Ok(moved_count)
     }
 
+    /// Copy items under `new_parent_id`, or to the root of the `team_id` drive
+    /// (the personal drive when `None`)
+    ///
+    /// Reading the items is enough, so viewers can copy team files into their own drive.
     pub async fn bulk_copy(
         &self,
         user_id: &str,
         item_ids: Vec<String>,
         new_parent_id: Option<String>,
+        team_id: Option<String>,
     ) -> Result<usize, AppError> {
         use sea_orm::TransactionTrait;
 
-        // Lock user scope
-        let _lock = self.bulk_lock.lock(user_id).await;
+        let target = DriveAccess::destination(
+            &self.db,
+            user_id,
+            new_parent_id.as_deref(),
+            team_id.as_deref(),
+        )
+        .await?
+        .drive;
+
+        // Lock the target drive
+        let _lock = self.lock_drive(&target).await;
         tracing::info!("🔒 Scoped lock acquired for bulk copy by user {}", user_id);
 
         let txn = self.db.begin().await.map_err(AppError::Database)?;
+        QuotaService::lock(&txn, &target).await?;
+        let usage = QuotaService::drive_usage(&txn, &self.config, &target).await?;
         let mut copied_count = 0;
+        let mut copied_bytes = 0;
 
         for id in item_ids {
-            let item = UserFiles::find_by_id(&id)
-                .filter(user_files::Column::UserId.eq(user_id))
-                .one(&txn)
-                .await
-                .map_err(|e| AppError::Internal(e.to_string()))?
+            let item = DriveAccess::find_item(&txn, user_id, &id, Permission::Read)
+                .await?
                 .ok_or_else(|| AppError::NotFound(format!("Item {} not found", id)))?;
 
             let new_filename = if item.is_folder {
@@ -100,17 +131,24 @@ impl FileService {
                 }
             };
 
-            self.copy_recursive(
-                &txn,
-                user_id,
-                &item,
-                new_parent_id.clone(),
-                Some(new_filename),
-            )
-            .await?;
+            copied_bytes += self
+                .copy_recursive(
+                    &txn,
+                    user_id,
+                    &target,
+                    &item,
+                    new_parent_id.clone(),
+                    Some(new_filename),
+                )
+                .await?;
             copied_count += 1;
         }
 
+        // Copies count in full against the quota; dropping the transaction undoes them
+        if !usage.allows(copied_bytes) {
+            return Err(QuotaService::exceeded(&usage, copied_bytes));
+        }
+
         txn.commit().await.map_err(AppError::Database)?;
 
         // Background update facts
@@ -124,21 +162,26 @@ impl FileService {
         Ok(copied_count)
     }
 
+    /// Copy `item` (and any folder contents) under `target_parent_id` in `target`
+    ///
+    /// Returns the total size of the file content copied
     #[async_recursion::async_recursion]
     pub(crate) async fn copy_recursive(
         &self,
         txn: &sea_orm::DatabaseTransaction,
         user_id: &str,
+        target: &Drive,
         item: &user_files::Model,
         target_parent_id: Option<String>,
         new_name: Option<String>,
-    ) -> Result<(), AppError> {
+    ) -> Result<i64, AppError> {
         let new_id = Uuid::new_v4().to_string();
 
         // 1. Clone the item record
         let new_item = user_files::ActiveModel {
             id: Set(new_id.clone()),
             user_id: Set(user_id.to_string()),
+            team_id: Set(target.team_id()),
             filename: Set(new_name.unwrap_or_else(|| item.filename.clone())),
             parent_id: Set(target_parent_id),
             is_folder: Set(item.is_folder),
@@ -153,6 +196,8 @@ impl FileService {
             .await
             .map_err(|e| AppError::Internal(e.to_string()))?;
 
+        let mut copied_bytes = 0;
+
         // 2. Increment ref count if it's a file
         if !item.is_folder
             && let Some(ref sid) = item.storage_file_id
@@ -165,6 +210,7 @@ impl FileService {
                     AppError::NotFound("Storage file missing during copy".to_string())
                 })?;
 
+            copied_bytes += sf.size;
             let mut active_sf: storage_files::ActiveModel = sf.into();
             active_sf.ref_count = Set(active_sf.ref_count.unwrap() + 1);
             active_sf
@@ -177,18 +223,94 @@ impl FileService {
         if item.is_folder {
             let children = UserFiles::find()
                 .filter(user_files::Column::ParentId.eq(Some(item.id.clone())))
-                .filter(user_files::Column::UserId.eq(user_id))
+                .filter(Drive::of(item).condition())
                 .filter(user_files::Column::DeletedAt.is_null())
                 .all(txn)
                 .await
                 .map_err(|e| AppError::Internal(e.to_string()))?;
 
             for child in children {
-                self.copy_recursive(txn, user_id, &child, Some(new_id.clone()), None)
+                copied_bytes += self
+                    .copy_recursive(txn, user_id, target, &child, Some(new_id.clone()), None)
                     .await?;
             }
         }
 
+        Ok(copied_bytes)
+    }
+
+    /// Move `item` and all its contents, live or trashed, into another drive
+    ///
+    /// Trashed descendants move too, so they are charged to the drive they
+    /// would be restored into. The moved content must fit the target
+    /// drive's quota. Items moved into
+    /// the personal drive become the mover's; share links on them that other
+    /// users created are removed, since those users lose access.
+    pub(crate) async fn move_to_drive(
+        &self,
+        txn: &sea_orm::DatabaseTransaction,
+        user_id: &str,
+        item: &user_files::Model,
+        target: &Drive,
+    ) -> Result<(), AppError> {
+        let mut ids = vec![item.id.clone()];
+        let mut frontier = if item.is_folder {
+            vec![item.id.clone()]
+        } else {
+            Vec::new()
+        };
+        while !frontier.is_empty() {
+            let children = UserFiles::find()
+                .filter(user_files::Column::ParentId.is_in(frontier))
+                .all(txn)
+                .await?;
+            frontier = children
+                .iter()
+                .filter(|c| c.is_folder)
+                .map(|c| c.id.clone())
+                .collect();
+            ids.extend(children.into_iter().map(|c| c.id));
+        }
+
+        let bytes = QuotaService::bytes_of(txn, ids.clone()).await?;
+        QuotaService::ensure_room(txn, &self.config, target, bytes).await?;
+
+        let mut update = UserFiles::update_many()
+            .col_expr(user_files::Column::TeamId, Expr::value(target.team_id()));
+        if let Drive::Personal(owner) = target {
+            update = update.col_expr(user_files::Column::UserId, Expr::value(owner.clone()));
+        }
+        update
+            .filter(user_files::Column::Id.is_in(ids.clone()))
+            .exec(txn)
+            .await?;
+
+        if matches!(target, Drive::Personal(_)) {
+            ShareLinks::delete_many()
+                .filter(share_links::Column::UserFileId.is_in(ids))
+                .filter(share_links::Column::CreatedBy.ne(user_id))
+                .exec(txn)
+                .await?;
+        }
         Ok(())
     }
+
+    /// Whether `folder_id` is `ancestor_id` or lies somewhere below it
+    pub(crate) async fn is_within(
+        db: &impl sea_orm::ConnectionTrait,
+        folder_id: &str,
+        ancestor_id: &str,
+    ) -> Result<bool, AppError> {
+        let mut current = Some(folder_id.to_string());
+        while let Some(id) = current {
+            if id == ancestor_id {
+                return Ok(true);
+            }
+            current = UserFiles::find_by_id(id)
+                .one(db)
+                .await?
+                .and_then(|f| f.parent_id);
+        }
+        Ok(false)
+    }
 }
@@ -1,6 +1,5 @@
 use crate::api::error::AppError;
-use crate::entities::{prelude::*, *};
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use crate::services::drives::{Drive, DriveAccess, Permission};
 
 use super::FileService;
 
@@ -9,17 +8,13 @@ impl FileService {
         use crate::services::storage_lifecycle::StorageLifecycleService;
         use sea_orm::TransactionTrait;
 
-        let item = UserFiles::find_by_id(id)
-            .filter(user_files::Column::UserId.eq(user_id))
-            .filter(user_files::Column::DeletedAt.is_null())
-            .one(&self.db)
-            .await
-            .map_err(|e| AppError::Internal(e.to_string()))?
+        let item = DriveAccess::find_item(&self.db, user_id, id, Permission::Write)
+            .await?
             .ok_or_else(|| AppError::NotFound("Item not found".to_string()))?;
 
-        // Lock user scope
+        // Lock drive scope
         let _lock = if item.is_folder {
-            let lock = self.bulk_lock.lock(user_id).await;
+            let lock = self.lock_drive(&Drive::of(&item)).await;
             tracing::info!(
                 "🔒 Scoped lock acquired for folder delete: {} (User: {})",
                 item.filename,
@@ -33,13 +28,7 @@ impl FileService {
         // Start Transaction
         let txn = self.db.begin().await.map_err(AppError::Database)?;
 
-        if item.is_folder {
-            StorageLifecycleService::delete_folder_recursive(&txn, self.storage.as_ref(), &item.id)
-                .await
-                .map_err(|e| AppError::Internal(e.to_string()))?;
-        }
-
-        StorageLifecycleService::soft_delete_user_file(&txn, self.storage.as_ref(), &item)
+        StorageLifecycleService::trash_user_file(&txn, &item)
             .await
             .map_err(|e| AppError::Internal(e.to_string()))?;
 
@@ -68,16 +57,23 @@ impl FileService {
             user_id
         );
 
+        let mut items = Vec::with_capacity(item_ids.len());
+        for item_id in item_ids {
+            match DriveAccess::find_item(&self.db, user_id, &item_id, Permission::Write).await? {
+                Some(item) => items.push(item),
+                None => tracing::warn!(
+                    "Item {} not found or already deleted for user {}",
+                    item_id,
+                    user_id
+                ),
+            }
+        }
+
         use crate::services::storage_lifecycle::StorageLifecycleService;
         // bulk_delete handles its own transaction
-        let count = StorageLifecycleService::bulk_delete(
-            &self.db,
-            self.storage.as_ref(),
-            user_id,
-            item_ids,
-        )
-        .await
-        .map_err(|e| AppError::Internal(e.to_string()))?;
+        let count = StorageLifecycleService::bulk_delete(&self.db, items)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
 
         // Background update facts
         let db = self.db.clone();
@@ -1,15 +1,19 @@
+use crate::api::error::AppError;
 use crate::config::SecurityConfig;
 use crate::services::{scanner::VirusScanner, storage::StorageService};
+use crate::utils::validation::{ValidationRules, ValidationRulesCache};
 use sea_orm::DatabaseConnection;
 use std::sync::Arc;
 
 pub mod bulk;
 pub mod delete;
 pub mod metadata;
+pub mod possession;
+pub mod trash;
 pub mod types;
 pub mod upload;
 
-pub use types::StagedFile;
+pub use types::{ByteRange, PossessionChallenge, PossessionProof, StagedFile};
 
 pub struct FileService {
     db: DatabaseConnection,
@@ -17,6 +21,7 @@ pub struct FileService {
     scanner: Arc<dyn VirusScanner>,
     config: SecurityConfig,
     bulk_lock: crate::utils::keyed_mutex::KeyedMutex,
+    validation_rules: ValidationRulesCache,
 }
 
 impl FileService {
@@ -27,6 +32,11 @@ impl FileService {
         config: SecurityConfig,
     ) -> Self {
         Self {
+            validation_rules: ValidationRulesCache::new(
+                db.clone(),
+                config.max_file_size,
+                config.chunk_size,
+            ),
             db,
             storage,
             scanner,
@@ -34,4 +44,28 @@ impl FileService {
             bulk_lock: crate::utils::keyed_mutex::KeyedMutex::new(),
         }
     }
+
+    /// Upload validation rules, from the in-memory cache
+    pub async fn validation_rules(&self) -> Result<Arc<ValidationRules>, AppError> {
+        self.validation_rules
+            .get()
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to load validation rules: {}", e)))
+    }
+
+    /// Serialize writes to `drive` within this process until the guard is dropped
+    ///
+    /// Quotas do not rely on it: `QuotaService::ensure_room` locks the drive
+    /// in the database, which also covers other instances.
+    pub async fn lock_drive(
+        &self,
+        drive: &crate::services::drives::Drive,
+    ) -> tokio::sync::OwnedMutexGuard<()> {
+        self.bulk_lock.lock(&drive.lock_key()).await
+    }
+
+    /// Reload the validation rules on next use, after they were changed
+    pub fn invalidate_validation_rules(&self) {
+        self.validation_rules.invalidate();
+    }
 }
@@ -0,0 +1,136 @@
+use crate::api::error::AppError;
+use crate::entities::storage_files;
+use chrono::{Duration, Utc};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use tokio::io::AsyncReadExt;
+
+use super::{
+    FileService,
+    types::{ByteRange, PossessionChallenge, PossessionProof},
+};
+
+/// Number of random ranges requested per challenge
+const CHALLENGE_RANGES: usize = 3;
+/// Maximum length of a single challenged range
+const CHALLENGE_RANGE_LEN: i64 = 4096;
+/// How long a challenge stays valid
+const CHALLENGE_TTL_MINUTES: i64 = 10;
+
+#[derive(Serialize, Deserialize)]
+struct ChallengeClaims {
+    sub: String,
+    storage_file_id: String,
+    ranges: Vec<ByteRange>,
+    exp: usize,
+}
+
+impl FileService {
+    /// Challenge tokens use a key derived from the JWT secret so they can never
+    /// be accepted as session tokens (and vice versa).
+    fn challenge_key(&self) -> String {
+        format!("{}:dedup-possession", self.config.jwt_secret)
+    }
+
+    /// Issue a challenge asking for a hash of random byte ranges of `storage_file`
+    pub fn create_possession_challenge(
+        &self,
+        user_id: &str,
+        storage_file: &storage_files::Model,
+    ) -> Result<PossessionChallenge, AppError> {
+        let size = storage_file.size.max(0);
+        let mut rng = rand::thread_rng();
+        let ranges: Vec<ByteRange> = if size == 0 {
+            Vec::new()
+        } else {
+            let length = size.min(CHALLENGE_RANGE_LEN);
+            (0..CHALLENGE_RANGES)
+                .map(|_| ByteRange {
+                    offset: rng.gen_range(0..=size - length),
+                    length,
+                })
+                .collect()
+        };
+
+        let expires_at = Utc::now() + Duration::minutes(CHALLENGE_TTL_MINUTES);
+        let claims = ChallengeClaims {
+            sub: user_id.to_string(),
+            storage_file_id: storage_file.id.clone(),
+            ranges: ranges.clone(),
+            exp: expires_at.timestamp() as usize,
+        };
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(self.challenge_key().as_bytes()),
+        )
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(PossessionChallenge {
+            token,
+            ranges,
+            expires_at,
+        })
+    }
+
+    /// Check a challenge response against the bytes of the stored object
+    pub(super) async fn verify_possession(
+        &self,
+        user_id: &str,
+        storage_file: &storage_files::Model,
+        proof: &PossessionProof,
+    ) -> Result<(), AppError> {
+        let claims = decode::<ChallengeClaims>(
+            &proof.token,
+            &DecodingKey::from_secret(self.challenge_key().as_bytes()),
+            &Validation::default(),
+        )
+        .map_err(|_| AppError::Forbidden("Invalid or expired possession challenge".to_string()))?
+        .claims;
+
+        if claims.sub != user_id || claims.storage_file_id != storage_file.id {
+            return Err(AppError::Forbidden(
+                "Possession challenge does not match this file".to_string(),
+            ));
+        }
+
+        let mut hasher = Sha256::new();
+        for range in &claims.ranges {
+            let end = range.offset + range.length - 1;
+            let object = self
+                .storage
+                .get_object_range(
+                    &storage_file.s3_key,
+                    &format!("bytes={}-{}", range.offset, end),
+                )
+                .await
+                .map_err(|e| AppError::Internal(format!("Failed to read range: {}", e)))?;
+            let mut bytes = Vec::with_capacity(range.length as usize);
+            object
+                .body
+                .into_async_read()
+                .read_to_end(&mut bytes)
+                .await
+                .map_err(|e| AppError::Internal(format!("Failed to read range: {}", e)))?;
+            hasher.update(&bytes);
+        }
+        let expected = hasher.finalize();
+
+        let provided = hex::decode(proof.response.trim()).unwrap_or_default();
+        if provided.len() != expected.len() || !bool::from(provided.ct_eq(expected.as_slice())) {
+            tracing::warn!(
+                "Failed possession proof for storage_file={} by user={}",
+                storage_file.id,
+                user_id
+            );
+            return Err(AppError::Forbidden(
+                "Proof of possession failed".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
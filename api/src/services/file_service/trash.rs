@@ -0,0 +1,107 @@
+use crate::api::error::AppError;
+use crate::entities::{prelude::*, *};
+use crate::services::drives::{Drive, DriveAccess, Permission};
+use crate::services::storage_lifecycle::{ReleasedObjects, StorageLifecycleService};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, TransactionTrait};
+
+use super::FileService;
+
+impl FileService {
+    /// Items trashed in `drive`, newest first (children trashed with a folder are not listed)
+    pub async fn list_trash(
+        &self,
+        drive: &Drive,
+    ) -> Result<Vec<(user_files::Model, Option<storage_files::Model>)>, AppError> {
+        let items = UserFiles::find()
+            .filter(drive.condition())
+            .filter(user_files::Column::DeletedAt.is_not_null())
+            .filter(user_files::Column::TrashRootId.is_not_null())
+            .order_by_desc(user_files::Column::DeletedAt)
+            .find_also_related(StorageFiles)
+            .all(&self.db)
+            .await?;
+
+        Ok(items
+            .into_iter()
+            .filter(|(f, _)| f.trash_root_id.as_deref() == Some(f.id.as_str()))
+            .collect())
+    }
+
+    async fn find_trashed(&self, user_id: &str, id: &str) -> Result<user_files::Model, AppError> {
+        let item = UserFiles::find_by_id(id)
+            .filter(user_files::Column::TrashRootId.eq(id))
+            .one(&self.db)
+            .await?;
+        DriveAccess::authorize(&self.db, user_id, item, Permission::Write)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Item not found in trash".to_string()))
+    }
+
+    pub async fn restore_item(
+        &self,
+        user_id: &str,
+        id: &str,
+    ) -> Result<user_files::Model, AppError> {
+        let item = self.find_trashed(user_id, id).await?;
+        let _lock = self.lock_drive(&Drive::of(&item)).await;
+
+        let txn = self.db.begin().await.map_err(AppError::Database)?;
+        let restored = StorageLifecycleService::restore_user_file(&txn, &item)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        txn.commit().await.map_err(AppError::Database)?;
+
+        self.spawn_facts_update(user_id);
+        Ok(restored)
+    }
+
+    /// Permanently delete one trashed item
+    pub async fn purge_item(&self, user_id: &str, id: &str) -> Result<(), AppError> {
+        let item = self.find_trashed(user_id, id).await?;
+        let _lock = self.lock_drive(&Drive::of(&item)).await;
+
+        let txn = self.db.begin().await.map_err(AppError::Database)?;
+        let mut released = ReleasedObjects::default();
+        StorageLifecycleService::purge_trashed(&txn, &item, &mut released)
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        txn.commit().await.map_err(AppError::Database)?;
+        released.delete_objects(self.storage.as_ref()).await;
+
+        self.spawn_facts_update(user_id);
+        Ok(())
+    }
+
+    /// Permanently delete everything in the trash of `drive`
+    ///
+    /// Callers must already have checked write access to the drive.
+    /// Returns the number of trashed items purged
+    pub async fn empty_trash(&self, user_id: &str, drive: &Drive) -> Result<usize, AppError> {
+        let _lock = self.lock_drive(drive).await;
+        let items = self.list_trash(drive).await?;
+
+        // Objects are deleted once every row is gone, so a failure part way
+        // through rolls back without losing content
+        let txn = self.db.begin().await.map_err(AppError::Database)?;
+        let mut released = ReleasedObjects::default();
+        for (item, _) in &items {
+            StorageLifecycleService::purge_trashed(&txn, item, &mut released)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+        }
+        txn.commit().await.map_err(AppError::Database)?;
+        released.delete_objects(self.storage.as_ref()).await;
+
+        self.spawn_facts_update(user_id);
+        Ok(items.len())
+    }
+
+    fn spawn_facts_update(&self, user_id: &str) {
+        let db = self.db.clone();
+        let uid = user_id.to_string();
+        tokio::spawn(async move {
+            let _ =
+                crate::services::facts_service::FactsService::update_user_facts(&db, &uid).await;
+        });
+    }
+}
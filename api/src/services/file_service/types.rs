@@ -1,8 +1,37 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
 pub struct StagedFile {
     pub key: String,
+    /// xxh3-128 of the content
     pub hash: String,
+    /// SHA-256 of the content, the authoritative deduplication key
+    pub sha256: String,
     pub size: i64,
     pub s3_key: String,
     // Path to local temp file if available (for optimization)
     pub temp_path: Option<String>,
 }
+
+/// A byte range of stored content the client must prove it holds
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq)]
+pub struct ByteRange {
+    pub offset: i64,
+    pub length: i64,
+}
+
+/// Proof-of-possession challenge issued by `/pre-check` on a dedup hit
+#[derive(Serialize, ToSchema)]
+pub struct PossessionChallenge {
+    /// Signed token binding the ranges to the user and storage file
+    pub token: String,
+    /// Ranges to hash, in order: response = hex(SHA-256(range_1 || range_2 || ...))
+    pub ranges: Vec<ByteRange>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Client answer to a [`PossessionChallenge`]
+pub struct PossessionProof {
+    pub token: String,
+    pub response: String,
+}
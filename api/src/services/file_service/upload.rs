@@ -2,23 +2,37 @@ use crate::api::error::AppError;
 use crate::entities::{prelude::*, *};
 use crate::services::{
     audit::{AuditEventType, AuditService},
+    drives::{Destination, Drive},
+    file_versions::FileVersionService,
     metadata::MetadataService,
+    quota::QuotaService,
+    storage_lifecycle::StorageLifecycleService,
 };
 use crate::utils::validation::validate_upload;
 use chrono::{Duration, Utc};
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set, TransactionTrait};
 use tokio::io::{AsyncRead, AsyncReadExt};
 use uuid::Uuid;
 
-use super::{FileService, types::StagedFile};
+use super::{
+    FileService,
+    types::{PossessionProof, StagedFile},
+};
 
 impl FileService {
     pub async fn upload_to_staging<'a>(
         &self,
+        drive: &Drive,
         filename: &str,
         content_type: Option<&str>,
         mut reader: impl AsyncRead + Unpin + Send + 'a,
     ) -> Result<StagedFile, AppError> {
+        // 0. Refuse before streaming anything if the target drive has no space left
+        let usage = QuotaService::drive_usage(&self.db, &self.config, drive).await?;
+        if usage.remaining() == Some(0) {
+            return Err(QuotaService::exceeded(&usage, 1));
+        }
+
         // 1. Peek into stream for magic bytes
         let mut header_buffer = [0u8; 1024];
         let n = reader
@@ -27,14 +41,8 @@ impl FileService {
             .map_err(|e| AppError::Internal(format!("Read error: {}", e)))?;
         let header = &header_buffer[..n];
 
-        // 2. Load Validation Rules from DB
-        let rules = crate::utils::validation::ValidationRules::load(
-            &self.db,
-            self.config.max_file_size,
-            self.config.chunk_size,
-        )
-        .await
-        .map_err(|e| AppError::Internal(format!("Failed to load validation rules: {}", e)))?;
+        // 2. Load Validation Rules
+        let rules = self.validation_rules().await?;
 
         // 3. Early Validation
         validate_upload(
@@ -76,30 +84,46 @@ impl FileService {
                 "File size limits exceeded".to_string(),
             ));
         }
+        if !usage.allows(upload_res.size) {
+            let _ = self.storage.delete_file(&staging_key).await;
+            return Err(QuotaService::exceeded(&usage, upload_res.size));
+        }
 
         Ok(StagedFile {
             key: staging_key.clone(),
             hash: upload_res.hash,
+            sha256: upload_res.sha256,
             size: upload_res.size,
             s3_key: staging_key,
             temp_path: None, // No local copy
         })
     }
 
+    /// Record `staged` as `filename` in `destination`, added by `user_id`
+    ///
+    /// Callers must already have checked `user_id`'s write access to the destination.
     pub async fn process_upload(
         &self,
         staged: StagedFile,
         filename: String,
         user_id: String,
-        parent_id: Option<String>,
+        destination: Destination,
         expiration_hours: Option<i64>,
         _total_size: Option<u64>,
     ) -> Result<(String, Option<chrono::DateTime<Utc>>), AppError> {
+        let Destination { drive, parent_id } = destination;
+        // Fail before storing anything; checked again under the drive's lock
+        // when the user file is recorded
+        if let Err(e) = QuotaService::check_room(&self.db, &self.config, &drive, staged.size).await
+        {
+            if staged.s3_key != "skipped" {
+                let _ = self.storage.delete_file(&staged.s3_key).await;
+            }
+            return Err(e);
+        }
+
         // Check for deduplication (Required to handle the staged file correctly)
-        let existing_storage_file = StorageFiles::find()
-            .filter(storage_files::Column::Hash.eq(&staged.hash))
-            .one(&self.db)
-            .await?;
+        let existing_storage_file = self.find_dedup_candidate(&staged).await?;
 
         let mut analysis_result = None;
         let storage_file_id = if let Some(sf) = existing_storage_file {
@@ -156,7 +180,7 @@ impl FileService {
             analysis_result = Some(analysis);
 
             let id = Uuid::new_v4().to_string();
-            let permanent_key = format!("{}/{}", staged.hash, filename);
+            let permanent_key = format!("{}/{}", staged.sha256, filename);
 
             if staged.s3_key != "skipped" {
                 // Move S3 Object to permanent location
@@ -178,6 +202,7 @@ impl FileService {
             let new_storage_file = storage_files::ActiveModel {
                 id: Set(id.clone()),
                 hash: Set(staged.hash.clone()),
+                sha256: Set(Some(staged.sha256.clone())),
                 s3_key: Set(permanent_key.clone()),
                 size: Set(staged.size),
                 ref_count: Set(1),
@@ -297,7 +322,7 @@ impl FileService {
                         "Duplicate hash detected during insert (race condition). Using existing record."
                     );
                     let existing = StorageFiles::find()
-                        .filter(storage_files::Column::Hash.eq(&staged.hash))
+                        .filter(storage_files::Column::Sha256.eq(&staged.sha256))
                         .one(&self.db)
                         .await
                         .map_err(|e| AppError::Internal(e.to_string()))?
@@ -329,42 +354,44 @@ impl FileService {
 
         let expires_at = expiration_hours.map(|h| Utc::now() + Duration::hours(h));
 
+        // Record the file under the drive's lock so concurrent uploads, on any
+        // instance, cannot overshoot the quota
+        let txn = self.db.begin().await?;
+        if let Err(e) = QuotaService::ensure_room(&txn, &self.config, &drive, staged.size).await {
+            txn.rollback().await?;
+            match StorageLifecycleService::decrement_ref_count(&self.db, &storage_file_id).await {
+                Ok(released) => released.delete_objects(self.storage.as_ref()).await,
+                Err(release) => {
+                    tracing::error!("Failed to release storage {}: {}", storage_file_id, release)
+                }
+            }
+            return Err(e);
+        }
+
         // Check for existing file with same name in the same folder for merging
         let existing_user_file = UserFiles::find()
-            .filter(user_files::Column::UserId.eq(&user_id))
+            .filter(drive.condition())
             .filter(user_files::Column::Filename.eq(&filename))
-            .filter(user_files::Column::ParentId.eq(parent_id.clone()))
+            .filter(StorageLifecycleService::in_folder(&parent_id))
             .filter(user_files::Column::IsFolder.eq(false))
             .filter(user_files::Column::DeletedAt.is_null())
-            .one(&self.db)
+            .one(&txn)
             .await?;
 
+        let created = existing_user_file.is_none();
         let user_file_id = if let Some(existing) = existing_user_file {
-            // Merge logic: Update existing record to point to new storage file
-            let old_storage_file_id = existing.storage_file_id.clone();
-            let existing_id = existing.id.clone();
+            // Merge logic: the previous content becomes a version of the existing record
+            let updated = FileVersionService::replace_content(&txn, &existing, &storage_file_id)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to update existing user_file: {}", e);
+                    AppError::Internal(e.to_string())
+                })?;
 
-            let mut active: user_files::ActiveModel = existing.into();
-            active.storage_file_id = Set(Some(storage_file_id.clone()));
+            let mut active: user_files::ActiveModel = updated.into();
             active.expires_at = Set(expires_at);
-            active.created_at = Set(Some(Utc::now())); // Update timestamp to "latest"
-            active.update(&self.db).await.map_err(|e| {
-                tracing::error!("Failed to update existing user_file: {}", e);
-                AppError::Internal(e.to_string())
-            })?;
-
-            // Decrement ref count of old storage file if it's different
-            if let Some(old_id) = old_storage_file_id
-                && old_id != storage_file_id
-            {
-                let _ = crate::services::storage_lifecycle::StorageLifecycleService::decrement_ref_count(
-                        &self.db,
-                        self.storage.as_ref(),
-                        &old_id,
-                    )
-                    .await;
-            }
-            existing_id
+            active.update(&txn).await?;
+            existing.id
         } else {
             // No existing file, create new one
             let new_id = Uuid::new_v4().to_string();
@@ -372,6 +399,7 @@ impl FileService {
             let new_user_file = user_files::ActiveModel {
                 id: Set(new_id.clone()),
                 user_id: Set(user_id.clone()),
+                team_id: Set(drive.team_id()),
                 storage_file_id: Set(Some(storage_file_id.clone())),
                 filename: Set(filename.clone()),
                 parent_id: Set(parent_id),
@@ -382,27 +410,30 @@ impl FileService {
                 ..Default::default()
             };
 
-            let _res = new_user_file.insert(&self.db).await.map_err(|e| {
+            let _res = new_user_file.insert(&txn).await.map_err(|e| {
                 tracing::error!("Failed to insert user_file: {}", e);
                 AppError::Internal(e.to_string())
             })?;
 
+            new_id
+        };
+        txn.commit().await?;
+
+        if created {
             // Audit Log
             let audit = AuditService::new(self.db.clone());
             audit
                 .log(
                     AuditEventType::FileUpload,
                     Some(user_id.clone()), // Use captured user_id
-                    Some(new_id.clone()),
+                    Some(user_file_id.clone()),
                     "upload",
                     "success",
                     None,
                     None,
                 )
                 .await;
-
-            new_id
-        };
+        }
 
         // Save Metadata and Tags
         if let Err(e) = self
@@ -423,13 +454,62 @@ impl FileService {
         Ok((user_file_id, expires_at))
     }
 
+    /// Find the stored content identical to `staged`, keyed on SHA-256.
+    ///
+    /// Rows created before SHA-256 was recorded only carry an xxh3 hash. An
+    /// xxh3 match against such a row is confirmed by hashing the stored object
+    /// (and recording the result) before it is trusted.
+    async fn find_dedup_candidate(
+        &self,
+        staged: &StagedFile,
+    ) -> Result<Option<storage_files::Model>, AppError> {
+        if let Some(sf) = StorageFiles::find()
+            .filter(storage_files::Column::Sha256.eq(&staged.sha256))
+            .one(&self.db)
+            .await?
+        {
+            return Ok(Some(sf));
+        }
+
+        let legacy = StorageFiles::find()
+            .filter(storage_files::Column::Hash.eq(&staged.hash))
+            .filter(storage_files::Column::Sha256.is_null())
+            .all(&self.db)
+            .await?;
+        for sf in legacy {
+            let sha256 = match crate::services::hash_backfill::HashBackfillService::backfill_one(
+                &self.db,
+                self.storage.as_ref(),
+                &sf,
+            )
+            .await
+            {
+                Ok(sha256) => sha256,
+                Err(e) => {
+                    tracing::warn!("Failed to backfill SHA-256 for {}: {}", sf.id, e);
+                    continue;
+                }
+            };
+            if sha256 == staged.sha256 {
+                // Re-read: the row may have been merged into an existing duplicate
+                return Ok(StorageFiles::find()
+                    .filter(storage_files::Column::Sha256.eq(&sha256))
+                    .one(&self.db)
+                    .await?);
+            }
+        }
+
+        Ok(None)
+    }
+
     pub async fn link_existing_file(
         &self,
         storage_file_id: String,
         filename: String,
         user_id: String,
-        parent_id: Option<String>,
+        destination: Destination,
         expiration_hours: Option<i64>,
+        proof: &PossessionProof,
     ) -> Result<(String, Option<chrono::DateTime<Utc>>), AppError> {
         // 1. Verify storage file exists
         let sf = StorageFiles::find_by_id(&storage_file_id)
@@ -437,52 +517,73 @@ impl FileService {
             .await?
             .ok_or_else(|| AppError::NotFound("Storage file not found".to_string()))?;
 
+        // Knowing the hash is not enough: the caller must prove it holds the content
+        self.verify_possession(&user_id, &sf, proof).await?;
+
+        self.link_storage_file(sf, filename, user_id, destination, expiration_hours)
+            .await
+    }
+
+    /// Give `user_id` a file in `destination` backed by the existing `sf`, without copying content
+    ///
+    /// Callers must already have established the user's right to the content,
+    /// e.g. by proof of possession or a share granting it, and their write
+    /// access to the destination.
+    pub async fn link_storage_file(
+        &self,
+        sf: storage_files::Model,
+        filename: String,
+        user_id: String,
+        destination: Destination,
+        expiration_hours: Option<i64>,
+    ) -> Result<(String, Option<chrono::DateTime<Utc>>), AppError> {
+        let storage_file_id = sf.id.clone();
+        let Destination { drive, parent_id } = destination;
+
+        // Linked content counts in full against the drive's quota, which
+        // stays locked until the link is recorded
+        let txn = self.db.begin().await?;
+        QuotaService::ensure_room(&txn, &self.config, &drive, sf.size).await?;
+
         // 2. Increment ref_count
-        let mut active: storage_files::ActiveModel = sf.clone().into();
-        active.ref_count = Set(sf.ref_count + 1);
-        active.update(&self.db).await?;
+        StorageFiles::update_many()
+            .col_expr(
+                storage_files::Column::RefCount,
+                sea_orm::sea_query::Expr::col(storage_files::Column::RefCount).add(1),
+            )
+            .filter(storage_files::Column::Id.eq(&storage_file_id))
+            .exec(&txn)
+            .await?;
 
         let expires_at = expiration_hours.map(|h| Utc::now() + Duration::hours(h));
 
         // Check for existing file with same name in the same folder for merging
         let existing_user_file = UserFiles::find()
-            .filter(user_files::Column::UserId.eq(&user_id))
+            .filter(drive.condition())
             .filter(user_files::Column::Filename.eq(&filename))
-            .filter(user_files::Column::ParentId.eq(parent_id.clone()))
+            .filter(StorageLifecycleService::in_folder(&parent_id))
             .filter(user_files::Column::IsFolder.eq(false))
             .filter(user_files::Column::DeletedAt.is_null())
-            .one(&self.db)
+            .one(&txn)
             .await?;
 
         let user_file_id = if let Some(existing) = existing_user_file {
-            // Merge logic: Update existing record to point to new storage file
-            let old_storage_file_id = existing.storage_file_id.clone();
-            let existing_id = existing.id.clone();
+            // Merge logic: the previous content becomes a version of the existing record
+            let updated = FileVersionService::replace_content(&txn, &existing, &storage_file_id)
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
 
-            let mut active: user_files::ActiveModel = existing.into();
-            active.storage_file_id = Set(Some(storage_file_id.clone()));
+            let mut active: user_files::ActiveModel = updated.into();
             active.expires_at = Set(expires_at);
-            active.created_at = Set(Some(Utc::now()));
-            active.update(&self.db).await?;
-
-            // Decrement ref count of old storage file if it's different
-            if let Some(old_id) = old_storage_file_id
-                && old_id != storage_file_id
-            {
-                let _ = crate::services::storage_lifecycle::StorageLifecycleService::decrement_ref_count(
-                        &self.db,
-                        self.storage.as_ref(),
-                        &old_id,
-                    )
-                    .await;
-            }
-            existing_id
+            active.update(&txn).await?;
+            existing.id
         } else {
             // 3. Create user file entry
             let new_id = Uuid::new_v4().to_string();
             let user_file = user_files::ActiveModel {
                 id: Set(new_id.clone()),
                 user_id: Set(user_id),
+                team_id: Set(drive.team_id()),
                 storage_file_id: Set(Some(storage_file_id.clone())),
                 filename: Set(filename),
                 parent_id: Set(parent_id),
@@ -493,9 +594,10 @@ impl FileService {
                 ..Default::default()
             };
 
-            user_file.insert(&self.db).await?;
+            user_file.insert(&txn).await?;
             new_id
         };
+        txn.commit().await?;
 
         // 4. Link metadata and tags (reuse existing logic)
         let _ = self
@@ -0,0 +1,196 @@
+use crate::entities::{prelude::*, *};
+use crate::services::storage_lifecycle::{ReleasedObjects, StorageLifecycleService};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, ModelTrait, QueryFilter, QueryOrder, QuerySelect,
+    Set,
+};
+use uuid::Uuid;
+
+/// Service for the version history of user files
+///
+/// Every version row holds one reference on its storage file, so replaced
+/// content stays in storage until the version is pruned or the file is purged.
+pub struct FileVersionService;
+
+impl FileVersionService {
+    /// Point `user_file` at `new_storage_file_id`, archiving its current content
+    ///
+    /// The caller must already hold a reference on `new_storage_file_id` for the
+    /// user file. If the content is unchanged that extra reference is released
+    /// instead of creating a version.
+    pub async fn replace_content(
+        db: &impl sea_orm::ConnectionTrait,
+        user_file: &user_files::Model,
+        new_storage_file_id: &str,
+    ) -> Result<user_files::Model> {
+        match user_file.storage_file_id.as_deref() {
+            Some(current) if current == new_storage_file_id => {
+                // The user file keeps its own reference, so nothing is released
+                let _ =
+                    StorageLifecycleService::decrement_ref_count(db, new_storage_file_id).await?;
+            }
+            Some(current) => {
+                let version_number = Self::next_version_number(db, &user_file.id).await?;
+                file_versions::ActiveModel {
+                    id: Set(Uuid::new_v4().to_string()),
+                    user_file_id: Set(user_file.id.clone()),
+                    storage_file_id: Set(current.to_string()),
+                    version_number: Set(version_number),
+                    created_at: Set(user_file.created_at.unwrap_or_else(Utc::now)),
+                    archived_at: Set(Utc::now()),
+                }
+                .insert(db)
+                .await?;
+                tracing::info!(
+                    "Archived version {} of user_file {}",
+                    version_number,
+                    user_file.id
+                );
+            }
+            None => {}
+        }
+
+        let mut active: user_files::ActiveModel = user_file.clone().into();
+        active.storage_file_id = Set(Some(new_storage_file_id.to_string()));
+        active.created_at = Set(Some(Utc::now())); // Update timestamp to "latest"
+        Ok(active.update(db).await?)
+    }
+
+    /// Versions of a user file, newest first, with their storage files
+    pub async fn list(
+        db: &impl sea_orm::ConnectionTrait,
+        user_file_id: &str,
+    ) -> Result<Vec<(file_versions::Model, Option<storage_files::Model>)>> {
+        Ok(FileVersions::find()
+            .filter(file_versions::Column::UserFileId.eq(user_file_id))
+            .order_by_desc(file_versions::Column::VersionNumber)
+            .find_also_related(StorageFiles)
+            .all(db)
+            .await?)
+    }
+
+    pub async fn find(
+        db: &impl sea_orm::ConnectionTrait,
+        user_file_id: &str,
+        version_id: &str,
+    ) -> Result<Option<file_versions::Model>> {
+        Ok(FileVersions::find_by_id(version_id)
+            .filter(file_versions::Column::UserFileId.eq(user_file_id))
+            .one(db)
+            .await?)
+    }
+
+    /// Make `version` the current content of `user_file`
+    ///
+    /// The current content becomes a new version and the restored version row is
+    /// removed, so the reference it held passes to the user file.
+    pub async fn restore(
+        db: &impl sea_orm::ConnectionTrait,
+        user_file: &user_files::Model,
+        version: &file_versions::Model,
+    ) -> Result<user_files::Model> {
+        if version.user_file_id != user_file.id {
+            return Err(anyhow!("Version does not belong to this file"));
+        }
+        let restored = Self::replace_content(db, user_file, &version.storage_file_id).await?;
+        version.clone().delete(db).await?;
+        Ok(restored)
+    }
+
+    /// Delete versions beyond the newest `keep_latest`, and versions archived before `older_than`
+    ///
+    /// Objects no longer referenced are added to `released`. Returns the
+    /// number of versions removed
+    pub async fn prune(
+        db: &impl sea_orm::ConnectionTrait,
+        user_file_id: &str,
+        keep_latest: Option<usize>,
+        older_than: Option<DateTime<Utc>>,
+        released: &mut ReleasedObjects,
+    ) -> Result<usize> {
+        let versions = FileVersions::find()
+            .filter(file_versions::Column::UserFileId.eq(user_file_id))
+            .order_by_desc(file_versions::Column::VersionNumber)
+            .all(db)
+            .await?;
+
+        let mut pruned = 0;
+        for (index, version) in versions.into_iter().enumerate() {
+            let over_count = keep_latest.is_some_and(|keep| index >= keep);
+            let too_old = older_than.is_some_and(|cutoff| version.archived_at < cutoff);
+            if over_count || too_old {
+                Self::delete_version(db, version, released).await?;
+                pruned += 1;
+            }
+        }
+
+        Ok(pruned)
+    }
+
+    /// Move the versions of `from_user_file_id` onto `to_user_file_id`, after its own
+    pub async fn adopt(
+        db: &impl sea_orm::ConnectionTrait,
+        from_user_file_id: &str,
+        to_user_file_id: &str,
+    ) -> Result<()> {
+        let versions = FileVersions::find()
+            .filter(file_versions::Column::UserFileId.eq(from_user_file_id))
+            .order_by_asc(file_versions::Column::VersionNumber)
+            .all(db)
+            .await?;
+        let mut version_number = Self::next_version_number(db, to_user_file_id).await?;
+        for version in versions {
+            let mut active: file_versions::ActiveModel = version.into();
+            active.user_file_id = Set(to_user_file_id.to_string());
+            active.version_number = Set(version_number);
+            active.update(db).await?;
+            version_number += 1;
+        }
+        Ok(())
+    }
+
+    /// Delete every version of the given user files, adding the objects no
+    /// longer referenced to `released`
+    pub async fn release_all(
+        db: &impl sea_orm::ConnectionTrait,
+        user_file_ids: Vec<String>,
+        released: &mut ReleasedObjects,
+    ) -> Result<()> {
+        let versions = FileVersions::find()
+            .filter(file_versions::Column::UserFileId.is_in(user_file_ids))
+            .all(db)
+            .await?;
+        for version in versions {
+            Self::delete_version(db, version, released).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_version(
+        db: &impl sea_orm::ConnectionTrait,
+        version: file_versions::Model,
+        released: &mut ReleasedObjects,
+    ) -> Result<()> {
+        let storage_file_id = version.storage_file_id.clone();
+        version.delete(db).await?;
+        released.extend(StorageLifecycleService::decrement_ref_count(db, &storage_file_id).await?);
+        Ok(())
+    }
+
+    async fn next_version_number(
+        db: &impl sea_orm::ConnectionTrait,
+        user_file_id: &str,
+    ) -> Result<i32> {
+        let latest: Option<i32> = FileVersions::find()
+            .filter(file_versions::Column::UserFileId.eq(user_file_id))
+            .select_only()
+            .column_as(file_versions::Column::VersionNumber.max(), "max")
+            .into_tuple()
+            .one(db)
+            .await?
+            .flatten();
+        Ok(latest.unwrap_or(0) + 1)
+    }
+}
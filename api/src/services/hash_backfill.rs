@@ -0,0 +1,153 @@
+use crate::entities::{prelude::*, *};
+use crate::services::storage::StorageService;
+use crate::utils::hash::content_hashes_from_reader;
+use anyhow::Result;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter,
+    QuerySelect, Set, TransactionTrait,
+};
+
+/// Service that records SHA-256 digests for storage files created before
+/// SHA-256 became the deduplication key
+pub struct HashBackfillService;
+
+impl HashBackfillService {
+    /// Hash up to `limit` storage files that have no SHA-256 yet.
+    ///
+    /// Returns the number of rows processed (backfilled or merged).
+    pub async fn backfill_batch(
+        db: &DatabaseConnection,
+        storage: &dyn StorageService,
+        limit: u64,
+    ) -> Result<usize> {
+        let pending = StorageFiles::find()
+            .filter(storage_files::Column::Sha256.is_null())
+            .limit(limit)
+            .all(db)
+            .await?;
+
+        let mut processed = 0;
+        for sf in pending {
+            match Self::backfill_one(db, storage, &sf).await {
+                Ok(_) => processed += 1,
+                Err(e) => tracing::warn!("Failed to backfill SHA-256 for {}: {}", sf.id, e),
+            }
+        }
+
+        Ok(processed)
+    }
+
+    /// Hash the stored object of `storage_file` and record its SHA-256.
+    ///
+    /// If another storage file already holds the same content, `storage_file`
+    /// is merged into it: user files and versions are repointed, reference counts combined
+    /// and the duplicate object deleted. Returns the SHA-256.
+    pub async fn backfill_one(
+        db: &DatabaseConnection,
+        storage: &dyn StorageService,
+        storage_file: &storage_files::Model,
+    ) -> Result<String> {
+        let object = storage
+            .get_object_stream(&storage_file.s3_key)
+            .await?
+            .body
+            .into_async_read();
+        let hashes = content_hashes_from_reader(object).await?;
+
+        let existing = StorageFiles::find()
+            .filter(storage_files::Column::Sha256.eq(&hashes.sha256))
+            .one(db)
+            .await?;
+
+        match existing {
+            Some(canonical) if canonical.id != storage_file.id => {
+                tracing::info!(
+                    "Merging duplicate storage_file {} into {} (sha256: {})",
+                    storage_file.id,
+                    canonical.id,
+                    hashes.sha256
+                );
+                Self::merge_into(db, storage, storage_file, &canonical).await?;
+            }
+            Some(_) => {}
+            None => {
+                let mut active: storage_files::ActiveModel = storage_file.clone().into();
+                active.hash = Set(hashes.xxh3);
+                active.sha256 = Set(Some(hashes.sha256.clone()));
+                active.update(db).await?;
+            }
+        }
+
+        Ok(hashes.sha256)
+    }
+
+    async fn merge_into(
+        db: &DatabaseConnection,
+        storage: &dyn StorageService,
+        duplicate: &storage_files::Model,
+        canonical: &storage_files::Model,
+    ) -> Result<()> {
+        let txn = db.begin().await?;
+        // Uploads and deletes may have changed either ref count since the
+        // rows were read, so lock the duplicate and add to the canonical
+        // count in place
+        let Some(duplicate) = StorageFiles::find_by_id(&duplicate.id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        UserFiles::update_many()
+            .col_expr(
+                user_files::Column::StorageFileId,
+                sea_orm::sea_query::Expr::value(canonical.id.clone()),
+            )
+            .filter(user_files::Column::StorageFileId.eq(&duplicate.id))
+            .exec(&txn)
+            .await?;
+
+        FileVersions::update_many()
+            .col_expr(
+                file_versions::Column::StorageFileId,
+                sea_orm::sea_query::Expr::value(canonical.id.clone()),
+            )
+            .filter(file_versions::Column::StorageFileId.eq(&duplicate.id))
+            .exec(&txn)
+            .await?;
+
+        StorageFiles::update_many()
+            .col_expr(
+                storage_files::Column::RefCount,
+                sea_orm::sea_query::Expr::col(storage_files::Column::RefCount)
+                    .add(duplicate.ref_count),
+            )
+            .filter(storage_files::Column::Id.eq(&canonical.id))
+            .exec(&txn)
+            .await?;
+
+        FileMetadata::delete_many()
+            .filter(file_metadata::Column::StorageFileId.eq(&duplicate.id))
+            .exec(&txn)
+            .await?;
+        duplicate.clone().delete(&txn).await?;
+
+        txn.commit().await?;
+
+        if let Err(e) = storage.delete_file(&duplicate.s3_key).await {
+            tracing::warn!(
+                "Failed to delete merged duplicate object {}: {}",
+                duplicate.s3_key,
+                e
+            );
+        }
+        if duplicate.has_thumbnail {
+            let _ = storage
+                .delete_file(&format!("thumbnails/{}.webp", duplicate.id))
+                .await;
+        }
+
+        Ok(())
+    }
+}
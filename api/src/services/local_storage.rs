@@ -0,0 +1,624 @@
+use crate::services::storage::{FileMetadata, StorageService, UploadResult};
+use crate::utils::hash::ContentHasher;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use aws_sdk_s3::operation::get_object::GetObjectOutput;
+use aws_sdk_s3::primitives::{ByteStream, DateTime, Length};
+use hmac::{Hmac, Mac};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use sha2::Sha256;
+use std::path::{Component, Path, PathBuf};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use uuid::Uuid;
+use xxhash_rust::xxh3::xxh3_128;
+
+/// Directory (relative to the storage root) holding in-progress multipart uploads
+const MULTIPART_DIR: &str = ".multipart";
+
+/// Route prefix under which the API serves signed object URLs
+pub const SIGNED_OBJECT_ROUTE: &str = "/storage/local";
+
+/// Characters escaped in object key path segments ('/' is kept as separator)
+const KEY_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.');
+
+/// Compute the signature for an API-served object URL.
+///
+/// The signature covers every query parameter the serving handler honours, so
+/// callers cannot extend the expiry or swap the response headers.
+pub fn sign_object_url(
+    secret: &str,
+    key: &str,
+    expires: i64,
+    content_type: &str,
+    content_disposition: &str,
+) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(
+        format!(
+            "{}\n{}\n{}\n{}",
+            key, expires, content_type, content_disposition
+        )
+        .as_bytes(),
+    );
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Verify a signature produced by [`sign_object_url`] in constant time
+pub fn verify_object_url(
+    secret: &str,
+    key: &str,
+    expires: i64,
+    content_type: &str,
+    content_disposition: &str,
+    signature: &str,
+) -> bool {
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(
+        format!(
+            "{}\n{}\n{}\n{}",
+            key, expires, content_type, content_disposition
+        )
+        .as_bytes(),
+    );
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Parse an HTTP `Range` header value (`bytes=start-end`, `bytes=start-` or
+/// `bytes=-suffix`) into an inclusive byte range clamped to `size`.
+pub fn parse_byte_range(range: &str, size: u64) -> Option<(u64, u64)> {
+    let spec = range.trim().strip_prefix("bytes=")?;
+    // Multi-range requests are not supported
+    if spec.contains(',') || size == 0 {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 {
+                return None;
+            }
+            (size.saturating_sub(suffix), size - 1)
+        }
+        (start, "") => (start.parse().ok()?, size - 1),
+        (start, end) => (start.parse().ok()?, end.parse::<u64>().ok()?.min(size - 1)),
+    };
+    if start > end || start >= size {
+        return None;
+    }
+    Some((start, end))
+}
+
+/// Storage backend that keeps objects on the local filesystem.
+///
+/// Object keys map to paths below `root`; presigned URLs point back at the API
+/// itself (see [`SIGNED_OBJECT_ROUTE`]) and are authenticated with an HMAC.
+pub struct LocalFsStorageService {
+    root: PathBuf,
+    endpoint_url: String,
+    public_base_url: String,
+    signing_secret: String,
+}
+
+impl LocalFsStorageService {
+    pub fn new(root: PathBuf, endpoint_url: String, signing_secret: String) -> Self {
+        let public_base_url =
+            std::env::var("LOCAL_STORAGE_PUBLIC_URL").unwrap_or_else(|_| "/api".to_string());
+        Self {
+            root,
+            endpoint_url: endpoint_url.trim_end_matches('/').to_string(),
+            public_base_url: public_base_url.trim_end_matches('/').to_string(),
+            signing_secret,
+        }
+    }
+
+    /// Resolve an object key to a path below the root, rejecting traversal
+    fn object_path(&self, key: &str) -> Result<PathBuf> {
+        let relative = Path::new(key);
+        if key.is_empty()
+            || !relative
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(anyhow!("Invalid object key: {}", key));
+        }
+        if relative
+            .components()
+            .next()
+            .is_some_and(|c| c.as_os_str() == MULTIPART_DIR)
+        {
+            return Err(anyhow!("Invalid object key: {}", key));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    fn multipart_dir(&self, upload_id: &str) -> Result<PathBuf> {
+        if Uuid::parse_str(upload_id).is_err() {
+            return Err(anyhow!("Invalid upload ID: {}", upload_id));
+        }
+        Ok(self.root.join(MULTIPART_DIR).join(upload_id))
+    }
+
+    /// Write to a sibling temp file and rename so readers never see partial objects
+    async fn temp_path_for(&self, path: &Path) -> Result<PathBuf> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let tmp_dir = self.root.join(MULTIPART_DIR).join("tmp");
+        tokio::fs::create_dir_all(&tmp_dir).await?;
+        Ok(tmp_dir.join(Uuid::new_v4().to_string()))
+    }
+
+    async fn object_output(
+        &self,
+        path: &Path,
+        range: Option<(u64, u64)>,
+    ) -> Result<GetObjectOutput> {
+        let metadata = tokio::fs::metadata(path)
+            .await
+            .map_err(|e| anyhow!("Object not found ({}): {}", path.display(), e))?;
+        let size = metadata.len();
+        let last_modified = metadata.modified().ok().map(DateTime::from);
+
+        let (offset, length) = match range {
+            Some((start, end)) => (start, end - start + 1),
+            None => (0, size),
+        };
+
+        let body = ByteStream::read_from()
+            .path(path)
+            .offset(offset)
+            .length(Length::Exact(length))
+            .build()
+            .await?;
+
+        let mut builder = GetObjectOutput::builder()
+            .body(body)
+            .content_length(length as i64)
+            .accept_ranges("bytes")
+            .set_last_modified(last_modified);
+        if let Some((start, end)) = range {
+            builder = builder.content_range(format!("bytes {}-{}/{}", start, end, size));
+        }
+        Ok(builder.build())
+    }
+
+    #[async_recursion::async_recursion]
+    async fn collect_keys(&self, dir: PathBuf, keys: &mut Vec<String>) -> Result<()> {
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if entry.file_type().await?.is_dir() {
+                if dir == self.root && entry.file_name() == MULTIPART_DIR {
+                    continue;
+                }
+                self.collect_keys(path, keys).await?;
+            } else if let Ok(relative) = path.strip_prefix(&self.root) {
+                let key = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                keys.push(key);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageService for LocalFsStorageService {
+    async fn upload_file(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        let path = self.object_path(key)?;
+        let tmp = self.temp_path_for(&path).await?;
+        tokio::fs::write(&tmp, data).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    async fn upload_stream_with_hash<'a>(
+        &self,
+        key: &str,
+        mut reader: Box<dyn AsyncRead + Unpin + Send + 'a>,
+    ) -> Result<UploadResult> {
+        let path = self.object_path(key)?;
+        let tmp = self.temp_path_for(&path).await?;
+        let mut file = tokio::fs::File::create(&tmp).await?;
+
+        let mut hasher = ContentHasher::new();
+        let mut total_size = 0i64;
+        let mut buffer = vec![0u8; 64 * 1024];
+
+        let copy_res: Result<()> = async {
+            loop {
+                let n = reader.read(&mut buffer).await?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..n]);
+                file.write_all(&buffer[..n]).await?;
+                total_size += n as i64;
+            }
+            file.flush().await?;
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = copy_res {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return Err(e);
+        }
+        drop(file);
+        tokio::fs::rename(&tmp, &path).await?;
+
+        let hashes = hasher.finalize();
+        Ok(UploadResult {
+            hash: hashes.xxh3,
+            sha256: hashes.sha256,
+            size: total_size,
+            s3_key: key.to_string(),
+        })
+    }
+
+    async fn copy_object(&self, source_key: &str, dest_key: &str) -> Result<()> {
+        let source = self.object_path(source_key)?;
+        let dest = self.object_path(dest_key)?;
+        let tmp = self.temp_path_for(&dest).await?;
+        tokio::fs::copy(&source, &tmp).await.map_err(|e| {
+            tracing::error!(
+                "Local copy_object failed: source={}, dest={}, error={:?}",
+                source_key,
+                dest_key,
+                e
+            );
+            e
+        })?;
+        tokio::fs::rename(&tmp, &dest).await?;
+        Ok(())
+    }
+
+    async fn delete_file(&self, key: &str) -> Result<()> {
+        let path = self.object_path(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            // S3 semantics: deleting a missing object is not an error
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn file_exists(&self, key: &str) -> Result<bool> {
+        let path = self.object_path(key)?;
+        Ok(tokio::fs::metadata(&path)
+            .await
+            .map(|m| m.is_file())
+            .unwrap_or(false))
+    }
+
+    async fn generate_presigned_url_raw(
+        &self,
+        key: &str,
+        expires_in_secs: u64,
+        content_type: &str,
+        content_disposition: &str,
+    ) -> Result<String> {
+        self.object_path(key)?;
+        let expires = chrono::Utc::now().timestamp() + expires_in_secs as i64;
+        let signature = sign_object_url(
+            &self.signing_secret,
+            key,
+            expires,
+            content_type,
+            content_disposition,
+        );
+
+        let encoded_key = key
+            .split('/')
+            .map(|segment| utf8_percent_encode(segment, KEY_SEGMENT).to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+        let query = serde_urlencoded::to_string([
+            ("expires", expires.to_string()),
+            ("content_type", content_type.to_string()),
+            ("content_disposition", content_disposition.to_string()),
+            ("signature", signature),
+        ])?;
+
+        Ok(format!(
+            "{}{}/{}?{}",
+            self.endpoint_url, SIGNED_OBJECT_ROUTE, encoded_key, query
+        ))
+    }
+
+    async fn generate_presigned_url(
+        &self,
+        key: &str,
+        expires_in_secs: u64,
+        content_type: &str,
+        content_disposition: &str,
+    ) -> Result<String> {
+        let raw_url = self
+            .generate_presigned_url_raw(key, expires_in_secs, content_type, content_disposition)
+            .await?;
+
+        // Rewrite URL: replace internal API endpoint with public base path
+        // e.g. http://localhost:3000/storage/local/key?... → /api/storage/local/key?...
+        Ok(raw_url.replace(&self.endpoint_url, &self.public_base_url))
+    }
+
+    async fn get_object_stream(&self, key: &str) -> Result<GetObjectOutput> {
+        let path = self.object_path(key)?;
+        self.object_output(&path, None).await
+    }
+
+    async fn get_object_range(&self, key: &str, range: &str) -> Result<GetObjectOutput> {
+        let path = self.object_path(key)?;
+        let size = tokio::fs::metadata(&path)
+            .await
+            .map_err(|e| anyhow!("Object not found ({}): {}", key, e))?
+            .len();
+        let range = parse_byte_range(range, size)
+            .ok_or_else(|| anyhow!("Invalid range '{}' for object of {} bytes", range, size))?;
+        self.object_output(&path, Some(range)).await
+    }
+
+    async fn get_file(&self, key: &str) -> Result<Vec<u8>> {
+        let path = self.object_path(key)?;
+        Ok(tokio::fs::read(&path).await?)
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        self.collect_keys(self.root.clone(), &mut keys).await?;
+        keys.retain(|k| k.starts_with(prefix));
+        keys.sort();
+        Ok(keys)
+    }
+
+    async fn get_object_metadata(&self, key: &str) -> Result<FileMetadata> {
+        let path = self.object_path(key)?;
+        let metadata = tokio::fs::metadata(&path).await?;
+        Ok(FileMetadata {
+            last_modified: metadata.modified().ok().map(chrono::DateTime::from),
+            size: metadata.len() as i64,
+        })
+    }
+
+    async fn create_multipart_upload(&self, key: &str) -> Result<String> {
+        self.object_path(key)?;
+        let upload_id = Uuid::new_v4().to_string();
+        let dir = self.multipart_dir(&upload_id)?;
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(dir.join("key"), key).await?;
+        Ok(upload_id)
+    }
+
+    async fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Vec<u8>,
+    ) -> Result<String> {
+        let dir = self.multipart_dir(upload_id)?;
+        let owner = tokio::fs::read_to_string(dir.join("key"))
+            .await
+            .map_err(|_| anyhow!("No such multipart upload: {}", upload_id))?;
+        if owner != key {
+            return Err(anyhow!(
+                "Multipart upload {} does not belong to {}",
+                upload_id,
+                key
+            ));
+        }
+        if part_number < 1 {
+            return Err(anyhow!("Invalid part number: {}", part_number));
+        }
+
+        let etag = format!("\"{:032x}\"", xxh3_128(&data));
+        let tmp = dir.join(format!("part-{}.tmp", part_number));
+        tokio::fs::write(&tmp, data).await?;
+        tokio::fs::rename(&tmp, dir.join(format!("part-{}", part_number))).await?;
+        Ok(etag)
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        key: &str,
+        upload_id: &str,
+        mut parts: Vec<(i32, String)>,
+    ) -> Result<()> {
+        let dir = self.multipart_dir(upload_id)?;
+        let owner = tokio::fs::read_to_string(dir.join("key"))
+            .await
+            .map_err(|_| anyhow!("No such multipart upload: {}", upload_id))?;
+        if owner != key {
+            return Err(anyhow!(
+                "Multipart upload {} does not belong to {}",
+                upload_id,
+                key
+            ));
+        }
+
+        parts.sort_by_key(|(part_number, _)| *part_number);
+
+        let path = self.object_path(key)?;
+        let tmp = self.temp_path_for(&path).await?;
+        let mut output = tokio::fs::File::create(&tmp).await?;
+
+        let assemble: Result<()> = async {
+            for (part_number, etag) in &parts {
+                let data = tokio::fs::read(dir.join(format!("part-{}", part_number)))
+                    .await
+                    .map_err(|_| {
+                        anyhow!("Missing part {} for upload {}", part_number, upload_id)
+                    })?;
+                let actual = format!("\"{:032x}\"", xxh3_128(&data));
+                if actual.trim_matches('"') != etag.trim_matches('"') {
+                    return Err(anyhow!("ETag mismatch for part {}", part_number));
+                }
+                output.write_all(&data).await?;
+            }
+            output.flush().await?;
+            Ok(())
+        }
+        .await;
+
+        drop(output);
+        if let Err(e) = assemble {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return Err(e);
+        }
+
+        tokio::fs::rename(&tmp, &path).await?;
+        tokio::fs::remove_dir_all(&dir).await?;
+        Ok(())
+    }
+
+    async fn abort_multipart_upload(&self, _key: &str, upload_id: &str) -> Result<()> {
+        let dir = self.multipart_dir(upload_id)?;
+        match tokio::fs::remove_dir_all(&dir).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(root: &Path) -> LocalFsStorageService {
+        LocalFsStorageService::new(
+            root.to_path_buf(),
+            "http://localhost:3000".to_string(),
+            "test_secret".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_upload_read_delete_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = service(dir.path());
+
+        let res = storage
+            .upload_stream_with_hash("staging/abc", Box::new(&b"hello world"[..]))
+            .await
+            .unwrap();
+        assert_eq!(res.size, 11);
+        assert_eq!(res.hash, format!("{:032x}", xxh3_128(b"hello world")));
+
+        storage
+            .copy_object("staging/abc", "perm/file.txt")
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.get_file("perm/file.txt").await.unwrap(),
+            b"hello world"
+        );
+
+        let range = storage
+            .get_object_range("perm/file.txt", "bytes=6-")
+            .await
+            .unwrap();
+        assert_eq!(range.content_range(), Some("bytes 6-10/11"));
+        let body = range.body.collect().await.unwrap().to_vec();
+        assert_eq!(body, b"world");
+
+        let keys = storage.list_objects("").await.unwrap();
+        assert_eq!(keys, vec!["perm/file.txt", "staging/abc"]);
+
+        storage.delete_file("staging/abc").await.unwrap();
+        assert!(!storage.file_exists("staging/abc").await.unwrap());
+        // Deleting a missing key is a no-op, as on S3
+        storage.delete_file("staging/abc").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = service(dir.path());
+
+        let upload_id = storage
+            .create_multipart_upload("multipart/x")
+            .await
+            .unwrap();
+        let e2 = storage
+            .upload_part("multipart/x", &upload_id, 2, b"world".to_vec())
+            .await
+            .unwrap();
+        let e1 = storage
+            .upload_part("multipart/x", &upload_id, 1, b"hello ".to_vec())
+            .await
+            .unwrap();
+        storage
+            .complete_multipart_upload("multipart/x", &upload_id, vec![(2, e2), (1, e1)])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            storage.get_file("multipart/x").await.unwrap(),
+            b"hello world"
+        );
+        // In-progress uploads never leak into listings
+        assert_eq!(storage.list_objects("").await.unwrap(), vec!["multipart/x"]);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_path_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = service(dir.path());
+        assert!(storage.upload_file("../escape", vec![1]).await.is_err());
+        assert!(storage.upload_file("/etc/passwd", vec![1]).await.is_err());
+        assert!(storage.upload_file(".multipart/x", vec![1]).await.is_err());
+    }
+
+    #[test]
+    fn test_signed_url_roundtrip() {
+        let sig = sign_object_url("s", "a/b", 100, "text/plain", "inline");
+        assert!(verify_object_url(
+            "s",
+            "a/b",
+            100,
+            "text/plain",
+            "inline",
+            &sig
+        ));
+        assert!(!verify_object_url(
+            "s",
+            "a/b",
+            101,
+            "text/plain",
+            "inline",
+            &sig
+        ));
+        assert!(!verify_object_url(
+            "other",
+            "a/b",
+            100,
+            "text/plain",
+            "inline",
+            &sig
+        ));
+    }
+
+    #[test]
+    fn test_parse_byte_range() {
+        assert_eq!(parse_byte_range("bytes=0-9", 100), Some((0, 9)));
+        assert_eq!(parse_byte_range("bytes=90-", 100), Some((90, 99)));
+        assert_eq!(parse_byte_range("bytes=-10", 100), Some((90, 99)));
+        assert_eq!(parse_byte_range("bytes=50-500", 100), Some((50, 99)));
+        assert_eq!(parse_byte_range("bytes=100-", 100), None);
+        assert_eq!(parse_byte_range("bytes=0-1,5-6", 100), None);
+    }
+}
@@ -0,0 +1,315 @@
+use anyhow::{Context, Result};
+use lettre::message::{Mailbox, Message, header::ContentType};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const DEFAULT_FROM: &str = "Rust File Backend <noreply@localhost>";
+
+/// A plain-text email
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Trait for outgoing mail implementations
+#[async_trait::async_trait]
+pub trait Mailer: Send + Sync {
+    /// Deliver `email`, or hand it to the transport for delivery
+    async fn send(&self, email: &Email) -> Result<()>;
+
+    /// Check if the mail transport is reachable
+    async fn health_check(&self) -> bool;
+}
+
+/// Sends mail through an SMTP server
+///
+/// Any SMTP server works for local testing, e.g. Mailpit:
+/// ```bash
+/// docker run -d --name mailpit -p 1025:1025 -p 8025:8025 axllent/mailpit
+/// ```
+/// with `SMTP_HOST=localhost SMTP_PORT=1025 SMTP_TLS=none`.
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// `tls` is "tls" (implicit TLS), "starttls" or "none"
+    pub fn new(
+        host: &str,
+        port: u16,
+        tls: &str,
+        credentials: Option<(String, String)>,
+        from: &str,
+    ) -> Result<Self> {
+        let mut builder = match tls.to_lowercase().as_str() {
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+            _ => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+        }
+        .port(port);
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+        Ok(Self {
+            transport: builder.build(),
+            from: from.parse().context("Invalid sender address")?,
+        })
+    }
+
+    pub fn from_env() -> Result<Self> {
+        let host = std::env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string());
+        let tls = std::env::var("SMTP_TLS").unwrap_or_else(|_| "starttls".to_string());
+        let port = std::env::var("SMTP_PORT")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(match tls.as_str() {
+                "tls" => 465,
+                "starttls" => 587,
+                _ => 25,
+            });
+        let credentials = std::env::var("SMTP_USERNAME")
+            .ok()
+            .map(|user| (user, std::env::var("SMTP_PASSWORD").unwrap_or_default()));
+        Self::new(&host, port, &tls, credentials, &sender_from_env())
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &Email) -> Result<()> {
+        let message = build_message(&self.from, email)?;
+        self.transport
+            .send(message)
+            .await
+            .context("SMTP delivery failed")?;
+        Ok(())
+    }
+
+    async fn health_check(&self) -> bool {
+        self.transport.test_connection().await.unwrap_or(false)
+    }
+}
+
+/// Writes each email as an `.eml` file into a directory
+pub struct FileMailer {
+    dir: PathBuf,
+    from: Mailbox,
+}
+
+impl FileMailer {
+    pub fn new(dir: impl Into<PathBuf>, from: &str) -> Result<Self> {
+        Ok(Self {
+            dir: dir.into(),
+            from: from.parse().context("Invalid sender address")?,
+        })
+    }
+
+    pub fn from_env() -> Result<Self> {
+        let dir = std::env::var("MAIL_SINK_DIR").unwrap_or_else(|_| "./mail".to_string());
+        Self::new(dir, &sender_from_env())
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: &Email) -> Result<()> {
+        let message = build_message(&self.from, email)?;
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let name = format!(
+            "{}-{}.eml",
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+            uuid::Uuid::new_v4()
+        );
+        tokio::fs::write(self.dir.join(name), message.formatted()).await?;
+        Ok(())
+    }
+
+    async fn health_check(&self) -> bool {
+        tokio::fs::create_dir_all(&self.dir).await.is_ok()
+    }
+}
+
+/// Logs emails instead of sending them (development only: bodies carry tokens)
+pub struct LogMailer;
+
+#[async_trait::async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: &Email) -> Result<()> {
+        tracing::info!(
+            "📧 Mail to {} — {}\n{}",
+            email.to,
+            email.subject,
+            email.body
+        );
+        Ok(())
+    }
+
+    async fn health_check(&self) -> bool {
+        true
+    }
+}
+
+/// Keeps emails in memory so tests can read them
+#[derive(Default)]
+pub struct MemoryMailer {
+    sent: Mutex<Vec<Email>>,
+    failing: AtomicBool,
+}
+
+impl MemoryMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every email sent so far, oldest first
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    /// Make every following send fail, as an unreachable server would
+    pub fn set_failing(&self, failing: bool) {
+        self.failing.store(failing, Ordering::Relaxed);
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for MemoryMailer {
+    async fn send(&self, email: &Email) -> Result<()> {
+        if self.failing.load(Ordering::Relaxed) {
+            anyhow::bail!("Mail server unreachable");
+        }
+        self.sent.lock().unwrap().push(email.clone());
+        Ok(())
+    }
+
+    async fn health_check(&self) -> bool {
+        true
+    }
+}
+
+fn sender_from_env() -> String {
+    std::env::var("MAIL_FROM").unwrap_or_else(|_| DEFAULT_FROM.to_string())
+}
+
+fn build_message(from: &Mailbox, email: &Email) -> Result<Message> {
+    let to: Mailbox = email.to.parse().context("Invalid recipient address")?;
+    Ok(Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(&email.subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(email.body.clone())?)
+}
+
+/// Factory function to create mailer based on configuration
+///
+/// Fails when `smtp` or `file` cannot be set up, rather than falling back to
+/// logging mail bodies and the link tokens in them.
+pub fn create_mailer(transport: &str) -> Result<Box<dyn Mailer>> {
+    match transport.to_lowercase().as_str() {
+        "smtp" => Ok(Box::new(
+            SmtpMailer::from_env().context("Mail transport 'smtp' is misconfigured")?,
+        )),
+        "file" => Ok(Box::new(
+            FileMailer::from_env().context("Mail transport 'file' is misconfigured")?,
+        )),
+        "log" | "none" => Ok(Box::new(LogMailer)),
+        _ => {
+            tracing::warn!("Unknown mail transport '{}', using LogMailer", transport);
+            Ok(Box::new(LogMailer))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    fn email() -> Email {
+        Email {
+            to: "alice@example.com".to_string(),
+            subject: "Hello".to_string(),
+            body: "Line one\nLine two".to_string(),
+        }
+    }
+
+    /// Accept one SMTP session and return the DATA section it received
+    async fn fake_smtp_server(listener: TcpListener) -> String {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (read, mut write) = socket.into_split();
+        let mut lines = BufReader::new(read).lines();
+        write.write_all(b"220 fake ESMTP\r\n").await.unwrap();
+        let mut data = String::new();
+        let mut in_data = false;
+        while let Some(line) = lines.next_line().await.unwrap() {
+            if in_data {
+                if line == "." {
+                    in_data = false;
+                    write.write_all(b"250 queued\r\n").await.unwrap();
+                } else {
+                    data.push_str(&line);
+                    data.push('\n');
+                }
+                continue;
+            }
+            let reply: &[u8] = match line.split(' ').next().unwrap().to_uppercase().as_str() {
+                "EHLO" => b"250-fake\r\n250 8BITMIME\r\n",
+                "DATA" => {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                }
+                "QUIT" => {
+                    write.write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                }
+                _ => b"250 ok\r\n",
+            };
+            write.write_all(reply).await.unwrap();
+        }
+        data
+    }
+
+    #[tokio::test]
+    async fn test_smtp_mailer_against_fake_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(fake_smtp_server(listener));
+
+        let mailer =
+            SmtpMailer::new("127.0.0.1", port, "none", None, "RFB <noreply@test>").unwrap();
+        mailer.send(&email()).await.unwrap();
+        drop(mailer);
+
+        let data = server.await.unwrap();
+        assert!(data.contains("To: alice@example.com"));
+        assert!(data.contains("Subject: Hello"));
+        assert!(data.contains("Line one"));
+    }
+
+    #[tokio::test]
+    async fn test_file_mailer_writes_eml() {
+        let dir = tempfile::tempdir().unwrap();
+        let mailer = FileMailer::new(dir.path(), DEFAULT_FROM).unwrap();
+        mailer.send(&email()).await.unwrap();
+
+        let mut entries = std::fs::read_dir(dir.path()).unwrap();
+        let path = entries.next().unwrap().unwrap().path();
+        assert_eq!(path.extension().unwrap(), "eml");
+        let contents = std::fs::read_to_string(path).unwrap();
+        assert!(contents.contains("Subject: Hello"));
+
+        let bad = Email {
+            to: "not an address".to_string(),
+            ..email()
+        };
+        assert!(mailer.send(&bad).await.is_err());
+    }
+}
@@ -0,0 +1,237 @@
+use crate::services::local_storage::parse_byte_range;
+use crate::services::storage::{FileMetadata, StorageService, UploadResult};
+use crate::utils::hash::ContentHasher;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use aws_sdk_s3::operation::get_object::GetObjectOutput;
+use aws_sdk_s3::primitives::{ByteStream, DateTime};
+use chrono::Utc;
+use dashmap::DashMap;
+use std::collections::BTreeMap;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use uuid::Uuid;
+use xxhash_rust::xxh3::xxh3_128;
+
+#[derive(Clone)]
+struct StoredObject {
+    data: Vec<u8>,
+    last_modified: chrono::DateTime<Utc>,
+}
+
+struct PendingMultipart {
+    key: String,
+    parts: BTreeMap<i32, Vec<u8>>,
+}
+
+/// Storage backend that keeps every object in process memory.
+///
+/// Intended for tests and local experiments: nothing survives a restart and
+/// presigned URLs are opaque `memory://` references that no server can serve.
+#[derive(Default)]
+pub struct InMemoryStorageService {
+    objects: DashMap<String, StoredObject>,
+    multipart: DashMap<String, PendingMultipart>,
+}
+
+impl InMemoryStorageService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn put(&self, key: &str, data: Vec<u8>) {
+        self.objects.insert(
+            key.to_string(),
+            StoredObject {
+                data,
+                last_modified: Utc::now(),
+            },
+        );
+    }
+
+    fn get(&self, key: &str) -> Result<StoredObject> {
+        self.objects
+            .get(key)
+            .map(|o| o.value().clone())
+            .ok_or_else(|| anyhow!("Object not found: {}", key))
+    }
+
+    fn output(
+        object: &StoredObject,
+        data: Vec<u8>,
+        content_range: Option<String>,
+    ) -> GetObjectOutput {
+        GetObjectOutput::builder()
+            .content_length(data.len() as i64)
+            .accept_ranges("bytes")
+            .set_content_range(content_range)
+            .last_modified(DateTime::from_secs(object.last_modified.timestamp()))
+            .body(ByteStream::from(data))
+            .build()
+    }
+}
+
+#[async_trait]
+impl StorageService for InMemoryStorageService {
+    async fn upload_file(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        self.put(key, data);
+        Ok(())
+    }
+
+    async fn upload_stream_with_hash<'a>(
+        &self,
+        key: &str,
+        mut reader: Box<dyn AsyncRead + Unpin + Send + 'a>,
+    ) -> Result<UploadResult> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).await?;
+
+        let mut hasher = ContentHasher::new();
+        hasher.update(&data);
+        let hashes = hasher.finalize();
+        let size = data.len() as i64;
+        self.put(key, data);
+
+        Ok(UploadResult {
+            hash: hashes.xxh3,
+            sha256: hashes.sha256,
+            size,
+            s3_key: key.to_string(),
+        })
+    }
+
+    async fn copy_object(&self, source_key: &str, dest_key: &str) -> Result<()> {
+        let source = self.get(source_key)?;
+        self.put(dest_key, source.data);
+        Ok(())
+    }
+
+    async fn delete_file(&self, key: &str) -> Result<()> {
+        self.objects.remove(key);
+        Ok(())
+    }
+
+    async fn file_exists(&self, key: &str) -> Result<bool> {
+        Ok(self.objects.contains_key(key))
+    }
+
+    async fn generate_presigned_url_raw(
+        &self,
+        key: &str,
+        expires_in_secs: u64,
+        _content_type: &str,
+        _content_disposition: &str,
+    ) -> Result<String> {
+        let expires = Utc::now().timestamp() + expires_in_secs as i64;
+        Ok(format!("memory://storage/{}?expires={}", key, expires))
+    }
+
+    async fn generate_presigned_url(
+        &self,
+        key: &str,
+        expires_in_secs: u64,
+        content_type: &str,
+        content_disposition: &str,
+    ) -> Result<String> {
+        self.generate_presigned_url_raw(key, expires_in_secs, content_type, content_disposition)
+            .await
+    }
+
+    async fn get_object_stream(&self, key: &str) -> Result<GetObjectOutput> {
+        let object = self.get(key)?;
+        Ok(Self::output(&object, object.data.clone(), None))
+    }
+
+    async fn get_object_range(&self, key: &str, range: &str) -> Result<GetObjectOutput> {
+        let object = self.get(key)?;
+        let size = object.data.len() as u64;
+        let (start, end) = parse_byte_range(range, size)
+            .ok_or_else(|| anyhow!("Invalid range '{}' for object of {} bytes", range, size))?;
+        let data = object.data[start as usize..=end as usize].to_vec();
+        let content_range = format!("bytes {}-{}/{}", start, end, size);
+        Ok(Self::output(&object, data, Some(content_range)))
+    }
+
+    async fn get_file(&self, key: &str) -> Result<Vec<u8>> {
+        Ok(self.get(key)?.data)
+    }
+
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut keys: Vec<String> = self
+            .objects
+            .iter()
+            .filter(|o| o.key().starts_with(prefix))
+            .map(|o| o.key().clone())
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    async fn get_object_metadata(&self, key: &str) -> Result<FileMetadata> {
+        let object = self.get(key)?;
+        Ok(FileMetadata {
+            last_modified: Some(object.last_modified),
+            size: object.data.len() as i64,
+        })
+    }
+
+    async fn create_multipart_upload(&self, key: &str) -> Result<String> {
+        let upload_id = Uuid::new_v4().to_string();
+        self.multipart.insert(
+            upload_id.clone(),
+            PendingMultipart {
+                key: key.to_string(),
+                parts: BTreeMap::new(),
+            },
+        );
+        Ok(upload_id)
+    }
+
+    async fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Vec<u8>,
+    ) -> Result<String> {
+        let mut upload = self
+            .multipart
+            .get_mut(upload_id)
+            .filter(|u| u.key == key)
+            .ok_or_else(|| anyhow!("No such multipart upload: {}", upload_id))?;
+        let etag = format!("\"{:032x}\"", xxh3_128(&data));
+        upload.parts.insert(part_number, data);
+        Ok(etag)
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        key: &str,
+        upload_id: &str,
+        mut parts: Vec<(i32, String)>,
+    ) -> Result<()> {
+        let (_, upload) = self
+            .multipart
+            .remove_if(upload_id, |_, u| u.key == key)
+            .ok_or_else(|| anyhow!("No such multipart upload: {}", upload_id))?;
+
+        parts.sort_by_key(|(part_number, _)| *part_number);
+        let mut data = Vec::new();
+        for (part_number, etag) in &parts {
+            let part = upload
+                .parts
+                .get(part_number)
+                .ok_or_else(|| anyhow!("Missing part {} for upload {}", part_number, upload_id))?;
+            if format!("{:032x}", xxh3_128(part)) != etag.trim_matches('"') {
+                return Err(anyhow!("ETag mismatch for part {}", part_number));
+            }
+            data.extend_from_slice(part);
+        }
+        self.put(key, data);
+        Ok(())
+    }
+
+    async fn abort_multipart_upload(&self, _key: &str, upload_id: &str) -> Result<()> {
+        self.multipart.remove(upload_id);
+        Ok(())
+    }
+}
@@ -1,12 +1,30 @@
+pub mod account_email;
+pub mod api_tokens;
 pub mod audit;
+pub mod download_tickets;
+pub mod drives;
 pub mod expiration;
 pub mod facts_service;
 pub mod file_service;
+pub mod file_versions;
+pub mod hash_backfill;
+pub mod local_storage;
+pub mod mailer;
+pub mod memory_storage;
 pub mod metadata;
+pub mod quota;
+pub mod rate_limiter;
 pub mod scanner;
+pub mod sessions;
+pub mod share_lockout;
 pub mod share_service;
 pub mod storage;
 pub mod storage_lifecycle;
+pub mod teams;
 pub mod thumbnail_service;
+pub mod two_factor;
 pub mod upload_service;
+pub mod user_admin;
+pub mod validation_rules;
 pub mod worker;
+pub mod zip_stream;
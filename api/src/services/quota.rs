@@ -0,0 +1,258 @@
+use crate::api::error::AppError;
+use crate::config::SecurityConfig;
+use crate::entities::{prelude::*, *};
+use crate::services::drives::Drive;
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseTransaction, EntityTrait, JoinType, QueryFilter, QuerySelect,
+    RelationTrait, sea_query::Expr,
+};
+
+/// Storage a drive has consumed against its quota, in bytes
+#[derive(Debug, Clone, Copy)]
+pub struct QuotaUsage {
+    pub used: i64,
+    /// `None` when the drive is unlimited
+    pub limit: Option<i64>,
+}
+
+impl QuotaUsage {
+    pub fn remaining(&self) -> Option<i64> {
+        self.limit.map(|limit| (limit - self.used).max(0))
+    }
+
+    /// Whether `additional` bytes fit; adding nothing is always allowed
+    pub fn allows(&self, additional: i64) -> bool {
+        additional <= 0
+            || self
+                .limit
+                .is_none_or(|limit| self.used.saturating_add(additional) <= limit)
+    }
+}
+
+/// Service for per-user and per-team storage quotas
+///
+/// Usage is logical: every file counts its full size for its drive, even when
+/// the content is deduplicated against other files. Trashed items and archived
+/// versions count until they are purged, since they still hold their storage.
+/// Team drive files count against the team, not the member who added them.
+pub struct QuotaService;
+
+impl QuotaService {
+    /// The user's own quota, or the configured default
+    pub async fn limit_for(
+        db: &impl sea_orm::ConnectionTrait,
+        config: &SecurityConfig,
+        user_id: &str,
+    ) -> Result<Option<i64>, AppError> {
+        let user = Users::find_by_id(user_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        Ok(user.storage_quota.or(config.default_storage_quota))
+    }
+
+    /// The team's own quota, or the configured default
+    pub async fn team_limit_for(
+        db: &impl sea_orm::ConnectionTrait,
+        config: &SecurityConfig,
+        team_id: &str,
+    ) -> Result<Option<i64>, AppError> {
+        let team = Teams::find_by_id(team_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Team not found".to_string()))?;
+        Ok(team.storage_quota.or(config.default_storage_quota))
+    }
+
+    pub async fn used_bytes(
+        db: &impl sea_orm::ConnectionTrait,
+        drive: &Drive,
+    ) -> Result<i64, AppError> {
+        let files: Option<i64> = UserFiles::find()
+            .select_only()
+            .column_as(Self::sum_of_sizes(), "used")
+            .join(
+                JoinType::InnerJoin,
+                user_files::Relation::StorageFiles.def(),
+            )
+            .filter(drive.condition())
+            .filter(user_files::Column::IsFolder.eq(false))
+            .filter(Self::holds_storage())
+            .into_tuple()
+            .one(db)
+            .await?;
+
+        let versions: Option<i64> = FileVersions::find()
+            .select_only()
+            .column_as(Self::sum_of_sizes(), "used")
+            .join(
+                JoinType::InnerJoin,
+                file_versions::Relation::StorageFiles.def(),
+            )
+            .join(
+                JoinType::InnerJoin,
+                file_versions::Relation::UserFiles.def(),
+            )
+            .filter(drive.condition())
+            .into_tuple()
+            .one(db)
+            .await?;
+
+        Ok(files.unwrap_or(0) + versions.unwrap_or(0))
+    }
+
+    /// Bytes the rows `ids` and their versions count against a quota
+    pub async fn bytes_of(
+        db: &impl sea_orm::ConnectionTrait,
+        ids: Vec<String>,
+    ) -> Result<i64, AppError> {
+        let files: Option<i64> = UserFiles::find()
+            .select_only()
+            .column_as(Self::sum_of_sizes(), "used")
+            .join(
+                JoinType::InnerJoin,
+                user_files::Relation::StorageFiles.def(),
+            )
+            .filter(user_files::Column::Id.is_in(ids.clone()))
+            .filter(user_files::Column::IsFolder.eq(false))
+            .filter(Self::holds_storage())
+            .into_tuple()
+            .one(db)
+            .await?;
+
+        let versions: Option<i64> = FileVersions::find()
+            .select_only()
+            .column_as(Self::sum_of_sizes(), "used")
+            .join(
+                JoinType::InnerJoin,
+                file_versions::Relation::StorageFiles.def(),
+            )
+            .filter(file_versions::Column::UserFileId.is_in(ids))
+            .into_tuple()
+            .one(db)
+            .await?;
+
+        Ok(files.unwrap_or(0) + versions.unwrap_or(0))
+    }
+
+    /// Usage of the user's personal drive
+    pub async fn usage(
+        db: &impl sea_orm::ConnectionTrait,
+        config: &SecurityConfig,
+        user_id: &str,
+    ) -> Result<QuotaUsage, AppError> {
+        Self::drive_usage(db, config, &Drive::Personal(user_id.to_string())).await
+    }
+
+    pub async fn drive_usage(
+        db: &impl sea_orm::ConnectionTrait,
+        config: &SecurityConfig,
+        drive: &Drive,
+    ) -> Result<QuotaUsage, AppError> {
+        let limit = match drive {
+            Drive::Personal(user_id) => Self::limit_for(db, config, user_id).await?,
+            Drive::Team(team_id) => Self::team_limit_for(db, config, team_id).await?,
+        };
+        Ok(QuotaUsage {
+            used: Self::used_bytes(db, drive).await?,
+            limit,
+        })
+    }
+
+    /// Fail with 507 Insufficient Storage if `additional` bytes would exceed the quota
+    ///
+    /// Locks the drive first, so the new content must be recorded in `txn`
+    /// before it commits; concurrent writes then cannot both pass the check.
+    pub async fn ensure_room(
+        txn: &DatabaseTransaction,
+        config: &SecurityConfig,
+        drive: &Drive,
+        additional: i64,
+    ) -> Result<(), AppError> {
+        Self::lock(txn, drive).await?;
+        Self::check_room(txn, config, drive, additional).await
+    }
+
+    /// `ensure_room` without the lock, for failing early before any content
+    /// is written; the write itself must still call `ensure_room`
+    pub async fn check_room(
+        db: &impl sea_orm::ConnectionTrait,
+        config: &SecurityConfig,
+        drive: &Drive,
+        additional: i64,
+    ) -> Result<(), AppError> {
+        let usage = Self::drive_usage(db, config, drive).await?;
+        if usage.allows(additional) {
+            return Ok(());
+        }
+        Err(Self::exceeded(&usage, additional))
+    }
+
+    /// Lock the user or team row owning `drive` until `txn` ends
+    ///
+    /// Serialises quota checks across API instances, as
+    /// `RateLimitService::hit` does for the upload window.
+    pub async fn lock(txn: &DatabaseTransaction, drive: &Drive) -> Result<(), AppError> {
+        match drive {
+            Drive::Personal(user_id) => {
+                Users::find_by_id(user_id).lock_exclusive().one(txn).await?;
+            }
+            Drive::Team(team_id) => {
+                Teams::find_by_id(team_id).lock_exclusive().one(txn).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn exceeded(usage: &QuotaUsage, additional: i64) -> AppError {
+        AppError::InsufficientStorage(format!(
+            "Storage quota exceeded: {} bytes used of {}, {} more requested",
+            usage.used,
+            usage.limit.unwrap_or_default(),
+            additional
+        ))
+    }
+
+    /// Live and trashed rows; internal tombstones hold no storage of their own
+    fn holds_storage() -> Condition {
+        Condition::any()
+            .add(user_files::Column::DeletedAt.is_null())
+            .add(user_files::Column::TrashRootId.is_not_null())
+    }
+
+    /// SUM over storage sizes, cast so Postgres returns BIGINT rather than NUMERIC
+    fn sum_of_sizes() -> sea_orm::sea_query::SimpleExpr {
+        Expr::cust("CAST(COALESCE(SUM(storage_files.size), 0) AS BIGINT)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quota_usage_allows() {
+        let unlimited = QuotaUsage {
+            used: i64::MAX,
+            limit: None,
+        };
+        assert!(unlimited.allows(1));
+        assert_eq!(unlimited.remaining(), None);
+
+        let usage = QuotaUsage {
+            used: 60,
+            limit: Some(100),
+        };
+        assert!(usage.allows(40));
+        assert!(!usage.allows(41));
+        assert_eq!(usage.remaining(), Some(40));
+
+        let over = QuotaUsage {
+            used: 150,
+            limit: Some(100),
+        };
+        assert!(over.allows(0));
+        assert!(!over.allows(1));
+        assert_eq!(over.remaining(), Some(0));
+    }
+}
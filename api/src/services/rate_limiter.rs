@@ -0,0 +1,86 @@
+use crate::api::error::AppError;
+use crate::entities::{prelude::*, *};
+use chrono::{Duration, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+use uuid::Uuid;
+
+/// Requests that store new content: `/upload`, `/files/upload/init` and `/files/link`
+pub const UPLOAD_BUCKET: &str = "upload";
+/// Dedup lookups on `/pre-check`, limited separately so checking before uploading is free
+pub const PRE_CHECK_BUCKET: &str = "pre_check";
+
+/// Per-user sliding-window rate limits
+///
+/// Every counted request is a row in `rate_limit_events`, so all API
+/// instances sharing the database enforce the same limit.
+pub struct RateLimitService;
+
+impl RateLimitService {
+    /// Count one request against `bucket`, or fail with 429 if `limit` requests
+    /// already fall within the last `window`
+    pub async fn hit(
+        db: &DatabaseConnection,
+        user_id: &str,
+        bucket: &str,
+        limit: u32,
+        window: Duration,
+    ) -> Result<(), AppError> {
+        let txn = db.begin().await?;
+        // Serialise concurrent requests of the same user, across instances
+        Users::find_by_id(user_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?;
+
+        let now = Utc::now();
+        let in_window = RateLimitEvents::find()
+            .filter(rate_limit_events::Column::UserId.eq(user_id))
+            .filter(rate_limit_events::Column::Bucket.eq(bucket))
+            .filter(rate_limit_events::Column::CreatedAt.gt(now - window));
+        let count = in_window.clone().count(&txn).await?;
+
+        if count >= limit as u64 {
+            // A slot frees up once enough of the oldest requests leave the window
+            let freeing = in_window
+                .order_by_asc(rate_limit_events::Column::CreatedAt)
+                .offset(count - limit as u64)
+                .one(&txn)
+                .await?;
+            let wait = freeing.map_or(window, |event| event.created_at + window - now);
+            let retry_after_secs = ((wait.num_milliseconds() + 999) / 1000).max(1) as u64;
+            return Err(AppError::TooManyRequests {
+                message: format!(
+                    "Rate limit exceeded: {} {} requests per {} minutes. Retry in {} seconds",
+                    limit,
+                    bucket,
+                    window.num_minutes(),
+                    retry_after_secs
+                ),
+                retry_after_secs,
+            });
+        }
+
+        rate_limit_events::ActiveModel {
+            id: Set(Uuid::new_v4().to_string()),
+            user_id: Set(user_id.to_string()),
+            bucket: Set(bucket.to_string()),
+            created_at: Set(now),
+        }
+        .insert(&txn)
+        .await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Delete events older than `window`; they no longer count against any limit
+    pub async fn sweep(db: &DatabaseConnection, window: Duration) -> Result<u64, AppError> {
+        let res = RateLimitEvents::delete_many()
+            .filter(rate_limit_events::Column::CreatedAt.lte(Utc::now() - window))
+            .exec(db)
+            .await?;
+        Ok(res.rows_affected)
+    }
+}
@@ -0,0 +1,280 @@
+use crate::api::error::AppError;
+use crate::config::SecurityConfig;
+use crate::entities::{prelude::*, *};
+use crate::utils::auth::{Claims, create_jwt};
+use crate::utils::hash::calculate_hash;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, Set, sea_query::Expr,
+};
+use uuid::Uuid;
+
+/// Longest user agent kept for the session list
+const MAX_DEVICE_LEN: usize = 255;
+
+/// Tokens handed to a client when it signs in or refreshes
+#[derive(Debug, Clone)]
+pub struct IssuedTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Lifetime of the access token, in seconds
+    pub expires_in: i64,
+    pub session_id: String,
+}
+
+/// Where a request came from, shown in the session list
+#[derive(Debug, Default, Clone)]
+pub struct ClientInfo {
+    pub device: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+/// Sign-in sessions kept in `tokens`, with rotating refresh tokens
+///
+/// Access tokens are short-lived JWTs carrying their session's ID. Ending a
+/// session deletes its row and puts the session ID on the `revoked_tokens`
+/// denylist until every access token it issued has expired.
+pub struct SessionService;
+
+impl SessionService {
+    /// Open a session for `user_id`
+    pub async fn start(
+        db: &DatabaseConnection,
+        config: &SecurityConfig,
+        user_id: &str,
+        client: ClientInfo,
+    ) -> Result<IssuedTokens, AppError> {
+        let refresh_token = Self::generate_token();
+        let now = Utc::now();
+        let session = tokens::ActiveModel {
+            id: Set(Uuid::new_v4().to_string()),
+            user_id: Set(user_id.to_string()),
+            token: Set(calculate_hash(refresh_token.as_bytes())),
+            previous_token: Set(None),
+            device: Set(client.device),
+            ip_address: Set(client.ip_address),
+            created_at: Set(Some(now)),
+            last_used_at: Set(Some(now)),
+            expires_at: Set(now + Duration::days(config.refresh_token_ttl_days)),
+        }
+        .insert(db)
+        .await?;
+
+        Self::issue(config, &session, refresh_token)
+    }
+
+    /// Swap a refresh token for a new access token and refresh token
+    ///
+    /// Each refresh token works once. Presenting one that was already
+    /// swapped means it was copied, so the whole session is ended.
+    pub async fn refresh(
+        db: &DatabaseConnection,
+        config: &SecurityConfig,
+        refresh_token: &str,
+        client: ClientInfo,
+    ) -> Result<IssuedTokens, AppError> {
+        let hash = calculate_hash(refresh_token.as_bytes());
+        let Some(session) = Tokens::find()
+            .filter(tokens::Column::Token.eq(&hash))
+            .one(db)
+            .await?
+        else {
+            if let Some(replayed) = Tokens::find()
+                .filter(tokens::Column::PreviousToken.eq(&hash))
+                .one(db)
+                .await?
+            {
+                tracing::warn!(
+                    "Refresh token of session {} was replayed; ending the session",
+                    replayed.id
+                );
+                Self::end(db, config, &replayed).await?;
+            }
+            return Err(AppError::Unauthorized("Invalid refresh token".to_string()));
+        };
+
+        let now = Utc::now();
+        if session.expires_at < now {
+            Self::end(db, config, &session).await?;
+            return Err(AppError::Unauthorized("Session has expired".to_string()));
+        }
+
+        // Rotate only if no concurrent refresh got there first
+        let new_token = Self::generate_token();
+        let mut update = Tokens::update_many()
+            .col_expr(
+                tokens::Column::Token,
+                Expr::value(calculate_hash(new_token.as_bytes())),
+            )
+            .col_expr(tokens::Column::PreviousToken, Expr::value(hash.clone()))
+            .col_expr(tokens::Column::LastUsedAt, Expr::value(now))
+            .col_expr(
+                tokens::Column::ExpiresAt,
+                Expr::value(now + Duration::days(config.refresh_token_ttl_days)),
+            );
+        if let Some(device) = client.device {
+            update = update.col_expr(tokens::Column::Device, Expr::value(device));
+        }
+        if let Some(ip) = client.ip_address {
+            update = update.col_expr(tokens::Column::IpAddress, Expr::value(ip));
+        }
+        let res = update
+            .filter(tokens::Column::Id.eq(&session.id))
+            .filter(tokens::Column::Token.eq(&hash))
+            .exec(db)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(AppError::Unauthorized("Invalid refresh token".to_string()));
+        }
+
+        Self::issue(config, &session, new_token)
+    }
+
+    /// Active sessions of `user_id`, most recently used first
+    pub async fn list(
+        db: &DatabaseConnection,
+        user_id: &str,
+    ) -> Result<Vec<tokens::Model>, AppError> {
+        let sessions = Tokens::find()
+            .filter(tokens::Column::UserId.eq(user_id))
+            .filter(tokens::Column::ExpiresAt.gt(Utc::now()))
+            .order_by_desc(tokens::Column::LastUsedAt)
+            .all(db)
+            .await?;
+        Ok(sessions)
+    }
+
+    /// End one of `user_id`'s sessions
+    pub async fn revoke(
+        db: &DatabaseConnection,
+        config: &SecurityConfig,
+        user_id: &str,
+        session_id: &str,
+    ) -> Result<(), AppError> {
+        let session = Tokens::find_by_id(session_id)
+            .filter(tokens::Column::UserId.eq(user_id))
+            .one(db)
+            .await?
+            .ok_or(AppError::NotFound("Session not found".to_string()))?;
+        Self::end(db, config, &session).await
+    }
+
+    /// End every session of `user_id`, returning how many there were
+    pub async fn revoke_all(
+        db: &DatabaseConnection,
+        config: &SecurityConfig,
+        user_id: &str,
+    ) -> Result<usize, AppError> {
+        let sessions = Tokens::find()
+            .filter(tokens::Column::UserId.eq(user_id))
+            .all(db)
+            .await?;
+        for session in &sessions {
+            Self::end(db, config, session).await?;
+        }
+        Ok(sessions.len())
+    }
+
+    /// Refuse one access token until it expires
+    pub async fn revoke_access_token(
+        db: &DatabaseConnection,
+        claims: &Claims,
+    ) -> Result<(), AppError> {
+        let expires_at = DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_else(Utc::now);
+        Self::deny(db, &claims.jti, &claims.sub, expires_at).await
+    }
+
+    /// Whether the access token, or its whole session, was revoked
+    pub async fn is_revoked(db: &DatabaseConnection, claims: &Claims) -> Result<bool, AppError> {
+        let ids = std::iter::once(claims.jti.as_str()).chain(claims.sid.as_deref());
+        let revoked = RevokedTokens::find()
+            .filter(revoked_tokens::Column::Jti.is_in(ids))
+            .count(db)
+            .await?;
+        Ok(revoked > 0)
+    }
+
+    /// Delete expired sessions and denylist entries
+    pub async fn sweep(db: &DatabaseConnection) -> Result<u64, AppError> {
+        let now = Utc::now();
+        let sessions = Tokens::delete_many()
+            .filter(tokens::Column::ExpiresAt.lt(now))
+            .exec(db)
+            .await?;
+        let denied = RevokedTokens::delete_many()
+            .filter(revoked_tokens::Column::ExpiresAt.lt(now))
+            .exec(db)
+            .await?;
+        Ok(sessions.rows_affected + denied.rows_affected)
+    }
+
+    /// Delete a session and refuse the access tokens it issued
+    async fn end(
+        db: &DatabaseConnection,
+        config: &SecurityConfig,
+        session: &tokens::Model,
+    ) -> Result<(), AppError> {
+        let until = Utc::now() + Duration::minutes(config.access_token_ttl_minutes);
+        Self::deny(db, &session.id, &session.user_id, until).await?;
+        Tokens::delete_by_id(&session.id).exec(db).await?;
+        Ok(())
+    }
+
+    async fn deny(
+        db: &DatabaseConnection,
+        jti: &str,
+        user_id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        if RevokedTokens::find_by_id(jti).one(db).await?.is_some() {
+            return Ok(());
+        }
+        revoked_tokens::ActiveModel {
+            jti: Set(jti.to_string()),
+            user_id: Set(user_id.to_string()),
+            expires_at: Set(expires_at),
+        }
+        .insert(db)
+        .await?;
+        Ok(())
+    }
+
+    fn issue(
+        config: &SecurityConfig,
+        session: &tokens::Model,
+        refresh_token: String,
+    ) -> Result<IssuedTokens, AppError> {
+        let ttl = Duration::minutes(config.access_token_ttl_minutes);
+        let access_token = create_jwt(&session.user_id, Some(&session.id), ttl, &config.jwt_secret)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        Ok(IssuedTokens {
+            access_token,
+            refresh_token,
+            expires_in: ttl.num_seconds(),
+            session_id: session.id.clone(),
+        })
+    }
+
+    fn generate_token() -> String {
+        use rand::Rng;
+        let bytes: [u8; 32] = rand::thread_rng().r#gen();
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+    }
+}
+
+impl ClientInfo {
+    /// Describe the client behind `headers`
+    pub fn from_headers(headers: &axum::http::HeaderMap) -> Self {
+        let device = headers
+            .get(axum::http::header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(|ua| ua.chars().take(MAX_DEVICE_LEN).collect());
+        let ip = crate::api::handlers::captcha::extract_client_ip(headers);
+        Self {
+            device,
+            ip_address: (ip != "unknown").then_some(ip),
+        }
+    }
+}
@@ -0,0 +1,307 @@
+use crate::api::error::AppError;
+use crate::config::SecurityConfig;
+use crate::entities::{prelude::*, *};
+use crate::services::share_service::ShareService;
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+use uuid::Uuid;
+
+/// Length of the first lockout; every further failure doubles it
+const BASE_LOCKOUT_SECS: i64 = 30;
+/// Longest a single lockout lasts
+const MAX_LOCKOUT_SECS: i64 = 60 * 60;
+/// Counters left alone this long (and not locked) start over
+const RESET_AFTER_MINUTES: i64 = 60;
+
+/// Exponential lockout for share password guesses
+///
+/// Failures are counted per client IP and per share in
+/// `share_password_attempts`, so every API instance enforces the same
+/// lockouts. The per-share counter catches guesses spread over many IPs.
+pub struct ShareLockoutService;
+
+impl ShareLockoutService {
+    /// Fail with 429 while the share or this client is locked out; otherwise
+    /// return how many recent failures the client has
+    ///
+    /// Only a quick early check: the attempt itself must be counted with
+    /// `reserve_attempt` before the password is verified.
+    pub async fn check(
+        db: &DatabaseConnection,
+        share_id: &str,
+        client_ip: Option<&str>,
+    ) -> Result<u32, AppError> {
+        let now = Utc::now();
+        let counters = Self::counters(db, share_id, client_ip).await?;
+        Self::ensure_unlocked(&counters, now)?;
+        Ok(counters
+            .iter()
+            .find(|c| c.client_ip.is_some() && !Self::is_stale(c, now))
+            .map_or(0, |c| c.failed_attempts.max(0) as u32))
+    }
+
+    /// Count a password attempt from `client_ip` as failed before it is
+    /// verified, locking the client or the whole share once its threshold
+    /// is reached
+    ///
+    /// Fails with 429 if a lockout is already in place. Reserving up front
+    /// means a burst of parallel guesses cannot all pass the lockout check
+    /// before any of them is counted; `clear` refunds a correct password.
+    pub async fn reserve_attempt(
+        db: &DatabaseConnection,
+        share: &share_links::Model,
+        client_ip: Option<&str>,
+        config: &SecurityConfig,
+    ) -> Result<(), AppError> {
+        let txn = db.begin().await?;
+        // Serialise concurrent attempts on the same share, across instances
+        ShareLinks::find_by_id(&share.id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?;
+
+        let now = Utc::now();
+        let existing = Self::counters(&txn, &share.id, client_ip).await?;
+        Self::ensure_unlocked(&existing, now)?;
+        let scopes = client_ip
+            .map(|ip| (Some(ip), config.share_password_ip_attempts))
+            .into_iter()
+            .chain([(None, config.share_password_share_attempts)]);
+
+        let mut locked = Vec::new();
+        for (ip, threshold) in scopes {
+            let counter = existing
+                .iter()
+                .find(|c| c.client_ip.as_deref() == ip)
+                .cloned();
+            let failures = match &counter {
+                Some(c) if !Self::is_stale(c, now) => c.failed_attempts + 1,
+                _ => 1,
+            };
+            let locked_until =
+                lockout_secs(failures as u32, threshold).map(|secs| now + Duration::seconds(secs));
+            if let Some(until) = locked_until {
+                locked.push((ip.map(str::to_string), failures, until));
+            }
+
+            match counter {
+                Some(c) => {
+                    let mut active: share_password_attempts::ActiveModel = c.into();
+                    active.failed_attempts = Set(failures);
+                    active.last_attempt = Set(now);
+                    active.locked_until = Set(locked_until);
+                    active.update(&txn).await?;
+                }
+                None => {
+                    share_password_attempts::ActiveModel {
+                        id: Set(Uuid::new_v4().to_string()),
+                        share_link_id: Set(share.id.clone()),
+                        client_ip: Set(ip.map(str::to_string)),
+                        failed_attempts: Set(failures),
+                        last_attempt: Set(now),
+                        locked_until: Set(locked_until),
+                    }
+                    .insert(&txn)
+                    .await?;
+                }
+            }
+        }
+        txn.commit().await?;
+
+        for (ip, failures, until) in locked {
+            tracing::warn!(
+                "Share {} locked for {} until {} after {} failed password attempts",
+                share.id,
+                ip.as_deref().unwrap_or("all clients"),
+                until,
+                failures
+            );
+            ShareService::log_access(
+                db,
+                &share.id,
+                None,
+                client_ip.map(str::to_string),
+                None,
+                if ip.is_some() {
+                    "lockout_ip"
+                } else {
+                    "lockout_share"
+                },
+            )
+            .await;
+        }
+        Ok(())
+    }
+
+    /// Forget the failures of a client that got the password right, and
+    /// take its reserved attempt back off the share-wide counter
+    pub async fn clear(
+        db: &DatabaseConnection,
+        share_id: &str,
+        client_ip: Option<&str>,
+        config: &SecurityConfig,
+    ) -> Result<(), AppError> {
+        let txn = db.begin().await?;
+        ShareLinks::find_by_id(share_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?;
+        if let Some(ip) = client_ip {
+            SharePasswordAttempts::delete_many()
+                .filter(share_password_attempts::Column::ShareLinkId.eq(share_id))
+                .filter(share_password_attempts::Column::ClientIp.eq(ip))
+                .exec(&txn)
+                .await?;
+        }
+        let shared = SharePasswordAttempts::find()
+            .filter(share_password_attempts::Column::ShareLinkId.eq(share_id))
+            .filter(share_password_attempts::Column::ClientIp.is_null())
+            .one(&txn)
+            .await?;
+        if let Some(counter) = shared.filter(|c| c.failed_attempts > 0) {
+            let failures = counter.failed_attempts - 1;
+            let lifted = lockout_secs(failures as u32, config.share_password_share_attempts)
+                .is_none();
+            let mut active: share_password_attempts::ActiveModel = counter.into();
+            active.failed_attempts = Set(failures);
+            if lifted {
+                active.locked_until = Set(None);
+            }
+            active.update(&txn).await?;
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Failure counters of one of `user_id`'s shares, most recent first
+    pub async fn list(
+        db: &DatabaseConnection,
+        share_id: &str,
+        user_id: &str,
+    ) -> Result<Vec<share_password_attempts::Model>, AppError> {
+        Self::owned_share(db, share_id, user_id).await?;
+        let counters = SharePasswordAttempts::find()
+            .filter(share_password_attempts::Column::ShareLinkId.eq(share_id))
+            .order_by_desc(share_password_attempts::Column::LastAttempt)
+            .all(db)
+            .await?;
+        Ok(counters)
+    }
+
+    /// Lift every lockout of one of `user_id`'s shares
+    pub async fn reset(
+        db: &DatabaseConnection,
+        share_id: &str,
+        user_id: &str,
+    ) -> Result<u64, AppError> {
+        Self::owned_share(db, share_id, user_id).await?;
+        let res = SharePasswordAttempts::delete_many()
+            .filter(share_password_attempts::Column::ShareLinkId.eq(share_id))
+            .exec(db)
+            .await?;
+        Ok(res.rows_affected)
+    }
+
+    /// Delete counters that have gone stale and hold no lockout
+    pub async fn sweep(db: &DatabaseConnection) -> Result<u64, AppError> {
+        let now = Utc::now();
+        let res = SharePasswordAttempts::delete_many()
+            .filter(
+                share_password_attempts::Column::LastAttempt
+                    .lt(now - Duration::minutes(RESET_AFTER_MINUTES)),
+            )
+            .filter(
+                Condition::any()
+                    .add(share_password_attempts::Column::LockedUntil.is_null())
+                    .add(share_password_attempts::Column::LockedUntil.lt(now)),
+            )
+            .exec(db)
+            .await?;
+        Ok(res.rows_affected)
+    }
+
+    /// The share-wide counter and, if known, the client's own
+    async fn counters<C: sea_orm::ConnectionTrait>(
+        db: &C,
+        share_id: &str,
+        client_ip: Option<&str>,
+    ) -> Result<Vec<share_password_attempts::Model>, AppError> {
+        let mut scope = Condition::any().add(share_password_attempts::Column::ClientIp.is_null());
+        if let Some(ip) = client_ip {
+            scope = scope.add(share_password_attempts::Column::ClientIp.eq(ip));
+        }
+        let counters = SharePasswordAttempts::find()
+            .filter(share_password_attempts::Column::ShareLinkId.eq(share_id))
+            .filter(scope)
+            .all(db)
+            .await?;
+        Ok(counters)
+    }
+
+    /// Fail with 429 if any of `counters` holds a lockout
+    fn ensure_unlocked(
+        counters: &[share_password_attempts::Model],
+        now: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        let Some(locked_until) = counters
+            .iter()
+            .filter_map(|c| c.locked_until)
+            .filter(|until| *until > now)
+            .max()
+        else {
+            return Ok(());
+        };
+        let retry_after_secs = ((locked_until - now).num_seconds() + 1).max(1) as u64;
+        Err(AppError::TooManyRequests {
+            message: format!(
+                "Too many failed password attempts. Retry in {} seconds",
+                retry_after_secs
+            ),
+            retry_after_secs,
+        })
+    }
+
+    fn is_stale(counter: &share_password_attempts::Model, now: DateTime<Utc>) -> bool {
+        counter.locked_until.is_none_or(|until| until <= now)
+            && now - counter.last_attempt > Duration::minutes(RESET_AFTER_MINUTES)
+    }
+
+    async fn owned_share(
+        db: &DatabaseConnection,
+        share_id: &str,
+        user_id: &str,
+    ) -> Result<share_links::Model, AppError> {
+        ShareLinks::find_by_id(share_id)
+            .filter(share_links::Column::CreatedBy.eq(user_id))
+            .one(db)
+            .await?
+            .ok_or(AppError::NotFound("Share not found".to_string()))
+    }
+}
+
+/// Seconds to lock for after `failures` failed attempts, once `threshold` is reached
+fn lockout_secs(failures: u32, threshold: u32) -> Option<i64> {
+    if failures < threshold {
+        return None;
+    }
+    let doublings = (failures - threshold).min(16);
+    Some((BASE_LOCKOUT_SECS << doublings).min(MAX_LOCKOUT_SECS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockout_doubles_up_to_the_cap() {
+        assert_eq!(lockout_secs(4, 5), None);
+        assert_eq!(lockout_secs(5, 5), Some(30));
+        assert_eq!(lockout_secs(6, 5), Some(60));
+        assert_eq!(lockout_secs(8, 5), Some(240));
+        assert_eq!(lockout_secs(12, 5), Some(MAX_LOCKOUT_SECS));
+        assert_eq!(lockout_secs(500, 5), Some(MAX_LOCKOUT_SECS));
+    }
+}
@@ -1,17 +1,35 @@
 use crate::api::error::AppError;
 use crate::entities::{prelude::*, *};
+use crate::services::drives::{Destination, DriveAccess, Permission};
+use crate::services::storage_lifecycle::StorageLifecycleService;
+use crate::utils::net::parse_network;
 use argon2::{
     Argon2,
     password_hash::{PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
 use base64::Engine;
-use chrono::Utc;
+use chrono::{Duration, Utc};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, Condition, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
-    Set,
+    Set, sea_query::Expr,
 };
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+/// How long a client may keep resuming a download it started
+const RESUME_WINDOW_HOURS: i64 = 12;
+
+#[derive(Serialize, Deserialize)]
+struct ResumeClaims {
+    /// Share link id
+    sub: String,
+    file_id: String,
+    /// Ties the token to the one download that issued it
+    jti: String,
+    exp: usize,
+}
+
 pub struct ShareService;
 
 pub struct CreateShareParams {
@@ -22,6 +40,14 @@ pub struct CreateShareParams {
     pub password: Option<String>,
     pub permission: String,
     pub expires_at: chrono::DateTime<Utc>,
+    pub max_uploads: Option<i32>,
+    pub max_upload_size: Option<i64>,
+    pub allowed_upload_types: Option<String>,
+    pub max_downloads: Option<i32>,
+    pub max_views: Option<i32>,
+    pub allowed_ips: Option<String>,
+    pub denied_ips: Option<String>,
+    pub allowed_referrers: Option<String>,
 }
 
 impl ShareService {
@@ -59,15 +85,80 @@ impl ShareService {
         db: &sea_orm::DatabaseConnection,
         params: CreateShareParams,
     ) -> Result<share_links::Model, AppError> {
-        // Verify the user owns the file
-        let _user_file = UserFiles::find_by_id(&params.user_file_id)
-            .filter(user_files::Column::UserId.eq(&params.created_by))
-            .filter(user_files::Column::DeletedAt.is_null())
-            .one(db)
-            .await?
-            .ok_or(AppError::NotFound(
-                "File not found or access denied".to_string(),
-            ))?;
+        // Verify the user owns the file, or may edit it in a team drive
+        let user_file = DriveAccess::find_item(
+            db,
+            &params.created_by,
+            &params.user_file_id,
+            Permission::Write,
+        )
+        .await?
+        .ok_or(AppError::NotFound(
+            "File not found or access denied".to_string(),
+        ))?;
+
+        if let Some(recipient) = &params.shared_with_user_id {
+            if recipient == &params.created_by {
+                return Err(AppError::BadRequest(
+                    "Cannot share an item with yourself".to_string(),
+                ));
+            }
+            Users::find_by_id(recipient)
+                .one(db)
+                .await?
+                .ok_or(AppError::BadRequest("Recipient not found".to_string()))?;
+        }
+
+        if params.permission == "edit" {
+            if !user_file.is_folder {
+                return Err(AppError::BadRequest(
+                    "Edit shares must point to a folder".to_string(),
+                ));
+            }
+            if params.share_type != "user" {
+                return Err(AppError::BadRequest(
+                    "Edit shares must be shared with a user".to_string(),
+                ));
+            }
+        }
+
+        if params.permission == "upload" {
+            if !user_file.is_folder {
+                return Err(AppError::BadRequest(
+                    "Upload shares must point to a folder".to_string(),
+                ));
+            }
+            if params.share_type != "public" {
+                return Err(AppError::BadRequest(
+                    "Upload shares must be public".to_string(),
+                ));
+            }
+        } else if params.max_uploads.is_some()
+            || params.max_upload_size.is_some()
+            || params.allowed_upload_types.is_some()
+        {
+            return Err(AppError::BadRequest(
+                "Upload limits only apply to upload shares".to_string(),
+            ));
+        }
+
+        if (params.max_downloads.is_some() || params.max_views.is_some())
+            && (params.share_type != "public" || params.permission == "upload")
+        {
+            return Err(AppError::BadRequest(
+                "Download and view limits only apply to public view or download shares".to_string(),
+            ));
+        }
+
+        if (params.allowed_ips.is_some()
+            || params.denied_ips.is_some()
+            || params.allowed_referrers.is_some())
+            && params.share_type != "public"
+        {
+            return Err(AppError::BadRequest(
+                "IP and referrer restrictions only apply to public shares".to_string(),
+            ));
+        }
 
         let password_hash = match params.password {
             Some(ref p) if !p.is_empty() => Some(Self::hash_password(p)?),
@@ -88,6 +179,17 @@ impl ShareService {
             permission: Set(params.permission),
             expires_at: Set(params.expires_at),
             created_at: Set(Some(Utc::now())),
+            max_uploads: Set(params.max_uploads),
+            max_upload_size: Set(params.max_upload_size),
+            allowed_upload_types: Set(params.allowed_upload_types),
+            upload_count: Set(0),
+            max_downloads: Set(params.max_downloads),
+            download_count: Set(0),
+            max_views: Set(params.max_views),
+            view_count: Set(0),
+            allowed_ips: Set(params.allowed_ips),
+            denied_ips: Set(params.denied_ips),
+            allowed_referrers: Set(params.allowed_referrers),
         };
 
         let result = share.insert(db).await?;
@@ -131,6 +233,74 @@ impl ShareService {
     pub async fn get_share_by_token(
         db: &sea_orm::DatabaseConnection,
         token: &str,
+    ) -> Result<share_links::Model, AppError> {
+        let share = Self::find_public_share(db, token).await?;
+
+        if Self::remaining(share.max_downloads, share.download_count) == Some(0) {
+            return Err(AppError::Gone(
+                "Share link has reached its download limit".to_string(),
+            ));
+        }
+
+        Ok(share)
+    }
+
+    /// Like `get_share_by_token`, but a used-up download limit is left to
+    /// `record_download`, so a request carrying a resume token can still
+    /// finish the download it belongs to
+    pub async fn get_share_to_resume(
+        db: &sea_orm::DatabaseConnection,
+        token: &str,
+    ) -> Result<share_links::Model, AppError> {
+        Self::find_public_share(db, token).await
+    }
+
+    /// Resume tokens use a key derived from the JWT secret so they can never
+    /// be accepted as session tokens (and vice versa)
+    fn resume_key(jwt_secret: &str) -> String {
+        format!("{}:share-resume", jwt_secret)
+    }
+
+    /// Sign a token that lets the client of one counted download of `file_id`
+    /// through `share_id` fetch the rest of it with `Range` requests
+    pub fn issue_resume_token(
+        jwt_secret: &str,
+        share_id: &str,
+        file_id: &str,
+    ) -> Result<String, AppError> {
+        let claims = ResumeClaims {
+            sub: share_id.to_string(),
+            file_id: file_id.to_string(),
+            jti: Uuid::new_v4().to_string(),
+            exp: (Utc::now() + Duration::hours(RESUME_WINDOW_HOURS)).timestamp() as usize,
+        };
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(Self::resume_key(jwt_secret).as_bytes()),
+        )
+        .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    /// Whether `token` is an unexpired resume token for `file_id` in `share_id`
+    pub fn resume_token_matches(
+        jwt_secret: &str,
+        token: &str,
+        share_id: &str,
+        file_id: &str,
+    ) -> bool {
+        decode::<ResumeClaims>(
+            token,
+            &DecodingKey::from_secret(Self::resume_key(jwt_secret).as_bytes()),
+            &Validation::default(),
+        )
+        .is_ok_and(|data| data.claims.sub == share_id && data.claims.file_id == file_id)
+    }
+
+    /// Look up an unexpired link share by token, whatever its counters say
+    async fn find_public_share(
+        db: &sea_orm::DatabaseConnection,
+        token: &str,
     ) -> Result<share_links::Model, AppError> {
         let share = ShareLinks::find()
             .filter(share_links::Column::ShareToken.eq(token))
@@ -143,9 +313,140 @@ impl ShareService {
             return Err(AppError::Gone("Share link has expired".to_string()));
         }
 
+        // User shares are opened by their recipient through /shares/incoming
+        if share.share_type == "user" {
+            return Err(AppError::Forbidden(
+                "This share is only available to its recipient".to_string(),
+            ));
+        }
+
+        Ok(share)
+    }
+
+    /// Active shares addressed to `user_id`, with the shared item and its owner
+    pub async fn list_incoming_shares(
+        db: &sea_orm::DatabaseConnection,
+        user_id: &str,
+    ) -> Result<Vec<(share_links::Model, user_files::Model, Option<users::Model>)>, AppError> {
+        let shares = ShareLinks::find()
+            .filter(share_links::Column::ShareType.eq("user"))
+            .filter(share_links::Column::SharedWithUserId.eq(user_id))
+            .filter(share_links::Column::ExpiresAt.gt(Utc::now()))
+            .find_also_related(UserFiles)
+            .order_by_desc(share_links::Column::CreatedAt)
+            .all(db)
+            .await?;
+
+        let mut result = Vec::with_capacity(shares.len());
+        for (share, user_file) in shares {
+            let Some(user_file) = user_file.filter(|f| f.deleted_at.is_none()) else {
+                continue;
+            };
+            let owner = Users::find_by_id(&share.created_by).one(db).await?;
+            result.push((share, user_file, owner));
+        }
+        Ok(result)
+    }
+
+    /// A share addressed to `user_id`, as long as it has not expired
+    pub async fn get_incoming_share(
+        db: &sea_orm::DatabaseConnection,
+        share_id: &str,
+        user_id: &str,
+    ) -> Result<share_links::Model, AppError> {
+        let share = ShareLinks::find_by_id(share_id)
+            .filter(share_links::Column::ShareType.eq("user"))
+            .filter(share_links::Column::SharedWithUserId.eq(user_id))
+            .one(db)
+            .await?
+            .ok_or(AppError::NotFound("Share not found".to_string()))?;
+
+        if Utc::now() > share.expires_at {
+            return Err(AppError::Gone("Share link has expired".to_string()));
+        }
+
         Ok(share)
     }
 
+    /// The live item `item_id` refers to, if it is the shared item or lies
+    /// anywhere beneath it; the shared item itself when `item_id` is `None`
+    pub async fn resolve_shared_item(
+        db: &sea_orm::DatabaseConnection,
+        share: &share_links::Model,
+        item_id: Option<&str>,
+    ) -> Result<user_files::Model, AppError> {
+        let item_id = item_id.unwrap_or(&share.user_file_id);
+        let item = UserFiles::find_by_id(item_id)
+            .filter(user_files::Column::DeletedAt.is_null())
+            .one(db)
+            .await?
+            .ok_or(AppError::NotFound(
+                "Shared file no longer exists".to_string(),
+            ))?;
+
+        // Walk up towards the root until the shared item turns up
+        let mut current = Some(item.clone());
+        while let Some(node) = current {
+            if node.id == share.user_file_id {
+                return Ok(item);
+            }
+            current = match node.parent_id {
+                Some(parent_id) => {
+                    UserFiles::find_by_id(parent_id)
+                        .filter(user_files::Column::DeletedAt.is_null())
+                        .one(db)
+                        .await?
+                }
+                None => None,
+            };
+        }
+
+        Err(AppError::Forbidden(
+            "File does not belong to this share".to_string(),
+        ))
+    }
+
+    /// Fail unless `share` lets its recipient change the shared folder
+    pub fn require_edit(share: &share_links::Model) -> Result<(), AppError> {
+        if share.permission != "edit" {
+            return Err(AppError::Forbidden(
+                "This share does not permit changes".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// A folder inside an edit share; the shared folder itself when
+    /// `folder_id` is `None`
+    pub async fn resolve_shared_folder(
+        db: &sea_orm::DatabaseConnection,
+        share: &share_links::Model,
+        folder_id: Option<&str>,
+    ) -> Result<user_files::Model, AppError> {
+        let folder = Self::resolve_shared_item(db, share, folder_id).await?;
+        if !folder.is_folder {
+            return Err(AppError::BadRequest(
+                "Destination is not a folder".to_string(),
+            ));
+        }
+        Ok(folder)
+    }
+
+    /// An item strictly beneath the shared folder; the shared folder itself
+    /// can only be changed by its owner
+    pub async fn resolve_shared_child(
+        db: &sea_orm::DatabaseConnection,
+        share: &share_links::Model,
+        item_id: &str,
+    ) -> Result<user_files::Model, AppError> {
+        if item_id == share.user_file_id {
+            return Err(AppError::Forbidden(
+                "The shared folder itself cannot be changed".to_string(),
+            ));
+        }
+        Self::resolve_shared_item(db, share, Some(item_id)).await
+    }
+
     /// Log an access event for a share link
     pub async fn log_access(
         db: &sea_orm::DatabaseConnection,
@@ -229,4 +530,398 @@ impl ShareService {
 
         Ok(shares)
     }
+
+    /// What is left of a `max` limit after `count` uses; `None` when unlimited
+    pub fn remaining(max: Option<i32>, count: i32) -> Option<i32> {
+        max.map(|max| (max - count).max(0))
+    }
+
+    /// Count a download of `share`, failing once `max_downloads` is used up
+    pub async fn record_download(
+        db: &sea_orm::DatabaseConnection,
+        share: &mut share_links::Model,
+    ) -> Result<(), AppError> {
+        let res = ShareLinks::update_many()
+            .col_expr(
+                share_links::Column::DownloadCount,
+                Expr::col(share_links::Column::DownloadCount).add(1),
+            )
+            .filter(share_links::Column::Id.eq(&share.id))
+            .filter(
+                Condition::any()
+                    .add(share_links::Column::MaxDownloads.is_null())
+                    .add(
+                        Expr::col(share_links::Column::DownloadCount)
+                            .lt(Expr::col(share_links::Column::MaxDownloads)),
+                    ),
+            )
+            .exec(db)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(AppError::Gone(
+                "Share link has reached its download limit".to_string(),
+            ));
+        }
+        share.download_count += 1;
+        Ok(())
+    }
+
+    /// Count a view of `share`, failing once `max_views` is used up
+    pub async fn record_view(
+        db: &sea_orm::DatabaseConnection,
+        share: &mut share_links::Model,
+    ) -> Result<(), AppError> {
+        let res = ShareLinks::update_many()
+            .col_expr(
+                share_links::Column::ViewCount,
+                Expr::col(share_links::Column::ViewCount).add(1),
+            )
+            .filter(share_links::Column::Id.eq(&share.id))
+            .filter(
+                Condition::any()
+                    .add(share_links::Column::MaxViews.is_null())
+                    .add(
+                        Expr::col(share_links::Column::ViewCount)
+                            .lt(Expr::col(share_links::Column::MaxViews)),
+                    ),
+            )
+            .exec(db)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(AppError::Gone(
+                "Share link has reached its view limit".to_string(),
+            ));
+        }
+        share.view_count += 1;
+        Ok(())
+    }
+
+    /// Claim one of an upload share's `max_uploads` slots
+    pub async fn reserve_upload(
+        db: &sea_orm::DatabaseConnection,
+        share: &share_links::Model,
+    ) -> Result<(), AppError> {
+        // Conditional increment, so concurrent uploads cannot exceed the limit
+        let res = ShareLinks::update_many()
+            .col_expr(
+                share_links::Column::UploadCount,
+                Expr::col(share_links::Column::UploadCount).add(1),
+            )
+            .filter(share_links::Column::Id.eq(&share.id))
+            .filter(
+                Condition::any()
+                    .add(share_links::Column::MaxUploads.is_null())
+                    .add(
+                        Expr::col(share_links::Column::UploadCount)
+                            .lt(Expr::col(share_links::Column::MaxUploads)),
+                    ),
+            )
+            .exec(db)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(AppError::Forbidden(
+                "This share is no longer accepting uploads".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Give back a slot claimed by `reserve_upload` when the upload failed
+    pub async fn release_upload(db: &sea_orm::DatabaseConnection, share_id: &str) {
+        let res = ShareLinks::update_many()
+            .col_expr(
+                share_links::Column::UploadCount,
+                Expr::col(share_links::Column::UploadCount).sub(1),
+            )
+            .filter(share_links::Column::Id.eq(share_id))
+            .filter(share_links::Column::UploadCount.gt(0))
+            .exec(db)
+            .await;
+        if let Err(e) = res {
+            tracing::error!("Failed to release upload slot of share {}: {}", share_id, e);
+        }
+    }
+
+    /// Check a file against an upload share's `allowed_upload_types`
+    ///
+    /// Entries are extensions ("pdf", ".pdf"), MIME types ("application/pdf")
+    /// or MIME prefixes ("image/*").
+    pub fn check_upload_type(
+        share: &share_links::Model,
+        filename: &str,
+        content_type: Option<&str>,
+    ) -> Result<(), AppError> {
+        let Some(allowed) = share.allowed_upload_types.as_deref() else {
+            return Ok(());
+        };
+        let extension = std::path::Path::new(filename)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase);
+        let content_type = content_type.map(str::to_lowercase);
+
+        let accepted = allowed
+            .split(',')
+            .map(|entry| entry.trim().to_lowercase())
+            .filter(|entry| !entry.is_empty())
+            .any(|entry| match entry.split_once('/') {
+                Some((major, "*")) => content_type
+                    .as_deref()
+                    .and_then(|ct| ct.split_once('/'))
+                    .is_some_and(|(ct_major, _)| ct_major == major),
+                Some(_) => content_type.as_deref() == Some(entry.as_str()),
+                None => extension.as_deref() == Some(entry.trim_start_matches('.')),
+            });
+        if accepted {
+            Ok(())
+        } else {
+            Err(AppError::BadRequest(format!(
+                "File type not accepted by this share (allowed: {})",
+                allowed
+            )))
+        }
+    }
+
+    /// Normalise CIDR ranges for storage; bare addresses become single-host ranges
+    pub fn normalize_networks(field: &str, entries: &[String]) -> Result<String, AppError> {
+        let networks = entries
+            .iter()
+            .map(|entry| {
+                parse_network(entry)
+                    .map(|net| net.trunc().to_string())
+                    .ok_or_else(|| {
+                        AppError::BadRequest(format!("{} has an invalid range: '{}'", field, entry))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if networks.is_empty() {
+            return Err(AppError::BadRequest(format!(
+                "{} must list IP addresses or CIDR ranges",
+                field
+            )));
+        }
+        Ok(networks.join(","))
+    }
+
+    /// Normalise allowed referrers, given as hosts or URLs, to lowercase hosts
+    pub fn normalize_referrers(entries: &[String]) -> Result<String, AppError> {
+        let hosts = entries
+            .iter()
+            .map(|entry| {
+                let entry = entry.trim().to_lowercase();
+                let host = if entry.contains("://") {
+                    url::Url::parse(&entry)
+                        .ok()
+                        .and_then(|url| url.host_str().map(str::to_string))
+                } else {
+                    Some(entry.clone())
+                };
+                host.filter(|h| {
+                    !h.is_empty() && !h.contains(['/', ',', ':', ' ']) && !h.starts_with('.')
+                })
+                .ok_or_else(|| {
+                    AppError::BadRequest(format!(
+                        "allowed_referrers has an invalid host: '{}'",
+                        entry
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if hosts.is_empty() {
+            return Err(AppError::BadRequest(
+                "allowed_referrers must list host names".to_string(),
+            ));
+        }
+        Ok(hosts.join(","))
+    }
+
+    /// Refuse a client outside a share's IP ranges or referrer list
+    ///
+    /// A denied range always wins. When an allow list is set, clients whose
+    /// address is unknown are refused, as are requests without a `Referer`
+    /// when referrers are restricted.
+    pub fn check_restrictions(
+        share: &share_links::Model,
+        client_ip: Option<&str>,
+        referer: Option<&str>,
+    ) -> Result<(), AppError> {
+        let ip = client_ip.and_then(|ip| ip.trim().parse::<std::net::IpAddr>().ok());
+        let in_ranges = |list: &str| {
+            ip.is_some_and(|ip| {
+                list.split(',')
+                    .filter_map(parse_network)
+                    .any(|net| net.contains(&ip))
+            })
+        };
+
+        if share.denied_ips.as_deref().is_some_and(in_ranges) {
+            return Err(AppError::Forbidden(
+                "This share cannot be opened from your network".to_string(),
+            ));
+        }
+        if share
+            .allowed_ips
+            .as_deref()
+            .is_some_and(|list| !in_ranges(list))
+        {
+            return Err(AppError::Forbidden(
+                "This share cannot be opened from your network".to_string(),
+            ));
+        }
+
+        if let Some(allowed) = share.allowed_referrers.as_deref() {
+            let host = referer
+                .and_then(|r| url::Url::parse(r).ok())
+                .and_then(|url| url.host_str().map(str::to_lowercase));
+            let permitted = host.is_some_and(|host| {
+                allowed.split(',').any(|entry| {
+                    host == entry
+                        || host
+                            .strip_suffix(entry)
+                            .is_some_and(|sub| sub.ends_with('.'))
+                })
+            });
+            if !permitted {
+                return Err(AppError::Forbidden(
+                    "This share can only be opened from an approved site".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// `filename`, suffixed with " (n)" if the destination folder already holds an item of that name
+    ///
+    /// Files arriving through a share must never replace (and version) the
+    /// receiving drive's own files.
+    pub async fn available_name(
+        db: &sea_orm::DatabaseConnection,
+        destination: &Destination,
+        filename: &str,
+    ) -> Result<String, AppError> {
+        let path = std::path::Path::new(filename);
+        let stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(filename);
+        let extension = path.extension().and_then(|e| e.to_str());
+
+        let mut attempt = 0;
+        loop {
+            let candidate = match (attempt, extension) {
+                (0, _) => filename.to_string(),
+                (n, Some(ext)) => format!("{} ({}).{}", stem, n, ext),
+                (n, None) => format!("{} ({})", filename, n),
+            };
+            let taken = UserFiles::find()
+                .filter(destination.drive.condition())
+                .filter(StorageLifecycleService::in_folder(&destination.parent_id))
+                .filter(user_files::Column::Filename.eq(&candidate))
+                .filter(user_files::Column::DeletedAt.is_null())
+                .count(db)
+                .await?
+                > 0;
+            if !taken {
+                return Ok(candidate);
+            }
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upload_share(allowed: Option<&str>) -> share_links::Model {
+        share_links::Model {
+            id: "share".to_string(),
+            user_file_id: "folder".to_string(),
+            created_by: "owner".to_string(),
+            share_token: "token".to_string(),
+            share_type: "public".to_string(),
+            shared_with_user_id: None,
+            password_hash: None,
+            permission: "upload".to_string(),
+            expires_at: Utc::now(),
+            created_at: None,
+            max_uploads: None,
+            max_upload_size: None,
+            allowed_upload_types: allowed.map(str::to_string),
+            upload_count: 0,
+            max_downloads: None,
+            download_count: 0,
+            max_views: None,
+            view_count: 0,
+            allowed_ips: None,
+            denied_ips: None,
+            allowed_referrers: None,
+        }
+    }
+
+    #[test]
+    fn test_check_upload_type() {
+        let any = upload_share(None);
+        assert!(ShareService::check_upload_type(&any, "run.exe", None).is_ok());
+
+        let share = upload_share(Some("pdf, .DOCX,image/*,text/csv"));
+        let ok = |name: &str, ct: Option<&str>| {
+            ShareService::check_upload_type(&share, name, ct).is_ok()
+        };
+        assert!(ok("report.PDF", None));
+        assert!(ok("letter.docx", Some("application/octet-stream")));
+        assert!(ok("photo.heic", Some("image/heic")));
+        assert!(ok("data.txt", Some("text/csv")));
+        assert!(!ok("data.txt", Some("text/plain")));
+        assert!(!ok("pdf", None));
+        assert!(!ok("notes.md", Some("imagery/x")));
+    }
+
+    #[test]
+    fn test_normalize_restrictions() {
+        let list = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            ShareService::normalize_networks("allowed_ips", &list(&["10.1.2.3/8", "192.0.2.7"]))
+                .unwrap(),
+            "10.0.0.0/8,192.0.2.7/32"
+        );
+        assert!(ShareService::normalize_networks("allowed_ips", &list(&["intranet"])).is_err());
+        assert!(ShareService::normalize_networks("denied_ips", &[]).is_err());
+
+        assert_eq!(
+            ShareService::normalize_referrers(&list(&[
+                "Intranet.Example.com",
+                "https://wiki.example.org/page"
+            ]))
+            .unwrap(),
+            "intranet.example.com,wiki.example.org"
+        );
+        assert!(ShareService::normalize_referrers(&list(&["example.com/path"])).is_err());
+        assert!(ShareService::normalize_referrers(&list(&[""])).is_err());
+    }
+
+    #[test]
+    fn test_check_restrictions() {
+        let mut share = upload_share(None);
+        share.allowed_ips = Some("10.0.0.0/8,2001:db8::/32".to_string());
+        share.denied_ips = Some("10.9.0.0/16".to_string());
+        let ip_ok = |ip: Option<&str>| ShareService::check_restrictions(&share, ip, None).is_ok();
+        assert!(ip_ok(Some("10.1.2.3")));
+        assert!(ip_ok(Some("2001:db8::5")));
+        assert!(!ip_ok(Some("10.9.1.1")));
+        assert!(!ip_ok(Some("203.0.113.1")));
+        assert!(!ip_ok(None));
+        assert!(!ip_ok(Some("unknown")));
+
+        let mut share = upload_share(None);
+        share.allowed_referrers = Some("example.com".to_string());
+        let referer_ok = |r: Option<&str>| {
+            ShareService::check_restrictions(&share, Some("192.0.2.1"), r).is_ok()
+        };
+        assert!(referer_ok(Some("https://example.com/intranet")));
+        assert!(referer_ok(Some("https://docs.EXAMPLE.com/")));
+        assert!(!referer_ok(Some("https://badexample.com/")));
+        assert!(!referer_ok(Some("https://example.com.evil.net/")));
+        assert!(!referer_ok(None));
+    }
 }
@@ -6,10 +6,14 @@ use aws_sdk_s3::primitives::ByteStream;
 use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
 use std::time::Duration;
 use tokio::io::{AsyncRead, AsyncReadExt};
-use xxhash_rust::xxh3::Xxh3;
+
+use crate::utils::hash::ContentHasher;
 
 pub struct UploadResult {
+    /// xxh3-128 of the content
     pub hash: String,
+    /// SHA-256 of the content
+    pub sha256: String,
     pub size: i64,
     pub s3_key: String,
 }
@@ -122,69 +126,81 @@ impl StorageService for S3StorageService {
         let upload_id = multipart_upload_res
             .upload_id()
             .ok_or_else(|| anyhow::anyhow!("No upload ID"))?;
-        let mut chunk_index = 1;
-        let mut completed_parts = Vec::new();
-        let mut hasher = Xxh3::new();
+        let mut hasher = ContentHasher::new();
         let mut total_size = 0;
 
-        let chunk_size = 7 * 1024 * 1024; // 7MB
-        let mut buffer = vec![0u8; chunk_size];
+        // A reader that fails midway (e.g. a cut-off request body) must not
+        // leave the multipart upload and its parts behind
+        let uploaded: Result<()> = async {
+            let mut chunk_index = 1;
+            let mut completed_parts = Vec::new();
+
+            let chunk_size = 7 * 1024 * 1024; // 7MB
+            let mut buffer = vec![0u8; chunk_size];
+
+            loop {
+                let mut n = 0;
+                while n < chunk_size {
+                    let read = reader.read(&mut buffer[n..]).await?;
+                    if read == 0 {
+                        break;
+                    }
+                    hasher.update(&buffer[n..n + read]);
+                    n += read;
+                }
 
-        loop {
-            let mut n = 0;
-            while n < chunk_size {
-                let read = reader.read(&mut buffer[n..]).await?;
-                if read == 0 {
+                if n == 0 {
                     break;
                 }
-                hasher.update(&buffer[n..n + read]);
-                n += read;
-            }
 
-            if n == 0 {
-                break;
+                total_size += n as i64;
+                let body = ByteStream::from(buffer[..n].to_vec());
+                let upload_part_res = self
+                    .client
+                    .upload_part()
+                    .bucket(&self.bucket)
+                    .key(key)
+                    .upload_id(upload_id)
+                    .body(body)
+                    .part_number(chunk_index)
+                    .send()
+                    .await?;
+
+                completed_parts.push(
+                    CompletedPart::builder()
+                        .e_tag(upload_part_res.e_tag().unwrap_or_default())
+                        .part_number(chunk_index)
+                        .build(),
+                );
+
+                chunk_index += 1;
             }
 
-            total_size += n as i64;
-            let body = ByteStream::from(buffer[..n].to_vec());
-            let upload_part_res = self
-                .client
-                .upload_part()
+            let completed_multipart_upload = CompletedMultipartUpload::builder()
+                .set_parts(Some(completed_parts))
+                .build();
+
+            self.client
+                .complete_multipart_upload()
                 .bucket(&self.bucket)
                 .key(key)
                 .upload_id(upload_id)
-                .body(body)
-                .part_number(chunk_index)
+                .multipart_upload(completed_multipart_upload)
                 .send()
                 .await?;
-
-            completed_parts.push(
-                CompletedPart::builder()
-                    .e_tag(upload_part_res.e_tag().unwrap_or_default())
-                    .part_number(chunk_index)
-                    .build(),
-            );
-
-            chunk_index += 1;
+            Ok(())
+        }
+        .await;
+        if let Err(e) = uploaded {
+            let _ = self.abort_multipart_upload(key, upload_id).await;
+            return Err(e);
         }
 
-        let completed_multipart_upload = CompletedMultipartUpload::builder()
-            .set_parts(Some(completed_parts))
-            .build();
-
-        self.client
-            .complete_multipart_upload()
-            .bucket(&self.bucket)
-            .key(key)
-            .upload_id(upload_id)
-            .multipart_upload(completed_multipart_upload)
-            .send()
-            .await?;
-
-        let hash = format!("{:032x}", hasher.digest128());
+        let hashes = hasher.finalize();
 
         Ok(UploadResult {
-            hash,
+            hash: hashes.xxh3,
+            sha256: hashes.sha256,
             size: total_size,
             s3_key: key.to_string(),
         })
@@ -1,167 +1,461 @@
 use crate::entities::{prelude::*, *};
+use crate::services::drives::Drive;
 use crate::services::storage::StorageService;
 use anyhow::{Result, anyhow};
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter, Set,
-    TransactionTrait,
+    TransactionTrait, sea_query::Expr,
 };
 
+/// Storage objects whose last reference was dropped inside a transaction
+///
+/// Their rows are gone only once that transaction commits, so the objects
+/// are deleted by `delete_objects` afterwards. A rollback simply drops this
+/// and leaves every object in place.
+#[derive(Debug, Default)]
+#[must_use = "released objects are only deleted by `delete_objects`"]
+pub struct ReleasedObjects(Vec<String>);
+
+impl ReleasedObjects {
+    pub fn extend(&mut self, other: ReleasedObjects) {
+        self.0.extend(other.0);
+    }
+
+    /// Delete the objects from storage; call only after the transaction
+    /// that released them has committed
+    ///
+    /// A failure leaves an unreferenced object behind, which is logged
+    /// rather than returned since the database change already stands.
+    pub async fn delete_objects(self, storage: &dyn StorageService) {
+        for s3_key in self.0 {
+            match storage.delete_file(&s3_key).await {
+                Ok(()) => tracing::info!("Deleted released object {}", s3_key),
+                Err(e) => tracing::error!("Failed to delete released object {}: {}", s3_key, e),
+            }
+        }
+    }
+}
+
 /// Service for managing storage file lifecycle and reference counting
 pub struct StorageLifecycleService;
 
 impl StorageLifecycleService {
-    /// Decrement ref_count for a storage file and delete from S3 if count reaches 0
+    /// Decrement ref_count for a storage file, deleting its row once no reference is left
     ///
-    /// Returns true if the storage file was deleted from both DB and S3
+    /// The object itself is returned for deletion after the caller's
+    /// transaction commits.
     pub async fn decrement_ref_count(
         db: &impl sea_orm::ConnectionTrait,
-        storage: &dyn StorageService,
         storage_file_id: &str,
-    ) -> Result<bool> {
-        let storage_file = StorageFiles::find_by_id(storage_file_id)
+    ) -> Result<ReleasedObjects> {
+        let res = StorageFiles::update_many()
+            .col_expr(
+                storage_files::Column::RefCount,
+                Expr::col(storage_files::Column::RefCount).sub(1),
+            )
+            .filter(storage_files::Column::Id.eq(storage_file_id))
+            .exec(db)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(anyhow!("Storage file not found: {}", storage_file_id));
+        }
+
+        let Some(storage_file) = StorageFiles::find_by_id(storage_file_id)
+            .filter(storage_files::Column::RefCount.lte(0))
             .one(db)
             .await?
-            .ok_or_else(|| anyhow!("Storage file not found: {}", storage_file_id))?;
+        else {
+            tracing::debug!("storage_file {} is still referenced", storage_file_id);
+            return Ok(ReleasedObjects::default());
+        };
 
-        let new_count = storage_file.ref_count - 1;
         tracing::info!(
-            "Decrementing ref_count for storage_file {} from {} to {}",
+            "ref_count of storage_file {} reached 0, releasing {}",
             storage_file_id,
-            storage_file.ref_count,
-            new_count
+            storage_file.s3_key
         );
+        storage_file.clone().delete(db).await?;
+        Ok(ReleasedObjects(vec![storage_file.s3_key]))
+    }
 
-        let mut active: storage_files::ActiveModel = storage_file.clone().into();
-        active.ref_count = Set(new_count);
-        let updated = active.update(db).await?;
+    /// Move a user_file to the trash, along with everything under it if it is a folder
+    ///
+    /// Every affected row gets `deleted_at` and a `trash_root_id` pointing at
+    /// `user_file`. Storage ref counts are left untouched until the item is purged.
+    pub async fn trash_user_file(
+        db: &impl sea_orm::ConnectionTrait,
+        user_file: &user_files::Model,
+    ) -> Result<()> {
+        tracing::info!("Moving user_file to trash: {}", user_file.id);
 
-        let deleted = if new_count <= 0 {
-            tracing::info!("ref_count reached 0, deleting from S3: {}", updated.s3_key);
+        let original_path = Self::folder_path(db, user_file.parent_id.clone()).await?;
+
+        // Collect live descendants level by level
+        let mut ids = vec![user_file.id.clone()];
+        let mut frontier = if user_file.is_folder {
+            vec![user_file.id.clone()]
+        } else {
+            Vec::new()
+        };
+        while !frontier.is_empty() {
+            let children = UserFiles::find()
+                .filter(user_files::Column::ParentId.is_in(frontier))
+                .filter(user_files::Column::DeletedAt.is_null())
+                .all(db)
+                .await?;
+            frontier = children
+                .iter()
+                .filter(|c| c.is_folder)
+                .map(|c| c.id.clone())
+                .collect();
+            ids.extend(children.into_iter().map(|c| c.id));
+        }
+        tracing::debug!("Trashing {} rows under {}", ids.len(), user_file.id);
 
-            // Delete from S3
-            storage.delete_file(&updated.s3_key).await?;
+        UserFiles::update_many()
+            .col_expr(
+                user_files::Column::DeletedAt,
+                Expr::value(chrono::Utc::now()),
+            )
+            .col_expr(
+                user_files::Column::TrashRootId,
+                Expr::value(user_file.id.clone()),
+            )
+            .col_expr(user_files::Column::IsFavorite, Expr::value(false)) // remove favorite status on delete
+            .filter(user_files::Column::Id.is_in(ids.clone()))
+            .exec(db)
+            .await?;
 
-            // Delete from database
-            updated.delete(db).await?;
+        let mut active: user_files::ActiveModel = user_file.clone().into();
+        active.original_path = Set(original_path);
+        active.update(db).await?;
 
-            tracing::info!(
-                "Successfully deleted storage_file {} from S3 and DB",
-                storage_file_id
-            );
-            true
+        // Delete associated share links
+        crate::entities::share_links::Entity::delete_many()
+            .filter(crate::entities::share_links::Column::UserFileId.is_in(ids))
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Restore a trashed item together with everything trashed alongside it
+    ///
+    /// The item goes back under its original parent. If that folder is no longer
+    /// live, the folders along `original_path` are looked up or recreated in the
+    /// item's drive. A name clash in the target folder is resolved by renaming
+    /// the restored item.
+    pub async fn restore_user_file(
+        db: &impl sea_orm::ConnectionTrait,
+        root: &user_files::Model,
+    ) -> Result<user_files::Model> {
+        tracing::info!("Restoring user_file from trash: {}", root.id);
+
+        let drive = Drive::of(root);
+        let parent_is_live = match &root.parent_id {
+            Some(parent_id) => UserFiles::find_by_id(parent_id)
+                .filter(drive.condition())
+                .filter(user_files::Column::IsFolder.eq(true))
+                .filter(user_files::Column::DeletedAt.is_null())
+                .one(db)
+                .await?
+                .is_some(),
+            None => true,
+        };
+        let parent_id = if parent_is_live {
+            root.parent_id.clone()
         } else {
-            tracing::debug!(
-                "ref_count is {}, keeping storage_file {} in S3",
-                new_count,
-                storage_file_id
-            );
-            false
+            Self::ensure_folder_path(db, root, root.original_path.as_deref()).await?
         };
 
-        Ok(deleted)
+        let filename = Self::available_name(db, root, parent_id.clone()).await?;
+
+        UserFiles::update_many()
+            .col_expr(
+                user_files::Column::DeletedAt,
+                Expr::value(Option::<chrono::DateTime<chrono::Utc>>::None),
+            )
+            .col_expr(
+                user_files::Column::TrashRootId,
+                Expr::value(Option::<String>::None),
+            )
+            .filter(user_files::Column::TrashRootId.eq(&root.id))
+            .exec(db)
+            .await?;
+
+        let mut active: user_files::ActiveModel = root.clone().into();
+        active.deleted_at = Set(None);
+        active.trash_root_id = Set(None);
+        active.original_path = Set(None);
+        active.parent_id = Set(parent_id);
+        active.filename = Set(filename);
+        // Items that expired into the trash would otherwise be trashed again right away
+        if root.expires_at.is_some_and(|e| e < chrono::Utc::now()) {
+            active.expires_at = Set(None);
+        }
+        Ok(active.update(db).await?)
     }
 
-    /// Recursively delete a folder and all its children, managing ref counts
+    /// Permanently delete a trashed item and everything trashed alongside it
     ///
-    /// This performs soft delete on user_files (sets deleted_at) and decrements
-    /// ref_count on storage_files, triggering S3 cleanup when ref_count reaches 0
-    #[async_recursion::async_recursion]
-    pub async fn delete_folder_recursive(
+    /// This is where storage ref counts are released. Returns the number of rows removed.
+    pub async fn purge_trashed(
         db: &impl sea_orm::ConnectionTrait,
-        storage: &dyn StorageService,
-        folder_id: &str,
-    ) -> Result<()> {
-        tracing::info!("Recursively deleting folder: {}", folder_id);
+        root: &user_files::Model,
+        released: &mut ReleasedObjects,
+    ) -> Result<usize> {
+        tracing::info!("Purging trashed user_file: {}", root.id);
 
-        // Find all children of this folder
-        let children = UserFiles::find()
-            .filter(user_files::Column::ParentId.eq(folder_id))
-            .filter(user_files::Column::DeletedAt.is_null()) // Only non-deleted items
+        let rows = UserFiles::find()
+            .filter(user_files::Column::TrashRootId.eq(&root.id))
             .all(db)
             .await?;
+        Self::purge_rows(db, rows, released).await
+    }
 
-        tracing::debug!("Found {} children in folder {}", children.len(), folder_id);
+    /// Permanently delete every file and folder in a user's personal drive, live or trashed
+    ///
+    /// Used when an account is deleted. Returns the number of rows removed.
+    pub async fn purge_user_files(
+        db: &impl sea_orm::ConnectionTrait,
+        user_id: &str,
+        released: &mut ReleasedObjects,
+    ) -> Result<usize> {
+        tracing::info!("Purging all files of user: {}", user_id);
 
-        for child in children {
-            if child.is_folder {
-                // Recursively delete subfolders
-                Self::delete_folder_recursive(db, storage, &child.id).await?;
-            }
+        let rows = UserFiles::find()
+            .filter(Drive::Personal(user_id.to_string()).condition())
+            .all(db)
+            .await?;
+        Self::purge_rows(db, rows, released).await
+    }
 
-            // Soft delete the child
-            Self::soft_delete_user_file(db, storage, &child).await?;
-        }
+    /// Permanently delete every file and folder in a team drive, live or trashed
+    ///
+    /// Used when a team is deleted. Returns the number of rows removed.
+    pub async fn purge_team_files(
+        db: &impl sea_orm::ConnectionTrait,
+        team_id: &str,
+        released: &mut ReleasedObjects,
+    ) -> Result<usize> {
+        tracing::info!("Purging all files of team: {}", team_id);
 
-        tracing::info!("Completed recursive deletion of folder {}", folder_id);
-        Ok(())
+        let rows = UserFiles::find()
+            .filter(Drive::Team(team_id.to_string()).condition())
+            .all(db)
+            .await?;
+        Self::purge_rows(db, rows, released).await
     }
 
-    /// Soft delete a user_file and decrement storage ref_count
-    pub async fn soft_delete_user_file(
+    /// Release the storage, versions and tags of `rows`, then delete them
+    async fn purge_rows(
         db: &impl sea_orm::ConnectionTrait,
-        storage: &dyn StorageService,
-        user_file: &user_files::Model,
-    ) -> Result<()> {
-        tracing::info!("Soft deleting user_file: {}", user_file.id);
-
-        // Set deleted_at timestamp
-        let mut active: user_files::ActiveModel = user_file.clone().into();
-        active.deleted_at = Set(Some(chrono::Utc::now()));
-        active.is_favorite = Set(false); // remove favorite status on delete
-        active.update(db).await?;
+        rows: Vec<user_files::Model>,
+        released: &mut ReleasedObjects,
+    ) -> Result<usize> {
+        // A failure must abort the purge, so the caller rolls back instead
+        // of dropping rows that still hold a reference
+        for row in &rows {
+            if let Some(ref storage_file_id) = row.storage_file_id {
+                released.extend(
+                    Self::decrement_ref_count(db, storage_file_id)
+                        .await
+                        .map_err(|e| anyhow!("Failed to release storage for {}: {}", row.id, e))?,
+                );
+            }
+        }
 
-        // Delete associated share links
-        crate::entities::share_links::Entity::delete_many()
-            .filter(crate::entities::share_links::Column::UserFileId.eq(&user_file.id))
+        let ids: Vec<String> = rows.iter().map(|r| r.id.clone()).collect();
+        crate::services::file_versions::FileVersionService::release_all(db, ids.clone(), released)
+            .await?;
+        FileTags::delete_many()
+            .filter(file_tags::Column::UserFileId.is_in(ids.clone()))
             .exec(db)
             .await?;
+        UserFiles::delete_many()
+            .filter(user_files::Column::Id.is_in(ids))
+            .exec(db)
+            .await?;
+
+        Ok(rows.len())
+    }
+
+    /// Purge trashed items older than `retention_days`
+    ///
+    /// Returns the number of trashed items purged
+    pub async fn purge_expired_trash(
+        db: &DatabaseConnection,
+        storage: &dyn StorageService,
+        retention_days: u64,
+    ) -> Result<usize> {
+        let cutoff = chrono::Utc::now() - chrono::Duration::days(retention_days as i64);
+        let roots = UserFiles::find()
+            .filter(user_files::Column::DeletedAt.lt(cutoff))
+            .filter(user_files::Column::TrashRootId.is_not_null())
+            .all(db)
+            .await?
+            .into_iter()
+            .filter(|f| f.trash_root_id.as_deref() == Some(f.id.as_str()));
 
-        // Decrement ref_count if this file has storage
-        if let Some(ref storage_file_id) = user_file.storage_file_id {
-            Self::decrement_ref_count(db, storage, storage_file_id).await?;
+        let mut purged = 0;
+        for root in roots {
+            let txn = db.begin().await?;
+            let mut released = ReleasedObjects::default();
+            match Self::purge_trashed(&txn, &root, &mut released).await {
+                Ok(_) => {
+                    txn.commit().await?;
+                    released.delete_objects(storage).await;
+                    purged += 1;
+                }
+                Err(e) => {
+                    tracing::error!("Failed to purge trashed item {}: {}", root.id, e);
+                    let _ = txn.rollback().await;
+                }
+            }
         }
 
-        Ok(())
+        Ok(purged)
     }
 
-    /// Bulk delete multiple files/folders
+    /// Filter on `parent_id`, matching root-level items when it is `None`
+    pub(crate) fn in_folder(parent_id: &Option<String>) -> sea_orm::sea_query::SimpleExpr {
+        match parent_id {
+            Some(id) => user_files::Column::ParentId.eq(id.clone()),
+            None => user_files::Column::ParentId.is_null(),
+        }
+    }
+
+    /// Folder names from the root down to `folder_id`, joined by `/`
+    async fn folder_path(
+        db: &impl sea_orm::ConnectionTrait,
+        mut folder_id: Option<String>,
+    ) -> Result<Option<String>> {
+        let mut names = Vec::new();
+        while let Some(id) = folder_id {
+            let Some(folder) = UserFiles::find_by_id(&id).one(db).await? else {
+                break;
+            };
+            names.push(folder.filename);
+            folder_id = folder.parent_id;
+        }
+        if names.is_empty() {
+            return Ok(None);
+        }
+        names.reverse();
+        Ok(Some(names.join("/")))
+    }
+
+    /// Find or create the live folders along `path` in `item`'s drive, returning the innermost one
+    async fn ensure_folder_path(
+        db: &impl sea_orm::ConnectionTrait,
+        item: &user_files::Model,
+        path: Option<&str>,
+    ) -> Result<Option<String>> {
+        let drive = Drive::of(item);
+        let mut parent_id: Option<String> = None;
+        for name in path
+            .unwrap_or_default()
+            .split('/')
+            .filter(|n| !n.is_empty())
+        {
+            let existing = UserFiles::find()
+                .filter(drive.condition())
+                .filter(Self::in_folder(&parent_id))
+                .filter(user_files::Column::Filename.eq(name))
+                .filter(user_files::Column::IsFolder.eq(true))
+                .filter(user_files::Column::DeletedAt.is_null())
+                .one(db)
+                .await?;
+            parent_id = Some(match existing {
+                Some(folder) => folder.id,
+                None => {
+                    let id = uuid::Uuid::new_v4().to_string();
+                    user_files::ActiveModel {
+                        id: Set(id.clone()),
+                        user_id: Set(item.user_id.clone()),
+                        team_id: Set(item.team_id.clone()),
+                        storage_file_id: Set(None),
+                        filename: Set(name.to_string()),
+                        is_folder: Set(true),
+                        parent_id: Set(parent_id.clone()),
+                        created_at: Set(Some(chrono::Utc::now())),
+                        is_favorite: Set(false),
+                        ..Default::default()
+                    }
+                    .insert(db)
+                    .await?;
+                    id
+                }
+            });
+        }
+        Ok(parent_id)
+    }
+
+    /// `item`'s filename, suffixed with "(restored)" if it clashes with a live item in `parent_id`
+    async fn available_name(
+        db: &impl sea_orm::ConnectionTrait,
+        item: &user_files::Model,
+        parent_id: Option<String>,
+    ) -> Result<String> {
+        let path = std::path::Path::new(&item.filename);
+        let stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(&item.filename);
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .filter(|_| !item.is_folder);
+
+        let mut attempt = 0;
+        loop {
+            let candidate = match (attempt, extension) {
+                (0, _) => item.filename.clone(),
+                (1, Some(ext)) => format!("{} (restored).{}", stem, ext),
+                (1, None) => format!("{} (restored)", item.filename),
+                (n, Some(ext)) => format!("{} (restored {}).{}", stem, n, ext),
+                (n, None) => format!("{} (restored {})", item.filename, n),
+            };
+            let taken = UserFiles::find()
+                .filter(Drive::of(item).condition())
+                .filter(Self::in_folder(&parent_id))
+                .filter(user_files::Column::Filename.eq(&candidate))
+                .filter(user_files::Column::DeletedAt.is_null())
+                .one(db)
+                .await?
+                .is_some();
+            if !taken {
+                return Ok(candidate);
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Move multiple files/folders to the trash in one transaction
     ///
+    /// Callers must already have checked write access to every item.
     /// Returns the number of items deleted
     pub async fn bulk_delete(
         db: &DatabaseConnection,
-        storage: &dyn StorageService,
-        user_id: &str,
-        item_ids: Vec<String>,
+        items: Vec<user_files::Model>,
     ) -> Result<usize> {
-        tracing::info!(
-            "Bulk deleting {} items for user {}",
-            item_ids.len(),
-            user_id
-        );
+        tracing::info!("Bulk deleting {} items", items.len());
 
         let txn = db.begin().await?;
         let mut deleted_count = 0;
 
-        for item_id in item_ids {
-            // Verify ownership
-            let item = UserFiles::find_by_id(&item_id)
-                .filter(user_files::Column::UserId.eq(user_id))
+        for item in items {
+            // Skip items trashed along with a folder earlier in the batch
+            let still_live = UserFiles::find_by_id(&item.id)
                 .filter(user_files::Column::DeletedAt.is_null())
                 .one(&txn)
                 .await?;
 
-            if let Some(item) = item {
-                if item.is_folder {
-                    Self::delete_folder_recursive(&txn, storage, &item.id).await?;
-                }
-                Self::soft_delete_user_file(&txn, storage, &item).await?;
+            if let Some(item) = still_live {
+                Self::trash_user_file(&txn, &item).await?;
                 deleted_count += 1;
             } else {
-                tracing::warn!(
-                    "Item {} not found or already deleted for user {}",
-                    item_id,
-                    user_id
-                );
+                tracing::warn!("Item {} already deleted", item.id);
             }
         }
 
@@ -169,6 +463,7 @@ impl StorageLifecycleService {
         tracing::info!("Bulk deleted {} items", deleted_count);
         Ok(deleted_count)
     }
+
     /// Hard delete a storage file and all its associated user files
     pub async fn delete_storage_file(
         db: &DatabaseConnection,
@@ -187,7 +482,13 @@ impl StorageLifecycleService {
             .exec(db)
             .await?;
 
-        // 3. Delete the storage file record
+        // 3. Drop versions that point at it
+        FileVersions::delete_many()
+            .filter(file_versions::Column::StorageFileId.eq(&storage_file.id))
+            .exec(db)
+            .await?;
+
+        // 4. Delete the storage file record
         storage_file.clone().delete(db).await?;
 
         Ok(())
@@ -0,0 +1,461 @@
+use crate::api::error::AppError;
+use crate::entities::{prelude::*, *};
+use crate::services::drives::{DriveAccess, Permission};
+use crate::services::storage::StorageService;
+use crate::services::storage_lifecycle::{ReleasedObjects, StorageLifecycleService};
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    IntoActiveModel, QueryFilter, QueryOrder, QuerySelect, QueryTrait, Set, TransactionTrait,
+    sea_query::Expr,
+};
+use serde::{Deserialize, Serialize};
+
+/// Longest team name accepted
+pub const MAX_TEAM_NAME_LEN: usize = 100;
+
+/// What a member may do in a team, stored in `team_members.role`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TeamRole {
+    /// Manages the team and its members, and edits files
+    Owner,
+    /// Adds, changes and removes team files
+    Editor,
+    /// Reads team files only
+    Viewer,
+}
+
+impl TeamRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TeamRole::Owner => "owner",
+            TeamRole::Editor => "editor",
+            TeamRole::Viewer => "viewer",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "owner" => Some(TeamRole::Owner),
+            "editor" => Some(TeamRole::Editor),
+            "viewer" => Some(TeamRole::Viewer),
+            _ => None,
+        }
+    }
+
+    pub fn allows(&self, permission: Permission) -> bool {
+        match permission {
+            Permission::Read => true,
+            Permission::Write => matches!(self, TeamRole::Owner | TeamRole::Editor),
+        }
+    }
+}
+
+/// A team as seen by one of its members
+#[derive(Debug, Clone)]
+pub struct Membership {
+    pub team: teams::Model,
+    pub role: TeamRole,
+}
+
+/// Teams, their members and their shared drives
+///
+/// Only owners manage a team. Every team keeps at least one owner: the last
+/// one can neither leave nor be demoted, only delete the team.
+pub struct TeamService;
+
+impl TeamService {
+    /// Create a team with `user_id` as its owner
+    pub async fn create(
+        db: &DatabaseConnection,
+        user_id: &str,
+        name: &str,
+    ) -> Result<teams::Model, AppError> {
+        let name = normalize_name(name)?;
+        let now = Utc::now();
+        let txn = db.begin().await?;
+        let team = teams::ActiveModel {
+            id: Set(uuid::Uuid::new_v4().to_string()),
+            name: Set(name),
+            storage_quota: Set(None),
+            created_by: Set(Some(user_id.to_string())),
+            created_at: Set(now),
+        }
+        .insert(&txn)
+        .await?;
+        team_members::ActiveModel {
+            team_id: Set(team.id.clone()),
+            user_id: Set(user_id.to_string()),
+            role: Set(TeamRole::Owner.as_str().to_string()),
+            created_at: Set(now),
+        }
+        .insert(&txn)
+        .await?;
+        txn.commit().await?;
+        Ok(team)
+    }
+
+    /// Teams `user_id` belongs to, by name
+    pub async fn list_for(
+        db: &DatabaseConnection,
+        user_id: &str,
+    ) -> Result<Vec<Membership>, AppError> {
+        let rows = TeamMembers::find()
+            .filter(team_members::Column::UserId.eq(user_id))
+            .find_also_related(Teams)
+            .order_by_asc(teams::Column::Name)
+            .all(db)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(member, team)| {
+                Some(Membership {
+                    team: team?,
+                    role: TeamRole::parse(&member.role)?,
+                })
+            })
+            .collect())
+    }
+
+    /// `team_id` as seen by `user_id`; 404 unless they are a member
+    pub async fn get(
+        db: &impl ConnectionTrait,
+        user_id: &str,
+        team_id: &str,
+    ) -> Result<Membership, AppError> {
+        let not_found = || AppError::NotFound("Team not found".to_string());
+        let role = DriveAccess::role(db, user_id, team_id)
+            .await?
+            .ok_or_else(not_found)?;
+        let team = Teams::find_by_id(team_id)
+            .one(db)
+            .await?
+            .ok_or_else(not_found)?;
+        Ok(Membership { team, role })
+    }
+
+    /// Members of `team_id` with their accounts, oldest first
+    pub async fn members(
+        db: &DatabaseConnection,
+        team_id: &str,
+    ) -> Result<Vec<(team_members::Model, users::Model)>, AppError> {
+        let rows = TeamMembers::find()
+            .filter(team_members::Column::TeamId.eq(team_id))
+            .find_also_related(Users)
+            .order_by_asc(team_members::Column::CreatedAt)
+            .all(db)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(member, user)| Some((member, user?)))
+            .collect())
+    }
+
+    pub async fn rename(
+        db: &DatabaseConnection,
+        acting_user: &str,
+        team_id: &str,
+        name: &str,
+    ) -> Result<teams::Model, AppError> {
+        let membership = Self::get_as_owner(db, acting_user, team_id).await?;
+        let mut active = membership.team.into_active_model();
+        active.name = Set(normalize_name(name)?);
+        Ok(active.update(db).await?)
+    }
+
+    /// Delete a team and permanently delete its drive, trash included
+    ///
+    /// Returns the number of file rows removed.
+    pub async fn delete(
+        db: &DatabaseConnection,
+        storage: &dyn StorageService,
+        acting_user: &str,
+        team_id: &str,
+    ) -> Result<usize, AppError> {
+        Self::get_as_owner(db, acting_user, team_id).await?;
+        let txn = db.begin().await?;
+        let mut released = ReleasedObjects::default();
+        let purged = Self::purge(&txn, team_id, &mut released).await?;
+        txn.commit().await?;
+        released.delete_objects(storage).await;
+        Ok(purged)
+    }
+
+    /// Add the user called `username` to the team
+    pub async fn add_member(
+        db: &DatabaseConnection,
+        acting_user: &str,
+        team_id: &str,
+        username: &str,
+        role: TeamRole,
+    ) -> Result<(team_members::Model, users::Model), AppError> {
+        Self::get_as_owner(db, acting_user, team_id).await?;
+        let user = Users::find()
+            .filter(users::Column::Username.eq(username.trim()))
+            .one(db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        if TeamMembers::find_by_id((team_id.to_string(), user.id.clone()))
+            .one(db)
+            .await?
+            .is_some()
+        {
+            return Err(AppError::Conflict(format!(
+                "{} is already a member of this team",
+                user.username
+            )));
+        }
+        let member = team_members::ActiveModel {
+            team_id: Set(team_id.to_string()),
+            user_id: Set(user.id.clone()),
+            role: Set(role.as_str().to_string()),
+            created_at: Set(Utc::now()),
+        }
+        .insert(db)
+        .await?;
+        Ok((member, user))
+    }
+
+    pub async fn set_role(
+        db: &DatabaseConnection,
+        acting_user: &str,
+        team_id: &str,
+        member_id: &str,
+        role: TeamRole,
+    ) -> Result<team_members::Model, AppError> {
+        Self::get_as_owner(db, acting_user, team_id).await?;
+        let txn = db.begin().await?;
+        let member = Self::find_member(&txn, team_id, member_id).await?;
+        if role != TeamRole::Owner {
+            Self::ensure_other_owner(&txn, &member).await?;
+        }
+        let mut active = member.into_active_model();
+        active.role = Set(role.as_str().to_string());
+        let member = active.update(&txn).await?;
+        txn.commit().await?;
+        Ok(member)
+    }
+
+    /// Remove `member_id` from the team; owners remove anyone, members only themselves
+    ///
+    /// Files the member added stay in the team drive and pass to the
+    /// longest-standing remaining owner; share links they made for them are
+    /// revoked.
+    pub async fn remove_member(
+        db: &DatabaseConnection,
+        acting_user: &str,
+        team_id: &str,
+        member_id: &str,
+    ) -> Result<(), AppError> {
+        let membership = Self::get(db, acting_user, team_id).await?;
+        if member_id != acting_user && membership.role != TeamRole::Owner {
+            return Err(AppError::Forbidden(
+                "Only team owners can remove other members".to_string(),
+            ));
+        }
+        let txn = db.begin().await?;
+        let member = Self::find_member(&txn, team_id, member_id).await?;
+        Self::ensure_other_owner(&txn, &member).await?;
+        let team_items = UserFiles::find()
+            .select_only()
+            .column(user_files::Column::Id)
+            .filter(user_files::Column::TeamId.eq(&member.team_id))
+            .into_query();
+        ShareLinks::delete_many()
+            .filter(share_links::Column::CreatedBy.eq(&member.user_id))
+            .filter(share_links::Column::UserFileId.in_subquery(team_items))
+            .exec(&txn)
+            .await?;
+        // Every team keeps an owner, and `ensure_other_owner` made sure it
+        // is not this member
+        let heir = TeamMembers::find()
+            .filter(team_members::Column::TeamId.eq(&member.team_id))
+            .filter(team_members::Column::UserId.ne(&member.user_id))
+            .filter(team_members::Column::Role.eq(TeamRole::Owner.as_str()))
+            .order_by_asc(team_members::Column::CreatedAt)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| AppError::Internal("Team has no owner".to_string()))?;
+        Self::hand_over_files(&txn, &member.team_id, &member.user_id, &heir.user_id).await?;
+        TeamMembers::delete_by_id((member.team_id, member.user_id))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Set the quota of a team drive in bytes; `None` restores the default
+    pub async fn set_quota(
+        db: &DatabaseConnection,
+        team_id: &str,
+        quota: Option<i64>,
+    ) -> Result<teams::Model, AppError> {
+        if quota.is_some_and(|q| q < 0) {
+            return Err(AppError::BadRequest(
+                "storage_quota must not be negative".to_string(),
+            ));
+        }
+        let team = Teams::find_by_id(team_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Team not found".to_string()))?;
+        let mut active = team.into_active_model();
+        active.storage_quota = Set(quota);
+        Ok(active.update(db).await?)
+    }
+
+    /// Hand the teams of a user being deleted over to the remaining members
+    ///
+    /// Team files they added are reassigned to a remaining owner, promoting
+    /// the longest-standing member if they were the last one. Teams they were
+    /// the only member of are deleted with their drive, and the objects that
+    /// frees are added to `released`.
+    pub async fn release_user(
+        db: &impl ConnectionTrait,
+        user_id: &str,
+        released: &mut ReleasedObjects,
+    ) -> Result<usize, AppError> {
+        let memberships = TeamMembers::find()
+            .filter(team_members::Column::UserId.eq(user_id))
+            .all(db)
+            .await?;
+
+        let mut purged = 0;
+        for membership in memberships {
+            let others = TeamMembers::find()
+                .filter(team_members::Column::TeamId.eq(&membership.team_id))
+                .filter(team_members::Column::UserId.ne(user_id))
+                .order_by_asc(team_members::Column::CreatedAt)
+                .all(db)
+                .await?;
+            let heir = match others
+                .iter()
+                .find(|m| m.role == TeamRole::Owner.as_str())
+                .or(others.first())
+            {
+                Some(heir) => heir.clone(),
+                None => {
+                    purged += Self::purge(db, &membership.team_id, released).await?;
+                    continue;
+                }
+            };
+            if heir.role != TeamRole::Owner.as_str() {
+                let mut active = heir.clone().into_active_model();
+                active.role = Set(TeamRole::Owner.as_str().to_string());
+                active.update(db).await?;
+            }
+            Self::hand_over_files(db, &membership.team_id, user_id, &heir.user_id).await?;
+        }
+        Ok(purged)
+    }
+
+    /// Make `to_user` the owner of the files `from_user` added to the team drive,
+    /// so they outlive `from_user`'s account
+    async fn hand_over_files(
+        db: &impl ConnectionTrait,
+        team_id: &str,
+        from_user: &str,
+        to_user: &str,
+    ) -> Result<(), AppError> {
+        UserFiles::update_many()
+            .col_expr(user_files::Column::UserId, Expr::value(to_user))
+            .filter(user_files::Column::TeamId.eq(team_id))
+            .filter(user_files::Column::UserId.eq(from_user))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Release every file of the team drive, then delete the team
+    async fn purge(
+        db: &impl ConnectionTrait,
+        team_id: &str,
+        released: &mut ReleasedObjects,
+    ) -> Result<usize, AppError> {
+        let purged = StorageLifecycleService::purge_team_files(db, team_id, released)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to delete team files: {}", e)))?;
+        Teams::delete_by_id(team_id).exec(db).await?;
+        Ok(purged)
+    }
+
+    async fn get_as_owner(
+        db: &impl ConnectionTrait,
+        user_id: &str,
+        team_id: &str,
+    ) -> Result<Membership, AppError> {
+        let membership = Self::get(db, user_id, team_id).await?;
+        if membership.role != TeamRole::Owner {
+            return Err(AppError::Forbidden(
+                "Only team owners can manage the team".to_string(),
+            ));
+        }
+        Ok(membership)
+    }
+
+    async fn find_member(
+        db: &impl ConnectionTrait,
+        team_id: &str,
+        user_id: &str,
+    ) -> Result<team_members::Model, AppError> {
+        TeamMembers::find_by_id((team_id.to_string(), user_id.to_string()))
+            .one(db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Team member not found".to_string()))
+    }
+
+    /// Refuse to take the owner role away from `member` if no other owner is left
+    async fn ensure_other_owner(
+        db: &impl ConnectionTrait,
+        member: &team_members::Model,
+    ) -> Result<(), AppError> {
+        if member.role != TeamRole::Owner.as_str() {
+            return Ok(());
+        }
+        let other_owner = TeamMembers::find()
+            .filter(team_members::Column::TeamId.eq(&member.team_id))
+            .filter(team_members::Column::UserId.ne(&member.user_id))
+            .filter(team_members::Column::Role.eq(TeamRole::Owner.as_str()))
+            .one(db)
+            .await?;
+        if other_owner.is_none() {
+            return Err(AppError::BadRequest(
+                "A team needs at least one owner; promote another member or delete the team"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn normalize_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_TEAM_NAME_LEN {
+        return Err(AppError::BadRequest(format!(
+            "Team name must be 1 to {} characters",
+            MAX_TEAM_NAME_LEN
+        )));
+    }
+    Ok(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_team_role_permissions() {
+        for role in [TeamRole::Owner, TeamRole::Editor, TeamRole::Viewer] {
+            assert_eq!(TeamRole::parse(role.as_str()), Some(role));
+            assert!(role.allows(Permission::Read));
+        }
+        assert!(TeamRole::Editor.allows(Permission::Write));
+        assert!(!TeamRole::Viewer.allows(Permission::Write));
+        assert_eq!(TeamRole::parse("admin"), None);
+
+        assert_eq!(normalize_name("  Finance ").unwrap(), "Finance");
+        assert!(normalize_name("   ").is_err());
+        assert!(normalize_name(&"x".repeat(MAX_TEAM_NAME_LEN + 1)).is_err());
+    }
+}
@@ -0,0 +1,416 @@
+use crate::api::error::AppError;
+use crate::entities::{prelude::*, *};
+use crate::utils::hash::calculate_hash;
+use crate::utils::totp;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, IntoActiveModel,
+    PaginatorTrait, QueryFilter, Set, TransactionTrait, sea_query::Expr,
+};
+use uuid::Uuid;
+
+/// Issuer shown next to the account in authenticator apps
+pub const TOTP_ISSUER: &str = "Rust File Backend";
+/// Recovery codes handed out when two-factor authentication is enabled
+pub const RECOVERY_CODE_COUNT: usize = 10;
+/// How long a password login may wait for its second factor
+const CHALLENGE_TTL_MINUTES: i64 = 5;
+/// Codes a challenge accepts before the login must start over
+const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+/// Codes a user may try across challenges before logins are locked out
+const MAX_USER_ATTEMPTS: i32 = 10;
+/// How long that lockout lasts
+const LOCKOUT_MINUTES: i64 = 15;
+/// Recovery codes avoid characters that are easily confused
+const RECOVERY_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+/// Which second factor completed a login
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecondFactor {
+    Totp,
+    RecoveryCode,
+}
+
+impl SecondFactor {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecondFactor::Totp => "totp",
+            SecondFactor::RecoveryCode => "recovery_code",
+        }
+    }
+}
+
+/// TOTP two-factor authentication, recovery codes and login challenges
+///
+/// A user is enrolled once `totp_enabled_at` is set. Until then a stored
+/// secret is only pending confirmation and logins need just the password.
+pub struct TwoFactorService;
+
+impl TwoFactorService {
+    pub fn is_enabled(user: &users::Model) -> bool {
+        user.totp_enabled_at.is_some() && user.totp_secret.is_some()
+    }
+
+    /// Store a fresh secret awaiting confirmation, returning it and its
+    /// `otpauth://` URI
+    pub async fn enroll(
+        db: &DatabaseConnection,
+        user: users::Model,
+    ) -> Result<(String, String), AppError> {
+        if Self::is_enabled(&user) {
+            return Err(AppError::Conflict(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+        let secret = totp::generate_secret();
+        let uri = totp::otpauth_uri(TOTP_ISSUER, &user.username, &secret);
+        let mut active = user.into_active_model();
+        active.totp_secret = Set(Some(secret.clone()));
+        active.totp_enabled_at = Set(None);
+        active.totp_last_step = Set(None);
+        active.update(db).await?;
+        Ok((secret, uri))
+    }
+
+    /// Turn on two-factor authentication once `code` proves the
+    /// authenticator app has the pending secret, returning recovery codes
+    pub async fn confirm(
+        db: &DatabaseConnection,
+        user: users::Model,
+        code: &str,
+    ) -> Result<Vec<String>, AppError> {
+        if Self::is_enabled(&user) {
+            return Err(AppError::Conflict(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+        let secret = user.totp_secret.as_deref().ok_or(AppError::BadRequest(
+            "Start two-factor enrollment first".to_string(),
+        ))?;
+        let step = totp::verify(secret, code, Utc::now().timestamp(), None)
+            .ok_or(AppError::BadRequest("Invalid code".to_string()))?;
+
+        let user_id = user.id.clone();
+        let mut active = user.into_active_model();
+        active.totp_enabled_at = Set(Some(Utc::now()));
+        active.totp_last_step = Set(Some(step));
+        active.update(db).await?;
+
+        Self::regenerate_recovery_codes(db, &user_id).await
+    }
+
+    /// Turn off two-factor authentication and drop its recovery codes
+    pub async fn disable(db: &DatabaseConnection, user: users::Model) -> Result<(), AppError> {
+        let user_id = user.id.clone();
+        let mut active = user.into_active_model();
+        active.totp_secret = Set(None);
+        active.totp_enabled_at = Set(None);
+        active.totp_last_step = Set(None);
+        active.update(db).await?;
+        RecoveryCodes::delete_many()
+            .filter(recovery_codes::Column::UserId.eq(&user_id))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Replace a user's recovery codes, returning the new ones
+    pub async fn regenerate_recovery_codes(
+        db: &DatabaseConnection,
+        user_id: &str,
+    ) -> Result<Vec<String>, AppError> {
+        RecoveryCodes::delete_many()
+            .filter(recovery_codes::Column::UserId.eq(user_id))
+            .exec(db)
+            .await?;
+
+        let now = Utc::now();
+        let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| Self::generate_recovery_code())
+            .collect();
+        for code in &codes {
+            recovery_codes::ActiveModel {
+                id: Set(Uuid::new_v4().to_string()),
+                user_id: Set(user_id.to_string()),
+                code_hash: Set(Self::hash_recovery_code(code)),
+                created_at: Set(now),
+                used_at: Set(None),
+            }
+            .insert(db)
+            .await?;
+        }
+        Ok(codes)
+    }
+
+    /// Recovery codes the user has not used yet
+    pub async fn recovery_codes_remaining(
+        db: &DatabaseConnection,
+        user_id: &str,
+    ) -> Result<u64, AppError> {
+        let remaining = RecoveryCodes::find()
+            .filter(recovery_codes::Column::UserId.eq(user_id))
+            .filter(recovery_codes::Column::UsedAt.is_null())
+            .count(db)
+            .await?;
+        Ok(remaining)
+    }
+
+    /// Check a TOTP code or an unused recovery code for a signed-in user
+    ///
+    /// The code counts against the same per-user limit as login challenges,
+    /// so a stolen session cannot be used to guess codes. Fails with
+    /// `TooManyRequests` while the user is locked out.
+    pub async fn verify(
+        db: &DatabaseConnection,
+        user: &users::Model,
+        code: &str,
+    ) -> Result<Option<SecondFactor>, AppError> {
+        if let Some(retry_after_secs) = Self::claim_user_attempt(db, &user.id).await? {
+            return Err(AppError::TooManyRequests {
+                message: "Too many invalid two-factor codes, try again later".to_string(),
+                retry_after_secs,
+            });
+        }
+        let factor = Self::check_code(db, user, code).await?;
+        if factor.is_some() {
+            Self::reset_user_attempts(db, &user.id).await?;
+        }
+        Ok(factor)
+    }
+
+    /// Check a TOTP code or an unused recovery code, using it up
+    async fn check_code(
+        db: &DatabaseConnection,
+        user: &users::Model,
+        code: &str,
+    ) -> Result<Option<SecondFactor>, AppError> {
+        let Some(secret) = user
+            .totp_secret
+            .as_deref()
+            .filter(|_| Self::is_enabled(user))
+        else {
+            return Ok(None);
+        };
+
+        if let Some(step) = totp::verify(secret, code, Utc::now().timestamp(), user.totp_last_step)
+        {
+            // Only the first request presenting this code may use it
+            let res = Users::update_many()
+                .col_expr(users::Column::TotpLastStep, Expr::value(step))
+                .filter(users::Column::Id.eq(&user.id))
+                .filter(
+                    Condition::any()
+                        .add(users::Column::TotpLastStep.is_null())
+                        .add(users::Column::TotpLastStep.lt(step)),
+                )
+                .exec(db)
+                .await?;
+            return Ok((res.rows_affected > 0).then_some(SecondFactor::Totp));
+        }
+
+        let res = RecoveryCodes::update_many()
+            .col_expr(recovery_codes::Column::UsedAt, Expr::value(Utc::now()))
+            .filter(recovery_codes::Column::UserId.eq(&user.id))
+            .filter(recovery_codes::Column::CodeHash.eq(Self::hash_recovery_code(code)))
+            .filter(recovery_codes::Column::UsedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok((res.rows_affected > 0).then_some(SecondFactor::RecoveryCode))
+    }
+
+    /// Hold a password login until its second factor arrives, returning
+    /// the challenge token for `POST /login/2fa`
+    pub async fn start_challenge(
+        db: &DatabaseConnection,
+        user_id: &str,
+    ) -> Result<(String, i64), AppError> {
+        let now = Utc::now();
+        TwoFactorChallenges::delete_many()
+            .filter(two_factor_challenges::Column::ExpiresAt.lt(now))
+            .exec(db)
+            .await?;
+
+        use rand::Rng;
+        let bytes: [u8; 32] = rand::thread_rng().r#gen();
+        let token = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+        let ttl = Duration::minutes(CHALLENGE_TTL_MINUTES);
+        two_factor_challenges::ActiveModel {
+            id: Set(calculate_hash(token.as_bytes())),
+            user_id: Set(user_id.to_string()),
+            attempts: Set(0),
+            expires_at: Set(now + ttl),
+        }
+        .insert(db)
+        .await?;
+        Ok((token, ttl.num_seconds()))
+    }
+
+    /// Seconds left in the user's lockout after too many wrong codes
+    pub fn lockout_remaining(user: &users::Model) -> Option<u64> {
+        let secs = (user.totp_locked_until? - Utc::now()).num_seconds();
+        (secs > 0).then_some(secs as u64)
+    }
+
+    /// Complete a login challenge with a TOTP or recovery code
+    ///
+    /// Returns the user and which factor was used. Every code counts
+    /// against the challenge and the user before it is checked, so
+    /// parallel requests cannot try more codes than allowed. A challenge is
+    /// dropped once it succeeds, expires or runs out of attempts.
+    pub async fn complete_challenge(
+        db: &DatabaseConnection,
+        token: &str,
+        code: &str,
+    ) -> Result<(users::Model, SecondFactor), ChallengeError> {
+        let id = calculate_hash(token.as_bytes());
+        let claimed = TwoFactorChallenges::update_many()
+            .col_expr(
+                two_factor_challenges::Column::Attempts,
+                Expr::col(two_factor_challenges::Column::Attempts).add(1),
+            )
+            .filter(two_factor_challenges::Column::Id.eq(&id))
+            .filter(two_factor_challenges::Column::Attempts.lt(MAX_CHALLENGE_ATTEMPTS))
+            .filter(two_factor_challenges::Column::ExpiresAt.gt(Utc::now()))
+            .exec(db)
+            .await?;
+        if claimed.rows_affected == 0 {
+            TwoFactorChallenges::delete_by_id(&id).exec(db).await?;
+            return Err(ChallengeError::Invalid);
+        }
+        let challenge = TwoFactorChallenges::find_by_id(&id)
+            .one(db)
+            .await?
+            .ok_or(ChallengeError::Invalid)?;
+        let user = Users::find_by_id(&challenge.user_id)
+            .one(db)
+            .await?
+            .ok_or(ChallengeError::Invalid)?;
+        if let Some(retry_after_secs) = Self::claim_user_attempt(db, &user.id).await? {
+            return Err(ChallengeError::LockedOut {
+                user_id: user.id,
+                retry_after_secs,
+            });
+        }
+
+        match Self::check_code(db, &user, code).await? {
+            Some(factor) => {
+                TwoFactorChallenges::delete_by_id(&id).exec(db).await?;
+                Self::reset_user_attempts(db, &user.id).await?;
+                Ok((user, factor))
+            }
+            None => Err(ChallengeError::WrongCode(user.id)),
+        }
+    }
+
+    /// Count a code against the user before it is checked
+    ///
+    /// The attempt that reaches `MAX_USER_ATTEMPTS` starts the lockout, which
+    /// a correct code still lifts. Returns the seconds left while locked out.
+    async fn claim_user_attempt(
+        db: &DatabaseConnection,
+        user_id: &str,
+    ) -> Result<Option<u64>, AppError> {
+        let now = Utc::now();
+        let txn = db.begin().await?;
+        // An expired lockout starts a fresh count
+        Users::update_many()
+            .col_expr(users::Column::TotpFailedAttempts, Expr::value(0))
+            .col_expr(
+                users::Column::TotpLockedUntil,
+                Expr::value(Option::<DateTime<Utc>>::None),
+            )
+            .filter(users::Column::Id.eq(user_id))
+            .filter(users::Column::TotpLockedUntil.lte(now))
+            .exec(&txn)
+            .await?;
+        let claimed = Users::update_many()
+            .col_expr(
+                users::Column::TotpFailedAttempts,
+                Expr::col(users::Column::TotpFailedAttempts).add(1),
+            )
+            .filter(users::Column::Id.eq(user_id))
+            .filter(users::Column::TotpFailedAttempts.lt(MAX_USER_ATTEMPTS))
+            .exec(&txn)
+            .await?;
+        if claimed.rows_affected == 0 {
+            let user = Users::find_by_id(user_id).one(&txn).await?;
+            txn.commit().await?;
+            let remaining = user.as_ref().and_then(Self::lockout_remaining);
+            return Ok(Some(remaining.unwrap_or(LOCKOUT_MINUTES as u64 * 60)));
+        }
+        Users::update_many()
+            .col_expr(
+                users::Column::TotpLockedUntil,
+                Expr::value(now + Duration::minutes(LOCKOUT_MINUTES)),
+            )
+            .filter(users::Column::Id.eq(user_id))
+            .filter(users::Column::TotpFailedAttempts.gte(MAX_USER_ATTEMPTS))
+            .filter(users::Column::TotpLockedUntil.is_null())
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(None)
+    }
+
+    /// Clear the user's wrong-code count after a correct code
+    async fn reset_user_attempts(db: &DatabaseConnection, user_id: &str) -> Result<(), AppError> {
+        Users::update_many()
+            .col_expr(users::Column::TotpFailedAttempts, Expr::value(0))
+            .col_expr(
+                users::Column::TotpLockedUntil,
+                Expr::value(Option::<DateTime<Utc>>::None),
+            )
+            .filter(users::Column::Id.eq(user_id))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    fn generate_recovery_code() -> String {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let mut code: String = (0..10)
+            .map(|_| RECOVERY_ALPHABET[rng.gen_range(0..RECOVERY_ALPHABET.len())] as char)
+            .collect();
+        code.insert(5, '-');
+        code
+    }
+
+    /// Hash a recovery code as typed, ignoring case, spaces and dashes
+    fn hash_recovery_code(code: &str) -> String {
+        let normalized: String = code
+            .chars()
+            .filter(|c| !matches!(c, '-' | ' '))
+            .flat_map(char::to_lowercase)
+            .collect();
+        calculate_hash(normalized.as_bytes())
+    }
+}
+
+/// Why a login challenge could not be completed
+#[derive(Debug)]
+pub enum ChallengeError {
+    /// Unknown, expired or exhausted challenge token
+    Invalid,
+    /// The code was wrong; carries the user the challenge was for
+    WrongCode(String),
+    /// The user tried too many wrong codes and must wait
+    LockedOut {
+        user_id: String,
+        retry_after_secs: u64,
+    },
+    Other(AppError),
+}
+
+impl From<sea_orm::DbErr> for ChallengeError {
+    fn from(e: sea_orm::DbErr) -> Self {
+        ChallengeError::Other(e.into())
+    }
+}
+
+impl From<AppError> for ChallengeError {
+    fn from(e: AppError) -> Self {
+        ChallengeError::Other(e)
+    }
+}
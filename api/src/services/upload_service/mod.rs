@@ -1,8 +1,12 @@
 use crate::config::SecurityConfig;
 
+use crate::api::error::AppError;
 use crate::entities::upload_sessions;
+use crate::services::drives::{DriveAccess, Permission};
 use crate::services::file_service::{FileService, StagedFile};
+use crate::services::quota::QuotaService;
 use crate::services::storage::StorageService;
+use crate::utils::hash::content_hashes_from_reader;
 use anyhow::{Result, anyhow};
 use chrono::Utc;
 use sea_orm::ActiveValue::Set;
@@ -12,16 +16,21 @@ use sea_orm::{
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::sync::Arc;
-use tokio::io::AsyncReadExt;
 use utoipa::ToSchema;
 use uuid::Uuid;
-use xxhash_rust::xxh3::Xxh3;
+
+pub mod tus;
+
+/// `upload_sessions.protocol` of sessions created through `/files/upload/init`
+const CHUNKED_PROTOCOL: &str = "chunked";
 
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct InitUploadRequest {
     pub file_name: String,
     pub file_type: Option<String>,
     pub total_size: i64,
+    /// Team drive the file goes to, unless completed into a folder
+    pub team_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -106,6 +115,15 @@ impl UploadService {
                 self.config.max_file_size
             ));
         }
+        // Checked again when the upload completes and the file is recorded
+        let drive = DriveAccess::drive(
+            &self.db,
+            &user_id,
+            req.team_id.as_deref(),
+            Permission::Write,
+        )
+        .await?;
+        QuotaService::check_room(&self.db, &self.config, &drive, req.total_size).await?;
 
         let chunk_size = self.config.chunk_size as i64;
         let total_chunks = (req.total_size as f64 / chunk_size as f64).ceil() as i32;
@@ -128,6 +146,11 @@ impl UploadService {
             status: Set("pending".to_string()),
             created_at: Set(Utc::now().into()),
             expires_at: Set((Utc::now() + chrono::Duration::hours(24)).into()),
+            protocol: Set(CHUNKED_PROTOCOL.to_string()),
+            upload_offset: Set(0),
+            upload_metadata: Set(None),
+            parent_id: Set(None),
+            team_id: Set(req.team_id),
         };
 
         let saved_session = session.insert(&self.db).await?;
@@ -148,6 +171,7 @@ impl UploadService {
     ) -> Result<UploadPartResponse> {
         let session = upload_sessions::Entity::find_by_id(&session_id)
             .filter(upload_sessions::Column::UserId.eq(&user_id))
+            .filter(upload_sessions::Column::Protocol.eq(CHUNKED_PROTOCOL))
             .one(&self.db)
             .await?
             .ok_or_else(|| anyhow!("Upload session not found"))?;
@@ -213,6 +237,7 @@ impl UploadService {
     ) -> Result<FileResponse> {
         let session = upload_sessions::Entity::find_by_id(session_id.clone())
             .filter(upload_sessions::Column::UserId.eq(user_id.clone()))
+            .filter(upload_sessions::Column::Protocol.eq(CHUNKED_PROTOCOL))
             .one(&self.db)
             .await?
             .ok_or_else(|| anyhow!("Upload session not found"))?;
@@ -230,72 +255,105 @@ impl UploadService {
             ));
         }
 
-        // 1. Complete S3 Multipart
-        let s3_parts: Vec<(i32, String)> = parts
-            .iter()
-            .map(|p| (p.part_number, p.etag.clone()))
-            .collect();
-        self.storage
-            .complete_multipart_upload(&session.s3_key, &session.upload_id, s3_parts)
-            .await?;
+        self.finalize(
+            user_id,
+            session,
+            parts,
+            req.hash,
+            req.parent_id.map(|id| id.to_string()),
+        )
+        .await
+    }
 
-        // 2. Determine initial hash — use client-provided for fast response, verify async
-        let client_hash = req.hash.clone();
-        let hash = if let Some(ref h) = client_hash {
-            tracing::info!(
-                "Using client-provided hash (will verify async): {} for session: {}",
-                h,
-                session_id
-            );
-            h.clone()
+    /// Assemble a fully uploaded session and hand it to `FileService::process_upload`
+    async fn finalize(
+        &self,
+        user_id: String,
+        session: upload_sessions::Model,
+        parts: Vec<PartInfo>,
+        client_hash: Option<String>,
+        parent_id: Option<String>,
+    ) -> Result<FileResponse> {
+        let session_id = session.id.clone();
+
+        // 1. Complete S3 Multipart (S3 rejects completing without parts, so empty files are written directly)
+        if parts.is_empty() {
+            self.storage
+                .abort_multipart_upload(&session.s3_key, &session.upload_id)
+                .await?;
+            self.storage
+                .upload_file(&session.s3_key, Vec::new())
+                .await?;
         } else {
-            // No client hash provided — must compute synchronously
-            tracing::info!(
-                "No client hash provided, calculating synchronously for session: {}",
-                session_id
+            let s3_parts: Vec<(i32, String)> = parts
+                .iter()
+                .map(|p| (p.part_number, p.etag.clone()))
+                .collect();
+            self.storage
+                .complete_multipart_upload(&session.s3_key, &session.upload_id, s3_parts)
+                .await?;
+        }
+
+        // 2. Hash the assembled object server-side. The SHA-256 is the
+        // deduplication key, so a client-provided hash is never trusted for it.
+        let object = self
+            .storage
+            .get_object_stream(&session.s3_key)
+            .await?
+            .body
+            .into_async_read();
+        let hashes = content_hashes_from_reader(object).await?;
+        if let Some(ref client_hash) = client_hash
+            && *client_hash != hashes.xxh3
+        {
+            tracing::warn!(
+                "Client hash mismatch for session {}: client {} | server {}",
+                session_id,
+                client_hash,
+                hashes.xxh3
             );
-            let mut hasher = Xxh3::new();
-            let mut stream = self
-                .storage
-                .get_object_stream(&session.s3_key)
-                .await?
-                .body
-                .into_async_read();
-            let mut buffer = [0u8; 8192];
-            loop {
-                let n = stream.read(&mut buffer).await?;
-                if n == 0 {
-                    break;
-                }
-                hasher.update(&buffer[..n]);
-            }
-            let h = format!("{:032x}", hasher.digest128());
-            tracing::info!("Calculated hash: {} for session: {}", h, session_id);
-            h
-        };
+        }
 
         // 3. Delegate to FileService (uses the hash — possibly client-provided)
         let staged_file = StagedFile {
             key: session.s3_key.clone(),
-            hash: hash.clone(),
+            hash: hashes.xxh3,
+            sha256: hashes.sha256,
             size: session.total_size,
             s3_key: session.s3_key.clone(),
             temp_path: None,
         };
 
         tracing::info!("Delegating chunked file completion to FileService for processing...");
+        let destination = match DriveAccess::destination(
+            &self.db,
+            &user_id,
+            parent_id.as_deref(),
+            session.team_id.as_deref(),
+        )
+        .await
+        {
+            Ok(destination) => destination,
+            Err(e) => {
+                let _ = self.storage.delete_file(&session.s3_key).await;
+                return Err(e.into());
+            }
+        };
         let (file_id, _) = self
             .file_service
             .process_upload(
                 staged_file,
                 session.file_name.clone(),
                 user_id.clone(),
-                req.parent_id.map(|id| id.to_string()),
+                destination,
                 None, // expiration
                 Some(session.total_size as u64),
             )
             .await
-            .map_err(|e| anyhow!("File processing failed: {}", e))?;
+            .map_err(|e| match e {
+                AppError::InsufficientStorage(_) => anyhow::Error::from(e),
+                e => anyhow!("File processing failed: {}", e),
+            })?;
         tracing::info!(
             "File successfully processed by FileService. FileID: {}",
             file_id
@@ -316,22 +374,6 @@ impl UploadService {
             .await?
             .ok_or_else(|| anyhow!("File created but not found"))?;
 
-        // 6. If client hash was used, spawn async verification task
-        if client_hash.is_some() {
-            let storage = self.storage.clone();
-            let db = self.db.clone();
-            let client_hash_val = hash.clone();
-            let storage_file_id = file.storage_file_id.clone();
-
-            tokio::spawn(async move {
-                if let Err(e) =
-                    verify_hash_async(db, storage, storage_file_id, client_hash_val).await
-                {
-                    tracing::error!("❌ Async hash verification failed: {}", e);
-                }
-            });
-        }
-
         Ok(FileResponse {
             id: file.id,
             name: file.filename,
@@ -372,6 +414,7 @@ impl UploadService {
     ) -> Result<Vec<PendingSessionResponse>> {
         let sessions = upload_sessions::Entity::find()
             .filter(upload_sessions::Column::UserId.eq(user_id))
+            .filter(upload_sessions::Column::Protocol.eq(CHUNKED_PROTOCOL))
             .filter(upload_sessions::Column::Status.eq("pending"))
             .all(&self.db)
             .await?;
@@ -394,71 +437,35 @@ impl UploadService {
         }
         Ok(result)
     }
-}
-
-/// Background task: verify a client-provided hash by re-downloading and hashing the file server-side.
-/// If mismatch, update the storage_files record with the correct hash.
-async fn verify_hash_async(
-    db: DatabaseConnection,
-    storage: Arc<dyn StorageService>,
-    storage_file_id: Option<String>,
-    client_hash: String,
-) -> Result<()> {
-    use crate::entities::storage_files;
-
-    let sf_id = storage_file_id.ok_or_else(|| anyhow!("No storage_file_id to verify"))?;
-
-    let sf = storage_files::Entity::find_by_id(&sf_id)
-        .one(&db)
-        .await?
-        .ok_or_else(|| anyhow!("Storage file not found: {}", sf_id))?;
-
-    tracing::info!(
-        "🔐 Async hash verification started for storage_file: {} (s3: {})",
-        sf_id,
-        sf.s3_key
-    );
-
-    let mut hasher = Xxh3::new();
-    let mut stream = storage
-        .get_object_stream(&sf.s3_key)
-        .await?
-        .body
-        .into_async_read();
-    let mut buffer = [0u8; 65536]; // 64KB buffer for faster streaming
-
-    loop {
-        let n = stream.read(&mut buffer).await?;
-        if n == 0 {
-            break;
-        }
-        hasher.update(&buffer[..n]);
-    }
 
-    let server_hash = format!("{:032x}", hasher.digest128());
+    /// Abort unfinished sessions past their expiry, releasing their multipart uploads
+    ///
+    /// Returns the number of sessions removed
+    pub async fn purge_expired_sessions(
+        db: &DatabaseConnection,
+        storage: &dyn StorageService,
+    ) -> Result<usize> {
+        let expired = upload_sessions::Entity::find()
+            .filter(upload_sessions::Column::Status.ne("completed"))
+            .filter(upload_sessions::Column::ExpiresAt.lt(Utc::now()))
+            .all(db)
+            .await?;
 
-    if server_hash == client_hash {
-        tracing::info!(
-            "✅ Hash verified for storage_file {}: {}",
-            sf_id,
-            server_hash
-        );
-    } else {
-        tracing::warn!(
-            "⚠️ Hash MISMATCH for storage_file {}! Client: {} | Server: {} — updating record.",
-            sf_id,
-            client_hash,
-            server_hash
-        );
-        let mut active: storage_files::ActiveModel = sf.into();
-        active.hash = Set(server_hash.clone());
-        active.update(&db).await?;
-        tracing::info!(
-            "✅ Hash corrected for storage_file {} to: {}",
-            sf_id,
-            server_hash
-        );
+        let count = expired.len();
+        for session in expired {
+            if let Err(e) = storage
+                .abort_multipart_upload(&session.s3_key, &session.upload_id)
+                .await
+            {
+                tracing::warn!("Failed to abort multipart upload {}: {}", session.id, e);
+            }
+            if let Some(tail_key) = tus::tail_key(&session) {
+                let _ = storage.delete_file(&tail_key).await;
+            }
+            // Assembled object of a session whose processing failed
+            let _ = storage.delete_file(&session.s3_key).await;
+            session.delete(db).await?;
+        }
+        Ok(count)
     }
-
-    Ok(())
 }
@@ -0,0 +1,566 @@
+//! tus 1.0 resumable uploads on top of `upload_sessions`
+//!
+//! Each tus upload is an S3 multipart upload. tus requests may carry any
+//! number of bytes, while multipart parts must be `chunk_size` long (except
+//! the last), so bytes past the last full part are parked in a tail object
+//! until enough arrive to fill a part.
+
+use super::{FileResponse, PartInfo, UploadService};
+use crate::api::error::AppError;
+use crate::entities::upload_sessions;
+use crate::services::drives::DriveAccess;
+use crate::services::quota::QuotaService;
+use crate::utils::validation::sanitize_filename;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use bytes::Bytes;
+use chrono::Utc;
+use futures::{Stream, StreamExt};
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter,
+};
+use serde_json::json;
+use sha1::Digest;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// `upload_sessions.protocol` of tus uploads
+pub const TUS_PROTOCOL: &str = "tus";
+
+/// `upload_sessions.status` while a PATCH holds the upload
+pub const APPENDING: &str = "appending";
+
+/// Algorithms accepted in `Upload-Checksum`, as advertised in `Tus-Checksum-Algorithm`
+pub const CHECKSUM_ALGORITHMS: &str = "sha1,sha256";
+
+/// A parsed `Upload-Checksum` header: `<algorithm> <base64 digest>`
+pub struct UploadChecksum {
+    hasher: ChecksumHasher,
+    expected: Vec<u8>,
+}
+
+enum ChecksumHasher {
+    Sha1(sha1::Sha1),
+    Sha256(sha2::Sha256),
+}
+
+impl UploadChecksum {
+    pub fn parse(header: &str) -> Result<Self, AppError> {
+        let (algorithm, digest) = header
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| AppError::BadRequest("Malformed Upload-Checksum".to_string()))?;
+        let hasher = match algorithm {
+            "sha1" => ChecksumHasher::Sha1(sha1::Sha1::new()),
+            "sha256" => ChecksumHasher::Sha256(sha2::Sha256::new()),
+            other => {
+                return Err(AppError::BadRequest(format!(
+                    "Unsupported checksum algorithm: {}",
+                    other
+                )));
+            }
+        };
+        let expected = BASE64
+            .decode(digest.trim())
+            .map_err(|_| AppError::BadRequest("Malformed Upload-Checksum".to_string()))?;
+        Ok(Self { hasher, expected })
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match &mut self.hasher {
+            ChecksumHasher::Sha1(h) => h.update(data),
+            ChecksumHasher::Sha256(h) => h.update(data),
+        }
+    }
+
+    fn matches(self) -> bool {
+        let actual = match self.hasher {
+            ChecksumHasher::Sha1(h) => h.finalize().to_vec(),
+            ChecksumHasher::Sha256(h) => h.finalize().to_vec(),
+        };
+        actual == self.expected
+    }
+}
+
+/// Decode `Upload-Metadata`: comma-separated `key base64value` pairs, value optional
+pub fn parse_metadata(header: &str) -> Result<HashMap<String, String>, AppError> {
+    let mut metadata = HashMap::new();
+    for pair in header.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (key, value) = match pair.split_once(' ') {
+            Some((key, encoded)) => {
+                let decoded = BASE64
+                    .decode(encoded.trim())
+                    .ok()
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .ok_or_else(|| {
+                        AppError::BadRequest(format!("Invalid Upload-Metadata value for {}", key))
+                    })?;
+                (key, decoded)
+            }
+            None => (pair, String::new()),
+        };
+        metadata.insert(key.to_string(), value);
+    }
+    Ok(metadata)
+}
+
+/// Key of the object holding the bytes past the last full part, if there are any
+pub(crate) fn tail_key(session: &upload_sessions::Model) -> Option<String> {
+    let flushed = session.uploaded_chunks as i64 * session.chunk_size;
+    (session.protocol == TUS_PROTOCOL
+        && matches!(session.status.as_str(), "pending" | APPENDING)
+        && session.upload_offset > flushed)
+        .then(|| format!("{}.tail-{}", session.s3_key, session.upload_offset))
+}
+
+/// Outcome of a PATCH
+pub struct TusAppend {
+    pub offset: i64,
+    pub expires_at: chrono::DateTime<chrono::FixedOffset>,
+    /// Set once the last byte arrived and the file was created
+    pub file: Option<FileResponse>,
+}
+
+impl UploadService {
+    /// tus creation: start an upload of `length` bytes
+    pub async fn tus_create(
+        &self,
+        user_id: &str,
+        length: i64,
+        metadata: Option<String>,
+    ) -> Result<upload_sessions::Model, AppError> {
+        if length < 0 {
+            return Err(AppError::BadRequest("Invalid Upload-Length".to_string()));
+        }
+        if length > self.config.max_file_size as i64 {
+            return Err(AppError::PayloadTooLarge(format!(
+                "File too large. Max: {} bytes",
+                self.config.max_file_size
+            )));
+        }
+
+        let fields = match metadata.as_deref() {
+            Some(header) => parse_metadata(header)?,
+            None => HashMap::new(),
+        };
+        let rules = self.file_service.validation_rules().await?;
+        // Uppy sends `name`/`type`; other clients send `filename`/`filetype`
+        let raw_name = fields
+            .get("filename")
+            .or_else(|| fields.get("name"))
+            .map(String::as_str)
+            .unwrap_or("unnamed");
+        let file_name =
+            sanitize_filename(raw_name, &rules).map_err(|e| AppError::BadRequest(e.to_string()))?;
+        let file_type = fields
+            .get("filetype")
+            .or_else(|| fields.get("type"))
+            .filter(|t| !t.is_empty())
+            .cloned();
+        let parent_id = fields
+            .get("parent_id")
+            .filter(|p| !p.is_empty() && p.as_str() != "null")
+            .cloned();
+        let team_id = fields
+            .get("team_id")
+            .filter(|t| !t.is_empty() && t.as_str() != "null")
+            .cloned();
+
+        // Checked again when the upload finishes and the file is recorded
+        let destination =
+            DriveAccess::destination(&self.db, user_id, parent_id.as_deref(), team_id.as_deref())
+                .await?;
+        QuotaService::check_room(&self.db, &self.config, &destination.drive, length).await?;
+
+        let chunk_size = self.config.chunk_size as i64;
+        let s3_key = format!("multipart/{}", Uuid::new_v4());
+        let s3_upload_id = self
+            .storage
+            .create_multipart_upload(&s3_key)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to start upload: {}", e)))?;
+
+        let session = upload_sessions::ActiveModel {
+            id: Set(Uuid::new_v4().to_string()),
+            user_id: Set(user_id.to_string()),
+            file_name: Set(file_name),
+            file_type: Set(file_type),
+            s3_key: Set(s3_key),
+            upload_id: Set(s3_upload_id),
+            chunk_size: Set(chunk_size),
+            total_size: Set(length),
+            total_chunks: Set((length as f64 / chunk_size as f64).ceil() as i32),
+            uploaded_chunks: Set(0),
+            parts: Set(json!([])),
+            status: Set("pending".to_string()),
+            created_at: Set(Utc::now().into()),
+            expires_at: Set((Utc::now() + chrono::Duration::hours(24)).into()),
+            protocol: Set(TUS_PROTOCOL.to_string()),
+            upload_offset: Set(0),
+            upload_metadata: Set(metadata),
+            parent_id: Set(parent_id),
+            team_id: Set(team_id),
+        };
+        Ok(session.insert(&self.db).await?)
+    }
+
+    /// The user's tus upload, for HEAD and before every PATCH
+    pub async fn tus_status(
+        &self,
+        user_id: &str,
+        id: &str,
+    ) -> Result<upload_sessions::Model, AppError> {
+        let session = upload_sessions::Entity::find_by_id(id)
+            .filter(upload_sessions::Column::UserId.eq(user_id))
+            .filter(upload_sessions::Column::Protocol.eq(TUS_PROTOCOL))
+            .one(&self.db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Upload not found".to_string()))?;
+
+        let expired = session.status == "pending" && session.expires_at < Utc::now();
+        if expired || session.status == "failed" {
+            return Err(AppError::Gone("Upload expired".to_string()));
+        }
+        Ok(session)
+    }
+
+    /// tus PATCH: append `body` at `offset`
+    ///
+    /// Without a checksum, bytes received before an interrupted request are
+    /// kept so the client can resume from them. With one, the request is
+    /// all-or-nothing. The last byte triggers `FileService::process_upload`.
+    pub async fn tus_append<S, E>(
+        &self,
+        user_id: &str,
+        id: &str,
+        offset: i64,
+        checksum: Option<UploadChecksum>,
+        body: S,
+    ) -> Result<TusAppend, AppError>
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin,
+        E: std::fmt::Display,
+    {
+        let session = self.tus_status(user_id, id).await?;
+        match session.status.as_str() {
+            "pending" => {}
+            APPENDING => {
+                return Err(AppError::Conflict(
+                    "Another request is appending to this upload".to_string(),
+                ));
+            }
+            _ => return Err(AppError::Conflict("Upload already finished".to_string())),
+        }
+        if offset != session.upload_offset {
+            return Err(AppError::Conflict(format!(
+                "Upload-Offset {} does not match current offset {}",
+                offset, session.upload_offset
+            )));
+        }
+
+        // Parts are numbered by their position in the file, so only one
+        // request may write them: claim the offset before reading the body
+        let claimed = upload_sessions::Entity::update_many()
+            .col_expr(upload_sessions::Column::Status, Expr::value(APPENDING))
+            .filter(upload_sessions::Column::Id.eq(&session.id))
+            .filter(upload_sessions::Column::UploadOffset.eq(offset))
+            .filter(upload_sessions::Column::Status.eq("pending"))
+            .exec(&self.db)
+            .await?;
+        if claimed.rows_affected == 0 {
+            return Err(AppError::Conflict(
+                "Upload was modified concurrently".to_string(),
+            ));
+        }
+        let claim = AppendClaim {
+            db: self.db.clone(),
+            session_id: session.id.clone(),
+            released: false,
+        };
+        let result = self
+            .append_claimed(user_id, session, offset, checksum, body)
+            .await;
+        claim.release().await;
+        result
+    }
+
+    /// The body of a PATCH that holds the upload's offset
+    async fn append_claimed<S, E>(
+        &self,
+        user_id: &str,
+        session: upload_sessions::Model,
+        offset: i64,
+        mut checksum: Option<UploadChecksum>,
+        mut body: S,
+    ) -> Result<TusAppend, AppError>
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin,
+        E: std::fmt::Display,
+    {
+        let chunk_size = session.chunk_size as usize;
+        let old_tail = tail_key(&session);
+        let mut buffer =
+            match &old_tail {
+                Some(key) => self.storage.get_file(key).await.map_err(|e| {
+                    AppError::Internal(format!("Failed to read upload tail: {}", e))
+                })?,
+                None => Vec::new(),
+            };
+        let mut parts: Vec<PartInfo> = serde_json::from_value(session.parts.clone())
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let mut received: i64 = 0;
+        let mut interrupted = None;
+        while let Some(chunk) = body.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    interrupted = Some(e.to_string());
+                    break;
+                }
+            };
+            received += chunk.len() as i64;
+            if offset + received > session.total_size {
+                return Err(AppError::PayloadTooLarge(
+                    "Request exceeds Upload-Length".to_string(),
+                ));
+            }
+            if let Some(checksum) = checksum.as_mut() {
+                checksum.update(&chunk);
+            }
+            buffer.extend_from_slice(&chunk);
+            while buffer.len() >= chunk_size {
+                let part: Vec<u8> = buffer.drain(..chunk_size).collect();
+                parts.push(self.upload_tus_part(&session, &parts, part).await?);
+            }
+        }
+
+        if let Some(checksum) = checksum {
+            if let Some(e) = interrupted {
+                return Err(AppError::BadRequest(format!("Upload interrupted: {}", e)));
+            }
+            if !checksum.matches() {
+                return Err(AppError::ChecksumMismatch(
+                    "Upload-Checksum does not match the request body".to_string(),
+                ));
+            }
+        }
+
+        let new_offset = offset + received;
+        let finished = new_offset == session.total_size;
+        let mut new_tail = None;
+        if finished && !buffer.is_empty() {
+            let part = std::mem::take(&mut buffer);
+            parts.push(self.upload_tus_part(&session, &parts, part).await?);
+        } else if !buffer.is_empty() && new_offset != offset {
+            let key = format!("{}.tail-{}", session.s3_key, new_offset);
+            self.storage
+                .upload_file(&key, buffer)
+                .await
+                .map_err(|e| AppError::Internal(format!("Failed to store upload tail: {}", e)))?;
+            new_tail = Some(key);
+        }
+
+        // Hands the upload back, or on to completion
+        let status = if finished { "completing" } else { "pending" };
+        let updated = upload_sessions::Entity::update_many()
+            .col_expr(
+                upload_sessions::Column::UploadOffset,
+                Expr::value(new_offset),
+            )
+            .col_expr(upload_sessions::Column::Parts, Expr::value(json!(parts)))
+            .col_expr(
+                upload_sessions::Column::UploadedChunks,
+                Expr::value(parts.len() as i32),
+            )
+            .col_expr(upload_sessions::Column::Status, Expr::value(status))
+            .filter(upload_sessions::Column::Id.eq(&session.id))
+            .filter(upload_sessions::Column::UploadOffset.eq(offset))
+            .filter(upload_sessions::Column::Status.eq(APPENDING))
+            .exec(&self.db)
+            .await?;
+        if updated.rows_affected == 0 {
+            if let Some(key) = new_tail {
+                let _ = self.storage.delete_file(&key).await;
+            }
+            return Err(AppError::Conflict(
+                "Upload was modified concurrently".to_string(),
+            ));
+        }
+        if let Some(key) = old_tail
+            && new_offset != offset
+        {
+            let _ = self.storage.delete_file(&key).await;
+        }
+
+        if let Some(e) = interrupted {
+            return Err(AppError::BadRequest(format!("Upload interrupted: {}", e)));
+        }
+
+        let file = if finished {
+            Some(self.tus_finish(user_id, &session.id, parts).await?)
+        } else {
+            None
+        };
+
+        Ok(TusAppend {
+            offset: new_offset,
+            expires_at: session.expires_at,
+            file,
+        })
+    }
+
+    /// tus termination: abort an unfinished upload
+    pub async fn tus_terminate(&self, user_id: &str, id: &str) -> Result<(), AppError> {
+        let session = self.tus_status(user_id, id).await?;
+        match session.status.as_str() {
+            "pending" => {}
+            APPENDING => {
+                return Err(AppError::Conflict(
+                    "Another request is appending to this upload".to_string(),
+                ));
+            }
+            _ => return Err(AppError::Conflict("Upload already finished".to_string())),
+        }
+
+        if let Err(e) = self
+            .storage
+            .abort_multipart_upload(&session.s3_key, &session.upload_id)
+            .await
+        {
+            tracing::warn!("Failed to abort multipart upload {}: {}", session.id, e);
+        }
+        if let Some(key) = tail_key(&session) {
+            let _ = self.storage.delete_file(&key).await;
+        }
+        session.delete(&self.db).await?;
+        Ok(())
+    }
+
+    async fn upload_tus_part(
+        &self,
+        session: &upload_sessions::Model,
+        parts: &[PartInfo],
+        data: Vec<u8>,
+    ) -> Result<PartInfo, AppError> {
+        // Every part but the last is exactly chunk_size, so parts stay numbered in order
+        let part_number = parts.len() as i32 + 1;
+        let etag = self
+            .storage
+            .upload_part(&session.s3_key, &session.upload_id, part_number, data)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to upload part: {}", e)))?;
+        Ok(PartInfo { part_number, etag })
+    }
+
+    async fn tus_finish(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        parts: Vec<PartInfo>,
+    ) -> Result<FileResponse, AppError> {
+        let session = upload_sessions::Entity::find_by_id(session_id)
+            .one(&self.db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Upload not found".to_string()))?;
+        let parent_id = session.parent_id.clone();
+
+        match self
+            .finalize(user_id.to_string(), session.clone(), parts, None, parent_id)
+            .await
+        {
+            Ok(file) => Ok(file),
+            Err(e) => {
+                let mut active: upload_sessions::ActiveModel = session.into();
+                active.status = Set("failed".to_string());
+                let _ = active.update(&self.db).await;
+                Err(match e.downcast::<AppError>() {
+                    Ok(app_error) => app_error,
+                    Err(e) => AppError::Internal(e.to_string()),
+                })
+            }
+        }
+    }
+}
+
+/// A PATCH's hold on its upload
+///
+/// Released once the request ends; a request that is dropped midway hands
+/// the upload back from the background so the client can resume.
+struct AppendClaim {
+    db: DatabaseConnection,
+    session_id: String,
+    released: bool,
+}
+
+impl AppendClaim {
+    /// Hand the upload back unless the request already moved it on
+    async fn release(mut self) {
+        self.released = true;
+        if let Err(e) = Self::unclaim(&self.db, &self.session_id).await {
+            tracing::warn!("Failed to release upload {}: {}", self.session_id, e);
+        }
+    }
+
+    async fn unclaim(db: &DatabaseConnection, session_id: &str) -> Result<(), sea_orm::DbErr> {
+        upload_sessions::Entity::update_many()
+            .col_expr(upload_sessions::Column::Status, Expr::value("pending"))
+            .filter(upload_sessions::Column::Id.eq(session_id))
+            .filter(upload_sessions::Column::Status.eq(APPENDING))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+}
+
+impl Drop for AppendClaim {
+    fn drop(&mut self) {
+        if self.released {
+            return;
+        }
+        let db = self.db.clone();
+        let session_id = std::mem::take(&mut self.session_id);
+        tokio::spawn(async move {
+            if let Err(e) = Self::unclaim(&db, &session_id).await {
+                tracing::warn!("Failed to release upload {}: {}", session_id, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_metadata() {
+        let header = format!(
+            "filename {},is_confidential, filetype {}",
+            BASE64.encode("report.pdf"),
+            BASE64.encode("application/pdf")
+        );
+        let metadata = parse_metadata(&header).unwrap();
+        assert_eq!(metadata["filename"], "report.pdf");
+        assert_eq!(metadata["filetype"], "application/pdf");
+        assert_eq!(metadata["is_confidential"], "");
+        assert!(parse_metadata("filename !!!").is_err());
+    }
+
+    #[test]
+    fn test_upload_checksum() {
+        let digest = BASE64.encode(sha1::Sha1::digest(b"hello"));
+        let mut checksum = UploadChecksum::parse(&format!("sha1 {}", digest)).unwrap();
+        checksum.update(b"hel");
+        checksum.update(b"lo");
+        assert!(checksum.matches());
+
+        let mut checksum = UploadChecksum::parse(&format!("sha1 {}", digest)).unwrap();
+        checksum.update(b"world");
+        assert!(!checksum.matches());
+
+        assert!(UploadChecksum::parse("crc32 AAAA").is_err());
+        assert!(UploadChecksum::parse("sha256").is_err());
+    }
+}
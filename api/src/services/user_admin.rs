@@ -0,0 +1,245 @@
+use crate::api::error::AppError;
+use crate::config::SecurityConfig;
+use crate::entities::{prelude::*, *};
+use crate::services::sessions::SessionService;
+use crate::services::storage::StorageService;
+use crate::services::storage_lifecycle::{ReleasedObjects, StorageLifecycleService};
+use crate::services::teams::TeamService;
+use crate::utils::auth::Role;
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, EntityTrait, IntoActiveModel,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
+    sea_query::{Expr, Func},
+};
+
+/// Users returned by one page of `UserAdminService::list`, at most
+pub const MAX_PAGE_SIZE: u64 = 200;
+
+/// Filters for the admin user list
+#[derive(Debug, Default, Clone)]
+pub struct UserFilter {
+    /// Matched case-insensitively against username, email and name
+    pub search: Option<String>,
+    pub role: Option<Role>,
+    pub disabled: Option<bool>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+/// System-wide storage figures, with the space deduplication saves
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StorageStats {
+    pub users: u64,
+    pub disabled_users: u64,
+    /// Distinct stored objects
+    pub storage_objects: u64,
+    /// Bytes actually stored
+    pub physical_bytes: i64,
+    /// Bytes users would store without deduplication (`size * ref_count`)
+    pub logical_bytes: i64,
+}
+
+impl StorageStats {
+    pub fn dedup_savings(&self) -> i64 {
+        (self.logical_bytes - self.physical_bytes).max(0)
+    }
+}
+
+/// User management behind the `/admin` API
+///
+/// Admins cannot demote, disable or delete themselves, so there is always
+/// at least one admin left to undo a mistake.
+pub struct UserAdminService;
+
+impl UserAdminService {
+    /// Users matching `filter`, oldest first
+    pub async fn list(
+        db: &DatabaseConnection,
+        filter: &UserFilter,
+    ) -> Result<Vec<users::Model>, AppError> {
+        let mut query = Users::find();
+        if let Some(search) = filter.search.as_deref().map(str::trim)
+            && !search.is_empty()
+        {
+            let pattern = format!("%{}%", search.to_lowercase());
+            let matches =
+                |col: users::Column| Expr::expr(Func::lower(Expr::col(col))).like(pattern.clone());
+            query = query.filter(
+                Condition::any()
+                    .add(matches(users::Column::Username))
+                    .add(matches(users::Column::Email))
+                    .add(matches(users::Column::Name)),
+            );
+        }
+        if let Some(role) = filter.role {
+            query = query.filter(users::Column::Role.eq(role.as_str()));
+        }
+        match filter.disabled {
+            Some(true) => query = query.filter(users::Column::DisabledAt.is_not_null()),
+            Some(false) => query = query.filter(users::Column::DisabledAt.is_null()),
+            None => {}
+        }
+
+        Ok(query
+            .order_by_asc(users::Column::CreatedAt)
+            .order_by_asc(users::Column::Username)
+            .limit(filter.limit.unwrap_or(50).min(MAX_PAGE_SIZE))
+            .offset(filter.offset.unwrap_or(0))
+            .all(db)
+            .await?)
+    }
+
+    pub async fn get(db: &DatabaseConnection, user_id: &str) -> Result<users::Model, AppError> {
+        Users::find_by_id(user_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+    }
+
+    pub async fn set_role(
+        db: &DatabaseConnection,
+        acting_admin: &str,
+        user_id: &str,
+        role: Role,
+    ) -> Result<users::Model, AppError> {
+        if user_id == acting_admin && role != Role::Admin {
+            return Err(AppError::BadRequest(
+                "You cannot remove your own admin role".to_string(),
+            ));
+        }
+        let mut active = Self::get(db, user_id).await?.into_active_model();
+        active.role = Set(role.as_str().to_string());
+        Ok(active.update(db).await?)
+    }
+
+    /// Disable or re-enable an account; disabling ends all its sessions
+    pub async fn set_disabled(
+        db: &DatabaseConnection,
+        config: &SecurityConfig,
+        acting_admin: &str,
+        user_id: &str,
+        disabled: bool,
+    ) -> Result<users::Model, AppError> {
+        if user_id == acting_admin && disabled {
+            return Err(AppError::BadRequest(
+                "You cannot disable your own account".to_string(),
+            ));
+        }
+        let user = Self::get(db, user_id).await?;
+        if user.disabled_at.is_some() == disabled {
+            return Ok(user);
+        }
+        let mut active = user.into_active_model();
+        active.disabled_at = Set(disabled.then(Utc::now));
+        let user = active.update(db).await?;
+        if disabled {
+            SessionService::revoke_all(db, config, user_id).await?;
+        }
+        Ok(user)
+    }
+
+    /// Set a new password hash and end every session of the account
+    pub async fn reset_password(
+        db: &DatabaseConnection,
+        config: &SecurityConfig,
+        user_id: &str,
+        password_hash: String,
+    ) -> Result<usize, AppError> {
+        let mut active = Self::get(db, user_id).await?.into_active_model();
+        active.password_hash = Set(Some(password_hash));
+        active.update(db).await?;
+        SessionService::revoke_all(db, config, user_id).await
+    }
+
+    /// Delete an account with all its files, releasing deduplicated storage
+    ///
+    /// Team drives the user belongs to are handed over to the remaining
+    /// members, or deleted with them if nobody is left. Returns the number
+    /// of file and folder rows removed.
+    pub async fn delete(
+        db: &DatabaseConnection,
+        storage: &dyn StorageService,
+        config: &SecurityConfig,
+        acting_admin: &str,
+        user_id: &str,
+    ) -> Result<usize, AppError> {
+        if user_id == acting_admin {
+            return Err(AppError::BadRequest(
+                "You cannot delete your own account".to_string(),
+            ));
+        }
+        let user = Self::get(db, user_id).await?;
+        // Access tokens stay refused until they expire
+        SessionService::revoke_all(db, config, user_id).await?;
+
+        let txn = db.begin().await?;
+        let mut released = ReleasedObjects::default();
+        let mut purged = TeamService::release_user(&txn, user_id, &mut released).await?;
+        purged += StorageLifecycleService::purge_user_files(&txn, user_id, &mut released)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to delete files: {}", e)))?;
+        Users::delete_by_id(&user.id).exec(&txn).await?;
+        txn.commit().await?;
+        released.delete_objects(storage).await;
+
+        if user.avatar_url.is_some()
+            && let Err(e) = storage
+                .delete_file(&format!("avatars/{}.jpg", user.id))
+                .await
+        {
+            tracing::warn!("Failed to delete avatar of user {}: {}", user.id, e);
+        }
+        Ok(purged)
+    }
+
+    pub async fn storage_stats(db: &DatabaseConnection) -> Result<StorageStats, AppError> {
+        let users = Users::find().count(db).await?;
+        let disabled_users = Users::find()
+            .filter(users::Column::DisabledAt.is_not_null())
+            .count(db)
+            .await?;
+        let storage_objects = StorageFiles::find().count(db).await?;
+        let (physical_bytes, logical_bytes): (i64, i64) = StorageFiles::find()
+            .select_only()
+            .column_as(
+                Expr::cust("CAST(COALESCE(SUM(storage_files.size), 0) AS BIGINT)"),
+                "physical",
+            )
+            .column_as(
+                Expr::cust(
+                    "CAST(COALESCE(SUM(storage_files.size * storage_files.ref_count), 0) AS BIGINT)",
+                ),
+                "logical",
+            )
+            .into_tuple()
+            .one(db)
+            .await?
+            .unwrap_or_default();
+
+        Ok(StorageStats {
+            users,
+            disabled_users,
+            storage_objects,
+            physical_bytes,
+            logical_bytes,
+        })
+    }
+
+    /// Give the admin role to one account, for bootstrapping from the CLI
+    ///
+    /// Accounts always register as `user`; besides the seeded `admin`, this
+    /// is the only way to create an admin without an existing one.
+    pub async fn grant_admin(
+        db: &DatabaseConnection,
+        user_id: &str,
+    ) -> Result<users::Model, AppError> {
+        let user = Self::get(db, user_id).await?;
+        if Role::of(&user) == Role::Admin {
+            return Ok(user);
+        }
+        let mut active = user.into_active_model();
+        active.role = Set(Role::Admin.as_str().to_string());
+        Ok(active.update(db).await?)
+    }
+}
@@ -0,0 +1,341 @@
+use crate::api::error::AppError;
+use crate::entities::{allowed_mimes, blocked_extensions, magic_signatures, prelude::*};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder, Set,
+};
+
+/// Longest magic signature accepted, in bytes
+pub const MAX_SIGNATURE_LEN: usize = 64;
+
+/// Admin management of the `allowed_mimes`, `magic_signatures` and
+/// `blocked_extensions` tables
+///
+/// Values are normalized the way uploads are checked against them: MIME
+/// types and extensions lowercase, extensions without a leading dot.
+/// Callers must invalidate the cached `ValidationRules` after a change.
+pub struct ValidationRuleService;
+
+impl ValidationRuleService {
+    pub async fn list_mimes(
+        db: &DatabaseConnection,
+    ) -> Result<Vec<allowed_mimes::Model>, AppError> {
+        Ok(AllowedMimes::find()
+            .order_by_asc(allowed_mimes::Column::Category)
+            .order_by_asc(allowed_mimes::Column::MimeType)
+            .all(db)
+            .await?)
+    }
+
+    pub async fn create_mime(
+        db: &DatabaseConnection,
+        mime_type: &str,
+        category: &str,
+        description: Option<String>,
+    ) -> Result<allowed_mimes::Model, AppError> {
+        let mime_type = normalize_mime(mime_type)?;
+        let category = normalize_category(category)?;
+        Self::ensure_mime_free(db, &mime_type, None).await?;
+        Ok(allowed_mimes::ActiveModel {
+            mime_type: Set(mime_type),
+            category: Set(category),
+            description: Set(normalize_description(description)),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?)
+    }
+
+    pub async fn update_mime(
+        db: &DatabaseConnection,
+        id: i32,
+        mime_type: &str,
+        category: &str,
+        description: Option<String>,
+    ) -> Result<allowed_mimes::Model, AppError> {
+        let existing = AllowedMimes::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("MIME type not found".to_string()))?;
+        let mime_type = normalize_mime(mime_type)?;
+        let category = normalize_category(category)?;
+        Self::ensure_mime_free(db, &mime_type, Some(id)).await?;
+        let mut active = existing.into_active_model();
+        active.mime_type = Set(mime_type);
+        active.category = Set(category);
+        active.description = Set(normalize_description(description));
+        Ok(active.update(db).await?)
+    }
+
+    pub async fn delete_mime(db: &DatabaseConnection, id: i32) -> Result<(), AppError> {
+        let res = AllowedMimes::delete_by_id(id).exec(db).await?;
+        if res.rows_affected == 0 {
+            return Err(AppError::NotFound("MIME type not found".to_string()));
+        }
+        Ok(())
+    }
+
+    pub async fn list_signatures(
+        db: &DatabaseConnection,
+    ) -> Result<Vec<magic_signatures::Model>, AppError> {
+        Ok(MagicSignatures::find()
+            .order_by_asc(magic_signatures::Column::MimeType)
+            .order_by_asc(magic_signatures::Column::Id)
+            .all(db)
+            .await?)
+    }
+
+    pub async fn create_signature(
+        db: &DatabaseConnection,
+        signature: &str,
+        mime_type: &str,
+        description: Option<String>,
+    ) -> Result<magic_signatures::Model, AppError> {
+        let signature = parse_signature(signature)?;
+        let mime_type = normalize_mime(mime_type)?;
+        Self::ensure_signature_free(db, &signature, &mime_type, None).await?;
+        Ok(magic_signatures::ActiveModel {
+            signature: Set(signature),
+            mime_type: Set(mime_type),
+            description: Set(normalize_description(description)),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?)
+    }
+
+    pub async fn update_signature(
+        db: &DatabaseConnection,
+        id: i32,
+        signature: &str,
+        mime_type: &str,
+        description: Option<String>,
+    ) -> Result<magic_signatures::Model, AppError> {
+        let existing = MagicSignatures::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Magic signature not found".to_string()))?;
+        let signature = parse_signature(signature)?;
+        let mime_type = normalize_mime(mime_type)?;
+        Self::ensure_signature_free(db, &signature, &mime_type, Some(id)).await?;
+        let mut active = existing.into_active_model();
+        active.signature = Set(signature);
+        active.mime_type = Set(mime_type);
+        active.description = Set(normalize_description(description));
+        Ok(active.update(db).await?)
+    }
+
+    pub async fn delete_signature(db: &DatabaseConnection, id: i32) -> Result<(), AppError> {
+        let res = MagicSignatures::delete_by_id(id).exec(db).await?;
+        if res.rows_affected == 0 {
+            return Err(AppError::NotFound("Magic signature not found".to_string()));
+        }
+        Ok(())
+    }
+
+    pub async fn list_extensions(
+        db: &DatabaseConnection,
+    ) -> Result<Vec<blocked_extensions::Model>, AppError> {
+        Ok(BlockedExtensions::find()
+            .order_by_asc(blocked_extensions::Column::Extension)
+            .all(db)
+            .await?)
+    }
+
+    pub async fn create_extension(
+        db: &DatabaseConnection,
+        extension: &str,
+        description: Option<String>,
+    ) -> Result<blocked_extensions::Model, AppError> {
+        let extension = normalize_extension(extension)?;
+        Self::ensure_extension_free(db, &extension, None).await?;
+        Ok(blocked_extensions::ActiveModel {
+            extension: Set(extension),
+            description: Set(normalize_description(description)),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?)
+    }
+
+    pub async fn update_extension(
+        db: &DatabaseConnection,
+        id: i32,
+        extension: &str,
+        description: Option<String>,
+    ) -> Result<blocked_extensions::Model, AppError> {
+        let existing = BlockedExtensions::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Blocked extension not found".to_string()))?;
+        let extension = normalize_extension(extension)?;
+        Self::ensure_extension_free(db, &extension, Some(id)).await?;
+        let mut active = existing.into_active_model();
+        active.extension = Set(extension);
+        active.description = Set(normalize_description(description));
+        Ok(active.update(db).await?)
+    }
+
+    pub async fn delete_extension(db: &DatabaseConnection, id: i32) -> Result<(), AppError> {
+        let res = BlockedExtensions::delete_by_id(id).exec(db).await?;
+        if res.rows_affected == 0 {
+            return Err(AppError::NotFound(
+                "Blocked extension not found".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn ensure_mime_free(
+        db: &DatabaseConnection,
+        mime_type: &str,
+        except: Option<i32>,
+    ) -> Result<(), AppError> {
+        let mut query = AllowedMimes::find().filter(allowed_mimes::Column::MimeType.eq(mime_type));
+        if let Some(id) = except {
+            query = query.filter(allowed_mimes::Column::Id.ne(id));
+        }
+        if query.one(db).await?.is_some() {
+            return Err(AppError::Conflict(format!(
+                "MIME type '{}' is already allowed",
+                mime_type
+            )));
+        }
+        Ok(())
+    }
+
+    async fn ensure_signature_free(
+        db: &DatabaseConnection,
+        signature: &[u8],
+        mime_type: &str,
+        except: Option<i32>,
+    ) -> Result<(), AppError> {
+        let mut query = MagicSignatures::find()
+            .filter(magic_signatures::Column::Signature.eq(signature.to_vec()))
+            .filter(magic_signatures::Column::MimeType.eq(mime_type));
+        if let Some(id) = except {
+            query = query.filter(magic_signatures::Column::Id.ne(id));
+        }
+        if query.one(db).await?.is_some() {
+            return Err(AppError::Conflict(format!(
+                "Signature {} is already registered for '{}'",
+                hex::encode_upper(signature),
+                mime_type
+            )));
+        }
+        Ok(())
+    }
+
+    async fn ensure_extension_free(
+        db: &DatabaseConnection,
+        extension: &str,
+        except: Option<i32>,
+    ) -> Result<(), AppError> {
+        let mut query =
+            BlockedExtensions::find().filter(blocked_extensions::Column::Extension.eq(extension));
+        if let Some(id) = except {
+            query = query.filter(blocked_extensions::Column::Id.ne(id));
+        }
+        if query.one(db).await?.is_some() {
+            return Err(AppError::Conflict(format!(
+                "Extension '.{}' is already blocked",
+                extension
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A bare `type/subtype` in lowercase
+fn normalize_mime(mime_type: &str) -> Result<String, AppError> {
+    let mime_type = mime_type.trim().to_lowercase();
+    let valid = mime_type.len() <= 255
+        && matches!(mime_type.split_once('/'), Some((t, s)) if !t.is_empty() && !s.is_empty())
+        && mime_type
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'/' | b'.' | b'+' | b'-' | b'_'))
+        && mime_type.matches('/').count() == 1;
+    if !valid {
+        return Err(AppError::BadRequest(format!(
+            "'{}' is not a MIME type like 'application/pdf'",
+            mime_type
+        )));
+    }
+    Ok(mime_type)
+}
+
+fn normalize_category(category: &str) -> Result<String, AppError> {
+    let category = category.trim();
+    if category.is_empty() || category.len() > 100 {
+        return Err(AppError::BadRequest(
+            "category must be 1 to 100 characters".to_string(),
+        ));
+    }
+    Ok(category.to_string())
+}
+
+/// Lowercase, without the leading dot
+fn normalize_extension(extension: &str) -> Result<String, AppError> {
+    let extension = extension.trim().trim_start_matches('.').to_lowercase();
+    let valid = !extension.is_empty()
+        && extension.len() <= 32
+        && extension
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_'));
+    if !valid {
+        return Err(AppError::BadRequest(format!(
+            "'{}' is not a file extension like 'exe'",
+            extension
+        )));
+    }
+    Ok(extension)
+}
+
+/// Hex bytes, e.g. `25504446` or `25 50 44 46`
+fn parse_signature(signature: &str) -> Result<Vec<u8>, AppError> {
+    let compact: String = signature.split_whitespace().collect();
+    let bytes = hex::decode(&compact).map_err(|_| {
+        AppError::BadRequest("signature must be hex bytes, e.g. '25504446'".to_string())
+    })?;
+    if bytes.is_empty() || bytes.len() > MAX_SIGNATURE_LEN {
+        return Err(AppError::BadRequest(format!(
+            "signature must be 1 to {} bytes",
+            MAX_SIGNATURE_LEN
+        )));
+    }
+    Ok(bytes)
+}
+
+fn normalize_description(description: Option<String>) -> Option<String> {
+    description
+        .map(|d| d.trim().to_string())
+        .filter(|d| !d.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_rule_values() {
+        assert_eq!(normalize_mime(" Image/WebP ").unwrap(), "image/webp");
+        assert_eq!(
+            normalize_mime("application/vnd.ms-excel").unwrap(),
+            "application/vnd.ms-excel"
+        );
+        assert!(normalize_mime("text").is_err());
+        assert!(normalize_mime("text/plain; charset=utf-8").is_err());
+        assert!(normalize_mime("a/b/c").is_err());
+
+        assert_eq!(normalize_extension(".EXE").unwrap(), "exe");
+        assert!(normalize_extension("..").is_err());
+        assert!(normalize_extension("ex e").is_err());
+
+        assert_eq!(
+            parse_signature("25 50 44 46").unwrap(),
+            vec![0x25, 0x50, 0x44, 0x46]
+        );
+        assert!(parse_signature("2550zz").is_err());
+        assert!(parse_signature("").is_err());
+    }
+}
@@ -45,6 +45,7 @@ impl BackgroundWorker {
         let mut scan_interval = tokio::time::interval(Duration::from_secs(10));
         let mut facts_interval = tokio::time::interval(Duration::from_secs(60));
         let mut cleanup_interval = tokio::time::interval(Duration::from_secs(60));
+        let mut backfill_interval = tokio::time::interval(Duration::from_secs(60));
         let mut shutdown_rx = self.shutdown.clone();
 
         loop {
@@ -62,6 +63,9 @@ impl BackgroundWorker {
                 _ = cleanup_interval.tick() => {
                     self.perform_cleanup().await;
                 }
+                _ = backfill_interval.tick() => {
+                    self.perform_hash_backfill().await;
+                }
             }
         }
     }
@@ -71,6 +75,15 @@ impl BackgroundWorker {
         let _ = FactsService::update_all_users(&self.db).await;
     }
 
+    async fn perform_hash_backfill(&self) {
+        use crate::services::hash_backfill::HashBackfillService;
+        match HashBackfillService::backfill_batch(&self.db, self.storage.as_ref(), 50).await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("🔐 Backfilled SHA-256 for {} storage files", n),
+            Err(e) => tracing::error!("SHA-256 backfill failed: {}", e),
+        }
+    }
+
     async fn perform_virus_scans(&self) {
         // Safe for scaling: Mark files as 'scanning' before processing to avoid duplicate work
         let mut results = Vec::new();
@@ -170,11 +183,12 @@ impl BackgroundWorker {
         if let Ok(files) = expired_files {
             for file in files {
                 tracing::info!("Expiring file: {}", file.id);
-                if let Err(e) = crate::services::storage_lifecycle::StorageLifecycleService::soft_delete_user_file(
-                    &self.db,
-                    self.storage.as_ref(),
-                    &file,
-                ).await {
+                if let Err(e) =
+                    crate::services::storage_lifecycle::StorageLifecycleService::trash_user_file(
+                        &self.db, &file,
+                    )
+                    .await
+                {
                     tracing::error!("Failed to expire file {}: {}", file.id, e);
                 }
             }
@@ -200,13 +214,64 @@ impl BackgroundWorker {
             }
         }
 
-        // 3. Clean up expired tokens
-        let _ = Tokens::delete_many()
-            .filter(tokens::Column::ExpiresAt.lt(Utc::now()))
-            .exec(&self.db)
-            .await;
+        // 3. Purge items that outlived the trash retention period
+        match crate::services::storage_lifecycle::StorageLifecycleService::purge_expired_trash(
+            &self.db,
+            self.storage.as_ref(),
+            self.config.trash_retention_days,
+        )
+        .await
+        {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("🗑️ Purged {} items from trash", n),
+            Err(e) => tracing::error!("Failed to purge trash: {}", e),
+        }
+
+        // 4. Clean up expired sessions, revoked access tokens and email links
+        if let Err(e) = crate::services::sessions::SessionService::sweep(&self.db).await {
+            tracing::error!("Failed to sweep sessions: {}", e);
+        }
+        if let Err(e) = crate::services::account_email::AccountEmailService::sweep(&self.db).await {
+            tracing::error!("Failed to sweep email tokens: {}", e);
+        }
+
+        // 5. Drop rate limit events that left their window
+        if let Err(e) = crate::services::rate_limiter::RateLimitService::sweep(
+            &self.db,
+            chrono::Duration::hours(1),
+        )
+        .await
+        {
+            tracing::error!("Failed to sweep rate limit events: {}", e);
+        }
+
+        // 6. Drop download tickets that expired or were used up
+        match crate::services::download_tickets::DownloadTicketService::sweep(&self.db).await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("🗑️ Removed {} stale download tickets", n),
+            Err(e) => tracing::error!("Failed to remove stale download tickets: {}", e),
+        }
+
+        // 7. Drop share password counters that went stale
+        match crate::services::share_lockout::ShareLockoutService::sweep(&self.db).await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("🗑️ Removed {} stale share password counters", n),
+            Err(e) => tracing::error!("Failed to remove stale share password counters: {}", e),
+        }
+
+        // 8. Abort upload sessions that were never finished
+        match crate::services::upload_service::UploadService::purge_expired_sessions(
+            &self.db,
+            self.storage.as_ref(),
+        )
+        .await
+        {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("🗑️ Removed {} expired upload sessions", n),
+            Err(e) => tracing::error!("Failed to remove expired upload sessions: {}", e),
+        }
 
-        // 4. Clean up abandoned staging files
+        // 9. Clean up abandoned staging files
         match self.storage.list_objects("staging/").await {
             Ok(staged_files) => {
                 for key in staged_files {
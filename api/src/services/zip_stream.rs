@@ -0,0 +1,465 @@
+use crate::api::error::AppError;
+use crate::entities::{prelude::*, *};
+use crate::services::storage::StorageService;
+use anyhow::{Result, anyhow};
+use aws_sdk_s3::primitives::ByteStream;
+use bytes::Bytes;
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use futures::{SinkExt, Stream, channel::mpsc};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// One file or folder in a streamed archive
+pub struct ArchiveEntry {
+    /// Path inside the archive; folders end in `/`
+    pub path: String,
+    /// Object to stream, `None` for folders
+    pub s3_key: Option<String>,
+    pub size: i64,
+    pub modified: DateTime<Utc>,
+}
+
+/// Streams ZIP archives of user files straight from storage
+///
+/// Entries are stored uncompressed with their CRC and sizes in a trailing
+/// data descriptor, so each object is read once and never buffered whole.
+/// Only the central directory is kept in memory until the end.
+pub struct ZipStreamService;
+
+impl ZipStreamService {
+    /// Walk `roots` and their live descendants into archive entries
+    ///
+    /// Infected, expired and content-less files are skipped. Names that
+    /// collide within a folder get a ` (n)` suffix.
+    pub async fn collect(
+        db: &DatabaseConnection,
+        roots: Vec<(user_files::Model, Option<storage_files::Model>)>,
+    ) -> Result<Vec<ArchiveEntry>, AppError> {
+        let mut entries = Vec::new();
+        let mut taken = HashSet::new();
+        // Depth-first, reversed so items come out in their listed order
+        let mut stack: Vec<_> = roots
+            .into_iter()
+            .rev()
+            .map(|(file, storage)| (String::new(), file, storage))
+            .collect();
+
+        while let Some((prefix, file, storage)) = stack.pop() {
+            let modified = file.created_at.unwrap_or_else(Utc::now);
+            if file.is_folder {
+                let path = format!("{}/", unique_path(&mut taken, &prefix, &file.filename));
+                let children = UserFiles::find()
+                    .filter(user_files::Column::ParentId.eq(&file.id))
+                    .filter(user_files::Column::DeletedAt.is_null())
+                    .order_by_desc(user_files::Column::Filename)
+                    .find_also_related(StorageFiles)
+                    .all(db)
+                    .await?;
+                stack.extend(
+                    children
+                        .into_iter()
+                        .map(|(child, storage)| (path.clone(), child, storage)),
+                );
+                entries.push(ArchiveEntry {
+                    path,
+                    s3_key: None,
+                    size: 0,
+                    modified,
+                });
+                continue;
+            }
+
+            let Some(storage) = storage else {
+                continue;
+            };
+            if matches!(storage.scan_status.as_deref(), Some("infected")) {
+                tracing::warn!("Skipping infected file {} in archive", file.id);
+                continue;
+            }
+            if file.expires_at.is_some_and(|expires| Utc::now() > expires) {
+                continue;
+            }
+            entries.push(ArchiveEntry {
+                path: unique_path(&mut taken, &prefix, &file.filename),
+                s3_key: Some(storage.s3_key),
+                size: storage.size,
+                modified,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    /// Stream `entries` as a ZIP archive
+    ///
+    /// The archive is written by a background task as the client reads it. A
+    /// storage error mid-way ends the stream with an error, which aborts the
+    /// response rather than delivering a silently truncated archive.
+    pub fn stream(
+        storage: Arc<dyn StorageService>,
+        entries: Vec<ArchiveEntry>,
+    ) -> impl Stream<Item = std::io::Result<Bytes>> + Send + 'static {
+        let (tx, rx) = mpsc::channel(4);
+        tokio::spawn(async move {
+            let mut writer = ZipWriter::new(tx);
+            if let Err(e) = writer.write_archive(storage.as_ref(), entries).await {
+                tracing::error!("ZIP stream failed: {}", e);
+                let _ = writer.tx.send(Err(std::io::Error::other(e))).await;
+            }
+        });
+        rx
+    }
+}
+
+/// `prefix` + `name`, made safe for extraction and unique (case-insensitively) in `taken`
+fn unique_path(taken: &mut HashSet<String>, prefix: &str, name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c == '/' || c == '\\' { '_' } else { c })
+        .collect();
+    let name = match name.as_str() {
+        "" | "." | ".." => "_".to_string(),
+        _ => name,
+    };
+
+    let (stem, ext) = match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name.as_str(), ""),
+    };
+    let mut candidate = format!("{}{}", prefix, name);
+    let mut n = 1;
+    while !taken.insert(candidate.to_lowercase()) {
+        candidate = format!("{}{} ({}){}", prefix, stem, n, ext);
+        n += 1;
+    }
+    candidate
+}
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR: u32 = 0x0807_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const ZIP64_END: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR: u32 = 0x0706_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+
+/// Bit 3: CRC and sizes follow the data; bit 11: names are UTF-8
+const FLAG_DATA_DESCRIPTOR: u16 = 0x0008;
+const FLAG_UTF8: u16 = 0x0800;
+/// Made by Unix, spec version 4.5 (ZIP64)
+const VERSION_MADE_BY: u16 = (3 << 8) | 45;
+const U32_MAX: u64 = 0xFFFF_FFFF;
+
+struct ZipWriter {
+    tx: mpsc::Sender<std::io::Result<Bytes>>,
+    offset: u64,
+    central: Vec<u8>,
+    count: u64,
+}
+
+impl ZipWriter {
+    fn new(tx: mpsc::Sender<std::io::Result<Bytes>>) -> Self {
+        Self {
+            tx,
+            offset: 0,
+            central: Vec::new(),
+            count: 0,
+        }
+    }
+
+    async fn emit(&mut self, data: Bytes) -> Result<()> {
+        self.offset += data.len() as u64;
+        self.tx
+            .send(Ok(data))
+            .await
+            .map_err(|_| anyhow!("client disconnected"))
+    }
+
+    async fn write_archive(
+        &mut self,
+        storage: &dyn StorageService,
+        entries: Vec<ArchiveEntry>,
+    ) -> Result<()> {
+        for entry in entries {
+            match &entry.s3_key {
+                Some(key) => {
+                    let body = storage.get_object_stream(key).await?.body;
+                    self.write_file(&entry, body).await?;
+                }
+                None => self.write_folder(&entry).await?,
+            }
+        }
+        self.finish().await
+    }
+
+    async fn write_folder(&mut self, entry: &ArchiveEntry) -> Result<()> {
+        let header_offset = self.offset;
+        let (time, date) = dos_datetime(entry.modified);
+        let name = entry.path.as_bytes();
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        put_u32(&mut header, LOCAL_HEADER);
+        put_u16(&mut header, 20);
+        put_u16(&mut header, FLAG_UTF8);
+        put_u16(&mut header, 0); // stored
+        put_u16(&mut header, time);
+        put_u16(&mut header, date);
+        put_u32(&mut header, 0); // crc
+        put_u32(&mut header, 0); // compressed size
+        put_u32(&mut header, 0); // uncompressed size
+        put_u16(&mut header, name.len() as u16);
+        put_u16(&mut header, 0);
+        header.extend_from_slice(name);
+        self.emit(header.into()).await?;
+
+        self.add_central(
+            entry,
+            FLAG_UTF8,
+            0,
+            0,
+            false,
+            header_offset,
+            0o040755 << 16 | 0x10,
+        );
+        Ok(())
+    }
+
+    async fn write_file(&mut self, entry: &ArchiveEntry, mut body: ByteStream) -> Result<()> {
+        let header_offset = self.offset;
+        let zip64 = entry.size as u64 >= U32_MAX;
+        let flags = FLAG_DATA_DESCRIPTOR | FLAG_UTF8;
+        let (time, date) = dos_datetime(entry.modified);
+        let name = entry.path.as_bytes();
+
+        let mut header = Vec::with_capacity(50 + name.len());
+        put_u32(&mut header, LOCAL_HEADER);
+        put_u16(&mut header, if zip64 { 45 } else { 20 });
+        put_u16(&mut header, flags);
+        put_u16(&mut header, 0); // stored
+        put_u16(&mut header, time);
+        put_u16(&mut header, date);
+        put_u32(&mut header, 0); // crc, in the data descriptor
+        let size_placeholder = if zip64 { U32_MAX as u32 } else { 0 };
+        put_u32(&mut header, size_placeholder);
+        put_u32(&mut header, size_placeholder);
+        put_u16(&mut header, name.len() as u16);
+        put_u16(&mut header, if zip64 { 20 } else { 0 });
+        header.extend_from_slice(name);
+        if zip64 {
+            put_u16(&mut header, 0x0001);
+            put_u16(&mut header, 16);
+            put_u64(&mut header, 0);
+            put_u64(&mut header, 0);
+        }
+        self.emit(header.into()).await?;
+
+        let mut crc = crc32fast::Hasher::new();
+        let mut size: u64 = 0;
+        while let Some(chunk) = body.try_next().await? {
+            crc.update(&chunk);
+            size += chunk.len() as u64;
+            self.emit(chunk).await?;
+        }
+        if size >= U32_MAX && !zip64 {
+            return Err(anyhow!("{} is larger than recorded", entry.path));
+        }
+        let crc = crc.finalize();
+
+        let mut descriptor = Vec::with_capacity(24);
+        put_u32(&mut descriptor, DATA_DESCRIPTOR);
+        put_u32(&mut descriptor, crc);
+        if zip64 {
+            put_u64(&mut descriptor, size);
+            put_u64(&mut descriptor, size);
+        } else {
+            put_u32(&mut descriptor, size as u32);
+            put_u32(&mut descriptor, size as u32);
+        }
+        self.emit(descriptor.into()).await?;
+
+        self.add_central(
+            entry,
+            flags,
+            crc,
+            size,
+            zip64,
+            header_offset,
+            0o100644 << 16,
+        );
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn add_central(
+        &mut self,
+        entry: &ArchiveEntry,
+        flags: u16,
+        crc: u32,
+        size: u64,
+        zip64: bool,
+        header_offset: u64,
+        external_attributes: u32,
+    ) {
+        let (time, date) = dos_datetime(entry.modified);
+        let name = entry.path.as_bytes();
+
+        let mut extra = Vec::new();
+        if zip64 {
+            put_u64(&mut extra, size);
+            put_u64(&mut extra, size);
+        }
+        if header_offset >= U32_MAX {
+            put_u64(&mut extra, header_offset);
+        }
+
+        let cd = &mut self.central;
+        put_u32(cd, CENTRAL_HEADER);
+        put_u16(cd, VERSION_MADE_BY);
+        put_u16(cd, if extra.is_empty() { 20 } else { 45 });
+        put_u16(cd, flags);
+        put_u16(cd, 0); // stored
+        put_u16(cd, time);
+        put_u16(cd, date);
+        put_u32(cd, crc);
+        let size32 = if zip64 { U32_MAX as u32 } else { size as u32 };
+        put_u32(cd, size32);
+        put_u32(cd, size32);
+        put_u16(cd, name.len() as u16);
+        put_u16(
+            cd,
+            if extra.is_empty() {
+                0
+            } else {
+                4 + extra.len() as u16
+            },
+        );
+        put_u16(cd, 0); // comment
+        put_u16(cd, 0); // disk
+        put_u16(cd, 0); // internal attributes
+        put_u32(cd, external_attributes);
+        put_u32(cd, header_offset.min(U32_MAX) as u32);
+        cd.extend_from_slice(name);
+        if !extra.is_empty() {
+            put_u16(cd, 0x0001);
+            put_u16(cd, extra.len() as u16);
+            cd.extend_from_slice(&extra);
+        }
+        self.count += 1;
+    }
+
+    async fn finish(&mut self) -> Result<()> {
+        let cd_offset = self.offset;
+        let cd_size = self.central.len() as u64;
+        let central = std::mem::take(&mut self.central);
+        self.emit(central.into()).await?;
+
+        let mut end = Vec::with_capacity(98);
+        let zip64 = self.count >= 0xFFFF || cd_offset >= U32_MAX || cd_size >= U32_MAX;
+        if zip64 {
+            let zip64_end_offset = self.offset;
+            put_u32(&mut end, ZIP64_END);
+            put_u64(&mut end, 44);
+            put_u16(&mut end, VERSION_MADE_BY);
+            put_u16(&mut end, 45);
+            put_u32(&mut end, 0);
+            put_u32(&mut end, 0);
+            put_u64(&mut end, self.count);
+            put_u64(&mut end, self.count);
+            put_u64(&mut end, cd_size);
+            put_u64(&mut end, cd_offset);
+
+            put_u32(&mut end, ZIP64_LOCATOR);
+            put_u32(&mut end, 0);
+            put_u64(&mut end, zip64_end_offset);
+            put_u32(&mut end, 1);
+        }
+        put_u32(&mut end, END_OF_CENTRAL_DIRECTORY);
+        put_u16(&mut end, 0);
+        put_u16(&mut end, 0);
+        let count16 = self.count.min(0xFFFF) as u16;
+        put_u16(&mut end, count16);
+        put_u16(&mut end, count16);
+        put_u32(&mut end, cd_size.min(U32_MAX) as u32);
+        put_u32(&mut end, cd_offset.min(U32_MAX) as u32);
+        put_u16(&mut end, 0); // comment
+        self.emit(end.into()).await
+    }
+}
+
+/// MS-DOS time and date; the format cannot represent anything before 1980
+fn dos_datetime(at: DateTime<Utc>) -> (u16, u16) {
+    if at.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let time = (at.hour() << 11) | (at.minute() << 5) | (at.second() / 2);
+    let date = (((at.year() - 1980) as u32).min(127) << 9) | (at.month() << 5) | at.day();
+    (time as u16, date as u16)
+}
+
+fn put_u16(buf: &mut Vec<u8>, v: u16) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put_u64(buf: &mut Vec<u8>, v: u64) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::io::Read;
+
+    #[test]
+    fn test_unique_path() {
+        let mut taken = HashSet::new();
+        assert_eq!(unique_path(&mut taken, "", "a.txt"), "a.txt");
+        assert_eq!(unique_path(&mut taken, "", "A.txt"), "A (1).txt");
+        assert_eq!(unique_path(&mut taken, "", "a.txt"), "a (2).txt");
+        assert_eq!(unique_path(&mut taken, "docs/", "a.txt"), "docs/a.txt");
+        assert_eq!(unique_path(&mut taken, "", ".."), "_");
+        assert_eq!(unique_path(&mut taken, "", "x/y"), "x_y");
+        assert_eq!(unique_path(&mut taken, "", ".env"), ".env");
+        assert_eq!(unique_path(&mut taken, "", ".env"), ".env (1)");
+    }
+
+    #[tokio::test]
+    async fn test_archive_is_readable() {
+        let storage = Arc::new(crate::services::memory_storage::InMemoryStorageService::new());
+        storage
+            .upload_file("objects/a", b"hello zip".to_vec())
+            .await
+            .unwrap();
+        let entries = vec![
+            ArchiveEntry {
+                path: "docs/".to_string(),
+                s3_key: None,
+                size: 0,
+                modified: Utc::now(),
+            },
+            ArchiveEntry {
+                path: "docs/héllo.txt".to_string(),
+                s3_key: Some("objects/a".to_string()),
+                size: 9,
+                modified: Utc::now(),
+            },
+        ];
+
+        let bytes: Vec<u8> = ZipStreamService::stream(storage, entries)
+            .map(|chunk| chunk.unwrap().to_vec())
+            .concat()
+            .await;
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.len(), 2);
+        assert!(archive.by_index(0).unwrap().is_dir());
+        let mut file = archive.by_name("docs/héllo.txt").unwrap();
+        let mut content = String::new();
+        file.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "hello zip");
+    }
+}
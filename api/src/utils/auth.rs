@@ -9,11 +9,144 @@ pub struct Claims {
     pub sub: String, // user_id
     pub exp: usize,
     pub jti: String,
+    /// Session the token was issued for; absent for tokens from outside providers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    /// What a personal access token may do; absent for sign-in sessions,
+    /// which may do everything
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<Scope>>,
 }
 
-pub fn create_jwt(user_id: &str, secret: &str) -> Result<String> {
+impl Claims {
+    /// Whether the token grants `scope`
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes
+            .as_ref()
+            .is_none_or(|scopes| scopes.contains(&scope))
+    }
+}
+
+/// Permission a personal access token can be given
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+pub enum Scope {
+    #[serde(rename = "files:read")]
+    FilesRead,
+    #[serde(rename = "files:write")]
+    FilesWrite,
+    #[serde(rename = "shares:manage")]
+    SharesManage,
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::FilesRead => "files:read",
+            Scope::FilesWrite => "files:write",
+            Scope::SharesManage => "shares:manage",
+            Scope::Admin => "admin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "files:read" => Some(Scope::FilesRead),
+            "files:write" => Some(Scope::FilesWrite),
+            "shares:manage" => Some(Scope::SharesManage),
+            "admin" => Some(Scope::Admin),
+            _ => None,
+        }
+    }
+
+    /// Scope an authenticated route needs; `None` keeps it to sign-in sessions
+    ///
+    /// Account routes (sessions, tokens, profile changes) are never open to
+    /// personal access tokens, so a token cannot widen its own access.
+    pub fn required_for(method: &axum::http::Method, path: &str) -> Option<Self> {
+        use axum::http::Method;
+
+        let reading = matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS);
+        if path.starts_with("/auth/") {
+            return None;
+        }
+        if path.starts_with("/admin/") {
+            return Some(Scope::Admin);
+        }
+        if path.starts_with("/shares") && !path.starts_with("/shares/incoming") {
+            return Some(Scope::SharesManage);
+        }
+        if !reading && (path.starts_with("/users/me") || path == "/settings") {
+            return None;
+        }
+        // Reads sent as POST, and revoking a download ticket
+        let read_only = (path.starts_with("/files/") && path.ends_with("/ticket"))
+            || path == "/files/bulk-download"
+            || path.starts_with("/tickets/");
+        if reading || read_only {
+            Some(Scope::FilesRead)
+        } else {
+            Some(Scope::FilesWrite)
+        }
+    }
+}
+
+/// What a user may do beyond their own files, stored in `users.role`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    /// Full access to the `/admin` API
+    Admin,
+    /// Read-only access to the `/admin` API
+    Auditor,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Admin => "admin",
+            Role::Auditor => "auditor",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "user" => Some(Role::User),
+            "admin" => Some(Role::Admin),
+            "auditor" => Some(Role::Auditor),
+            _ => None,
+        }
+    }
+
+    /// Role stored for `user`; unknown values get no extra rights
+    pub fn of(user: &crate::entities::users::Model) -> Self {
+        Self::parse(&user.role).unwrap_or(Role::User)
+    }
+
+    /// Whether this role may call `method` on the `/admin` API
+    pub fn can_administer(&self, method: &axum::http::Method) -> bool {
+        use axum::http::Method;
+
+        match self {
+            Role::Admin => true,
+            Role::Auditor => matches!(*method, Method::GET | Method::HEAD),
+            Role::User => false,
+        }
+    }
+}
+
+/// Sign an access token for `user_id` that expires after `ttl`
+pub fn create_jwt(
+    user_id: &str,
+    session_id: Option<&str>,
+    ttl: Duration,
+    secret: &str,
+) -> Result<String> {
     let expiration = Utc::now()
-        .checked_add_signed(Duration::hours(24))
+        .checked_add_signed(ttl)
         .expect("valid timestamp")
         .timestamp();
 
@@ -21,6 +154,8 @@ pub fn create_jwt(user_id: &str, secret: &str) -> Result<String> {
         sub: user_id.to_owned(),
         exp: expiration as usize,
         jti: uuid::Uuid::new_v4().to_string(),
+        sid: session_id.map(str::to_owned),
+        scopes: None,
     };
 
     let token = encode(
@@ -57,8 +192,81 @@ mod tests {
     fn test_jwt_cycle() {
         let secret = "test_secret";
         let user_id = "user_123";
-        let token = create_jwt(user_id, secret).unwrap();
+        let token = create_jwt(user_id, Some("session_1"), Duration::minutes(15), secret).unwrap();
         let claims = validate_jwt(&token, secret).unwrap();
         assert_eq!(claims.sub, user_id);
+        assert_eq!(claims.sid.as_deref(), Some("session_1"));
+    }
+
+    #[test]
+    fn test_required_scope() {
+        use axum::http::Method;
+        let scope = |method: Method, path: &str| Scope::required_for(&method, path);
+
+        assert_eq!(scope(Method::GET, "/files"), Some(Scope::FilesRead));
+        assert_eq!(
+            scope(Method::HEAD, "/files/tus/abc"),
+            Some(Scope::FilesRead)
+        );
+        assert_eq!(scope(Method::POST, "/upload"), Some(Scope::FilesWrite));
+        assert_eq!(scope(Method::DELETE, "/files/abc"), Some(Scope::FilesWrite));
+        assert_eq!(
+            scope(Method::POST, "/files/abc/ticket"),
+            Some(Scope::FilesRead)
+        );
+        assert_eq!(
+            scope(Method::POST, "/files/bulk-download"),
+            Some(Scope::FilesRead)
+        );
+        assert_eq!(scope(Method::POST, "/shares"), Some(Scope::SharesManage));
+        assert_eq!(
+            scope(Method::GET, "/shares/abc/logs"),
+            Some(Scope::SharesManage)
+        );
+        assert_eq!(
+            scope(Method::GET, "/shares/incoming/abc/list"),
+            Some(Scope::FilesRead)
+        );
+        assert_eq!(
+            scope(Method::POST, "/shares/incoming/abc/upload"),
+            Some(Scope::FilesWrite)
+        );
+        assert_eq!(
+            scope(Method::PUT, "/admin/users/abc/quota"),
+            Some(Scope::Admin)
+        );
+        assert_eq!(
+            scope(Method::GET, "/users/me/quota"),
+            Some(Scope::FilesRead)
+        );
+        assert_eq!(scope(Method::PUT, "/users/me"), None);
+        assert_eq!(scope(Method::GET, "/auth/tokens"), None);
+    }
+
+    #[test]
+    fn test_claims_allow() {
+        let mut claims = Claims {
+            sub: "user".to_string(),
+            exp: 0,
+            jti: "jti".to_string(),
+            sid: None,
+            scopes: None,
+        };
+        assert!(claims.allows(Scope::Admin));
+        claims.scopes = Some(vec![Scope::FilesRead]);
+        assert!(claims.allows(Scope::FilesRead));
+        assert!(!claims.allows(Scope::FilesWrite));
+    }
+
+    #[test]
+    fn test_role_permissions() {
+        use axum::http::Method;
+
+        assert!(Role::Admin.can_administer(&Method::DELETE));
+        assert!(Role::Auditor.can_administer(&Method::GET));
+        assert!(!Role::Auditor.can_administer(&Method::POST));
+        assert!(!Role::User.can_administer(&Method::GET));
+        assert_eq!(Role::parse("auditor"), Some(Role::Auditor));
+        assert_eq!(Role::parse("root"), None);
     }
 }
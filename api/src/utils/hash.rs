@@ -1,5 +1,6 @@
 use hex;
 use sha2::{Digest, Sha256};
+use xxhash_rust::xxh3::Xxh3;
 
 pub fn calculate_hash(data: &[u8]) -> String {
     let mut hasher = Sha256::new();
@@ -24,6 +25,63 @@ pub async fn calculate_hash_from_reader<R: tokio::io::AsyncRead + Unpin>(
     Ok(hex::encode(result))
 }
 
+/// Digests produced by [`ContentHasher`]
+pub struct ContentHashes {
+    /// 128-bit xxh3 (fast lookup key, not collision-resistant)
+    pub xxh3: String,
+    /// SHA-256 (authoritative deduplication key)
+    pub sha256: String,
+}
+
+/// Computes xxh3-128 and SHA-256 in a single pass over streamed content
+pub struct ContentHasher {
+    xxh3: Xxh3,
+    sha256: Sha256,
+}
+
+impl Default for ContentHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContentHasher {
+    pub fn new() -> Self {
+        Self {
+            xxh3: Xxh3::new(),
+            sha256: Sha256::new(),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.xxh3.update(data);
+        self.sha256.update(data);
+    }
+
+    pub fn finalize(self) -> ContentHashes {
+        ContentHashes {
+            xxh3: format!("{:032x}", self.xxh3.digest128()),
+            sha256: hex::encode(self.sha256.finalize()),
+        }
+    }
+}
+
+/// Hash an entire reader with [`ContentHasher`]
+pub async fn content_hashes_from_reader<R: tokio::io::AsyncRead + Unpin>(
+    mut reader: R,
+) -> anyhow::Result<ContentHashes> {
+    let mut hasher = ContentHasher::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let n = tokio::io::AsyncReadExt::read(&mut reader, &mut buffer).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher.finalize())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -49,6 +107,17 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_content_hashes_from_reader() {
+        let data = b"hello world";
+        let hashes = content_hashes_from_reader(&data[..]).await.unwrap();
+        assert_eq!(hashes.sha256, calculate_hash(data));
+        assert_eq!(
+            hashes.xxh3,
+            format!("{:032x}", xxhash_rust::xxh3::xxh3_128(data))
+        );
+    }
+
     #[test]
     fn test_calculate_hash_empty() {
         let data = b"";
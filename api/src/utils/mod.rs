@@ -2,4 +2,6 @@ pub mod auth;
 pub mod hash;
 
 pub mod keyed_mutex;
+pub mod net;
+pub mod totp;
 pub mod validation;
@@ -0,0 +1,101 @@
+use ipnet::IpNet;
+use std::net::IpAddr;
+
+/// Parse a CIDR range such as `10.0.0.0/8`; a bare address is a single-host range
+pub fn parse_network(value: &str) -> Option<IpNet> {
+    let value = value.trim();
+    value
+        .parse::<IpNet>()
+        .ok()
+        .or_else(|| value.parse::<IpAddr>().ok().map(IpNet::from))
+}
+
+/// Parse a comma-separated list of CIDR ranges, skipping invalid entries
+pub fn parse_networks(list: &str) -> Vec<IpNet> {
+    list.split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .filter_map(|entry| {
+            let network = parse_network(entry);
+            if network.is_none() {
+                tracing::warn!("Ignoring invalid network '{}'", entry.trim());
+            }
+            network
+        })
+        .collect()
+}
+
+/// The client behind a chain of trusted proxies
+///
+/// Requests from untrusted peers are taken at face value. Otherwise
+/// `X-Forwarded-For` is read from the right, skipping trusted proxies, and
+/// the first hop they did not add is the client.
+pub fn resolve_client_ip(
+    peer: IpAddr,
+    forwarded_for: Option<&str>,
+    trusted_proxies: &[IpNet],
+) -> IpAddr {
+    let trusted = |ip: &IpAddr| trusted_proxies.iter().any(|net| net.contains(ip));
+    if !trusted(&peer) {
+        return peer;
+    }
+
+    let mut client = peer;
+    for hop in forwarded_for.unwrap_or_default().rsplit(',') {
+        let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+            break;
+        };
+        client = ip;
+        if !trusted(&ip) {
+            break;
+        }
+    }
+    client
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_network() {
+        assert!(
+            parse_network("10.0.0.0/8")
+                .unwrap()
+                .contains(&"10.1.2.3".parse::<IpAddr>().unwrap())
+        );
+        assert_eq!(parse_network("192.0.2.7").unwrap().prefix_len(), 32);
+        assert_eq!(parse_network("2001:db8::1").unwrap().prefix_len(), 128);
+        assert!(parse_network("10.0.0.0/33").is_none());
+        assert!(parse_network("intranet").is_none());
+        assert_eq!(parse_networks("10.0.0.0/8, nope ,192.0.2.1").len(), 2);
+    }
+
+    #[test]
+    fn test_resolve_client_ip() {
+        let trusted = parse_networks("10.0.0.0/8");
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+
+        // Untrusted peers cannot claim another address
+        assert_eq!(
+            resolve_client_ip(ip("203.0.113.5"), Some("198.51.100.1"), &trusted),
+            ip("203.0.113.5")
+        );
+        // A spoofed entry to the left of the real client is ignored
+        assert_eq!(
+            resolve_client_ip(
+                ip("10.0.0.2"),
+                Some("198.51.100.1, 203.0.113.9, 10.0.0.3"),
+                &trusted
+            ),
+            ip("203.0.113.9")
+        );
+        assert_eq!(
+            resolve_client_ip(ip("10.0.0.2"), None, &trusted),
+            ip("10.0.0.2")
+        );
+        assert_eq!(
+            resolve_client_ip(ip("10.0.0.2"), Some("garbage"), &trusted),
+            ip("10.0.0.2")
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+/// Seconds each code is valid for
+pub const STEP_SECS: i64 = 30;
+/// Digits in a code
+pub const DIGITS: u32 = 6;
+/// Steps either side of the current one still accepted, for clock drift
+const SKEW_STEPS: i64 = 1;
+/// Bytes of entropy in a generated secret (160 bits, as RFC 4226 recommends)
+const SECRET_LEN: usize = 20;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// A new random secret, base32-encoded for authenticator apps
+pub fn generate_secret() -> String {
+    use rand::Rng;
+    let bytes: [u8; SECRET_LEN] = rand::thread_rng().r#gen();
+    base32_encode(&bytes)
+}
+
+/// The `otpauth://` URI authenticator apps read from a QR code
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &str) -> String {
+    use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+    let issuer = utf8_percent_encode(issuer, NON_ALPHANUMERIC).to_string();
+    let account = utf8_percent_encode(account, NON_ALPHANUMERIC).to_string();
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer, account, secret, issuer, DIGITS, STEP_SECS
+    )
+}
+
+/// The code for `secret` at `now`, as an authenticator app would show it
+pub fn generate(secret: &str, now: i64) -> Option<String> {
+    let key = base32_decode(secret)?;
+    Some(format!(
+        "{:0width$}",
+        hotp(&key, now.div_euclid(STEP_SECS) as u64),
+        width = DIGITS as usize
+    ))
+}
+
+/// The time step `code` belongs to, if it is valid for `secret` at `now`
+///
+/// Codes from steps up to `last_step` are refused so a code cannot be
+/// replayed once used.
+pub fn verify(secret: &str, code: &str, now: i64, last_step: Option<i64>) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let code: u32 = code.parse().ok()?;
+    let key = base32_decode(secret)?;
+    let current = now.div_euclid(STEP_SECS);
+    (current - SKEW_STEPS..=current + SKEW_STEPS)
+        .filter(|step| last_step.is_none_or(|last| *step > last))
+        .find(|step| hotp(&key, *step as u64) == code)
+}
+
+/// RFC 4226 HOTP value for `counter`
+fn hotp(key: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    value % 10u32.pow(DIGITS)
+}
+
+/// RFC 4648 base32 without padding
+fn base32_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+/// Decode base32, ignoring case, spaces and padding
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in encoded.bytes().filter(|c| !matches!(c, b' ' | b'=')) {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        // RFC 6238 appendix B, SHA-1, truncated to six digits
+        let key = b"12345678901234567890";
+        let secret = base32_encode(key);
+        for (time, expected) in [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+        ] {
+            assert_eq!(
+                verify(&secret, expected, time, None),
+                Some(time / STEP_SECS)
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_window_and_replay() {
+        let secret = generate_secret();
+        let key = base32_decode(&secret).unwrap();
+        let now = 1_700_000_000;
+        let step = now / STEP_SECS;
+        let code = |s: i64| format!("{:06}", hotp(&key, s as u64));
+
+        assert_eq!(verify(&secret, &code(step - 1), now, None), Some(step - 1));
+        assert_eq!(verify(&secret, &code(step + 1), now, None), Some(step + 1));
+        assert_eq!(verify(&secret, &code(step - 2), now, None), None);
+        assert_eq!(verify(&secret, &code(step), now, Some(step)), None);
+        assert_eq!(verify(&secret, "12345", now, None), None);
+        assert_eq!(verify(&secret, "abcdef", now, None), None);
+    }
+
+    #[test]
+    fn test_base32_roundtrip() {
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32_decode("mzxw6ytboi======").unwrap(), b"foobar");
+        assert!(base32_decode("not base32!").is_none());
+        let secret = generate_secret();
+        assert_eq!(secret.len(), 32);
+        assert_eq!(base32_decode(&secret).unwrap().len(), SECRET_LEN);
+    }
+}
@@ -2,11 +2,18 @@ use anyhow::{Result, anyhow};
 use sea_orm::EntityTrait;
 use serde::Serialize;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use utoipa::ToSchema;
 
 /// Maximum file size: 256 MB
 pub const MAX_FILE_SIZE: usize = 512 * 1024 * 1024; // 256 MB
 
+/// How long cached rules are used before being reloaded, so changes made
+/// through another instance are picked up
+const RULES_CACHE_TTL: Duration = Duration::from_secs(60);
+
 #[derive(Debug, Clone, Default, Serialize, ToSchema)]
 pub struct ValidationRules {
     pub allowed_mimes: Vec<String>,
@@ -42,6 +49,55 @@ impl ValidationRules {
     }
 }
 
+/// `ValidationRules` kept in memory instead of being queried per request
+///
+/// Changes made through this instance call `invalidate`; changes made
+/// elsewhere show up once the cached copy is older than `RULES_CACHE_TTL`.
+pub struct ValidationRulesCache {
+    db: sea_orm::DatabaseConnection,
+    max_file_size: usize,
+    chunk_size: usize,
+    cached: RwLock<Option<(Instant, Arc<ValidationRules>)>>,
+    /// Bumped by `invalidate` so a load that raced with a change is not cached
+    generation: AtomicU64,
+}
+
+impl ValidationRulesCache {
+    pub fn new(db: sea_orm::DatabaseConnection, max_file_size: usize, chunk_size: usize) -> Self {
+        Self {
+            db,
+            max_file_size,
+            chunk_size,
+            cached: RwLock::new(None),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    pub async fn get(&self) -> Result<Arc<ValidationRules>, sea_orm::DbErr> {
+        if let Some((loaded_at, rules)) = self.cached.read().unwrap().as_ref()
+            && loaded_at.elapsed() < RULES_CACHE_TTL
+        {
+            return Ok(rules.clone());
+        }
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let rules =
+            Arc::new(ValidationRules::load(&self.db, self.max_file_size, self.chunk_size).await?);
+        let mut cached = self.cached.write().unwrap();
+        if self.generation.load(Ordering::SeqCst) == generation {
+            *cached = Some((Instant::now(), rules.clone()));
+        }
+        Ok(rules)
+    }
+
+    /// Drop the cached rules so the next `get` reads the tables again
+    pub fn invalidate(&self) {
+        let mut cached = self.cached.write().unwrap();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        *cached = None;
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ValidationError {
     pub code: &'static str,
@@ -111,11 +167,10 @@ pub fn validate_mime_type(content_type: &str, rules: &ValidationRules) -> Result
 /// Sanitizes filename to prevent path traversal and injection attacks
 /// Returns the sanitized filename or an error if the name is invalid
 pub fn sanitize_filename(filename: &str, rules: &ValidationRules) -> Result<String> {
-    // Get only the filename component (remove any path)
-    let name = Path::new(filename)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("");
+    // Get only the filename component (remove any path). `Path` only splits
+    // on the host's separator, so Windows paths such as `C:\fakepath\a.pdf`
+    // would otherwise keep their directories on Linux.
+    let name = filename.rsplit(['/', '\\']).next().unwrap_or("");
 
     if name.is_empty() {
         return Err(anyhow!(ValidationError {
@@ -318,6 +373,62 @@ pub fn validate_upload(
     Ok(sanitized_filename)
 }
 
+/// Result of one stage of `validate_upload`; `error` is `None` when it passed
+#[derive(Debug, Clone)]
+pub struct CheckOutcome {
+    pub check: &'static str,
+    pub error: Option<ValidationError>,
+}
+
+/// Runs every stage of `validate_upload` without stopping at the first failure
+///
+/// Returns the sanitized filename, if the name passed, and the outcome of
+/// each stage in pipeline order. Nothing is stored.
+pub fn dry_run_upload(
+    filename: &str,
+    content_type: Option<&str>,
+    size: usize,
+    header: &[u8],
+    max_size: usize,
+    rules: &ValidationRules,
+) -> (Option<String>, Vec<CheckOutcome>) {
+    fn outcome(check: &'static str, result: Result<()>) -> CheckOutcome {
+        let error = result.err().map(|e| {
+            e.downcast::<ValidationError>()
+                .unwrap_or_else(|e| ValidationError {
+                    code: "INVALID",
+                    message: e.to_string(),
+                })
+        });
+        CheckOutcome { check, error }
+    }
+
+    let mime = content_type.unwrap_or("application/octet-stream");
+    let sanitized = sanitize_filename(filename, rules);
+    let (sanitized, filename_result) = match sanitized {
+        Ok(name) => (Some(name), Ok(())),
+        Err(e) => (None, Err(e)),
+    };
+
+    let checks = vec![
+        outcome("size", validate_file_size(size, max_size)),
+        outcome("filename", filename_result),
+        outcome("mime_type", validate_mime_type(mime, rules)),
+        outcome("magic_bytes", verify_magic_bytes(header, mime, rules)),
+        outcome("content", inspect_content_security(header, mime)),
+    ];
+    (sanitized, checks)
+}
+
+/// MIME type of the first magic signature `header` starts with
+pub fn detect_mime<'a>(header: &[u8], rules: &'a ValidationRules) -> Option<&'a str> {
+    rules
+        .magic_signatures
+        .iter()
+        .find(|(signature, _)| header.starts_with(signature))
+        .map(|(_, mime_type)| mime_type.as_str())
+}
+
 /// Calculate Shannon entropy to detect packed/encrypted content
 pub fn calculate_entropy(data: &[u8]) -> f64 {
     if data.is_empty() {
@@ -475,6 +586,15 @@ mod tests {
             sanitize_filename("..\\..\\windows\\system32", &rules).unwrap(),
             "system32"
         );
+        assert_eq!(
+            sanitize_filename("C:\\fakepath\\report.pdf", &rules).unwrap(),
+            "report.pdf"
+        );
+        assert_eq!(
+            sanitize_filename("docs/..\\report.pdf", &rules).unwrap(),
+            "report.pdf"
+        );
+        assert!(sanitize_filename("uploads\\", &rules).is_err());
 
         // Blocked extensions
         assert!(sanitize_filename("virus.exe", &rules).is_err());
@@ -554,4 +674,50 @@ mod tests {
         // This should trigger the suspicious entropy error
         assert!(inspect_content_security(&high_entropy, "text/plain").is_err());
     }
+
+    #[test]
+    fn test_dry_run_upload_reports_every_check() {
+        let rules = get_test_rules();
+        let failed = |checks: &[CheckOutcome]| {
+            checks
+                .iter()
+                .filter_map(|c| c.error.as_ref().map(|e| (c.check, e.code)))
+                .collect::<Vec<_>>()
+        };
+
+        let pdf = b"%PDF-1.7 report";
+        let (name, checks) = dry_run_upload(
+            "report.pdf",
+            Some("application/pdf"),
+            pdf.len(),
+            pdf,
+            MAX_FILE_SIZE,
+            &rules,
+        );
+        assert_eq!(name.as_deref(), Some("report.pdf"));
+        assert_eq!(checks.len(), 5);
+        assert!(failed(&checks).is_empty());
+        assert_eq!(detect_mime(pdf, &rules), Some("application/pdf"));
+
+        // A blocked extension does not hide the other failures
+        let elf = [0x7F, 0x45, 0x4C, 0x46, 0x02];
+        let (name, checks) = dry_run_upload(
+            "tool.exe",
+            Some("application/x-msdownload"),
+            elf.len(),
+            &elf,
+            MAX_FILE_SIZE,
+            &rules,
+        );
+        assert_eq!(name, None);
+        assert_eq!(
+            failed(&checks),
+            [
+                ("filename", "BLOCKED_EXTENSION"),
+                ("mime_type", "INVALID_MIME_TYPE"),
+                ("magic_bytes", "EXECUTABLE_CONTENT"),
+            ]
+        );
+        assert_eq!(detect_mime(&elf, &rules), None);
+    }
 }
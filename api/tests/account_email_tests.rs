@@ -0,0 +1,197 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{TestApp, json_body};
+use rust_file_backend::entities::{email_tokens, prelude::*};
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+use serde_json::json;
+
+/// Token from the link in the most recent email
+fn last_link_token(app: &TestApp) -> String {
+    let sent = app.mailer.sent();
+    let body = &sent.last().expect("no email sent").body;
+    body.split("?token=")
+        .nth(1)
+        .unwrap()
+        .split_whitespace()
+        .next()
+        .unwrap()
+        .to_string()
+}
+
+async fn set_email(app: &TestApp, jwt: &str, email: &str) {
+    let res = app
+        .send_json("PUT", "/users/me", Some(jwt), json!({ "email": email }))
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+async fn verify(app: &TestApp, token: &str) -> StatusCode {
+    app.post_json("/auth/verify-email", None, json!({ "token": token }))
+        .await
+        .status()
+}
+
+async fn email_verified(app: &TestApp, jwt: &str) -> bool {
+    json_body(app.get("/users/me", Some(jwt)).await).await["email_verified"]
+        .as_bool()
+        .unwrap()
+}
+
+/// Register alice with a verified address
+async fn verified_user(app: &TestApp) -> String {
+    let jwt = app.register("alice", "password123").await;
+    set_email(app, &jwt, "alice@example.com").await;
+    let res = app
+        .post_json("/users/me/email/verify", Some(&jwt), json!({}))
+        .await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    assert_eq!(
+        verify(app, &last_link_token(app)).await,
+        StatusCode::NO_CONTENT
+    );
+    jwt
+}
+
+async fn request_reset(app: &TestApp, email: &str) {
+    let res = app
+        .post_json("/auth/password-reset", None, json!({ "email": email }))
+        .await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    // Reset emails go out in the background
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+}
+
+#[tokio::test]
+async fn test_email_verification() {
+    let app = TestApp::new().await;
+    let jwt = app.register("alice", "password123").await;
+    let res = app
+        .post_json("/users/me/email/verify", Some(&jwt), json!({}))
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    set_email(&app, &jwt, "alice@example.com").await;
+    let res = app
+        .post_json("/users/me/email/verify", Some(&jwt), json!({}))
+        .await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    let sent = app.mailer.sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, "alice@example.com");
+    assert!(sent[0].body.contains("/verify-email?token="));
+    let token = last_link_token(&app);
+    assert!(!email_verified(&app, &jwt).await);
+
+    assert_eq!(verify(&app, "bogus").await, StatusCode::BAD_REQUEST);
+    assert_eq!(verify(&app, &token).await, StatusCode::NO_CONTENT);
+    assert_eq!(verify(&app, &token).await, StatusCode::BAD_REQUEST);
+    assert!(email_verified(&app, &jwt).await);
+    let res = app
+        .post_json("/users/me/email/verify", Some(&jwt), json!({}))
+        .await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    // A new address needs verifying again, and old links do not carry over
+    set_email(&app, &jwt, "alice@example.org").await;
+    assert!(!email_verified(&app, &jwt).await);
+    app.post_json("/users/me/email/verify", Some(&jwt), json!({}))
+        .await;
+    let pending = last_link_token(&app);
+    set_email(&app, &jwt, "alice@example.net").await;
+    assert_eq!(verify(&app, &pending).await, StatusCode::BAD_REQUEST);
+    assert!(!email_verified(&app, &jwt).await);
+}
+
+#[tokio::test]
+async fn test_password_reset() {
+    let app = TestApp::new().await;
+    let jwt = verified_user(&app).await;
+    let emails_before = app.mailer.sent().len();
+
+    // Unknown addresses get the same answer and no email
+    request_reset(&app, "nobody@example.com").await;
+    assert_eq!(app.mailer.sent().len(), emails_before);
+
+    request_reset(&app, "alice@example.com").await;
+    let sent = app.mailer.sent();
+    assert_eq!(sent.len(), emails_before + 1);
+    assert!(sent.last().unwrap().body.contains("/reset-password?token="));
+    let token = last_link_token(&app);
+
+    let confirm = |password: &'static str, token: String| {
+        let app = &app;
+        async move {
+            app.post_json(
+                "/auth/password-reset/confirm",
+                None,
+                json!({ "token": token, "password": password }),
+            )
+            .await
+            .status()
+        }
+    };
+    assert_eq!(
+        confirm("short", token.clone()).await,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        confirm("new-password-456", token.clone()).await,
+        StatusCode::NO_CONTENT
+    );
+    assert_eq!(
+        confirm("another-password", token).await,
+        StatusCode::BAD_REQUEST
+    );
+
+    // Existing sessions end and only the new password works
+    assert_eq!(
+        app.get("/users/me", Some(&jwt)).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        app.login("alice", "password123").await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        app.login("alice", "new-password-456").await.status(),
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn test_password_reset_needs_verified_email_and_is_rate_limited() {
+    let app = TestApp::new().await;
+    let bob = app.register("bob", "password123").await;
+    set_email(&app, &bob, "bob@example.com").await;
+    request_reset(&app, "bob@example.com").await;
+    assert!(app.mailer.sent().is_empty());
+
+    verified_user(&app).await;
+    let emails_before = app.mailer.sent().len();
+    for _ in 0..5 {
+        request_reset(&app, "alice@example.com").await;
+    }
+    assert_eq!(app.mailer.sent().len(), emails_before + 3);
+}
+
+#[tokio::test]
+async fn test_password_reset_hides_delivery_failures() {
+    let app = TestApp::new().await;
+    verified_user(&app).await;
+    let emails_before = app.mailer.sent().len();
+
+    app.mailer.set_failing(true);
+    request_reset(&app, "alice@example.com").await;
+    assert_eq!(app.mailer.sent().len(), emails_before);
+    let pending = EmailTokens::find()
+        .filter(email_tokens::Column::Purpose.eq("password_reset"))
+        .count(&app.db)
+        .await
+        .unwrap();
+    assert_eq!(pending, 0);
+
+    app.mailer.set_failing(false);
+    request_reset(&app, "alice@example.com").await;
+    assert_eq!(app.mailer.sent().len(), emails_before + 1);
+}
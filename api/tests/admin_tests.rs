@@ -0,0 +1,225 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{TestApp, json_body};
+use serde_json::{Value, json};
+
+async fn usernames(app: &TestApp, jwt: &str, uri: &str) -> Vec<String> {
+    let res = app.get(uri, Some(jwt)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    json_body(res)
+        .await
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|u| u["username"].as_str().unwrap().to_string())
+        .collect()
+}
+
+async fn stats(app: &TestApp, jwt: &str) -> Value {
+    let res = app.get("/admin/stats", Some(jwt)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    json_body(res).await
+}
+
+#[tokio::test]
+async fn test_roles_gate_admin_api() {
+    let app = TestApp::new().await;
+    let admin = app.register_admin("admin", "password123").await;
+    let alice = app.register("alice", "password123").await;
+    app.register("bob", "password123").await;
+    let admin_id = app.user_id(&admin).await;
+    let alice_id = app.user_id(&alice).await;
+
+    assert_eq!(
+        app.get("/admin/users", Some(&alice)).await.status(),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        usernames(&app, &admin, "/admin/users").await,
+        ["admin", "alice", "bob"]
+    );
+    assert_eq!(
+        usernames(&app, &admin, "/admin/users?q=ALI").await,
+        ["alice"]
+    );
+    assert_eq!(
+        usernames(&app, &admin, "/admin/users?role=admin").await,
+        ["admin"]
+    );
+
+    // Auditors may read but not change anything
+    let res = app
+        .send_json(
+            "PUT",
+            &format!("/admin/users/{}/role", alice_id),
+            Some(&admin),
+            json!({ "role": "auditor" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["role"], "auditor");
+    assert_eq!(
+        usernames(&app, &alice, "/admin/users?role=auditor").await,
+        ["alice"]
+    );
+    assert_eq!(
+        app.get("/admin/stats", Some(&alice)).await.status(),
+        StatusCode::OK
+    );
+    let res = app
+        .post_json(
+            &format!("/admin/users/{}/disable", admin_id),
+            Some(&alice),
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // Admins cannot lock themselves out
+    let res = app
+        .send_json(
+            "PUT",
+            &format!("/admin/users/{}/role", admin_id),
+            Some(&admin),
+            json!({ "role": "user" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = app
+        .delete(&format!("/admin/users/{}", admin_id), Some(&admin))
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    // Role changes are audited
+    let res = app
+        .get("/admin/audit-logs?event_type=UserRoleChange", Some(&alice))
+        .await;
+    let logs = json_body(res).await;
+    assert_eq!(logs.as_array().unwrap().len(), 1);
+    assert_eq!(logs[0]["resource_id"], alice_id.as_str());
+}
+
+#[tokio::test]
+async fn test_disable_and_reset_password() {
+    let app = TestApp::new().await;
+    let admin = app.register_admin("admin", "password123").await;
+    let bob = app.register("bob", "password123").await;
+    let bob_id = app.user_id(&bob).await;
+
+    let res = app
+        .post_json(
+            &format!("/admin/users/{}/disable", bob_id),
+            Some(&admin),
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(json_body(res).await["disabled_at"].is_string());
+    assert_eq!(
+        app.get("/users/me", Some(&bob)).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        app.login("bob", "password123").await.status(),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        usernames(&app, &admin, "/admin/users?disabled=true").await,
+        ["bob"]
+    );
+
+    let res = app
+        .post_json(
+            &format!("/admin/users/{}/enable", bob_id),
+            Some(&admin),
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = app.login("bob", "password123").await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let bob = json_body(res).await["token"].as_str().unwrap().to_string();
+
+    let uri = format!("/admin/users/{}/password", bob_id);
+    let res = app
+        .post_json(&uri, Some(&admin), json!({ "password": "short" }))
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = app
+        .post_json(&uri, Some(&admin), json!({ "password": "new-password" }))
+        .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    // The reset signs bob out everywhere
+    assert_eq!(
+        app.get("/users/me", Some(&bob)).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        app.login("bob", "password123").await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        app.login("bob", "new-password").await.status(),
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn test_delete_user_and_dedup_stats() {
+    let app = TestApp::new().await;
+    let admin = app.register_admin("admin", "password123").await;
+    let alice = app.register("alice", "password123").await;
+    let bob = app.register("bob", "password123").await;
+    let bob_id = app.user_id(&bob).await;
+
+    let content = b"the same report, uploaded twice";
+    for jwt in [&alice, &bob] {
+        let res = app
+            .upload(jwt, "report.txt", "text/plain", content, None)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let size = content.len() as i64;
+    let before = stats(&app, &admin).await;
+    assert_eq!(before["users"], 3);
+    assert_eq!(before["storage_objects"], 1);
+    assert_eq!(before["physical_bytes"], size);
+    assert_eq!(before["logical_bytes"], 2 * size);
+    assert_eq!(before["dedup_savings_bytes"], size);
+
+    let res = app
+        .get(&format!("/admin/users/{}/usage", bob_id), Some(&admin))
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let usage = json_body(res).await;
+    assert_eq!(usage["total_files"], 1);
+    assert_eq!(usage["total_size"], size);
+    assert_eq!(usage["quota"]["used"], size);
+
+    let res = app
+        .delete(&format!("/admin/users/{}", bob_id), Some(&admin))
+        .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        app.get(&format!("/admin/users/{}", bob_id), Some(&admin))
+            .await
+            .status(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        app.get("/users/me", Some(&bob)).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    // Alice's copy keeps the shared content alive
+    let after = stats(&app, &admin).await;
+    assert_eq!(after["users"], 2);
+    assert_eq!(after["storage_objects"], 1);
+    assert_eq!(after["logical_bytes"], size);
+    assert_eq!(after["dedup_savings_bytes"], 0);
+    let files = json_body(app.get("/files", Some(&alice)).await).await;
+    assert_eq!(files.as_array().unwrap().len(), 1);
+}
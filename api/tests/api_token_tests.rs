@@ -0,0 +1,193 @@
+mod common;
+
+use common::{TestApp, json_body};
+use rust_file_backend::entities::{api_tokens, prelude::*};
+use sea_orm::{ActiveModelTrait, EntityTrait, IntoActiveModel, Set};
+use serde_json::{Value, json};
+
+use axum::http::StatusCode;
+
+async fn create_token(app: &TestApp, jwt: &str, body: Value) -> Value {
+    let res = app.post_json("/auth/tokens", Some(jwt), body).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    json_body(res).await
+}
+
+#[tokio::test]
+async fn test_read_only_token_is_limited_to_reads() {
+    let app = TestApp::new().await;
+    let jwt = app.register("alice", "password123").await;
+    let created = create_token(
+        &app,
+        &jwt,
+        json!({ "name": "backup script", "scopes": ["files:read"], "expires_in_days": 30 }),
+    )
+    .await;
+    let token = created["token"].as_str().unwrap();
+    assert!(token.starts_with("rfb_pat_"));
+    assert_eq!(created["scopes"], json!(["files:read"]));
+    assert!(created["expires_at"].is_string());
+
+    assert_eq!(
+        app.get("/files", Some(token)).await.status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        app.upload(token, "a.txt", "text/plain", b"hello", None)
+            .await
+            .status(),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        app.get("/shares", Some(token)).await.status(),
+        StatusCode::FORBIDDEN
+    );
+    // A token cannot mint or list other tokens
+    assert_eq!(
+        app.get("/auth/tokens", Some(token)).await.status(),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        app.post_json(
+            "/auth/tokens",
+            Some(token),
+            json!({ "name": "x", "scopes": ["files:write"] }),
+        )
+        .await
+        .status(),
+        StatusCode::FORBIDDEN
+    );
+
+    let writer = create_token(
+        &app,
+        &jwt,
+        json!({ "name": "sync", "scopes": ["files:read", "files:write"] }),
+    )
+    .await;
+    let res = app
+        .upload(
+            writer["token"].as_str().unwrap(),
+            "a.txt",
+            "text/plain",
+            b"hello",
+            None,
+        )
+        .await;
+    assert!(res.status().is_success(), "upload failed: {}", res.status());
+}
+
+#[tokio::test]
+async fn test_tokens_store_hash_and_track_use() {
+    let app = TestApp::new().await;
+    let jwt = app.register("alice", "password123").await;
+    let created = create_token(
+        &app,
+        &jwt,
+        json!({ "name": "ci", "scopes": ["files:read"] }),
+    )
+    .await;
+    let token = created["token"].as_str().unwrap();
+    let id = created["id"].as_str().unwrap();
+
+    let row = ApiTokens::find_by_id(id)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_ne!(row.token_hash, token);
+    assert!(!row.token_hash.contains(&token[8..]));
+    assert!(token.starts_with(&row.token_prefix));
+    assert!(row.last_used_at.is_none());
+
+    assert_eq!(
+        app.get("/files", Some(token)).await.status(),
+        StatusCode::OK
+    );
+    let listed = json_body(app.get("/auth/tokens", Some(&jwt)).await).await;
+    let listed = listed.as_array().unwrap();
+    assert_eq!(listed.len(), 1);
+    assert!(listed[0]["last_used_at"].is_string());
+    assert!(listed[0].get("token").is_none());
+}
+
+#[tokio::test]
+async fn test_revoked_and_expired_tokens_are_rejected() {
+    let app = TestApp::new().await;
+    let jwt = app.register("alice", "password123").await;
+    let bob = app.register("bob", "password123").await;
+
+    let revoked = create_token(
+        &app,
+        &jwt,
+        json!({ "name": "old", "scopes": ["files:read"] }),
+    )
+    .await;
+    let uri = format!("/auth/tokens/{}", revoked["id"].as_str().unwrap());
+    assert_eq!(
+        app.delete(&uri, Some(&bob)).await.status(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        app.delete(&uri, Some(&jwt)).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    assert_eq!(
+        app.get("/files", revoked["token"].as_str()).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    let expiring = create_token(
+        &app,
+        &jwt,
+        json!({ "name": "short", "scopes": ["files:read"], "expires_in_days": 1 }),
+    )
+    .await;
+    let mut row = ApiTokens::find_by_id(expiring["id"].as_str().unwrap())
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_active_model();
+    row.expires_at = Set(Some(chrono::Utc::now() - chrono::Duration::minutes(1)));
+    let _: api_tokens::Model = row.update(&app.db).await.unwrap();
+    assert_eq!(
+        app.get("/files", expiring["token"].as_str()).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn test_token_creation_is_validated() {
+    let app = TestApp::new().await;
+    let jwt = app.register("alice", "password123").await;
+
+    for body in [
+        json!({ "name": "", "scopes": ["files:read"] }),
+        json!({ "name": "ci", "scopes": [] }),
+        json!({ "name": "ci", "scopes": ["files:read"], "expires_in_days": 0 }),
+        json!({ "name": "ci", "scopes": ["files:read"], "expires_in_days": 366 }),
+    ] {
+        let res = app.post_json("/auth/tokens", Some(&jwt), body).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // Unknown scopes fail to deserialize
+    let res = app
+        .post_json(
+            "/auth/tokens",
+            Some(&jwt),
+            json!({ "name": "ci", "scopes": ["everything"] }),
+        )
+        .await;
+    assert!(res.status().is_client_error());
+
+    // Only admins may hand out the admin scope
+    let res = app
+        .post_json(
+            "/auth/tokens",
+            Some(&jwt),
+            json!({ "name": "ci", "scopes": ["admin"] }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,285 @@
+//! Shared harness for the end-to-end tests.
+//!
+//! Builds the full axum router from `create_app` on top of an in-memory
+//! SQLite database, the in-memory storage backend, the no-op scanner and an
+//! in-memory mailer, so whole request flows can be exercised without
+//! Postgres, S3, ClamAV or SMTP.
+#![allow(dead_code)]
+
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+    response::Response,
+};
+use dashmap::DashMap;
+use http_body_util::BodyExt;
+use rust_file_backend::config::SecurityConfig;
+use rust_file_backend::infrastructure::database;
+use rust_file_backend::services::file_service::FileService;
+use rust_file_backend::services::mailer::MemoryMailer;
+use rust_file_backend::services::memory_storage::InMemoryStorageService;
+use rust_file_backend::services::scanner::NoOpScanner;
+use rust_file_backend::services::upload_service::UploadService;
+use rust_file_backend::services::user_admin::UserAdminService;
+use rust_file_backend::{AppState, create_app};
+use sea_orm::{ConnectOptions, Database, DatabaseConnection};
+use serde_json::Value;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+const BOUNDARY: &str = "rfb-test-boundary";
+
+pub struct TestApp {
+    pub router: Router,
+    pub state: AppState,
+    pub db: DatabaseConnection,
+    pub storage: Arc<InMemoryStorageService>,
+    pub mailer: Arc<MemoryMailer>,
+}
+
+impl TestApp {
+    pub async fn new() -> Self {
+        Self::with_config(SecurityConfig::development()).await
+    }
+
+    pub async fn with_config(config: SecurityConfig) -> Self {
+        // A single connection keeps every query on the same `:memory:` database
+        let mut opt = ConnectOptions::new("sqlite::memory:");
+        opt.max_connections(1)
+            .min_connections(1)
+            .sqlx_logging(false);
+        let db = Database::connect(opt)
+            .await
+            .expect("failed to open in-memory SQLite");
+        database::run_migrations(&db)
+            .await
+            .expect("failed to run migrations");
+
+        let storage = Arc::new(InMemoryStorageService::new());
+        let scanner = Arc::new(NoOpScanner);
+        let mailer = Arc::new(MemoryMailer::new());
+
+        let file_service = Arc::new(FileService::new(
+            db.clone(),
+            storage.clone(),
+            scanner.clone(),
+            config.clone(),
+        ));
+        let upload_service = Arc::new(UploadService::new(
+            db.clone(),
+            storage.clone(),
+            config.clone(),
+            file_service.clone(),
+        ));
+
+        let state = AppState {
+            db: db.clone(),
+            storage: storage.clone(),
+            scanner,
+            mailer: mailer.clone(),
+            file_service,
+            upload_service,
+            config,
+            captchas: Arc::new(DashMap::new()),
+            cooldowns: Arc::new(DashMap::new()),
+        };
+
+        Self {
+            router: create_app(state.clone()),
+            state,
+            db,
+            storage,
+            mailer,
+        }
+    }
+
+    pub async fn request(&self, req: Request<Body>) -> Response {
+        self.router
+            .clone()
+            .oneshot(req)
+            .await
+            .expect("router is infallible")
+    }
+
+    pub async fn get(&self, uri: &str, token: Option<&str>) -> Response {
+        let mut builder = Request::get(uri);
+        if let Some(token) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        self.request(builder.body(Body::empty()).unwrap()).await
+    }
+
+    pub async fn delete(&self, uri: &str, token: Option<&str>) -> Response {
+        let mut builder = Request::delete(uri);
+        if let Some(token) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        self.request(builder.body(Body::empty()).unwrap()).await
+    }
+
+    pub async fn send_json(
+        &self,
+        method: &str,
+        uri: &str,
+        token: Option<&str>,
+        body: Value,
+    ) -> Response {
+        let mut builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        self.request(builder.body(Body::from(body.to_string())).unwrap())
+            .await
+    }
+
+    pub async fn post_json(&self, uri: &str, token: Option<&str>, body: Value) -> Response {
+        self.send_json("POST", uri, token, body).await
+    }
+
+    /// Solve a CAPTCHA by reading the expected answer from server state
+    pub async fn solve_captcha(&self) -> (String, i32) {
+        let res = self.get("/captcha", None).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = json_body(res).await;
+        let id = body["captcha_id"].as_str().unwrap().to_string();
+        let answer = self.state.captchas.get(&id).unwrap().answer;
+        (id, answer)
+    }
+
+    /// Register a user and return its JWT
+    pub async fn register(&self, username: &str, password: &str) -> String {
+        let (captcha_id, captcha_answer) = self.solve_captcha().await;
+        let res = self
+            .post_json(
+                "/register",
+                None,
+                serde_json::json!({
+                    "username": username,
+                    "password": password,
+                    "captcha_id": captcha_id,
+                    "captcha_answer": captcha_answer,
+                }),
+            )
+            .await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        json_body(res).await["token"].as_str().unwrap().to_string()
+    }
+
+    /// Register a user, grant it the admin role and return its JWT
+    pub async fn register_admin(&self, username: &str, password: &str) -> String {
+        let jwt = self.register(username, password).await;
+        let id = self.user_id(&jwt).await;
+        UserAdminService::grant_admin(&self.db, &id)
+            .await
+            .expect("failed to grant admin");
+        jwt
+    }
+
+    /// The id of the user signed in with `token`
+    pub async fn user_id(&self, token: &str) -> String {
+        json_body(self.get("/users/me", Some(token)).await).await["id"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    /// Create a folder and return its id
+    pub async fn create_folder(&self, token: &str, name: &str, parent_id: Option<&str>) -> String {
+        let res = self
+            .post_json(
+                "/folders",
+                Some(token),
+                serde_json::json!({ "name": name, "parent_id": parent_id }),
+            )
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        json_body(res).await["id"].as_str().unwrap().to_string()
+    }
+
+    pub async fn login(&self, username: &str, password: &str) -> Response {
+        let (captcha_id, captcha_answer) = self.solve_captcha().await;
+        self.post_json(
+            "/login",
+            None,
+            serde_json::json!({
+                "username": username,
+                "password": password,
+                "captcha_id": captcha_id,
+                "captcha_answer": captcha_answer,
+            }),
+        )
+        .await
+    }
+
+    /// Upload a file through `POST /upload` as multipart/form-data
+    pub async fn upload(
+        &self,
+        token: &str,
+        filename: &str,
+        content_type: &str,
+        data: &[u8],
+        parent_id: Option<&str>,
+    ) -> Response {
+        let mut body = Vec::new();
+        if let Some(parent_id) = parent_id {
+            body.extend_from_slice(
+                format!(
+                    "--{}\r\nContent-Disposition: form-data; name=\"parent_id\"\r\n\r\n{}\r\n",
+                    BOUNDARY, parent_id
+                )
+                .as_bytes(),
+            );
+        }
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                BOUNDARY, filename, content_type
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(data);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+
+        let req = Request::post("/upload")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", BOUNDARY),
+            )
+            .body(Body::from(body))
+            .unwrap();
+        self.request(req).await
+    }
+
+    /// Upload a text file, expecting success, and return its ID
+    pub async fn upload_file(
+        &self,
+        token: &str,
+        filename: &str,
+        data: &[u8],
+        parent_id: Option<&str>,
+    ) -> String {
+        let res = self
+            .upload(token, filename, "text/plain", data, parent_id)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        json_body(res).await["file_id"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+}
+
+pub async fn body_bytes(res: Response) -> Vec<u8> {
+    res.into_body().collect().await.unwrap().to_bytes().to_vec()
+}
+
+pub async fn json_body(res: Response) -> Value {
+    let bytes = body_bytes(res).await;
+    serde_json::from_slice(&bytes)
+        .unwrap_or_else(|_| panic!("response is not JSON: {}", String::from_utf8_lossy(&bytes)))
+}
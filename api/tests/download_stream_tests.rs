@@ -0,0 +1,205 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use axum::response::Response;
+use common::{TestApp, body_bytes, json_body};
+use rust_file_backend::config::SecurityConfig;
+use serde_json::json;
+
+const CONTENT: &[u8] = b"0123456789abcdefghij";
+
+async fn streaming_app() -> TestApp {
+    let mut config = SecurityConfig::development();
+    config.download_mode = "stream".to_string();
+    TestApp::with_config(config).await
+}
+
+async fn get_with(
+    app: &TestApp,
+    uri: &str,
+    token: Option<&str>,
+    headers: &[(header::HeaderName, &str)],
+) -> Response {
+    let mut builder = Request::get(uri);
+    if let Some(token) = token {
+        builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    for (name, value) in headers {
+        builder = builder.header(name, *value);
+    }
+    app.request(builder.body(Body::empty()).unwrap()).await
+}
+
+fn header_str(res: &Response, name: header::HeaderName) -> &str {
+    res.headers()[name].to_str().unwrap()
+}
+
+#[tokio::test]
+async fn test_full_and_partial_downloads() {
+    let app = streaming_app().await;
+    let token = app.register("alice", "password123").await;
+    let uri = format!(
+        "/files/{}",
+        app.upload_file(&token, "clip.txt", CONTENT, None).await
+    );
+
+    let res = get_with(&app, &uri, Some(&token), &[]).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(!res.headers().contains_key("x-accel-redirect"));
+    assert_eq!(header_str(&res, header::ACCEPT_RANGES), "bytes");
+    assert_eq!(header_str(&res, header::CONTENT_LENGTH), "20");
+    assert!(res.headers().contains_key(header::ETAG));
+    assert!(res.headers().contains_key(header::LAST_MODIFIED));
+    assert_eq!(body_bytes(res).await, CONTENT);
+
+    let res = get_with(&app, &uri, Some(&token), &[(header::RANGE, "bytes=2-5")]).await;
+    assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(header_str(&res, header::CONTENT_RANGE), "bytes 2-5/20");
+    assert_eq!(header_str(&res, header::CONTENT_LENGTH), "4");
+    assert_eq!(body_bytes(res).await, b"2345");
+
+    let res = get_with(&app, &uri, Some(&token), &[(header::RANGE, "bytes=-3")]).await;
+    assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(body_bytes(res).await, b"hij");
+
+    let res = get_with(&app, &uri, Some(&token), &[(header::RANGE, "bytes=15-")]).await;
+    assert_eq!(header_str(&res, header::CONTENT_RANGE), "bytes 15-19/20");
+    assert_eq!(body_bytes(res).await, b"fghij");
+
+    let res = get_with(&app, &uri, Some(&token), &[(header::RANGE, "bytes=50-60")]).await;
+    assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(header_str(&res, header::CONTENT_RANGE), "bytes */20");
+
+    // Multiple ranges fall back to the whole file
+    let res = get_with(
+        &app,
+        &uri,
+        Some(&token),
+        &[(header::RANGE, "bytes=0-1,4-5")],
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_bytes(res).await, CONTENT);
+}
+
+#[tokio::test]
+async fn test_conditional_requests() {
+    let app = streaming_app().await;
+    let token = app.register("alice", "password123").await;
+    let uri = format!(
+        "/files/{}",
+        app.upload_file(&token, "clip.txt", CONTENT, None).await
+    );
+
+    let res = get_with(&app, &uri, Some(&token), &[]).await;
+    let etag = header_str(&res, header::ETAG).to_string();
+    let last_modified = header_str(&res, header::LAST_MODIFIED).to_string();
+
+    let res = get_with(&app, &uri, Some(&token), &[(header::IF_NONE_MATCH, &etag)]).await;
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(header_str(&res, header::ETAG), etag);
+    assert!(body_bytes(res).await.is_empty());
+
+    let res = get_with(
+        &app,
+        &uri,
+        Some(&token),
+        &[(header::IF_MODIFIED_SINCE, &last_modified)],
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+    let res = get_with(
+        &app,
+        &uri,
+        Some(&token),
+        &[(header::IF_NONE_MATCH, "\"stale\"")],
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // A resumed download only gets the rest if the file is unchanged
+    let res = get_with(
+        &app,
+        &uri,
+        Some(&token),
+        &[(header::RANGE, "bytes=10-"), (header::IF_RANGE, &etag)],
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(body_bytes(res).await, b"abcdefghij");
+
+    let res = get_with(
+        &app,
+        &uri,
+        Some(&token),
+        &[
+            (header::RANGE, "bytes=10-"),
+            (header::IF_RANGE, "\"stale\""),
+        ],
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_bytes(res).await, CONTENT);
+}
+
+#[tokio::test]
+async fn test_ticket_and_share_downloads_stream() {
+    let app = streaming_app().await;
+    let token = app.register("alice", "password123").await;
+    let file_id = app.upload_file(&token, "clip.txt", CONTENT, None).await;
+
+    let res = app
+        .post_json(
+            &format!("/files/{}/ticket", file_id),
+            Some(&token),
+            json!({}),
+        )
+        .await;
+    let ticket = json_body(res).await["ticket"].as_str().unwrap().to_string();
+    let res = get_with(
+        &app,
+        &format!("/download/{}", ticket),
+        None,
+        &[(header::RANGE, "bytes=0-3")],
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(body_bytes(res).await, b"0123");
+
+    let res = app
+        .post_json(
+            "/shares",
+            Some(&token),
+            json!({
+                "user_file_id": file_id,
+                "share_type": "public",
+                "permission": "download",
+                "expires_in_hours": 24,
+            }),
+        )
+        .await;
+    let share_token = json_body(res).await["share_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let res = get_with(&app, &format!("/share/{}/download", share_token), None, &[]).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_bytes(res).await, CONTENT);
+}
+
+#[tokio::test]
+async fn test_default_mode_redirects_to_nginx() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let uri = format!(
+        "/files/{}",
+        app.upload_file(&token, "clip.txt", CONTENT, None).await
+    );
+
+    let res = get_with(&app, &uri, Some(&token), &[(header::RANGE, "bytes=2-5")]).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.headers().contains_key("x-accel-redirect"));
+    assert!(body_bytes(res).await.is_empty());
+}
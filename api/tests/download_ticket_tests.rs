@@ -0,0 +1,224 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use axum::response::Response;
+use common::{TestApp, json_body};
+use rust_file_backend::entities::{download_tickets, prelude::*};
+use rust_file_backend::services::download_tickets::DownloadTicketService;
+use sea_orm::{ActiveModelTrait, EntityTrait, PaginatorTrait, Set};
+use serde_json::{Value, json};
+
+/// `POST /files/:id/ticket`, optionally from a client IP behind the proxy
+async fn create_ticket(
+    app: &TestApp,
+    token: &str,
+    file_id: &str,
+    body: Value,
+    client_ip: Option<&str>,
+) -> Response {
+    let mut builder = Request::post(format!("/files/{}/ticket", file_id))
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::CONTENT_TYPE, "application/json");
+    if let Some(ip) = client_ip {
+        builder = builder.header("x-forwarded-for", ip);
+    }
+    app.request(builder.body(Body::from(body.to_string())).unwrap())
+        .await
+}
+
+async fn ticket(app: &TestApp, token: &str, file_id: &str, body: Value) -> String {
+    let res = create_ticket(app, token, file_id, body, None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    json_body(res).await["ticket"].as_str().unwrap().to_string()
+}
+
+async fn download(app: &TestApp, ticket: &str, client_ip: Option<&str>) -> StatusCode {
+    let mut builder = Request::get(format!("/download/{}", ticket));
+    if let Some(ip) = client_ip {
+        builder = builder.header("x-forwarded-for", ip);
+    }
+    app.request(builder.body(Body::empty()).unwrap())
+        .await
+        .status()
+}
+
+#[tokio::test]
+async fn test_ticket_is_persisted_and_revocable() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let file_id = app
+        .upload_file(&token, "report.txt", b"quarterly numbers", None)
+        .await;
+
+    // The web client posts without a body
+    let res = app
+        .request(
+            Request::post(format!("/files/{}/ticket", file_id))
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = json_body(res).await;
+    let ticket = body["ticket"].as_str().unwrap().to_string();
+    assert_eq!(body["url"], format!("/api/download/{}", ticket));
+    assert!(body["max_downloads"].is_null());
+
+    let stored = DownloadTickets::find_by_id(&ticket)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .expect("ticket stored in the database");
+    assert_eq!(stored.user_file_id, file_id);
+
+    // Unlimited tickets can be used repeatedly
+    assert_eq!(download(&app, &ticket, None).await, StatusCode::OK);
+    assert_eq!(download(&app, &ticket, None).await, StatusCode::OK);
+
+    let bob = app.register("bob", "password123").await;
+    let res = app
+        .delete(&format!("/tickets/{}", ticket), Some(&bob))
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = app
+        .delete(&format!("/tickets/{}", ticket), Some(&token))
+        .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(download(&app, &ticket, None).await, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_single_use_and_counted_tickets() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let file_id = app
+        .upload_file(&token, "report.txt", b"quarterly numbers", None)
+        .await;
+
+    let once = ticket(&app, &token, &file_id, json!({ "single_use": true })).await;
+    assert_eq!(download(&app, &once, None).await, StatusCode::OK);
+    assert_eq!(download(&app, &once, None).await, StatusCode::FORBIDDEN);
+
+    let twice = ticket(&app, &token, &file_id, json!({ "max_downloads": 2 })).await;
+    assert_eq!(download(&app, &twice, None).await, StatusCode::OK);
+    assert_eq!(download(&app, &twice, None).await, StatusCode::OK);
+    assert_eq!(download(&app, &twice, None).await, StatusCode::FORBIDDEN);
+
+    for body in [
+        json!({ "max_downloads": 0 }),
+        json!({ "single_use": true, "max_downloads": 3 }),
+        json!({ "expires_in_seconds": 0 }),
+        json!({ "expires_in_seconds": 30 * 24 * 3600 }),
+    ] {
+        let res = create_ticket(&app, &token, &file_id, body, None).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test]
+async fn test_ip_bound_ticket() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let file_id = app
+        .upload_file(&token, "report.txt", b"quarterly numbers", None)
+        .await;
+
+    let res = create_ticket(
+        &app,
+        &token,
+        &file_id,
+        json!({ "bind_ip": true }),
+        Some("203.0.113.7"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = json_body(res).await;
+    assert_eq!(body["ip_bound"], true);
+    let bound = body["ticket"].as_str().unwrap().to_string();
+
+    assert_eq!(
+        download(&app, &bound, Some("198.51.100.1")).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(download(&app, &bound, None).await, StatusCode::FORBIDDEN);
+    assert_eq!(
+        download(&app, &bound, Some("203.0.113.7")).await,
+        StatusCode::OK
+    );
+
+    // Nothing to bind to without a known client address
+    let res = create_ticket(&app, &token, &file_id, json!({ "bind_ip": true }), None).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_ticket_stops_working_when_its_creator_loses_access() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice", "password123").await;
+    let bob = app.register("bob", "password123").await;
+    let bob_id = app.user_id(&bob).await;
+
+    let res = app
+        .post_json("/teams", Some(&alice), json!({ "name": "Ops" }))
+        .await;
+    let team = json_body(res).await["id"].as_str().unwrap().to_string();
+    let res = app
+        .post_json(
+            &format!("/teams/{}/members", team),
+            Some(&alice),
+            json!({ "username": "bob", "role": "viewer" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let res = app
+        .post_json(
+            "/folders",
+            Some(&alice),
+            json!({ "name": "Reports", "team_id": team }),
+        )
+        .await;
+    let folder = json_body(res).await["id"].as_str().unwrap().to_string();
+    let file_id = app
+        .upload_file(&alice, "q3.txt", b"numbers", Some(&folder))
+        .await;
+
+    let ticket = ticket(&app, &bob, &file_id, json!({})).await;
+    assert_eq!(download(&app, &ticket, None).await, StatusCode::OK);
+
+    let res = app
+        .delete(&format!("/teams/{}/members/{}", team, bob_id), Some(&alice))
+        .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(download(&app, &ticket, None).await, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_sweep_removes_expired_and_used_up_tickets() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let file_id = app
+        .upload_file(&token, "report.txt", b"quarterly numbers", None)
+        .await;
+
+    let live = ticket(&app, &token, &file_id, json!({ "expires_in_seconds": 60 })).await;
+    let used = ticket(&app, &token, &file_id, json!({ "single_use": true })).await;
+    assert_eq!(download(&app, &used, None).await, StatusCode::OK);
+
+    let expired = ticket(&app, &token, &file_id, json!({})).await;
+    let model = DownloadTickets::find_by_id(&expired)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut active: download_tickets::ActiveModel = model.into();
+    active.expires_at = Set(chrono::Utc::now() - chrono::Duration::minutes(1));
+    active.update(&app.db).await.unwrap();
+    assert_eq!(download(&app, &expired, None).await, StatusCode::FORBIDDEN);
+
+    assert_eq!(DownloadTicketService::sweep(&app.db).await.unwrap(), 2);
+    assert_eq!(DownloadTickets::find().count(&app.db).await.unwrap(), 1);
+    assert_eq!(download(&app, &live, None).await, StatusCode::OK);
+}
@@ -0,0 +1,210 @@
+mod common;
+
+use axum::http::{StatusCode, header};
+use common::{TestApp, body_bytes, json_body};
+use rust_file_backend::entities::{prelude::*, storage_files, user_files};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde_json::json;
+
+/// Share `item_id` with `recipient` and return the share ID and token
+async fn share_with(
+    app: &TestApp,
+    token: &str,
+    item_id: &str,
+    recipient: &str,
+    permission: &str,
+) -> (String, String) {
+    let res = app
+        .post_json(
+            "/shares",
+            Some(token),
+            json!({
+                "user_file_id": item_id,
+                "share_type": "user",
+                "shared_with_user_id": recipient,
+                "permission": permission,
+                "expires_in_hours": 24,
+            }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let body = json_body(res).await;
+    (
+        body["id"].as_str().unwrap().to_string(),
+        body["share_token"].as_str().unwrap().to_string(),
+    )
+}
+
+#[tokio::test]
+async fn test_recipient_browses_and_downloads() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice", "password123").await;
+    let bob = app.register("bob", "password123").await;
+    let carol = app.register("carol", "password123").await;
+    let bob_id = app.user_id(&bob).await;
+
+    let project = app.create_folder(&alice, "project", None).await;
+    let drafts = app.create_folder(&alice, "drafts", Some(&project)).await;
+    let draft = app
+        .upload_file(&alice, "draft.txt", b"first draft", Some(&drafts))
+        .await;
+    let secret = app
+        .upload_file(&alice, "secret.txt", b"not shared", None)
+        .await;
+
+    let (share_id, share_token) = share_with(&app, &alice, &project, &bob_id, "view").await;
+
+    let res = app.get("/shares/incoming", Some(&bob)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let incoming = json_body(res).await;
+    assert_eq!(incoming.as_array().unwrap().len(), 1);
+    assert_eq!(incoming[0]["id"], share_id);
+    assert_eq!(incoming[0]["owner_username"], "alice");
+    assert_eq!(incoming[0]["filename"], "project");
+
+    let res = app.get("/shares/incoming", Some(&carol)).await;
+    assert!(json_body(res).await.as_array().unwrap().is_empty());
+
+    // Nested folders can be browsed
+    let res = app
+        .get(&format!("/shares/incoming/{}/list", share_id), Some(&bob))
+        .await;
+    assert_eq!(json_body(res).await[0]["filename"], "drafts");
+    let res = app
+        .get(
+            &format!("/shares/incoming/{}/list?folder_id={}", share_id, drafts),
+            Some(&bob),
+        )
+        .await;
+    assert_eq!(json_body(res).await[0]["filename"], "draft.txt");
+
+    // View shares preview inline
+    let res = app
+        .get(
+            &format!("/shares/incoming/{}/download?file_id={}", share_id, draft),
+            Some(&bob),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(
+        res.headers()[header::CONTENT_DISPOSITION]
+            .to_str()
+            .unwrap()
+            .starts_with("inline")
+    );
+    let _ = body_bytes(res).await;
+
+    // Files outside the shared folder stay private
+    let res = app
+        .get(
+            &format!("/shares/incoming/{}/download?file_id={}", share_id, secret),
+            Some(&bob),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // Only the recipient can use the share
+    let res = app
+        .get(&format!("/shares/incoming/{}/list", share_id), Some(&carol))
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let res = app.get(&format!("/share/{}", share_token), None).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = app
+        .get(&format!("/share/{}/download", share_token), None)
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_add_shared_file_to_drive() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice", "password123").await;
+    let bob = app.register("bob", "password123").await;
+    let bob_id = app.user_id(&bob).await;
+
+    let report = app
+        .upload_file(&alice, "report.txt", b"numbers", None)
+        .await;
+    app.upload_file(&bob, "report.txt", b"bob's own report", None)
+        .await;
+
+    let (view_id, _) = share_with(&app, &alice, &report, &bob_id, "view").await;
+    let res = app
+        .post_json(
+            &format!("/shares/incoming/{}/add", view_id),
+            Some(&bob),
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let (share_id, _) = share_with(&app, &alice, &report, &bob_id, "download").await;
+    let res = app
+        .post_json(
+            &format!("/shares/incoming/{}/add", share_id),
+            Some(&bob),
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = json_body(res).await;
+    // Bob's own file of the same name is left alone
+    assert_eq!(body["filename"], "report (1).txt");
+    let copy_id = body["file_id"].as_str().unwrap();
+
+    let copy = UserFiles::find_by_id(copy_id)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let original = UserFiles::find_by_id(&report)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(copy.user_id, bob_id);
+    assert_eq!(copy.storage_file_id, original.storage_file_id);
+    let storage = StorageFiles::find()
+        .filter(storage_files::Column::Id.eq(original.storage_file_id.unwrap()))
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(storage.ref_count, 2);
+
+    let res = app.get(&format!("/files/{}", copy_id), Some(&bob)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let bob_files = UserFiles::find()
+        .filter(user_files::Column::UserId.eq(&bob_id))
+        .all(&app.db)
+        .await
+        .unwrap();
+    assert_eq!(bob_files.len(), 2);
+}
+
+#[tokio::test]
+async fn test_user_share_requires_real_recipient() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice", "password123").await;
+    let alice_id = app.user_id(&alice).await;
+    let file = app.upload_file(&alice, "a.txt", b"alpha", None).await;
+
+    for recipient in ["no-such-user", alice_id.as_str()] {
+        let res = app
+            .post_json(
+                "/shares",
+                Some(&alice),
+                json!({
+                    "user_file_id": file,
+                    "share_type": "user",
+                    "shared_with_user_id": recipient,
+                    "permission": "view",
+                    "expires_in_hours": 24,
+                }),
+            )
+            .await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,432 @@
+mod common;
+
+use axum::http::{Request, StatusCode, header};
+use common::{TestApp, json_body};
+use rust_file_backend::config::SecurityConfig;
+use rust_file_backend::entities::{prelude::*, storage_files, user_files};
+use rust_file_backend::services::hash_backfill::HashBackfillService;
+use rust_file_backend::services::storage::StorageService;
+use sea_orm::{ActiveModelTrait, EntityTrait, Set};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+#[tokio::test]
+async fn test_register_login_and_profile() {
+    let app = TestApp::new().await;
+
+    let token = app.register("alice", "password123").await;
+    let res = app.get("/users/me", Some(&token)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["username"], "alice");
+
+    let res = app.login("alice", "password123").await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(json_body(res).await["token"].as_str().is_some());
+
+    let res = app.login("alice", "wrong-password").await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let res = app.get("/files", None).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_upload_list_and_download() {
+    let app = TestApp::new().await;
+    let token = app.register("bob", "password123").await;
+
+    let res = app
+        .upload(&token, "notes.txt", "text/plain", b"hello from bob", None)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let file_id = json_body(res).await["file_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let res = app.get("/files", Some(&token)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let listing = json_body(res).await;
+    assert!(listing.to_string().contains("notes.txt"));
+
+    let res = app.get(&format!("/files/{}", file_id), Some(&token)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let redirect = res.headers()["x-accel-redirect"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(redirect.starts_with("/minio_protected/"));
+
+    // The redirect targets the permanent object, which holds the uploaded bytes
+    let storage_file = StorageFiles::find().one(&app.db).await.unwrap().unwrap();
+    assert!(redirect.contains(&storage_file.s3_key));
+    assert_eq!(
+        app.storage.get_file(&storage_file.s3_key).await.unwrap(),
+        b"hello from bob"
+    );
+
+    // Other users cannot download it
+    let other = app.register("mallory", "password123").await;
+    let res = app.get(&format!("/files/{}", file_id), Some(&other)).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_identical_uploads_are_deduplicated() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice", "password123").await;
+    let bob = app.register("bob", "password123").await;
+
+    for token in [&alice, &bob] {
+        let res = app
+            .upload(token, "same.txt", "text/plain", b"identical content", None)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let storage_files = StorageFiles::find().all(&app.db).await.unwrap();
+    assert_eq!(storage_files.len(), 1);
+    assert_eq!(storage_files[0].ref_count, 2);
+    // Staging copies are cleaned up once processed
+    assert!(
+        app.storage
+            .list_objects("staging/")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_chunked_upload() {
+    let config = SecurityConfig {
+        chunk_size: 8,
+        ..SecurityConfig::development()
+    };
+    let app = TestApp::with_config(config).await;
+    let token = app.register("carol", "password123").await;
+
+    let content = b"chunked upload content!";
+    let res = app
+        .post_json(
+            "/files/upload/init",
+            Some(&token),
+            json!({
+                "file_name": "chunked.txt",
+                "file_type": "text/plain",
+                "total_size": content.len(),
+            }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let init = json_body(res).await;
+    let upload_id = init["upload_id"].as_str().unwrap().to_string();
+    assert_eq!(init["chunk_size"], 8);
+
+    for (i, chunk) in content.chunks(8).enumerate() {
+        let req = Request::put(format!("/files/upload/{}/chunk/{}", upload_id, i + 1))
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .body(axum::body::Body::from(chunk.to_vec()))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let res = app
+        .post_json(
+            &format!("/files/upload/{}/complete", upload_id),
+            Some(&token),
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let file = json_body(res).await;
+    assert_eq!(file["name"], "chunked.txt");
+
+    let storage_file = StorageFiles::find().one(&app.db).await.unwrap().unwrap();
+    assert_eq!(storage_file.size, content.len() as i64);
+    assert_eq!(
+        app.storage.get_file(&storage_file.s3_key).await.unwrap(),
+        content
+    );
+
+    let res = app.get("/files/upload/sessions", Some(&token)).await;
+    assert_eq!(json_body(res).await, json!([]));
+}
+
+#[tokio::test]
+async fn test_public_share_with_password() {
+    let app = TestApp::new().await;
+    let token = app.register("dave", "password123").await;
+
+    let file_id = app
+        .upload_file(&token, "shared.txt", b"shared content", None)
+        .await;
+
+    let res = app
+        .post_json(
+            "/shares",
+            Some(&token),
+            json!({
+                "user_file_id": file_id,
+                "share_type": "public",
+                "password": "letmein",
+                "permission": "download",
+                "expires_in_hours": 24,
+            }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let share = json_body(res).await;
+    let share_token = share["share_token"].as_str().unwrap().to_string();
+    assert_eq!(share["has_password"], true);
+
+    let res = app.get(&format!("/share/{}", share_token), None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let info = json_body(res).await;
+    assert_eq!(info["filename"], "shared.txt");
+    assert_eq!(info["requires_password"], true);
+
+    let res = app
+        .post_json(
+            &format!("/share/{}/verify", share_token),
+            None,
+            json!({ "password": "wrong" }),
+        )
+        .await;
+    assert_eq!(json_body(res).await["verified"], false);
+
+    let res = app
+        .post_json(
+            &format!("/share/{}/verify", share_token),
+            None,
+            json!({ "password": "letmein" }),
+        )
+        .await;
+    assert_eq!(json_body(res).await["verified"], true);
+
+    let res = app
+        .get(&format!("/share/{}/download", share_token), None)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.headers().contains_key("x-accel-redirect"));
+    let disposition = res.headers()[header::CONTENT_DISPOSITION].to_str().unwrap();
+    assert!(disposition.starts_with("attachment"));
+
+    let res = app
+        .get(
+            &format!("/shares/{}/logs", share["id"].as_str().unwrap()),
+            Some(&token),
+        )
+        .await;
+    let logs = json_body(res).await;
+    assert!(
+        logs.as_array()
+            .unwrap()
+            .iter()
+            .any(|l| l["action"] == "download")
+    );
+
+    let res = app.get("/share/does-not-exist", None).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_download_ticket() {
+    let app = TestApp::new().await;
+    let token = app.register("erin", "password123").await;
+
+    let file_id = app
+        .upload_file(&token, "ticket.txt", b"ticketed", None)
+        .await;
+
+    let res = app
+        .post_json(
+            &format!("/files/{}/ticket", file_id),
+            Some(&token),
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let ticket = json_body(res).await["ticket"].as_str().unwrap().to_string();
+
+    let res = app.get(&format!("/download/{}", ticket), None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.headers().contains_key("x-accel-redirect"));
+
+    let res = app.get("/download/not-a-ticket", None).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_dedup_link_requires_proof_of_possession() {
+    let app = TestApp::new().await;
+    let owner = app.register("owner", "password123").await;
+    let thief = app.register("thief", "password123").await;
+
+    let content = b"content only the owner really has".repeat(10);
+    let res = app
+        .upload(&owner, "secret.txt", "text/plain", &content, None)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let storage_file = StorageFiles::find().one(&app.db).await.unwrap().unwrap();
+
+    let res = app
+        .post_json(
+            "/pre-check",
+            Some(&thief),
+            json!({ "full_hash": storage_file.hash, "size": storage_file.size }),
+        )
+        .await;
+    let pre_check = json_body(res).await;
+    assert_eq!(pre_check["exists"], true);
+    let challenge = &pre_check["challenge"];
+    let token = challenge["token"].as_str().unwrap();
+
+    // Knowing only the hash is not enough
+    let link = |response: Option<String>| {
+        json!({
+            "storage_file_id": storage_file.id,
+            "filename": "stolen.txt",
+            "challenge_token": token,
+            "challenge_response": response,
+        })
+    };
+    let res = app.post_json("/files/link", Some(&thief), link(None)).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = app
+        .post_json("/files/link", Some(&thief), link(Some("00".repeat(32))))
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // Answering with the real bytes succeeds
+    let mut hasher = Sha256::new();
+    for range in challenge["ranges"].as_array().unwrap() {
+        let offset = range["offset"].as_u64().unwrap() as usize;
+        let length = range["length"].as_u64().unwrap() as usize;
+        hasher.update(&content[offset..offset + length]);
+    }
+    let answer = hex::encode(hasher.finalize());
+
+    // A challenge is bound to the user it was issued to
+    let res = app
+        .post_json("/files/link", Some(&owner), link(Some(answer.clone())))
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = app
+        .post_json("/files/link", Some(&thief), link(Some(answer)))
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let storage_file = StorageFiles::find().one(&app.db).await.unwrap().unwrap();
+    assert_eq!(storage_file.ref_count, 2);
+}
+
+#[tokio::test]
+async fn test_sha256_is_the_dedup_key() {
+    let app = TestApp::new().await;
+    let token = app.register("frank", "password123").await;
+
+    let content = b"content addressed by sha-256";
+    let res = app
+        .upload(&token, "a.txt", "text/plain", content, None)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let storage_file = StorageFiles::find().one(&app.db).await.unwrap().unwrap();
+    let sha256 = hex::encode(Sha256::digest(content));
+    assert_eq!(storage_file.sha256.as_deref(), Some(sha256.as_str()));
+    assert!(storage_file.s3_key.starts_with(&sha256));
+
+    // A row whose xxh3 collides but whose SHA-256 differs is not a dedup hit
+    let mut active: storage_files::ActiveModel = storage_file.clone().into();
+    active.sha256 = Set(Some("0".repeat(64)));
+    active.update(&app.db).await.unwrap();
+    let res = app
+        .upload(&token, "b.txt", "text/plain", content, None)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(StorageFiles::find().all(&app.db).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_legacy_rows_are_backfilled_and_merged() {
+    let app = TestApp::new().await;
+    let token = app.register("grace", "password123").await;
+
+    let content = b"uploaded before sha-256 existed";
+    let res = app
+        .upload(&token, "one.txt", "text/plain", content, None)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Simulate rows created before SHA-256 was recorded: the original loses its
+    // SHA-256 and a second copy of the same content is stored separately
+    let original = StorageFiles::find().one(&app.db).await.unwrap().unwrap();
+    let owner = UserFiles::find()
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap()
+        .user_id;
+    let mut active: storage_files::ActiveModel = original.clone().into();
+    active.sha256 = Set(None);
+    active.update(&app.db).await.unwrap();
+
+    app.storage
+        .upload_file("legacy/two.txt", content.to_vec())
+        .await
+        .unwrap();
+    storage_files::ActiveModel {
+        id: Set("legacy-copy".to_string()),
+        hash: Set(original.hash.clone()),
+        sha256: Set(None),
+        s3_key: Set("legacy/two.txt".to_string()),
+        size: Set(original.size),
+        ref_count: Set(1),
+        ..Default::default()
+    }
+    .insert(&app.db)
+    .await
+    .unwrap();
+    user_files::ActiveModel {
+        id: Set("legacy-user-file".to_string()),
+        user_id: Set(owner),
+        storage_file_id: Set(Some("legacy-copy".to_string())),
+        filename: Set("two.txt".to_string()),
+        is_folder: Set(false),
+        is_favorite: Set(false),
+        created_at: Set(Some(chrono::Utc::now())),
+        ..Default::default()
+    }
+    .insert(&app.db)
+    .await
+    .unwrap();
+
+    let processed = HashBackfillService::backfill_batch(&app.db, app.storage.as_ref(), 50)
+        .await
+        .unwrap();
+    assert_eq!(processed, 2);
+
+    let storage_files = StorageFiles::find().all(&app.db).await.unwrap();
+    assert_eq!(storage_files.len(), 1);
+    assert_eq!(storage_files[0].ref_count, 2);
+    assert_eq!(
+        storage_files[0].sha256.as_deref(),
+        Some(hex::encode(Sha256::digest(content)).as_str())
+    );
+    let user_files = UserFiles::find().all(&app.db).await.unwrap();
+    assert!(
+        user_files
+            .iter()
+            .all(|f| f.storage_file_id.as_deref() == Some(storage_files[0].id.as_str()))
+    );
+    assert!(app.storage.get_file("legacy/two.txt").await.is_err());
+
+    // Both files still download
+    for file in user_files {
+        let res = app.get(&format!("/files/{}", file.id), Some(&token)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{TestApp, json_body};
+use rust_file_backend::config::SecurityConfig;
+use rust_file_backend::entities::prelude::*;
+use rust_file_backend::services::storage::StorageService;
+use sea_orm::{EntityTrait, PaginatorTrait};
+use serde_json::{Value, json};
+
+async fn app_with_quota(default_storage_quota: Option<i64>) -> TestApp {
+    let mut config = SecurityConfig::development();
+    config.default_storage_quota = default_storage_quota;
+    TestApp::with_config(config).await
+}
+
+async fn quota(app: &TestApp, token: &str) -> Value {
+    let res = app.get("/users/me/quota", Some(token)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    json_body(res).await
+}
+
+#[tokio::test]
+async fn test_default_quota_blocks_uploads() {
+    let app = app_with_quota(Some(20)).await;
+    let token = app.register("alice", "password123").await;
+
+    let res = app
+        .upload(&token, "a.txt", "text/plain", b"0123456789", None)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = app
+        .upload(&token, "b.txt", "text/plain", b"abcdefghij", None)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let usage = quota(&app, &token).await;
+    assert_eq!(usage["used"], 20);
+    assert_eq!(usage["limit"], 20);
+    assert_eq!(usage["remaining"], 0);
+
+    let res = app.upload(&token, "c.txt", "text/plain", b"x", None).await;
+    assert_eq!(res.status(), StatusCode::INSUFFICIENT_STORAGE);
+    assert_eq!(UserFiles::find().count(&app.db).await.unwrap(), 2);
+    // The rejected upload leaves nothing behind in staging
+    assert!(
+        app.storage
+            .list_objects("staging/")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_deduplicated_content_counts_in_full() {
+    let app = app_with_quota(Some(25)).await;
+    let token = app.register("alice", "password123").await;
+
+    for name in ["a.txt", "b.txt"] {
+        let res = app
+            .upload(&token, name, "text/plain", b"same bytes", None)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+    // One stored object, but the user is charged for both files
+    assert_eq!(StorageFiles::find().count(&app.db).await.unwrap(), 1);
+    assert_eq!(quota(&app, &token).await["used"], 20);
+
+    let res = app
+        .upload(&token, "c.txt", "text/plain", b"same bytes", None)
+        .await;
+    assert_eq!(res.status(), StatusCode::INSUFFICIENT_STORAGE);
+}
+
+#[tokio::test]
+async fn test_bulk_copy_over_quota_is_rolled_back() {
+    let app = app_with_quota(Some(15)).await;
+    let token = app.register("alice", "password123").await;
+
+    let file_id = app.upload_file(&token, "a.txt", b"0123456789", None).await;
+
+    let res = app
+        .post_json(
+            "/files/bulk-copy",
+            Some(&token),
+            json!({ "item_ids": [file_id], "parent_id": null }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::INSUFFICIENT_STORAGE);
+
+    assert_eq!(UserFiles::find().count(&app.db).await.unwrap(), 1);
+    let sf = StorageFiles::find().one(&app.db).await.unwrap().unwrap();
+    assert_eq!(sf.ref_count, 1);
+}
+
+#[tokio::test]
+async fn test_chunked_upload_init_checks_quota() {
+    let app = app_with_quota(Some(1024)).await;
+    let token = app.register("alice", "password123").await;
+
+    let res = app
+        .post_json(
+            "/files/upload/init",
+            Some(&token),
+            json!({ "file_name": "big.bin", "file_type": null, "total_size": 2048 }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::INSUFFICIENT_STORAGE);
+    assert!(
+        json_body(res).await["error"]
+            .as_str()
+            .unwrap()
+            .contains("quota")
+    );
+}
+
+#[tokio::test]
+async fn test_admin_sets_user_quota() {
+    let app = app_with_quota(None).await;
+    let admin = app.register_admin("admin", "password123").await;
+    let alice = app.register("alice", "password123").await;
+    let alice_id = app.user_id(&alice).await;
+    let uri = format!("/admin/users/{}/quota", alice_id);
+
+    // Unlimited by default
+    assert_eq!(quota(&app, &alice).await["limit"], Value::Null);
+
+    // Only admins may change quotas
+    let res = app
+        .send_json(
+            "PUT",
+            &uri,
+            Some(&alice),
+            json!({ "storage_quota": 1 << 30 }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = app
+        .send_json("PUT", &uri, Some(&admin), json!({ "storage_quota": 5 }))
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["limit"], 5);
+
+    let res = app
+        .upload(&alice, "a.txt", "text/plain", b"0123456789", None)
+        .await;
+    assert_eq!(res.status(), StatusCode::INSUFFICIENT_STORAGE);
+
+    // Clearing the quota falls back to the (unlimited) default
+    let res = app
+        .send_json("PUT", &uri, Some(&admin), json!({ "storage_quota": null }))
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = app
+        .upload(&alice, "a.txt", "text/plain", b"0123456789", None)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = app.get(&uri, Some(&admin)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["used"], 10);
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use axum::http::{StatusCode, header};
+use chrono::{Duration, Utc};
+use common::TestApp;
+use rust_file_backend::config::SecurityConfig;
+use rust_file_backend::entities::{prelude::*, rate_limit_events};
+use rust_file_backend::services::rate_limiter::{RateLimitService, UPLOAD_BUCKET};
+use sea_orm::{ActiveModelTrait, EntityTrait, PaginatorTrait, Set};
+use serde_json::json;
+
+async fn app_with_limit(uploads_per_hour: u32) -> TestApp {
+    let mut config = SecurityConfig::development();
+    config.uploads_per_hour = uploads_per_hour;
+    TestApp::with_config(config).await
+}
+
+async fn record_event(app: &TestApp, user_id: &str, age: Duration) {
+    rate_limit_events::ActiveModel {
+        id: Set(uuid::Uuid::new_v4().to_string()),
+        user_id: Set(user_id.to_string()),
+        bucket: Set(UPLOAD_BUCKET.to_string()),
+        created_at: Set(Utc::now() - age),
+    }
+    .insert(&app.db)
+    .await
+    .unwrap();
+}
+
+fn retry_after(res: &axum::response::Response) -> u64 {
+    res.headers()
+        .get(header::RETRY_AFTER)
+        .expect("429 carries Retry-After")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_upload_routes_share_one_limit() {
+    let app = app_with_limit(2).await;
+    let token = app.register("alice", "password123").await;
+
+    let res = app
+        .upload(&token, "a.txt", "text/plain", b"one", None)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = app
+        .post_json(
+            "/files/upload/init",
+            Some(&token),
+            json!({ "file_name": "b.bin", "file_type": null, "total_size": 10 }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = app
+        .upload(&token, "c.txt", "text/plain", b"three", None)
+        .await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    let secs = retry_after(&res);
+    assert!((3500..=3600).contains(&secs), "Retry-After was {}", secs);
+
+    let res = app
+        .post_json(
+            "/files/link",
+            Some(&token),
+            json!({ "storage_file_id": "x", "filename": "d.txt" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Pre-checks have their own budget, and other users are unaffected
+    let res = app
+        .post_json(
+            "/pre-check",
+            Some(&token),
+            json!({ "full_hash": "0".repeat(32), "size": 3 }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let bob = app.register("bob", "password123").await;
+    let res = app.upload(&bob, "a.txt", "text/plain", b"one", None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_window_slides() {
+    let app = app_with_limit(2).await;
+    let token = app.register("alice", "password123").await;
+    let alice = app.user_id(&token).await;
+
+    // Requests older than an hour no longer count
+    record_event(&app, &alice, Duration::minutes(90)).await;
+    record_event(&app, &alice, Duration::minutes(90)).await;
+    let res = app
+        .upload(&token, "a.txt", "text/plain", b"one", None)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // The next slot opens when the oldest request in the window expires
+    record_event(&app, &alice, Duration::minutes(59)).await;
+    let res = app
+        .upload(&token, "b.txt", "text/plain", b"two", None)
+        .await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    let secs = retry_after(&res);
+    assert!((1..=61).contains(&secs), "Retry-After was {}", secs);
+
+    let swept = RateLimitService::sweep(&app.db, Duration::hours(1))
+        .await
+        .unwrap();
+    assert_eq!(swept, 2);
+    assert_eq!(RateLimitEvents::find().count(&app.db).await.unwrap(), 2);
+}
+
+#[tokio::test]
+async fn test_limit_is_shared_between_instances() {
+    let app = app_with_limit(1).await;
+    let token = app.register("alice", "password123").await;
+
+    // A second router on the same database stands in for another API instance
+    let other = rust_file_backend::create_app(app.state.clone());
+
+    let res = app
+        .upload(&token, "a.txt", "text/plain", b"one", None)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let req = axum::http::Request::post("/files/upload/init")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(axum::body::Body::from(
+            json!({ "file_name": "b.bin", "file_type": null, "total_size": 10 }).to_string(),
+        ))
+        .unwrap();
+    let res = tower::ServiceExt::oneshot(other, req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+}
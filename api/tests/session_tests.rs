@@ -0,0 +1,139 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use common::{TestApp, json_body};
+use serde_json::{Value, json};
+
+/// Log in from a client with the given user agent
+async fn login_from(app: &TestApp, user_agent: &str) -> Value {
+    let (captcha_id, captcha_answer) = app.solve_captcha().await;
+    let req = Request::post("/login")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::USER_AGENT, user_agent)
+        .header("x-forwarded-for", "198.51.100.20")
+        .body(Body::from(
+            json!({
+                "username": "alice",
+                "password": "password123",
+                "captcha_id": captcha_id,
+                "captcha_answer": captcha_answer,
+            })
+            .to_string(),
+        ))
+        .unwrap();
+    let res = app.request(req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    json_body(res).await
+}
+
+async fn refresh(app: &TestApp, refresh_token: &str) -> axum::response::Response {
+    app.post_json(
+        "/auth/refresh",
+        None,
+        json!({ "refresh_token": refresh_token }),
+    )
+    .await
+}
+
+async fn me(app: &TestApp, token: &str) -> StatusCode {
+    app.get("/users/me", Some(token)).await.status()
+}
+
+#[tokio::test]
+async fn test_refresh_rotates_and_detects_replay() {
+    let app = TestApp::new().await;
+    app.register("alice", "password123").await;
+    let session = login_from(&app, "laptop").await;
+    assert_eq!(session["expires_in"], 15 * 60);
+    let first_refresh = session["refresh_token"].as_str().unwrap();
+
+    let res = refresh(&app, first_refresh).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let rotated = json_body(res).await;
+    let access = rotated["token"].as_str().unwrap();
+    assert_ne!(rotated["refresh_token"], session["refresh_token"]);
+    assert_eq!(me(&app, access).await, StatusCode::OK);
+
+    // Replaying the swapped token ends the session for everyone holding it
+    assert_eq!(
+        refresh(&app, first_refresh).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(me(&app, access).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        refresh(&app, rotated["refresh_token"].as_str().unwrap())
+            .await
+            .status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    assert_eq!(
+        refresh(&app, "not-a-token").await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn test_logout_revokes_tokens() {
+    let app = TestApp::new().await;
+    app.register("alice", "password123").await;
+    let session = login_from(&app, "laptop").await;
+    let access = session["token"].as_str().unwrap();
+
+    let res = app.post_json("/auth/logout", Some(access), json!({})).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(me(&app, access).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        refresh(&app, session["refresh_token"].as_str().unwrap())
+            .await
+            .status(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn test_list_and_revoke_sessions() {
+    let app = TestApp::new().await;
+    let registered = app.register("alice", "password123").await;
+    let laptop = login_from(&app, "laptop").await;
+    let phone = login_from(&app, "phone").await;
+    let laptop_token = laptop["token"].as_str().unwrap();
+    let phone_token = phone["token"].as_str().unwrap();
+
+    let res = app.get("/auth/sessions", Some(laptop_token)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let sessions = json_body(res).await;
+    let sessions = sessions.as_array().unwrap();
+    assert_eq!(sessions.len(), 3);
+    let current = sessions.iter().find(|s| s["current"] == true).unwrap();
+    assert_eq!(current["device"], "laptop");
+    assert_eq!(current["ip_address"], "198.51.100.20");
+    let phone_session = sessions.iter().find(|s| s["device"] == "phone").unwrap();
+
+    // Another user cannot end alice's sessions
+    let bob = app.register("bob", "password123").await;
+    let uri = format!("/auth/sessions/{}", phone_session["id"].as_str().unwrap());
+    assert_eq!(
+        app.delete(&uri, Some(&bob)).await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    assert_eq!(
+        app.delete(&uri, Some(laptop_token)).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    assert_eq!(me(&app, phone_token).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(me(&app, laptop_token).await, StatusCode::OK);
+
+    // Signing out everywhere includes the caller
+    assert_eq!(
+        app.delete("/auth/sessions", Some(laptop_token))
+            .await
+            .status(),
+        StatusCode::NO_CONTENT
+    );
+    assert_eq!(me(&app, laptop_token).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(me(&app, &registered).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(me(&app, &bob).await, StatusCode::OK);
+}
@@ -0,0 +1,239 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use common::{TestApp, body_bytes, json_body};
+use rust_file_backend::config::SecurityConfig;
+use serde_json::{Value, json};
+
+/// Create a public download share of `file_id` with extra request fields
+async fn public_share(app: &TestApp, token: &str, file_id: &str, extra: Value) -> Value {
+    let mut body = json!({
+        "user_file_id": file_id,
+        "share_type": "public",
+        "permission": "download",
+        "expires_in_hours": 24,
+    });
+    body.as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    let res = app.post_json("/shares", Some(token), body).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    json_body(res).await
+}
+
+async fn download(app: &TestApp, share_token: &str) -> StatusCode {
+    let res = app
+        .get(&format!("/share/{}/download", share_token), None)
+        .await;
+    let status = res.status();
+    let _ = body_bytes(res).await;
+    status
+}
+
+#[tokio::test]
+async fn test_download_limit() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let file_id = app
+        .upload_file(&token, "report.txt", b"numbers", None)
+        .await;
+
+    let share = public_share(&app, &token, &file_id, json!({ "max_downloads": 2 })).await;
+    assert_eq!(share["max_downloads"], 2);
+    assert_eq!(share["remaining_downloads"], 2);
+    let share_token = share["share_token"].as_str().unwrap();
+
+    assert_eq!(download(&app, share_token).await, StatusCode::OK);
+    let res = app.get(&format!("/share/{}", share_token), None).await;
+    assert_eq!(json_body(res).await["remaining_downloads"], 1);
+    assert_eq!(download(&app, share_token).await, StatusCode::OK);
+
+    // The link stops working once the limit is reached
+    assert_eq!(download(&app, share_token).await, StatusCode::GONE);
+    let res = app.get(&format!("/share/{}", share_token), None).await;
+    assert_eq!(res.status(), StatusCode::GONE);
+
+    let res = app
+        .get(&format!("/shares?user_file_id={}", file_id), Some(&token))
+        .await;
+    let listed = json_body(res).await;
+    assert_eq!(listed[0]["download_count"], 2);
+    assert_eq!(listed[0]["remaining_downloads"], 0);
+
+    // Unlimited shares report no remaining count
+    let open = public_share(&app, &token, &file_id, json!({})).await;
+    assert!(open["remaining_downloads"].is_null());
+    assert!(open["remaining_views"].is_null());
+}
+
+#[tokio::test]
+async fn test_burn_after_download() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let file_id = app
+        .upload_file(&token, "report.txt", b"numbers", None)
+        .await;
+
+    let share = public_share(
+        &app,
+        &token,
+        &file_id,
+        json!({ "burn_after_download": true }),
+    )
+    .await;
+    assert_eq!(share["max_downloads"], 1);
+    let share_token = share["share_token"].as_str().unwrap();
+
+    assert_eq!(download(&app, share_token).await, StatusCode::OK);
+    assert_eq!(download(&app, share_token).await, StatusCode::GONE);
+}
+
+/// Fetch `uri` with an optional `Range`, returning the status and the
+/// resume token of a counted download
+async fn ranged_download(
+    app: &TestApp,
+    uri: &str,
+    range: Option<&str>,
+    if_range: Option<&str>,
+) -> (StatusCode, Option<String>) {
+    let mut builder = Request::get(uri);
+    if let Some(range) = range {
+        builder = builder.header(header::RANGE, range);
+    }
+    if let Some(if_range) = if_range {
+        builder = builder.header(header::IF_RANGE, if_range);
+    }
+    let res = app.request(builder.body(Body::empty()).unwrap()).await;
+    let status = res.status();
+    let token = res
+        .headers()
+        .get("x-resume-token")
+        .map(|v| v.to_str().unwrap().to_string());
+    let _ = body_bytes(res).await;
+    (status, token)
+}
+
+#[tokio::test]
+async fn test_resumed_downloads_are_not_counted() {
+    let mut config = SecurityConfig::development();
+    config.download_mode = "stream".to_string();
+    let app = TestApp::with_config(config).await;
+    let token = app.register("alice", "password123").await;
+    let file_id = app
+        .upload_file(&token, "report.txt", b"numbers", None)
+        .await;
+
+    let share = public_share(&app, &token, &file_id, json!({ "max_downloads": 2 })).await;
+    let uri = format!("/share/{}/download", share["share_token"].as_str().unwrap());
+    let remaining = || async {
+        let res = app
+            .get(
+                &format!("/share/{}", share["share_token"].as_str().unwrap()),
+                None,
+            )
+            .await;
+        json_body(res).await["remaining_downloads"].clone()
+    };
+
+    // A player opening the file at byte 0 counts, seeking with its token does not
+    let (status, resume) = ranged_download(&app, &uri, Some("bytes=0-"), None).await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    let resumed = format!("{}?resume={}", uri, resume.unwrap());
+    for _ in 0..3 {
+        let (status, token) = ranged_download(&app, &resumed, Some("bytes=3-"), None).await;
+        assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+        assert!(token.is_none());
+    }
+    assert_eq!(remaining().await, 1);
+
+    // Without a token a late range is a new download
+    let (status, _) = ranged_download(&app, &uri, Some("bytes=1-"), None).await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    let (status, _) = ranged_download(&app, &uri, Some("bytes=1-"), None).await;
+    assert_eq!(status, StatusCode::GONE);
+
+    // The token still finishes its download, but nothing that could return
+    // the whole file is let through
+    let (status, _) = ranged_download(&app, &resumed, Some("bytes=3-"), None).await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    for (range, if_range) in [
+        (None, None),
+        (Some("bytes=0-"), None),
+        (Some("bytes=-999999999"), None),
+        (Some("bytes=3-"), Some("\"stale\"")),
+    ] {
+        let (status, _) = ranged_download(&app, &resumed, range, if_range).await;
+        assert_eq!(status, StatusCode::GONE);
+    }
+
+    // A token only resumes the share that issued it
+    let burn = public_share(
+        &app,
+        &token,
+        &file_id,
+        json!({ "burn_after_download": true }),
+    )
+    .await;
+    let burn_uri = format!("/share/{}/download", burn["share_token"].as_str().unwrap());
+    let (status, burn_resume) = ranged_download(&app, &burn_uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = ranged_download(
+        &app,
+        &format!("{}?resume={}", burn_uri, burn_resume.unwrap()),
+        Some("bytes=3-"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    let (status, _) = ranged_download(
+        &app,
+        &resumed.replace(&uri, &burn_uri),
+        Some("bytes=3-"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::GONE);
+}
+
+#[tokio::test]
+async fn test_view_limit() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let file_id = app
+        .upload_file(&token, "report.txt", b"numbers", None)
+        .await;
+
+    let share = public_share(&app, &token, &file_id, json!({ "max_views": 2 })).await;
+    let share_token = share["share_token"].as_str().unwrap();
+
+    let res = app.get(&format!("/share/{}", share_token), None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["remaining_views"], 1);
+    let res = app.get(&format!("/share/{}", share_token), None).await;
+    assert_eq!(json_body(res).await["remaining_views"], 0);
+    let res = app.get(&format!("/share/{}", share_token), None).await;
+    assert_eq!(res.status(), StatusCode::GONE);
+}
+
+#[tokio::test]
+async fn test_limit_creation_rules() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice", "password123").await;
+    let bob = app.register("bob", "password123").await;
+    let bob_id = app.user_id(&bob).await;
+    let file_id = app
+        .upload_file(&alice, "report.txt", b"numbers", None)
+        .await;
+
+    for body in [
+        json!({ "user_file_id": file_id, "share_type": "public", "permission": "download", "expires_in_hours": 1, "max_downloads": 0 }),
+        json!({ "user_file_id": file_id, "share_type": "public", "permission": "download", "expires_in_hours": 1, "max_views": 0 }),
+        json!({ "user_file_id": file_id, "share_type": "public", "permission": "download", "expires_in_hours": 1, "burn_after_download": true, "max_downloads": 3 }),
+        // Limits belong to public links
+        json!({ "user_file_id": file_id, "share_type": "user", "shared_with_user_id": bob_id, "permission": "download", "expires_in_hours": 1, "max_downloads": 1 }),
+    ] {
+        let res = app.post_json("/shares", Some(&alice), body).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
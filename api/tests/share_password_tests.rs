@@ -0,0 +1,230 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use axum::response::Response;
+use common::{TestApp, json_body};
+use rust_file_backend::config::SecurityConfig;
+use rust_file_backend::entities::{prelude::*, share_access_logs, share_password_attempts};
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+use serde_json::{Value, json};
+
+async fn app_with(ip_attempts: u32, share_attempts: u32, captcha_after: u32) -> TestApp {
+    let mut config = SecurityConfig::development();
+    config.share_password_ip_attempts = ip_attempts;
+    config.share_password_share_attempts = share_attempts;
+    config.share_password_captcha_after = captcha_after;
+    TestApp::with_config(config).await
+}
+
+/// Create a password-protected public share and return its ID and token
+async fn protected_share(app: &TestApp, token: &str) -> (String, String) {
+    let file_id = app
+        .upload_file(token, "secret.txt", b"classified", None)
+        .await;
+    let res = app
+        .post_json(
+            "/shares",
+            Some(token),
+            json!({
+                "user_file_id": file_id,
+                "share_type": "public",
+                "permission": "download",
+                "password": "correct horse",
+                "expires_in_hours": 24,
+            }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let body = json_body(res).await;
+    (
+        body["id"].as_str().unwrap().to_string(),
+        body["share_token"].as_str().unwrap().to_string(),
+    )
+}
+
+/// `POST /share/:token/verify` from `client_ip`
+async fn verify(app: &TestApp, share_token: &str, client_ip: &str, body: Value) -> Response {
+    let req = Request::post(format!("/share/{}/verify", share_token))
+        .header(header::CONTENT_TYPE, "application/json")
+        .header("x-forwarded-for", client_ip)
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    app.request(req).await
+}
+
+async fn guess(app: &TestApp, share_token: &str, client_ip: &str) -> StatusCode {
+    verify(app, share_token, client_ip, json!({ "password": "wrong" }))
+        .await
+        .status()
+}
+
+#[tokio::test]
+async fn test_client_is_locked_out_and_owner_resets() {
+    let app = app_with(3, 100, 0).await;
+    let token = app.register("alice", "password123").await;
+    let (share_id, share_token) = protected_share(&app, &token).await;
+    let attacker = "203.0.113.9";
+
+    for _ in 0..3 {
+        assert_eq!(guess(&app, &share_token, attacker).await, StatusCode::OK);
+    }
+    // Locked out, even with the right password
+    let res = verify(
+        &app,
+        &share_token,
+        attacker,
+        json!({ "password": "correct horse" }),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = res.headers()[header::RETRY_AFTER]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=31).contains(&retry_after));
+
+    // Other clients are unaffected
+    let res = verify(
+        &app,
+        &share_token,
+        "198.51.100.4",
+        json!({ "password": "correct horse" }),
+    )
+    .await;
+    assert_eq!(json_body(res).await["verified"], true);
+
+    let lockouts = ShareAccessLogs::find()
+        .filter(share_access_logs::Column::Action.eq("lockout_ip"))
+        .count(&app.db)
+        .await
+        .unwrap();
+    assert_eq!(lockouts, 1);
+
+    // The owner sees the lockout and lifts it
+    let bob = app.register("bob", "password123").await;
+    let uri = format!("/shares/{}/lockouts", share_id);
+    assert_eq!(
+        app.get(&uri, Some(&bob)).await.status(),
+        StatusCode::NOT_FOUND
+    );
+    let res = app.get(&uri, Some(&token)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let counters = json_body(res).await;
+    let client = counters
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["client_ip"] == attacker)
+        .unwrap();
+    assert_eq!(client["failed_attempts"], 3);
+    assert!(!client["locked_until"].is_null());
+
+    let res = app.delete(&uri, Some(&token)).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = verify(
+        &app,
+        &share_token,
+        attacker,
+        json!({ "password": "correct horse" }),
+    )
+    .await;
+    assert_eq!(json_body(res).await["verified"], true);
+}
+
+#[tokio::test]
+async fn test_share_locks_after_distributed_guessing() {
+    let app = app_with(100, 3, 0).await;
+    let token = app.register("alice", "password123").await;
+    let (_, share_token) = protected_share(&app, &token).await;
+
+    for ip in ["192.0.2.1", "192.0.2.2", "192.0.2.3"] {
+        assert_eq!(guess(&app, &share_token, ip).await, StatusCode::OK);
+    }
+    assert_eq!(
+        guess(&app, &share_token, "192.0.2.4").await,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+
+    let lockouts = ShareAccessLogs::find()
+        .filter(share_access_logs::Column::Action.eq("lockout_share"))
+        .count(&app.db)
+        .await
+        .unwrap();
+    assert_eq!(lockouts, 1);
+}
+
+#[tokio::test]
+async fn test_captcha_required_after_failures() {
+    let app = app_with(10, 100, 2).await;
+    let token = app.register("alice", "password123").await;
+    let (_, share_token) = protected_share(&app, &token).await;
+    let client = "203.0.113.20";
+
+    let res = verify(&app, &share_token, client, json!({ "password": "wrong" })).await;
+    assert_eq!(json_body(res).await["captcha_required"], false);
+    let res = verify(&app, &share_token, client, json!({ "password": "wrong" })).await;
+    assert_eq!(json_body(res).await["captcha_required"], true);
+
+    let res = verify(
+        &app,
+        &share_token,
+        client,
+        json!({ "password": "correct horse" }),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let (captcha_id, captcha_answer) = app.solve_captcha().await;
+    let res = verify(
+        &app,
+        &share_token,
+        client,
+        json!({
+            "password": "correct horse",
+            "captcha_id": captcha_id,
+            "captcha_answer": captcha_answer,
+        }),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["verified"], true);
+
+    // Success clears the client's counter
+    let res = verify(&app, &share_token, client, json!({ "password": "wrong" })).await;
+    assert_eq!(json_body(res).await["captcha_required"], false);
+}
+
+#[tokio::test]
+async fn test_parallel_guesses_are_counted_before_checking() {
+    let app = app_with(3, 100, 0).await;
+    let token = app.register("alice", "password123").await;
+    let (_, share_token) = protected_share(&app, &token).await;
+
+    let burst = (0..10).map(|_| guess(&app, &share_token, "203.0.113.9"));
+    let statuses = futures::future::join_all(burst).await;
+    let answered = statuses.iter().filter(|s| **s == StatusCode::OK).count();
+    let locked = statuses
+        .iter()
+        .filter(|s| **s == StatusCode::TOO_MANY_REQUESTS)
+        .count();
+    assert_eq!((answered, locked), (3, 7));
+
+    // A correct password does not count against the share-wide limit
+    let res = verify(
+        &app,
+        &share_token,
+        "198.51.100.4",
+        json!({ "password": "correct horse" }),
+    )
+    .await;
+    assert_eq!(json_body(res).await["verified"], true);
+    let shared = SharePasswordAttempts::find()
+        .filter(share_password_attempts::Column::ClientIp.is_null())
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(shared.failed_attempts, 3);
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{Request, StatusCode, header};
+use common::{TestApp, body_bytes, json_body};
+use rust_file_backend::config::SecurityConfig;
+use rust_file_backend::entities::{prelude::*, share_access_logs};
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+
+/// Upload a file and share it publicly with extra request fields
+async fn restricted_share(app: &TestApp, token: &str, extra: Value) -> String {
+    let file_id = app
+        .upload_file(token, "handbook.txt", b"internal only", None)
+        .await;
+    let mut body = json!({
+        "user_file_id": file_id,
+        "share_type": "public",
+        "permission": "download",
+        "expires_in_hours": 24,
+    });
+    body.as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    let res = app.post_json("/shares", Some(token), body).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    json_body(res).await["share_token"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+/// GET `uri` with the given request headers
+async fn get_with(app: &TestApp, uri: &str, headers: &[(&str, &str)]) -> StatusCode {
+    let mut builder = Request::get(uri);
+    for (name, value) in headers {
+        builder = builder.header(*name, *value);
+    }
+    let res = app.request(builder.body(Body::empty()).unwrap()).await;
+    let status = res.status();
+    let _ = body_bytes(res).await;
+    status
+}
+
+async fn denials(app: &TestApp) -> u64 {
+    ShareAccessLogs::find()
+        .filter(share_access_logs::Column::Action.eq("access_denied"))
+        .count(&app.db)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_ip_allow_and_deny_lists() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let share_token = restricted_share(
+        &app,
+        &token,
+        json!({ "allowed_ips": ["10.0.0.0/8"], "denied_ips": ["10.66.0.0/16"] }),
+    )
+    .await;
+
+    let res = app.get("/shares", Some(&token)).await;
+    let listed = json_body(res).await;
+    assert_eq!(listed[0]["allowed_ips"], json!(["10.0.0.0/8"]));
+    assert_eq!(listed[0]["denied_ips"], json!(["10.66.0.0/16"]));
+
+    for path in ["", "/download"] {
+        let uri = format!("/share/{}{}", share_token, path);
+        let office = [("x-forwarded-for", "10.1.2.3")];
+        assert_eq!(get_with(&app, &uri, &office).await, StatusCode::OK);
+        let outside = [("x-forwarded-for", "203.0.113.7")];
+        assert_eq!(get_with(&app, &uri, &outside).await, StatusCode::FORBIDDEN);
+        let denied = [("x-forwarded-for", "10.66.4.5")];
+        assert_eq!(get_with(&app, &uri, &denied).await, StatusCode::FORBIDDEN);
+    }
+    assert_eq!(denials(&app).await, 4);
+}
+
+#[tokio::test]
+async fn test_referrer_restriction() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let share_token = restricted_share(
+        &app,
+        &token,
+        json!({ "allowed_referrers": ["https://Intranet.example.com/wiki"] }),
+    )
+    .await;
+    let uri = format!("/share/{}", share_token);
+
+    assert_eq!(get_with(&app, &uri, &[]).await, StatusCode::FORBIDDEN);
+    let foreign = [(header::REFERER.as_str(), "https://elsewhere.net/")];
+    assert_eq!(get_with(&app, &uri, &foreign).await, StatusCode::FORBIDDEN);
+    let intranet = [(
+        header::REFERER.as_str(),
+        "https://intranet.example.com/docs",
+    )];
+    assert_eq!(get_with(&app, &uri, &intranet).await, StatusCode::OK);
+    let list_uri = format!("/share/{}/list", share_token);
+    assert_eq!(get_with(&app, &list_uri, &[]).await, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_forwarded_for_only_trusted_from_proxies() {
+    let mut config = SecurityConfig::development();
+    config.trusted_proxies = vec!["172.16.0.0/12".parse().unwrap()];
+    let app = TestApp::with_config(config).await;
+    let token = app.register("alice", "password123").await;
+    let share_token =
+        restricted_share(&app, &token, json!({ "allowed_ips": ["198.51.100.0/24"] })).await;
+
+    let open = |peer: &str, forwarded: &str| {
+        let mut req = Request::get(format!("/share/{}", share_token))
+            .header("x-forwarded-for", forwarded)
+            .body(Body::empty())
+            .unwrap();
+        req.extensions_mut()
+            .insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+        app.request(req)
+    };
+
+    // Through the proxy
+    assert_eq!(
+        open("172.16.0.2:443", "198.51.100.7").await.status(),
+        StatusCode::OK
+    );
+    // Straight from outside, claiming an office address
+    assert_eq!(
+        open("203.0.113.9:5000", "198.51.100.7").await.status(),
+        StatusCode::FORBIDDEN
+    );
+    // Through the proxy, with an office address forged in front
+    assert_eq!(
+        open("172.16.0.2:443", "198.51.100.7, 203.0.113.9")
+            .await
+            .status(),
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn test_forwarded_for_ignored_without_trusted_proxies() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let share_token =
+        restricted_share(&app, &token, json!({ "allowed_ips": ["198.51.100.0/24"] })).await;
+
+    let open = |peer: &str, forwarded: &str| {
+        let mut req = Request::get(format!("/share/{}", share_token))
+            .header("x-forwarded-for", forwarded)
+            .header("x-real-ip", forwarded)
+            .body(Body::empty())
+            .unwrap();
+        req.extensions_mut()
+            .insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+        app.request(req)
+    };
+
+    assert_eq!(
+        open("198.51.100.7:5000", "203.0.113.9").await.status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        open("203.0.113.9:5000", "198.51.100.7").await.status(),
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn test_restriction_creation_rules() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice", "password123").await;
+    let bob = app.register("bob", "password123").await;
+    let bob_id = app.user_id(&bob).await;
+    let file_id = app.upload_file(&alice, "a.txt", b"a", None).await;
+
+    for body in [
+        json!({ "user_file_id": file_id, "share_type": "public", "permission": "view", "expires_in_hours": 1, "allowed_ips": ["10.0.0.0/33"] }),
+        json!({ "user_file_id": file_id, "share_type": "public", "permission": "view", "expires_in_hours": 1, "denied_ips": [] }),
+        json!({ "user_file_id": file_id, "share_type": "public", "permission": "view", "expires_in_hours": 1, "allowed_referrers": ["example.com/path"] }),
+        // Restrictions belong to public links
+        json!({ "user_file_id": file_id, "share_type": "user", "shared_with_user_id": bob_id, "permission": "view", "expires_in_hours": 1, "allowed_ips": ["10.0.0.0/8"] }),
+    ] {
+        let res = app.post_json("/shares", Some(&alice), body).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,287 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use axum::response::Response;
+use bytes::Bytes;
+use common::{TestApp, json_body};
+use futures::channel::mpsc;
+use rust_file_backend::entities::{prelude::*, share_access_logs, user_files};
+use rust_file_backend::services::storage::StorageService;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde_json::{Value, json};
+
+const BOUNDARY: &str = "share-upload-boundary";
+
+async fn create_share(app: &TestApp, token: &str, body: Value) -> Response {
+    app.post_json("/shares", Some(token), body).await
+}
+
+async fn upload_share(app: &TestApp, token: &str, folder_id: &str, extra: Value) -> String {
+    let mut body = json!({
+        "user_file_id": folder_id,
+        "share_type": "public",
+        "permission": "upload",
+        "expires_in_hours": 24,
+    });
+    body.as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    let res = create_share(app, token, body).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    json_body(res).await["share_token"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+/// Anonymous `POST /share/:token/upload`
+async fn share_upload(
+    app: &TestApp,
+    share_token: &str,
+    filename: &str,
+    content_type: &str,
+    data: &[u8],
+    password: Option<&str>,
+) -> Response {
+    let mut body = format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+        BOUNDARY, filename, content_type
+    )
+    .into_bytes();
+    body.extend_from_slice(data);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+
+    let mut builder = Request::post(format!("/share/{}/upload", share_token)).header(
+        header::CONTENT_TYPE,
+        format!("multipart/form-data; boundary={}", BOUNDARY),
+    );
+    if let Some(password) = password {
+        builder = builder.header("x-share-password", password);
+    }
+    app.request(builder.body(Body::from(body)).unwrap()).await
+}
+
+/// Share upload whose body stays open after `data`, as a client still sending would
+async fn open_ended_share_upload(
+    app: &TestApp,
+    share_token: &str,
+    data: &[u8],
+    content_length: Option<u64>,
+) -> (
+    Response,
+    mpsc::UnboundedSender<Result<Bytes, std::io::Error>>,
+) {
+    let (tx, rx) = mpsc::unbounded();
+    let mut head = format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"big.txt\"\r\nContent-Type: text/plain\r\n\r\n",
+        BOUNDARY
+    )
+    .into_bytes();
+    head.extend_from_slice(data);
+    tx.unbounded_send(Ok(Bytes::from(head))).unwrap();
+
+    let mut builder = Request::post(format!("/share/{}/upload", share_token)).header(
+        header::CONTENT_TYPE,
+        format!("multipart/form-data; boundary={}", BOUNDARY),
+    );
+    if let Some(len) = content_length {
+        builder = builder.header(header::CONTENT_LENGTH, len);
+    }
+    let req = builder.body(Body::from_stream(rx)).unwrap();
+    let res = tokio::time::timeout(std::time::Duration::from_secs(5), app.request(req))
+        .await
+        .expect("the upload was refused before the body ended");
+    (res, tx)
+}
+
+async fn folder_contents(app: &TestApp, folder_id: &str) -> Vec<String> {
+    let mut names: Vec<String> = UserFiles::find()
+        .filter(user_files::Column::ParentId.eq(folder_id))
+        .all(&app.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|f| f.filename)
+        .collect();
+    names.sort();
+    names
+}
+
+#[tokio::test]
+async fn test_anonymous_upload_into_shared_folder() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let inbox = app.create_folder(&token, "inbox", None).await;
+    let res = app
+        .upload(
+            &token,
+            "notes.txt",
+            "text/plain",
+            b"owner's notes",
+            Some(&inbox),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let share_token = upload_share(&app, &token, &inbox, json!({ "max_uploads": 2 })).await;
+
+    let res = app.get(&format!("/share/{}", share_token), None).await;
+    let info = json_body(res).await;
+    assert_eq!(info["permission"], "upload");
+    assert_eq!(info["upload"]["remaining_uploads"], 2);
+
+    // A clashing name is kept alongside the owner's file, never replacing it
+    let res = share_upload(&app, &share_token, "notes.txt", "text/plain", b"hi", None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["filename"], "notes (1).txt");
+    let res = share_upload(&app, &share_token, "b.txt", "text/plain", b"bravo", None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = share_upload(&app, &share_token, "c.txt", "text/plain", b"charlie", None).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    assert_eq!(
+        folder_contents(&app, &inbox).await,
+        ["b.txt", "notes (1).txt", "notes.txt"]
+    );
+    let owned = UserFiles::find()
+        .filter(user_files::Column::ParentId.eq(&inbox))
+        .all(&app.db)
+        .await
+        .unwrap();
+    assert!(owned.iter().all(|f| f.user_id == owned[0].user_id));
+
+    let uploads = ShareAccessLogs::find()
+        .filter(share_access_logs::Column::Action.eq("upload"))
+        .all(&app.db)
+        .await
+        .unwrap();
+    assert_eq!(uploads.len(), 2);
+
+    // Uploaders cannot browse the folder
+    let res = app.get(&format!("/share/{}/list", share_token), None).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_upload_share_restrictions() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let inbox = app.create_folder(&token, "inbox", None).await;
+
+    let share_token = upload_share(
+        &app,
+        &token,
+        &inbox,
+        json!({
+            "password": "letmein",
+            "max_upload_size": 10,
+            "allowed_upload_types": ["txt", "image/*"],
+        }),
+    )
+    .await;
+
+    let res = share_upload(&app, &share_token, "a.txt", "text/plain", b"alpha", None).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let res = share_upload(
+        &app,
+        &share_token,
+        "a.txt",
+        "text/plain",
+        b"alpha",
+        Some("wrong"),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let pass = Some("letmein");
+    let res = share_upload(&app, &share_token, "a.csv", "text/csv", b"1,2", pass).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = share_upload(
+        &app,
+        &share_token,
+        "big.txt",
+        "text/plain",
+        b"more than ten bytes",
+        pass,
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let res = share_upload(&app, &share_token, "a.txt", "text/plain", b"alpha", pass).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Executables are refused by the normal validation pipeline
+    let res = share_upload(
+        &app,
+        &share_token,
+        "a.exe",
+        "image/png",
+        b"MZ\x90\x00\x03",
+        pass,
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    assert_eq!(folder_contents(&app, &inbox).await, ["a.txt"]);
+}
+
+#[tokio::test]
+async fn test_oversized_share_uploads_are_cut_off() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let inbox = app.create_folder(&token, "inbox", None).await;
+    let share_token = upload_share(&app, &token, &inbox, json!({ "max_upload_size": 10 })).await;
+
+    // Refused as soon as the file passes the limit, not once it has all arrived
+    let (res, _tx) = open_ended_share_upload(&app, &share_token, &[b'a'; 64], None).await;
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(
+        app.storage
+            .list_objects("staging/")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    // A declared length that cannot fit is refused before reading anything
+    let (res, _tx) = open_ended_share_upload(&app, &share_token, b"", Some(1 << 20)).await;
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    assert!(folder_contents(&app, &inbox).await.is_empty());
+    let res = share_upload(&app, &share_token, "a.txt", "text/plain", b"alpha", None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_upload_share_creation_rules() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let inbox = app.create_folder(&token, "inbox", None).await;
+    let file_id = app.upload_file(&token, "a.txt", b"alpha", None).await;
+
+    for body in [
+        // Only folders collect uploads
+        json!({ "user_file_id": file_id, "share_type": "public", "permission": "upload", "expires_in_hours": 1 }),
+        // Limits belong to upload shares
+        json!({ "user_file_id": inbox, "share_type": "public", "permission": "download", "expires_in_hours": 1, "max_uploads": 3 }),
+        json!({ "user_file_id": inbox, "share_type": "public", "permission": "upload", "expires_in_hours": 1, "max_uploads": 0 }),
+    ] {
+        let res = create_share(&app, &token, body).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // Download shares do not accept uploads
+    let res = create_share(
+        &app,
+        &token,
+        json!({ "user_file_id": inbox, "share_type": "public", "permission": "download", "expires_in_hours": 1 }),
+    )
+    .await;
+    let download_token = json_body(res).await["share_token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let res = share_upload(&app, &download_token, "a.txt", "text/plain", b"x", None).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
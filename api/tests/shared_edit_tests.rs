@@ -0,0 +1,289 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use axum::response::Response;
+use common::{TestApp, json_body};
+use rust_file_backend::entities::{audit_logs, prelude::*, user_files};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde_json::json;
+
+const BOUNDARY: &str = "shared-edit-boundary";
+
+async fn share_with(
+    app: &TestApp,
+    token: &str,
+    item_id: &str,
+    recipient: &str,
+    permission: &str,
+) -> Response {
+    app.post_json(
+        "/shares",
+        Some(token),
+        json!({
+            "user_file_id": item_id,
+            "share_type": "user",
+            "shared_with_user_id": recipient,
+            "permission": permission,
+            "expires_in_hours": 24,
+        }),
+    )
+    .await
+}
+
+async fn edit_share(app: &TestApp, token: &str, folder_id: &str, recipient: &str) -> String {
+    let res = share_with(app, token, folder_id, recipient, "edit").await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    json_body(res).await["id"].as_str().unwrap().to_string()
+}
+
+/// `POST /shares/incoming/:id/upload`
+async fn shared_upload(
+    app: &TestApp,
+    token: &str,
+    share_id: &str,
+    folder_id: Option<&str>,
+    filename: &str,
+    data: &[u8],
+) -> Response {
+    let mut body = format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: text/plain\r\n\r\n",
+        BOUNDARY, filename
+    )
+    .into_bytes();
+    body.extend_from_slice(data);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+
+    let mut uri = format!("/shares/incoming/{}/upload", share_id);
+    if let Some(folder_id) = folder_id {
+        uri.push_str(&format!("?folder_id={}", folder_id));
+    }
+    let req = Request::post(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={}", BOUNDARY),
+        )
+        .body(Body::from(body))
+        .unwrap();
+    app.request(req).await
+}
+
+async fn item(app: &TestApp, id: &str) -> Option<user_files::Model> {
+    UserFiles::find_by_id(id)
+        .filter(user_files::Column::DeletedAt.is_null())
+        .one(&app.db)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_recipient_edits_shared_folder() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice", "password123").await;
+    let bob = app.register("bob", "password123").await;
+    let alice_id = app.user_id(&alice).await;
+    let bob_id = app.user_id(&bob).await;
+
+    let project = app.create_folder(&alice, "project", None).await;
+    let share_id = edit_share(&app, &alice, &project, &bob_id).await;
+
+    // Bob creates a subfolder and uploads into it
+    let res = app
+        .post_json(
+            &format!("/shares/incoming/{}/folders", share_id),
+            Some(&bob),
+            json!({ "name": "drafts" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let drafts = json_body(res).await["id"].as_str().unwrap().to_string();
+
+    let res = shared_upload(&app, &bob, &share_id, Some(&drafts), "plan.txt", b"v1").await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let plan = json_body(res).await["file_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // Everything belongs to, and is charged against, the owner
+    let stored = item(&app, &plan).await.unwrap();
+    assert_eq!(stored.user_id, alice_id);
+    assert_eq!(stored.parent_id.as_deref(), Some(drafts.as_str()));
+    assert_eq!(item(&app, &drafts).await.unwrap().user_id, alice_id);
+    let res = app.get("/users/me/quota", Some(&alice)).await;
+    assert_eq!(json_body(res).await["used"], 2);
+    let res = app.get("/users/me/quota", Some(&bob)).await;
+    assert_eq!(json_body(res).await["used"], 0);
+
+    // Rename, then move back up to the shared folder
+    let res = app
+        .send_json(
+            "PUT",
+            &format!("/shares/incoming/{}/items/{}", share_id, plan),
+            Some(&bob),
+            json!({ "name": "final.txt" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = app
+        .send_json(
+            "PUT",
+            &format!("/shares/incoming/{}/items/{}", share_id, plan),
+            Some(&bob),
+            json!({ "parent_id": project }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let moved = item(&app, &plan).await.unwrap();
+    assert_eq!(moved.filename, "final.txt");
+    assert_eq!(moved.parent_id.as_deref(), Some(project.as_str()));
+
+    let res = app
+        .delete(
+            &format!("/shares/incoming/{}/items/{}", share_id, drafts),
+            Some(&bob),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert!(item(&app, &drafts).await.is_none());
+
+    // Each change is audited against the acting user
+    let mut actions: Vec<String> = AuditLogs::find()
+        .filter(audit_logs::Column::EventType.eq("ShareEdit"))
+        .all(&app.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .inspect(|log| assert_eq!(log.user_id.as_deref(), Some(bob_id.as_str())))
+        .map(|log| log.action)
+        .collect();
+    actions.sort();
+    assert_eq!(
+        actions,
+        [
+            "share_create_folder",
+            "share_delete",
+            "share_move",
+            "share_rename",
+            "share_upload"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_edits_stay_inside_the_share() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice", "password123").await;
+    let bob = app.register("bob", "password123").await;
+    let bob_id = app.user_id(&bob).await;
+
+    let project = app.create_folder(&alice, "project", None).await;
+    let inner = app.create_folder(&alice, "inner", Some(&project)).await;
+    let private = app.create_folder(&alice, "private", None).await;
+    let share_id = edit_share(&app, &alice, &project, &bob_id).await;
+    let items = |id: &str| format!("/shares/incoming/{}/items/{}", share_id, id);
+
+    // Nothing outside the shared folder can be touched or targeted
+    let res = app.delete(&items(&private), Some(&bob)).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    for target in [private.as_str(), "root"] {
+        let res = app
+            .send_json(
+                "PUT",
+                &items(&inner),
+                Some(&bob),
+                json!({ "parent_id": target }),
+            )
+            .await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+    let res = app
+        .post_json(
+            &format!("/shares/incoming/{}/folders", share_id),
+            Some(&bob),
+            json!({ "name": "sneaky", "parent_id": private }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = shared_upload(&app, &bob, &share_id, Some(&private), "x.txt", b"x").await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // The shared folder itself is left to its owner
+    let res = app.delete(&items(&project), Some(&bob)).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = app
+        .send_json(
+            "PUT",
+            &items(&project),
+            Some(&bob),
+            json!({ "name": "mine" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    assert!(item(&app, &private).await.is_some());
+    assert_eq!(
+        item(&app, &inner).await.unwrap().parent_id.as_deref(),
+        Some(project.as_str())
+    );
+}
+
+#[tokio::test]
+async fn test_edit_permission_rules() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice", "password123").await;
+    let bob = app.register("bob", "password123").await;
+    let carol = app.register("carol", "password123").await;
+    let bob_id = app.user_id(&bob).await;
+
+    let project = app.create_folder(&alice, "project", None).await;
+    let file_id = app
+        .upload_file(&alice, "a.txt", b"alpha", Some(&project))
+        .await;
+
+    // Only user shares of folders can grant editing
+    let res = share_with(&app, &alice, &file_id, &bob_id, "edit").await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = app
+        .post_json(
+            "/shares",
+            Some(&alice),
+            json!({ "user_file_id": project, "share_type": "public", "permission": "edit", "expires_in_hours": 1 }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    // Read-only shares refuse changes
+    let res = share_with(&app, &alice, &project, &bob_id, "download").await;
+    let download_id = json_body(res).await["id"].as_str().unwrap().to_string();
+    let res = app
+        .delete(
+            &format!("/shares/incoming/{}/items/{}", download_id, file_id),
+            Some(&bob),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = shared_upload(&app, &bob, &download_id, None, "b.txt", b"bravo").await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // Only the recipient can edit
+    let share_id = edit_share(&app, &alice, &project, &bob_id).await;
+    let res = app
+        .delete(
+            &format!("/shares/incoming/{}/items/{}", share_id, file_id),
+            Some(&carol),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(item(&app, &file_id).await.is_some());
+
+    // Edit shares can also be read
+    let res = app
+        .get(
+            &format!("/shares/incoming/{}/download?file_id={}", share_id, file_id),
+            Some(&bob),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
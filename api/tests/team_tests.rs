@@ -0,0 +1,353 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{TestApp, json_body};
+use rust_file_backend::entities::prelude::*;
+use sea_orm::{EntityTrait, PaginatorTrait};
+use serde_json::{Value, json};
+
+async fn create_team(app: &TestApp, token: &str, name: &str) -> String {
+    let res = app
+        .post_json("/teams", Some(token), json!({ "name": name }))
+        .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let body = json_body(res).await;
+    assert_eq!(body["role"], "owner");
+    body["id"].as_str().unwrap().to_string()
+}
+
+async fn add_member(app: &TestApp, owner: &str, team_id: &str, username: &str, role: &str) {
+    let res = app
+        .post_json(
+            &format!("/teams/{}/members", team_id),
+            Some(owner),
+            json!({ "username": username, "role": role }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
+
+async fn create_team_folder(app: &TestApp, token: &str, team_id: &str, name: &str) -> String {
+    let res = app
+        .post_json(
+            "/folders",
+            Some(token),
+            json!({ "name": name, "team_id": team_id }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = json_body(res).await;
+    assert_eq!(body["team_id"], team_id);
+    body["id"].as_str().unwrap().to_string()
+}
+
+async fn names(app: &TestApp, token: &str, uri: &str) -> Vec<String> {
+    let res = app.get(uri, Some(token)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    json_body(res)
+        .await
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["filename"].as_str().unwrap().to_string())
+        .collect()
+}
+
+async fn team_usage(app: &TestApp, token: &str, team_id: &str) -> Value {
+    let res = app
+        .get(&format!("/teams/{}/usage", team_id), Some(token))
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    json_body(res).await
+}
+
+#[tokio::test]
+async fn test_team_drive_access_follows_roles() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice", "password123").await;
+    let bob = app.register("bob", "password123").await;
+    let carol = app.register("carol", "password123").await;
+    let dave = app.register("dave", "password123").await;
+
+    let team = create_team(&app, &alice, "Finance").await;
+    add_member(&app, &alice, &team, "bob", "editor").await;
+    add_member(&app, &alice, &team, "carol", "viewer").await;
+
+    let folder = create_team_folder(&app, &alice, &team, "Reports").await;
+    let file = app
+        .upload_file(&bob, "q3.txt", b"0123456789", Some(&folder))
+        .await;
+
+    // Team files stay out of personal drives
+    assert!(names(&app, &alice, "/files").await.is_empty());
+    let team_root = format!("/files?team_id={}", team);
+    assert_eq!(names(&app, &carol, &team_root).await, ["Reports"]);
+    assert_eq!(
+        names(&app, &carol, &format!("/files?parent_id={}", folder)).await,
+        ["q3.txt"]
+    );
+    let res = app.get(&format!("/files/{}", file), Some(&carol)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Viewers read but never write
+    let res = app
+        .upload(&carol, "mine.txt", "text/plain", b"nope", Some(&folder))
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = app
+        .send_json(
+            "PUT",
+            &format!("/files/{}/rename", file),
+            Some(&carol),
+            json!({ "name": "renamed.txt" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = app.delete(&format!("/files/{}", file), Some(&carol)).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // Outsiders see nothing at all
+    for uri in [
+        format!("/files/{}", file),
+        format!("/teams/{}", team),
+        team_root.clone(),
+    ] {
+        assert_eq!(
+            app.get(&uri, Some(&dave)).await.status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+    assert!(
+        names(&app, &dave, &format!("/files?parent_id={}", folder))
+            .await
+            .is_empty()
+    );
+
+    // Editors change files that others added
+    let res = app
+        .send_json(
+            "PUT",
+            &format!("/files/{}/rename", folder),
+            Some(&bob),
+            json!({ "name": "Quarterly" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(names(&app, &carol, &team_root).await, ["Quarterly"]);
+}
+
+#[tokio::test]
+async fn test_moving_between_personal_and_team_drives() {
+    let app = TestApp::new().await;
+    let admin = app.register_admin("admin", "password123").await;
+    let alice = app.register("alice", "password123").await;
+    let team = create_team(&app, &alice, "Design").await;
+
+    let logo = app
+        .upload_file(&alice, "logo.txt", b"0123456789", None)
+        .await;
+    let res = app
+        .post_json(
+            "/files/bulk-move",
+            Some(&alice),
+            json!({ "item_ids": [logo], "team_id": team }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["moved_count"], 1);
+
+    // The bytes are now charged to the team, not to alice
+    assert_eq!(team_usage(&app, &alice, &team).await["used"], 10);
+    let res = app.get("/users/me/quota", Some(&alice)).await;
+    assert_eq!(json_body(res).await["used"], 0);
+    assert!(names(&app, &alice, "/files").await.is_empty());
+
+    let res = app
+        .send_json(
+            "PUT",
+            &format!("/admin/teams/{}/quota", team),
+            Some(&admin),
+            json!({ "storage_quota": 15 }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["remaining"], 5);
+
+    let banner = app
+        .upload_file(&alice, "banner.txt", b"0123456789", None)
+        .await;
+    let res = app
+        .post_json(
+            "/files/bulk-move",
+            Some(&alice),
+            json!({ "item_ids": [banner], "team_id": team }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::INSUFFICIENT_STORAGE);
+    assert_eq!(names(&app, &alice, "/files").await, ["banner.txt"]);
+
+    // Moving back to the personal root takes neither parent nor team
+    let res = app
+        .post_json(
+            "/files/bulk-move",
+            Some(&alice),
+            json!({ "item_ids": [logo] }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(team_usage(&app, &alice, &team).await["used"], 0);
+    let mut personal = names(&app, &alice, "/files").await;
+    personal.sort();
+    assert_eq!(personal, ["banner.txt", "logo.txt"]);
+}
+
+#[tokio::test]
+async fn test_moving_a_folder_takes_its_trashed_contents() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice", "password123").await;
+    let team = create_team(&app, &alice, "Design").await;
+
+    let folder = app.create_folder(&alice, "Drafts", None).await;
+    let draft = app
+        .upload_file(&alice, "draft.txt", b"0123456789", Some(&folder))
+        .await;
+    app.delete(&format!("/files/{}", draft), Some(&alice)).await;
+
+    let res = app
+        .post_json(
+            "/files/bulk-move",
+            Some(&alice),
+            json!({ "item_ids": [folder], "team_id": team }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // The trashed file follows its folder and is charged to the team
+    let trashed = UserFiles::find_by_id(&draft)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(trashed.team_id.as_deref(), Some(team.as_str()));
+    assert_eq!(team_usage(&app, &alice, &team).await["used"], 10);
+    let res = app.get("/users/me/quota", Some(&alice)).await;
+    assert_eq!(json_body(res).await["used"], 0);
+}
+
+#[tokio::test]
+async fn test_removed_members_leave_their_files_to_the_team() {
+    let app = TestApp::new().await;
+    let admin = app.register_admin("admin", "password123").await;
+    let alice = app.register("alice", "password123").await;
+    let bob = app.register("bob", "password123").await;
+    let alice_id = app.user_id(&alice).await;
+    let bob_id = app.user_id(&bob).await;
+
+    let team = create_team(&app, &alice, "Ops").await;
+    add_member(&app, &alice, &team, "bob", "editor").await;
+    let folder = create_team_folder(&app, &alice, &team, "Runbooks").await;
+    let file = app
+        .upload_file(&bob, "restart.txt", b"0123456789", Some(&folder))
+        .await;
+
+    let res = app
+        .delete(&format!("/teams/{}/members/{}", team, bob_id), Some(&alice))
+        .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = app
+        .delete(&format!("/admin/users/{}", bob_id), Some(&admin))
+        .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    // Bob's file now belongs to alice and still holds its content
+    let kept = UserFiles::find_by_id(&file)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(kept.user_id, alice_id);
+    let content = StorageFiles::find_by_id(kept.storage_file_id.unwrap())
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(content.ref_count, 1);
+    assert_eq!(
+        names(&app, &alice, &format!("/files?parent_id={}", folder)).await,
+        ["restart.txt"]
+    );
+}
+
+#[tokio::test]
+async fn test_teams_always_keep_an_owner() {
+    let app = TestApp::new().await;
+    let admin = app.register_admin("admin", "password123").await;
+    let alice = app.register("alice", "password123").await;
+    let bob = app.register("bob", "password123").await;
+    let alice_id = app.user_id(&alice).await;
+    let bob_id = app.user_id(&bob).await;
+
+    let team = create_team(&app, &alice, "Legal").await;
+    add_member(&app, &alice, &team, "bob", "viewer").await;
+    let res = app
+        .post_json(
+            &format!("/teams/{}/members", team),
+            Some(&alice),
+            json!({ "username": "bob", "role": "editor" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    // The last owner can neither step down nor leave
+    let alice_member = format!("/teams/{}/members/{}", team, alice_id);
+    let res = app
+        .send_json(
+            "PUT",
+            &alice_member,
+            Some(&alice),
+            json!({ "role": "editor" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = app.delete(&alice_member, Some(&alice)).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    // Only owners manage members, but anyone may leave
+    let bob_member = format!("/teams/{}/members/{}", team, bob_id);
+    let res = app
+        .send_json("PUT", &bob_member, Some(&bob), json!({ "role": "owner" }))
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = app
+        .send_json(
+            "PUT",
+            &bob_member,
+            Some(&alice),
+            json!({ "role": "editor" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let folder = create_team_folder(&app, &alice, &team, "Contracts").await;
+    app.upload_file(&bob, "nda.txt", b"0123456789", Some(&folder))
+        .await;
+
+    // Deleting alice hands the team and her folder over to bob
+    let res = app
+        .delete(&format!("/admin/users/{}", alice_id), Some(&admin))
+        .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = app.get(&format!("/teams/{}", team), Some(&bob)).await;
+    assert_eq!(json_body(res).await["role"], "owner");
+    assert_eq!(
+        names(&app, &bob, &format!("/files?team_id={}", team)).await,
+        ["Contracts"]
+    );
+
+    let res = app.delete(&format!("/teams/{}", team), Some(&bob)).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(UserFiles::find().count(&app.db).await.unwrap(), 0);
+    assert_eq!(StorageFiles::find().count(&app.db).await.unwrap(), 0);
+    let res = app.get("/teams", Some(&bob)).await;
+    assert!(json_body(res).await.as_array().unwrap().is_empty());
+}
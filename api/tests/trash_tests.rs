@@ -0,0 +1,247 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{TestApp, json_body};
+use rust_file_backend::config::SecurityConfig;
+use rust_file_backend::entities::{prelude::*, user_files};
+use rust_file_backend::services::storage::StorageService;
+use rust_file_backend::services::storage_lifecycle::StorageLifecycleService;
+use sea_orm::{ActiveModelTrait, ConnectionTrait, EntityTrait, Set};
+use serde_json::{Value, json};
+
+async fn trash(app: &TestApp, token: &str) -> Vec<Value> {
+    let res = app.get("/trash", Some(token)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    json_body(res).await.as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn test_trash_and_restore_folder() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+
+    let folder = app.create_folder(&token, "Docs", None).await;
+    let file = app
+        .upload_file(&token, "a.txt", b"trash me", Some(&folder))
+        .await;
+
+    let res = app
+        .delete(&format!("/files/{}", folder), Some(&token))
+        .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    // Storage is kept while the item sits in the trash
+    let sf = StorageFiles::find().one(&app.db).await.unwrap().unwrap();
+    assert_eq!(sf.ref_count, 1);
+    assert!(app.storage.get_file(&sf.s3_key).await.is_ok());
+
+    // Only the folder the user deleted is listed
+    let items = trash(&app, &token).await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["id"], folder);
+    assert_eq!(items[0]["is_folder"], true);
+    let res = app.get(&format!("/files/{}", file), Some(&token)).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = app
+        .post_json(
+            &format!("/trash/{}/restore", folder),
+            Some(&token),
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["filename"], "Docs");
+
+    assert!(trash(&app, &token).await.is_empty());
+    let res = app.get(&format!("/files/{}", file), Some(&token)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Items that are not in the trash cannot be restored, nor can other users' items
+    let other = app.register("mallory", "password123").await;
+    let res = app
+        .post_json(&format!("/trash/{}/restore", file), Some(&token), json!({}))
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    app.delete(&format!("/files/{}", file), Some(&token)).await;
+    let res = app
+        .post_json(&format!("/trash/{}/restore", file), Some(&other), json!({}))
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_restore_recreates_missing_folders() {
+    let app = TestApp::new().await;
+    let token = app.register("bob", "password123").await;
+
+    let outer = app.create_folder(&token, "Outer", None).await;
+    let inner = app.create_folder(&token, "Inner", Some(&outer)).await;
+    let file = app
+        .upload_file(&token, "report.txt", b"trash me", Some(&inner))
+        .await;
+
+    // Trash the file, then its folders, and purge the folders for good
+    app.delete(&format!("/files/{}", file), Some(&token)).await;
+    app.delete(&format!("/files/{}", outer), Some(&token)).await;
+    let res = app.delete(&format!("/trash/{}", outer), Some(&token)).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let items = trash(&app, &token).await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["original_path"], "Outer/Inner");
+
+    let res = app
+        .post_json(&format!("/trash/{}/restore", file), Some(&token), json!({}))
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let restored = json_body(res).await;
+    let parent = restored["parent_id"].as_str().unwrap();
+    assert_ne!(parent, inner);
+
+    let res = app
+        .get(&format!("/files/{}/path", parent), Some(&token))
+        .await;
+    let path: Vec<String> = json_body(res)
+        .await
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["filename"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(path, ["Outer", "Inner"]);
+}
+
+#[tokio::test]
+async fn test_restore_renames_on_conflict() {
+    let app = TestApp::new().await;
+    let token = app.register("carol", "password123").await;
+
+    let first = app
+        .upload_file(&token, "notes.txt", b"trash me", None)
+        .await;
+    app.delete(&format!("/files/{}", first), Some(&token)).await;
+    app.upload_file(&token, "notes.txt", b"trash me", None)
+        .await;
+
+    let res = app
+        .post_json(
+            &format!("/trash/{}/restore", first),
+            Some(&token),
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["filename"], "notes (restored).txt");
+}
+
+#[tokio::test]
+async fn test_empty_trash_releases_storage() {
+    let app = TestApp::new().await;
+    let token = app.register("dave", "password123").await;
+
+    let file = app.upload_file(&token, "gone.txt", b"trash me", None).await;
+    let sf = StorageFiles::find().one(&app.db).await.unwrap().unwrap();
+    app.delete(&format!("/files/{}", file), Some(&token)).await;
+
+    let res = app.delete("/trash", Some(&token)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["purged_count"], 1);
+
+    assert!(StorageFiles::find().all(&app.db).await.unwrap().is_empty());
+    assert!(UserFiles::find().all(&app.db).await.unwrap().is_empty());
+    assert!(app.storage.get_file(&sf.s3_key).await.is_err());
+}
+
+#[tokio::test]
+async fn test_failed_purge_keeps_released_objects() {
+    let app = TestApp::new().await;
+    let token = app.register("frank", "password123").await;
+
+    let broken = app.upload_file(&token, "broken.txt", b"first", None).await;
+    let fine = app.upload_file(&token, "fine.txt", b"trash me", None).await;
+    let fine_row = UserFiles::find_by_id(&fine)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let sf = StorageFiles::find_by_id(fine_row.storage_file_id.unwrap())
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    app.delete(&format!("/files/{}", broken), Some(&token))
+        .await;
+    app.delete(&format!("/files/{}", fine), Some(&token)).await;
+
+    // The newest item is purged first, then the broken one fails
+    app.db
+        .execute_unprepared("PRAGMA foreign_keys = OFF")
+        .await
+        .unwrap();
+    let mut row: user_files::ActiveModel = UserFiles::find_by_id(&broken)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap()
+        .into();
+    row.storage_file_id = Set(Some("missing".to_string()));
+    row.update(&app.db).await.unwrap();
+    app.db
+        .execute_unprepared("PRAGMA foreign_keys = ON")
+        .await
+        .unwrap();
+
+    let res = app.delete("/trash", Some(&token)).await;
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    // The rollback brought back rows that still point at their objects
+    let kept = StorageFiles::find_by_id(&sf.id)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(kept.ref_count, 1);
+    assert!(app.storage.get_file(&sf.s3_key).await.is_ok());
+    assert_eq!(trash(&app, &token).await.len(), 2);
+}
+
+#[tokio::test]
+async fn test_retention_purge() {
+    let config = SecurityConfig {
+        trash_retention_days: 0,
+        ..SecurityConfig::development()
+    };
+    let app = TestApp::with_config(config).await;
+    let token = app.register("erin", "password123").await;
+
+    let keep = app.upload_file(&token, "keep.txt", b"trash me", None).await;
+    let folder = app.create_folder(&token, "Old", None).await;
+    let file = app
+        .upload(
+            &token,
+            "old.txt",
+            "text/plain",
+            b"older content",
+            Some(&folder),
+        )
+        .await;
+    assert_eq!(file.status(), StatusCode::OK);
+    app.delete(&format!("/files/{}", folder), Some(&token))
+        .await;
+
+    let purged = StorageLifecycleService::purge_expired_trash(
+        &app.db,
+        app.storage.as_ref(),
+        app.state.config.trash_retention_days,
+    )
+    .await
+    .unwrap();
+    assert_eq!(purged, 1);
+
+    assert!(trash(&app, &token).await.is_empty());
+    let remaining = UserFiles::find().all(&app.db).await.unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].id, keep);
+    assert_eq!(StorageFiles::find().all(&app.db).await.unwrap().len(), 1);
+}
@@ -0,0 +1,387 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, Response, StatusCode, header};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use bytes::Bytes;
+use chrono::{Duration, Utc};
+use common::{TestApp, json_body};
+use futures::channel::mpsc;
+use rust_file_backend::config::SecurityConfig;
+use rust_file_backend::entities::{prelude::*, upload_sessions};
+use rust_file_backend::services::storage::StorageService;
+use rust_file_backend::services::upload_service::UploadService;
+use sea_orm::{ActiveModelTrait, EntityTrait, PaginatorTrait, Set};
+use sha2::{Digest, Sha256};
+use tokio::task::JoinHandle;
+use tower::ServiceExt;
+
+/// Small parts so a few bytes span several of them, with a tail left over
+async fn app() -> TestApp {
+    let mut config = SecurityConfig::development();
+    config.chunk_size = 4;
+    TestApp::with_config(config).await
+}
+
+fn metadata(filename: &str) -> String {
+    format!(
+        "filename {},filetype {}",
+        BASE64.encode(filename),
+        BASE64.encode("text/plain")
+    )
+}
+
+fn header_str<'a>(res: &'a Response<Body>, name: &str) -> &'a str {
+    res.headers()
+        .get(name)
+        .unwrap_or_else(|| panic!("missing {}", name))
+        .to_str()
+        .unwrap()
+}
+
+async fn create(app: &TestApp, token: &str, length: usize, filename: &str) -> Response<Body> {
+    let req = Request::post("/files/tus")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header("Tus-Resumable", "1.0.0")
+        .header("Upload-Length", length.to_string())
+        .header("Upload-Metadata", metadata(filename))
+        .body(Body::empty())
+        .unwrap();
+    app.request(req).await
+}
+
+async fn patch(
+    app: &TestApp,
+    token: &str,
+    location: &str,
+    offset: usize,
+    data: &[u8],
+    checksum: Option<String>,
+) -> Response<Body> {
+    let mut builder = Request::patch(location)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header("Tus-Resumable", "1.0.0")
+        .header(header::CONTENT_TYPE, "application/offset+octet-stream")
+        .header("Upload-Offset", offset.to_string());
+    if let Some(checksum) = checksum {
+        builder = builder.header("Upload-Checksum", checksum);
+    }
+    app.request(builder.body(Body::from(data.to_vec())).unwrap())
+        .await
+}
+
+/// Start a PATCH whose body arrives as the test sends it
+fn streaming_patch(
+    app: &TestApp,
+    token: &str,
+    location: &str,
+    offset: usize,
+) -> (
+    mpsc::UnboundedSender<Result<Bytes, std::io::Error>>,
+    JoinHandle<Response<Body>>,
+) {
+    let (tx, rx) = mpsc::unbounded();
+    let req = Request::patch(location)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header("Tus-Resumable", "1.0.0")
+        .header(header::CONTENT_TYPE, "application/offset+octet-stream")
+        .header("Upload-Offset", offset.to_string())
+        .body(Body::from_stream(rx))
+        .unwrap();
+    let router = app.router.clone();
+    let handle = tokio::spawn(async move { router.oneshot(req).await.unwrap() });
+    (tx, handle)
+}
+
+async fn wait_for_status(app: &TestApp, status: &str) {
+    for _ in 0..200 {
+        let session = UploadSessions::find().one(&app.db).await.unwrap().unwrap();
+        if session.status == status {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("upload never became {}", status);
+}
+
+async fn head(app: &TestApp, token: &str, location: &str) -> Response<Body> {
+    let req = Request::head(location)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header("Tus-Resumable", "1.0.0")
+        .body(Body::empty())
+        .unwrap();
+    app.request(req).await
+}
+
+async fn new_upload(app: &TestApp, token: &str, length: usize) -> String {
+    let res = create(app, token, length, "notes.txt").await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert!(res.headers().contains_key("upload-expires"));
+    // Relative to the creation URL, `/files/tus`
+    let location = header_str(&res, "location");
+    assert!(location.starts_with("tus/"));
+    format!("/files/{}", location)
+}
+
+#[tokio::test]
+async fn test_resumable_upload_in_pieces() {
+    let app = app().await;
+    let token = app.register("alice", "password123").await;
+    let data = b"hello resumable world";
+
+    let res = app
+        .request(Request::options("/files/tus").body(Body::empty()).unwrap())
+        .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(header_str(&res, "tus-version"), "1.0.0");
+    assert!(header_str(&res, "tus-extension").contains("creation"));
+
+    let location = new_upload(&app, &token, data.len()).await;
+    let mut offset = 0;
+    // Pieces that straddle part boundaries exercise the tail object
+    for piece in [&data[..3], &data[3..10], &data[10..11], &data[11..]] {
+        let res = patch(&app, &token, &location, offset, piece, None).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        offset += piece.len();
+        assert_eq!(header_str(&res, "upload-offset"), offset.to_string());
+        assert_eq!(header_str(&res, "tus-resumable"), "1.0.0");
+
+        if offset < data.len() {
+            let res = head(&app, &token, &location).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(header_str(&res, "upload-offset"), offset.to_string());
+            assert_eq!(header_str(&res, "upload-length"), data.len().to_string());
+            assert_eq!(header_str(&res, "cache-control"), "no-store");
+        } else {
+            let file_id = header_str(&res, "x-file-id").to_string();
+            let res = app.get(&format!("/files/{}", file_id), Some(&token)).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+    }
+
+    let files = json_body(app.get("/files", Some(&token)).await).await;
+    let file = files
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|f| f["filename"] == "notes.txt")
+        .expect("uploaded file is listed");
+    assert_eq!(file["size"], data.len() as i64);
+
+    // Only the assembled object remains; tails and staging are cleaned up
+    assert!(
+        app.storage
+            .list_objects("multipart/")
+            .await
+            .unwrap()
+            .iter()
+            .all(|key| !key.contains(".tail-"))
+    );
+}
+
+#[tokio::test]
+async fn test_creation_with_upload() {
+    let app = app().await;
+    let token = app.register("alice", "password123").await;
+    let data = b"all at once";
+
+    let req = Request::post("/files/tus")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header("Tus-Resumable", "1.0.0")
+        .header("Upload-Length", data.len().to_string())
+        .header("Upload-Metadata", metadata("once.txt"))
+        .header(header::CONTENT_TYPE, "application/offset+octet-stream")
+        .body(Body::from(data.to_vec()))
+        .unwrap();
+    let res = app.request(req).await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    assert_eq!(header_str(&res, "upload-offset"), data.len().to_string());
+    assert!(res.headers().contains_key("x-file-id"));
+}
+
+#[tokio::test]
+async fn test_checksum_and_offset_are_enforced() {
+    let app = app().await;
+    let token = app.register("alice", "password123").await;
+    let location = new_upload(&app, &token, 10).await;
+
+    let wrong = format!("sha256 {}", BASE64.encode(Sha256::digest(b"other")));
+    let res = patch(&app, &token, &location, 0, b"12345", Some(wrong)).await;
+    assert_eq!(res.status().as_u16(), 460);
+    let res = head(&app, &token, &location).await;
+    assert_eq!(header_str(&res, "upload-offset"), "0");
+
+    let right = format!("sha256 {}", BASE64.encode(Sha256::digest(b"12345")));
+    let res = patch(&app, &token, &location, 0, b"12345", Some(right)).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    // A stale offset is a conflict, and the body may not overrun Upload-Length
+    let res = patch(&app, &token, &location, 0, b"12345", None).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let res = patch(&app, &token, &location, 5, b"123456", None).await;
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let req = Request::patch(location.as_str())
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header("Tus-Resumable", "1.0.0")
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header("Upload-Offset", "5")
+        .body(Body::from("12345"))
+        .unwrap();
+    assert_eq!(
+        app.request(req).await.status(),
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    );
+}
+
+#[tokio::test]
+async fn test_concurrent_patches_cannot_overwrite_parts() {
+    let app = app().await;
+    let token = app.register("alice", "password123").await;
+    let location = new_upload(&app, &token, 10).await;
+
+    let (tx, first) = streaming_patch(&app, &token, &location, 0);
+    tx.unbounded_send(Ok(Bytes::from_static(b"12345"))).unwrap();
+    wait_for_status(&app, "appending").await;
+
+    // The offset is taken until the first request ends
+    let res = patch(&app, &token, &location, 0, b"abcdefgh", None).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let req = Request::delete(location.as_str())
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header("Tus-Resumable", "1.0.0")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(app.request(req).await.status(), StatusCode::CONFLICT);
+
+    drop(tx);
+    let res = first.await.unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(header_str(&res, "upload-offset"), "5");
+
+    let res = patch(&app, &token, &location, 5, b"67890", None).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert!(res.headers().contains_key("x-file-id"));
+    let stored = StorageFiles::find().one(&app.db).await.unwrap().unwrap();
+    let content = app.storage.get_file(&stored.s3_key).await.unwrap();
+    assert_eq!(content, b"1234567890");
+}
+
+#[tokio::test]
+async fn test_dropped_patch_hands_the_upload_back() {
+    let app = app().await;
+    let token = app.register("alice", "password123").await;
+    let location = new_upload(&app, &token, 10).await;
+
+    let (tx, first) = streaming_patch(&app, &token, &location, 0);
+    tx.unbounded_send(Ok(Bytes::from_static(b"123"))).unwrap();
+    wait_for_status(&app, "appending").await;
+    first.abort();
+    wait_for_status(&app, "pending").await;
+
+    let res = patch(&app, &token, &location, 0, b"1234567890", None).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert!(res.headers().contains_key("x-file-id"));
+}
+
+#[tokio::test]
+async fn test_protocol_version_and_ownership() {
+    let app = app().await;
+    let token = app.register("alice", "password123").await;
+    let location = new_upload(&app, &token, 10).await;
+
+    let req = Request::head(location.as_str())
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+    let res = app.request(req).await;
+    assert_eq!(res.status(), StatusCode::PRECONDITION_FAILED);
+    assert_eq!(header_str(&res, "tus-version"), "1.0.0");
+
+    let bob = app.register("bob", "password123").await;
+    let res = head(&app, &bob, &location).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_termination() {
+    let app = app().await;
+    let token = app.register("alice", "password123").await;
+    let location = new_upload(&app, &token, 10).await;
+    let res = patch(&app, &token, &location, 0, b"123456", None).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let req = Request::delete(location.as_str())
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header("Tus-Resumable", "1.0.0")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(app.request(req).await.status(), StatusCode::NO_CONTENT);
+
+    let res = head(&app, &token, &location).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(
+        app.storage
+            .list_objects("multipart/")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_expired_uploads_are_gone_and_purged() {
+    let app = app().await;
+    let token = app.register("alice", "password123").await;
+    let location = new_upload(&app, &token, 10).await;
+    let res = patch(&app, &token, &location, 0, b"123456", None).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let id = location.rsplit('/').next().unwrap();
+    let session = UploadSessions::find_by_id(id)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut active: upload_sessions::ActiveModel = session.into();
+    active.expires_at = Set((Utc::now() - Duration::minutes(1)).into());
+    active.update(&app.db).await.unwrap();
+
+    let res = head(&app, &token, &location).await;
+    assert_eq!(res.status(), StatusCode::GONE);
+    let res = patch(&app, &token, &location, 6, b"7890", None).await;
+    assert_eq!(res.status(), StatusCode::GONE);
+
+    let purged = UploadService::purge_expired_sessions(&app.db, app.storage.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(purged, 1);
+    assert_eq!(UploadSessions::find().count(&app.db).await.unwrap(), 0);
+    assert!(
+        app.storage
+            .list_objects("multipart/")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_creation_checks_size_and_quota() {
+    let mut config = SecurityConfig::development();
+    config.chunk_size = 4;
+    config.default_storage_quota = Some(8);
+    let app = TestApp::with_config(config).await;
+    let token = app.register("alice", "password123").await;
+
+    let res = create(&app, &token, 9, "big.txt").await;
+    assert_eq!(res.status(), StatusCode::INSUFFICIENT_STORAGE);
+
+    let too_large = app.state.config.max_file_size + 1;
+    let res = create(&app, &token, too_large, "huge.txt").await;
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let res = create(&app, &token, 8, "fits.txt").await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+}
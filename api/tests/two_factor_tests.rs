@@ -0,0 +1,293 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{TestApp, json_body};
+use rust_file_backend::entities::{audit_logs, prelude::*, users};
+use rust_file_backend::utils::totp;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, sea_query::Expr};
+use serde_json::{Value, json};
+
+/// Enroll and confirm 2FA, returning the secret and recovery codes
+async fn enable_2fa(app: &TestApp, jwt: &str) -> (String, Vec<String>) {
+    let res = app
+        .post_json("/auth/2fa/enroll", Some(jwt), json!({}))
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let enrollment = json_body(res).await;
+    let secret = enrollment["secret"].as_str().unwrap().to_string();
+    let uri = enrollment["otpauth_uri"].as_str().unwrap();
+    assert!(uri.starts_with("otpauth://totp/"));
+    assert!(uri.contains(&format!("secret={}", secret)));
+
+    let res = app
+        .post_json("/auth/2fa/confirm", Some(jwt), json!({ "code": "000000" }))
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let code = totp::generate(&secret, chrono::Utc::now().timestamp()).unwrap();
+    let res = app
+        .post_json("/auth/2fa/confirm", Some(jwt), json!({ "code": code }))
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let codes = json_body(res).await["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c.as_str().unwrap().to_string())
+        .collect();
+    (secret, codes)
+}
+
+/// Log in with the password, expecting a 2FA challenge
+async fn challenge(app: &TestApp) -> String {
+    let res = app.login("alice", "password123").await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = json_body(res).await;
+    assert_eq!(body["two_factor_required"], true);
+    assert!(body.get("token").is_none());
+    body["challenge_token"].as_str().unwrap().to_string()
+}
+
+async fn second_step(app: &TestApp, challenge_token: &str, code: &str) -> (StatusCode, Value) {
+    let res = app
+        .post_json(
+            "/login/2fa",
+            None,
+            json!({ "challenge_token": challenge_token, "code": code }),
+        )
+        .await;
+    let status = res.status();
+    (status, json_body(res).await)
+}
+
+#[tokio::test]
+async fn test_login_with_totp() {
+    let app = TestApp::new().await;
+    let jwt = app.register("alice", "password123").await;
+    let status = json_body(app.get("/auth/2fa", Some(&jwt)).await).await;
+    assert_eq!(status["enabled"], false);
+
+    let (secret, recovery_codes) = enable_2fa(&app, &jwt).await;
+    assert_eq!(recovery_codes.len(), 10);
+    let status = json_body(app.get("/auth/2fa", Some(&jwt)).await).await;
+    assert_eq!(status["enabled"], true);
+    assert_eq!(status["recovery_codes_remaining"], 10);
+    assert_eq!(
+        app.post_json("/auth/2fa/enroll", Some(&jwt), json!({}))
+            .await
+            .status(),
+        StatusCode::CONFLICT
+    );
+
+    let token = challenge(&app).await;
+    let (status, _) = second_step(&app, &token, "000000").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // The confirmation code is spent, so use the next one
+    let code = totp::generate(&secret, chrono::Utc::now().timestamp() + 30).unwrap();
+    let (status, body) = second_step(&app, &token, &code).await;
+    assert_eq!(status, StatusCode::OK);
+    let access = body["token"].as_str().unwrap();
+    assert!(body["refresh_token"].is_string());
+    assert_eq!(
+        app.get("/users/me", Some(access)).await.status(),
+        StatusCode::OK
+    );
+
+    // Neither the challenge nor the code can be used twice
+    let (status, _) = second_step(&app, &token, &code).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = second_step(&app, &challenge(&app).await, &code).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let logins: Vec<(String, String, Option<String>)> = AuditLogs::find()
+        .filter(audit_logs::Column::EventType.eq("UserLogin"))
+        .all(&app.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|l| (l.action, l.status, l.details))
+        .collect();
+    assert!(
+        logins
+            .iter()
+            .any(|(action, status, details)| action == "login"
+                && status == "pending"
+                && details.as_deref().unwrap().contains("challenge_issued"))
+    );
+    assert!(
+        logins
+            .iter()
+            .any(|(action, status, details)| action == "login_2fa"
+                && status == "success"
+                && details.as_deref().unwrap().contains("\"totp\""))
+    );
+    assert!(
+        logins
+            .iter()
+            .any(|(action, status, _)| action == "login_2fa" && status == "failure")
+    );
+}
+
+#[tokio::test]
+async fn test_recovery_codes_and_disable() {
+    let app = TestApp::new().await;
+    let jwt = app.register("alice", "password123").await;
+    let (_, recovery_codes) = enable_2fa(&app, &jwt).await;
+
+    // Recovery codes work once, whatever their case or spacing
+    let typed = recovery_codes[0].to_uppercase().replace('-', " ");
+    let (status, _) = second_step(&app, &challenge(&app).await, &typed).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = second_step(&app, &challenge(&app).await, &recovery_codes[0]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let status = json_body(app.get("/auth/2fa", Some(&jwt)).await).await;
+    assert_eq!(status["recovery_codes_remaining"], 9);
+
+    // Disabling needs both the password and a second factor
+    for body in [
+        json!({ "password": "wrong-password", "code": recovery_codes[1] }),
+        json!({ "password": "password123", "code": "000000" }),
+    ] {
+        let res = app.post_json("/auth/2fa/disable", Some(&jwt), body).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+    let res = app
+        .post_json(
+            "/auth/2fa/disable",
+            Some(&jwt),
+            json!({ "password": "password123", "code": recovery_codes[1] }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let res = app.login("alice", "password123").await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(json_body(res).await["token"].is_string());
+    let status = json_body(app.get("/auth/2fa", Some(&jwt)).await).await;
+    assert_eq!(status["enabled"], false);
+    assert_eq!(status["recovery_codes_remaining"], 0);
+}
+
+#[tokio::test]
+async fn test_challenge_gives_up_after_wrong_codes() {
+    let app = TestApp::new().await;
+    let jwt = app.register("alice", "password123").await;
+    let (_, recovery_codes) = enable_2fa(&app, &jwt).await;
+
+    let token = challenge(&app).await;
+    for _ in 0..5 {
+        let (status, _) = second_step(&app, &token, "000000").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, _) = second_step(&app, &token, &recovery_codes[0]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // A fresh login still works, and the code was not spent
+    let (status, _) = second_step(&app, &challenge(&app).await, &recovery_codes[0]).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_wrong_codes_lock_out_across_challenges() {
+    let app = TestApp::new().await;
+    let jwt = app.register("alice", "password123").await;
+    let (_, recovery_codes) = enable_2fa(&app, &jwt).await;
+
+    for _ in 0..2 {
+        let token = challenge(&app).await;
+        for _ in 0..5 {
+            let (status, _) = second_step(&app, &token, "000000").await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    // Starting over does not reset the count, and the password alone no
+    // longer mints challenges
+    let res = app.login("alice", "password123").await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(res.headers().contains_key("retry-after"));
+
+    // Once the lockout ends, a correct code clears the count
+    Users::update_many()
+        .col_expr(
+            users::Column::TotpLockedUntil,
+            Expr::value(chrono::Utc::now() - chrono::Duration::seconds(1)),
+        )
+        .exec(&app.db)
+        .await
+        .unwrap();
+    let (status, _) = second_step(&app, &challenge(&app).await, &recovery_codes[0]).await;
+    assert_eq!(status, StatusCode::OK);
+    let user = Users::find().one(&app.db).await.unwrap().unwrap();
+    assert_eq!(user.totp_failed_attempts, 0);
+    assert!(user.totp_locked_until.is_none());
+}
+
+#[tokio::test]
+async fn test_signed_in_code_checks_share_the_lockout() {
+    let app = TestApp::new().await;
+    let jwt = app.register("alice", "password123").await;
+    let (_, recovery_codes) = enable_2fa(&app, &jwt).await;
+
+    for _ in 0..10 {
+        let res = app
+            .post_json(
+                "/auth/2fa/recovery-codes",
+                Some(&jwt),
+                json!({ "code": "000000" }),
+            )
+            .await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+    let res = app
+        .post_json(
+            "/auth/2fa/recovery-codes",
+            Some(&jwt),
+            json!({ "code": "000000" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(res.headers().contains_key("retry-after"));
+
+    // Even a correct code is refused while locked out, and logins are too
+    let res = app
+        .post_json(
+            "/auth/2fa/disable",
+            Some(&jwt),
+            json!({ "password": "password123", "code": recovery_codes[0] }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    let res = app.login("alice", "password123").await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    let status = json_body(app.get("/auth/2fa", Some(&jwt)).await).await;
+    assert_eq!(status["enabled"], true);
+    assert_eq!(status["recovery_codes_remaining"], 10);
+}
+
+#[tokio::test]
+async fn test_disable_counts_wrong_codes() {
+    let app = TestApp::new().await;
+    let jwt = app.register("alice", "password123").await;
+    enable_2fa(&app, &jwt).await;
+
+    for _ in 0..10 {
+        let res = app
+            .post_json(
+                "/auth/2fa/disable",
+                Some(&jwt),
+                json!({ "password": "password123", "code": "000000" }),
+            )
+            .await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+    let res = app
+        .post_json(
+            "/auth/2fa/disable",
+            Some(&jwt),
+            json!({ "password": "password123", "code": "000000" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+}
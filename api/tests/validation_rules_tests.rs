@@ -0,0 +1,206 @@
+mod common;
+
+use axum::http::StatusCode;
+use base64::Engine;
+use common::{TestApp, json_body};
+use serde_json::{Value, json};
+
+async fn dry_run(
+    app: &TestApp,
+    jwt: &str,
+    filename: &str,
+    content_type: &str,
+    data: &[u8],
+) -> Value {
+    let res = app
+        .post_json(
+            "/admin/validation/test",
+            Some(jwt),
+            json!({
+                "filename": filename,
+                "content_type": content_type,
+                "sample": base64::engine::general_purpose::STANDARD.encode(data),
+            }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    json_body(res).await
+}
+
+fn failed_checks(result: &Value) -> Vec<String> {
+    result["checks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|c| c["passed"] == false)
+        .map(|c| c["code"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_rule_changes_apply_immediately() {
+    let app = TestApp::new().await;
+    let admin = app.register_admin("admin", "password123").await;
+    let alice = app.register("alice", "password123").await;
+    let notes = b"plain meeting notes";
+
+    // Warm the cache with the seeded rules
+    let result = dry_run(&app, &admin, "notes.txt", "text/plain", notes).await;
+    assert_eq!(result["passed"], true);
+    assert_eq!(result["sanitized_filename"], "notes.txt");
+
+    let res = app
+        .post_json(
+            "/admin/validation/extensions",
+            Some(&admin),
+            json!({ "extension": ".TXT", "description": "no plain text" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let blocked = json_body(res).await;
+    assert_eq!(blocked["extension"], "txt");
+
+    let res = app
+        .post_json(
+            "/admin/validation/extensions",
+            Some(&admin),
+            json!({ "extension": "txt" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    let result = dry_run(&app, &admin, "notes.txt", "text/plain", notes).await;
+    assert_eq!(result["passed"], false);
+    assert!(result["sanitized_filename"].is_null());
+    assert_eq!(failed_checks(&result), ["BLOCKED_EXTENSION"]);
+    let res = app
+        .upload(&alice, "notes.txt", "text/plain", notes, None)
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = app
+        .delete(
+            &format!("/admin/validation/extensions/{}", blocked["id"]),
+            Some(&admin),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = app
+        .upload(&alice, "notes.txt", "text/plain", notes, None)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = app
+        .get(
+            "/admin/audit-logs?event_type=ValidationRuleChange",
+            Some(&admin),
+        )
+        .await;
+    assert_eq!(json_body(res).await.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_manage_mimes_and_signatures() {
+    let app = TestApp::new().await;
+    let admin = app.register_admin("admin", "password123").await;
+    let data = b"ACME\x01\x02 model data";
+
+    let result = dry_run(&app, &admin, "part.acme", "application/x-acme", data).await;
+    assert_eq!(failed_checks(&result), ["INVALID_MIME_TYPE"]);
+    assert!(result["detected_mime"].is_null());
+
+    let res = app
+        .post_json(
+            "/admin/validation/mimes",
+            Some(&admin),
+            json!({ "mime_type": "Application/X-Acme", "category": "cad" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let mime = json_body(res).await;
+    assert_eq!(mime["mime_type"], "application/x-acme");
+    let res = app
+        .post_json(
+            "/admin/validation/mimes",
+            Some(&admin),
+            json!({ "mime_type": "not a mime", "category": "cad" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = app
+        .post_json(
+            "/admin/validation/signatures",
+            Some(&admin),
+            json!({ "signature": "41 43 4d 45", "mime_type": "application/x-acme" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let signature = json_body(res).await;
+    assert_eq!(signature["signature"], "41434D45");
+
+    let result = dry_run(&app, &admin, "part.acme", "application/x-acme", data).await;
+    assert_eq!(result["passed"], true);
+    assert_eq!(result["detected_mime"], "application/x-acme");
+    let listed = json_body(app.get("/admin/validation/signatures", Some(&admin)).await).await;
+    assert!(
+        listed
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|s| s["id"] == signature["id"])
+    );
+
+    let res = app
+        .send_json(
+            "PUT",
+            &format!("/admin/validation/mimes/{}", mime["id"]),
+            Some(&admin),
+            json!({ "mime_type": "application/x-acme-v2", "category": "cad" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let result = dry_run(&app, &admin, "part.acme", "application/x-acme", data).await;
+    assert_eq!(failed_checks(&result), ["INVALID_MIME_TYPE"]);
+
+    let res = app
+        .delete("/admin/validation/mimes/999999", Some(&admin))
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_auditors_read_rules_only() {
+    let app = TestApp::new().await;
+    let admin = app.register_admin("admin", "password123").await;
+    let carol = app.register("carol", "password123").await;
+    let carol_id = app.user_id(&carol).await;
+
+    assert_eq!(
+        app.get("/admin/validation/mimes", Some(&carol))
+            .await
+            .status(),
+        StatusCode::FORBIDDEN
+    );
+    let res = app
+        .send_json(
+            "PUT",
+            &format!("/admin/users/{}/role", carol_id),
+            Some(&admin),
+            json!({ "role": "auditor" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = app.get("/admin/validation/extensions", Some(&carol)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(json_body(res).await.is_array());
+    let res = app
+        .post_json(
+            "/admin/validation/extensions",
+            Some(&carol),
+            json!({ "extension": "docm" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{TestApp, json_body};
+use rust_file_backend::entities::{prelude::*, storage_files};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde_json::{Value, json};
+
+async fn versions(app: &TestApp, token: &str, file_id: &str) -> Vec<Value> {
+    let res = app
+        .get(&format!("/files/{}/versions", file_id), Some(token))
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    json_body(res).await.as_array().unwrap().clone()
+}
+
+async fn ref_count(app: &TestApp, content: &[u8]) -> Option<i32> {
+    let sha256 = hex::encode(<sha2::Sha256 as sha2::Digest>::digest(content));
+    StorageFiles::find()
+        .filter(storage_files::Column::Sha256.eq(sha256))
+        .one(&app.db)
+        .await
+        .unwrap()
+        .map(|sf| sf.ref_count)
+}
+
+#[tokio::test]
+async fn test_reupload_keeps_previous_version() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+
+    let file_id = app
+        .upload_file(&token, "doc.txt", b"first draft", None)
+        .await;
+    let again = app
+        .upload_file(&token, "doc.txt", b"second draft", None)
+        .await;
+    assert_eq!(file_id, again);
+
+    let list = versions(&app, &token, &file_id).await;
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0]["version_number"], 1);
+    assert_eq!(list[0]["size"], b"first draft".len());
+    assert_eq!(ref_count(&app, b"first draft").await, Some(1));
+    assert_eq!(ref_count(&app, b"second draft").await, Some(1));
+
+    // The old content can still be downloaded
+    let version_id = list[0]["id"].as_str().unwrap().to_string();
+    let res = app
+        .get(
+            &format!("/files/{}/versions/{}", file_id, version_id),
+            Some(&token),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let sha256 = hex::encode(<sha2::Sha256 as sha2::Digest>::digest(b"first draft"));
+    assert!(
+        res.headers()["x-accel-redirect"]
+            .to_str()
+            .unwrap()
+            .contains(&sha256)
+    );
+
+    // Re-uploading identical content does not create a version or leak a reference
+    app.upload_file(&token, "doc.txt", b"second draft", None)
+        .await;
+    assert_eq!(versions(&app, &token, &file_id).await.len(), 1);
+    assert_eq!(ref_count(&app, b"second draft").await, Some(1));
+
+    // Other users cannot see the history
+    let other = app.register("mallory", "password123").await;
+    let res = app
+        .get(&format!("/files/{}/versions", file_id), Some(&other))
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_restore_and_prune_versions() {
+    let app = TestApp::new().await;
+    let token = app.register("bob", "password123").await;
+
+    let file_id = app.upload_file(&token, "doc.txt", b"v1", None).await;
+    app.upload_file(&token, "doc.txt", b"v2", None).await;
+    app.upload_file(&token, "doc.txt", b"v3", None).await;
+
+    let list = versions(&app, &token, &file_id).await;
+    assert_eq!(list.len(), 2);
+    let v1 = list.iter().find(|v| v["version_number"] == 1).unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let res = app
+        .post_json(
+            &format!("/files/{}/versions/{}/restore", file_id, v1),
+            Some(&token),
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["size"], 2);
+
+    // v3 became a version; v1 is current again
+    let list = versions(&app, &token, &file_id).await;
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0]["version_number"], 3);
+    for content in [b"v1", b"v2", b"v3"] {
+        assert_eq!(ref_count(&app, content).await, Some(1));
+    }
+
+    let res = app
+        .post_json(
+            &format!("/files/{}/versions/prune", file_id),
+            Some(&token),
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = app
+        .post_json(
+            &format!("/files/{}/versions/prune", file_id),
+            Some(&token),
+            json!({ "keep_latest": 1 }),
+        )
+        .await;
+    assert_eq!(json_body(res).await["pruned_count"], 1);
+    assert_eq!(ref_count(&app, b"v2").await, None);
+    assert_eq!(ref_count(&app, b"v3").await, Some(1));
+
+    let res = app
+        .post_json(
+            &format!("/files/{}/versions/prune", file_id),
+            Some(&token),
+            json!({ "older_than_days": 0 }),
+        )
+        .await;
+    assert_eq!(json_body(res).await["pruned_count"], 1);
+    assert!(versions(&app, &token, &file_id).await.is_empty());
+    assert_eq!(StorageFiles::find().all(&app.db).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_rename_onto_existing_file_keeps_version() {
+    let app = TestApp::new().await;
+    let token = app.register("carol", "password123").await;
+
+    let target = app
+        .upload_file(&token, "report.txt", b"old report", None)
+        .await;
+    let source = app
+        .upload_file(&token, "draft.txt", b"new report", None)
+        .await;
+
+    let res = app
+        .send_json(
+            "PUT",
+            &format!("/files/{}/rename", source),
+            Some(&token),
+            json!({ "name": "report.txt" }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(json_body(res).await["id"], target);
+
+    let list = versions(&app, &token, &target).await;
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0]["size"], b"old report".len());
+    assert_eq!(ref_count(&app, b"old report").await, Some(1));
+    assert_eq!(ref_count(&app, b"new report").await, Some(1));
+}
+
+#[tokio::test]
+async fn test_purging_file_releases_versions() {
+    let app = TestApp::new().await;
+    let token = app.register("dave", "password123").await;
+
+    let file_id = app.upload_file(&token, "doc.txt", b"one", None).await;
+    app.upload_file(&token, "doc.txt", b"two", None).await;
+
+    app.delete(&format!("/files/{}", file_id), Some(&token))
+        .await;
+    // Versions survive while the file is in the trash
+    assert_eq!(ref_count(&app, b"one").await, Some(1));
+
+    let res = app.delete("/trash", Some(&token)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(StorageFiles::find().all(&app.db).await.unwrap().is_empty());
+    assert!(FileVersions::find().all(&app.db).await.unwrap().is_empty());
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use axum::http::{StatusCode, header};
+use common::{TestApp, body_bytes, json_body};
+use rust_file_backend::entities::{prelude::*, storage_files};
+use sea_orm::{ActiveModelTrait, EntityTrait, Set};
+use serde_json::json;
+use std::io::{Cursor, Read};
+
+async fn mark_infected(app: &TestApp, file_id: &str) {
+    let file = UserFiles::find_by_id(file_id)
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let storage = StorageFiles::find_by_id(file.storage_file_id.unwrap())
+        .one(&app.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut active: storage_files::ActiveModel = storage.into();
+    active.scan_status = Set(Some("infected".to_string()));
+    active.update(&app.db).await.unwrap();
+}
+
+/// Archive paths mapped to their contents, in archive order
+fn unzip(bytes: Vec<u8>) -> Vec<(String, String)> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).expect("valid ZIP");
+    (0..archive.len())
+        .map(|i| {
+            let mut entry = archive.by_index(i).unwrap();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            (entry.name().to_string(), content)
+        })
+        .collect()
+}
+
+/// A folder tree with a nested folder, a clash of names and an infected file
+async fn fixture(app: &TestApp, token: &str) -> String {
+    let docs = app.create_folder(token, "docs", None).await;
+    let nested = app.create_folder(token, "nested", Some(&docs)).await;
+    app.upload_file(token, "a.txt", b"alpha", Some(&docs)).await;
+    app.upload_file(token, "b.txt", b"bravo", Some(&nested))
+        .await;
+    let bad = app
+        .upload_file(token, "virus.txt", b"evil", Some(&docs))
+        .await;
+    mark_infected(app, &bad).await;
+    docs
+}
+
+#[tokio::test]
+async fn test_folder_zip_keeps_hierarchy() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let docs = fixture(&app, &token).await;
+
+    let res = app.get(&format!("/files/{}/zip", docs), Some(&token)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()[header::CONTENT_TYPE], "application/zip");
+    assert!(
+        res.headers()[header::CONTENT_DISPOSITION]
+            .to_str()
+            .unwrap()
+            .contains("docs.zip")
+    );
+
+    let entries = unzip(body_bytes(res).await);
+    let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["docs/", "docs/a.txt", "docs/nested/", "docs/nested/b.txt"]
+    );
+    assert_eq!(entries[1].1, "alpha");
+    assert_eq!(entries[3].1, "bravo");
+
+    // Other users' folders are not found
+    let bob = app.register("bob", "password123").await;
+    let res = app.get(&format!("/files/{}/zip", docs), Some(&bob)).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_bulk_download_renames_duplicates() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let docs = fixture(&app, &token).await;
+    let other = app.create_folder(&token, "other", None).await;
+    let top = app.upload_file(&token, "a.txt", b"top level", None).await;
+    let clash = app
+        .upload_file(&token, "A.TXT", b"clash", Some(&other))
+        .await;
+
+    let res = app
+        .post_json(
+            "/files/bulk-download",
+            Some(&token),
+            json!({ "item_ids": [top, clash, docs] }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let entries = unzip(body_bytes(res).await);
+    assert_eq!(entries[0], ("a.txt".to_string(), "top level".to_string()));
+    assert_eq!(entries[1], ("A (1).TXT".to_string(), "clash".to_string()));
+    assert_eq!(entries[2].0, "docs/");
+
+    let res = app
+        .post_json(
+            "/files/bulk-download",
+            Some(&token),
+            json!({ "item_ids": [] }),
+        )
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_shared_folder_zip_requires_download_permission() {
+    let app = TestApp::new().await;
+    let token = app.register("alice", "password123").await;
+    let docs = fixture(&app, &token).await;
+
+    let mut tokens = Vec::new();
+    for permission in ["view", "download"] {
+        let res = app
+            .post_json(
+                "/shares",
+                Some(&token),
+                json!({
+                    "user_file_id": docs,
+                    "share_type": "public",
+                    "permission": permission,
+                    "expires_in_hours": 24,
+                }),
+            )
+            .await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        tokens.push(
+            json_body(res).await["share_token"]
+                .as_str()
+                .unwrap()
+                .to_string(),
+        );
+    }
+
+    let res = app.get(&format!("/share/{}/zip", tokens[0]), None).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = app.get(&format!("/share/{}/zip", tokens[1]), None).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let entries = unzip(body_bytes(res).await);
+    assert_eq!(entries.len(), 4);
+    assert!(entries.iter().all(|(name, _)| !name.contains("virus")));
+}
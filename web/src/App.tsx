@@ -5,6 +5,8 @@ import {
   clearAuthToken,
 } from "./services/httpClient";
 import { AuthPage } from "./features/auth/AuthPage";
+import { AccountLinkPage } from "./features/auth/AccountLinkPage";
+import { authService } from "./services/authService";
 import Dashboard from "./features/dashboard/Dashboard";
 import { PublicSharePage } from "./features/share/PublicSharePage";
 import { BackendStatusMonitor } from "./components/BackendStatusMonitor";
@@ -18,12 +20,16 @@ function App() {
     // Optional: validate token validity on mount?
   }, []);
 
-  const handleLogin = (token: string) => {
-    setAuthToken(token);
+  const handleLogin = (token: string, refreshToken?: string) => {
+    setAuthToken(token, refreshToken);
     setIsAuthenticated(true);
   };
 
   const handleLogout = () => {
+    // End the session server-side; the local tokens are dropped regardless
+    if (getAuthToken()) {
+      authService.logout().catch(() => {});
+    }
     clearAuthToken();
     setIsAuthenticated(false);
     // Clean up local storage
@@ -38,6 +44,16 @@ function App() {
     return <PublicSharePage />;
   }
 
+  // Links sent by email
+  const path = window.location.pathname;
+  if (path === "/verify-email" || path === "/reset-password") {
+    return (
+      <AccountLinkPage
+        mode={path.slice(1) as "verify-email" | "reset-password"}
+      />
+    );
+  }
+
   return (
     <div className="app-root">
       {isAuthenticated ? (
@@ -0,0 +1,98 @@
+import React, { useEffect, useState } from "react";
+import { authService } from "../../services/authService";
+import { formatFriendlyError } from "../../utils/errorFormatter";
+import "./Auth.css";
+
+type Mode = "verify-email" | "reset-password";
+
+/** Landing page for the links sent by email */
+export const AccountLinkPage: React.FC<{ mode: Mode }> = ({ mode }) => {
+  const token = new URLSearchParams(window.location.search).get("token") || "";
+  const [password, setPassword] = useState("");
+  const [status, setStatus] = useState<"idle" | "working" | "done">("idle");
+  const [error, setError] = useState("");
+
+  useEffect(() => {
+    if (mode !== "verify-email") return;
+    setStatus("working");
+    authService
+      .verifyEmail(token)
+      .then(() => setStatus("done"))
+      .catch((err: any) => {
+        setError(formatFriendlyError(err.message));
+        setStatus("idle");
+      });
+  }, [mode, token]);
+
+  const handleReset = async (e: React.FormEvent) => {
+    e.preventDefault();
+    setError("");
+    if (password.length < 8) {
+      setError("Password must be at least 8 characters");
+      return;
+    }
+    setStatus("working");
+    try {
+      await authService.confirmPasswordReset(token, password);
+      setStatus("done");
+    } catch (err: any) {
+      setError(formatFriendlyError(err.message));
+      setStatus("idle");
+    }
+  };
+
+  const backToLogin = (
+    <div className="auth-buttons">
+      <button
+        type="button"
+        className="login-btn"
+        onClick={() => window.location.assign("/")}
+      >
+        Go to login
+      </button>
+    </div>
+  );
+
+  return (
+    <div className="auth-container">
+      <div className="auth-card">
+        {mode === "verify-email" ? (
+          <>
+            <p>Email verification</p>
+            {status === "working" && <p>Verifying...</p>}
+            {status === "done" && <p>Your email address is verified.</p>}
+            {error && <div className="error">{error}</div>}
+            {status !== "working" && backToLogin}
+          </>
+        ) : status === "done" ? (
+          <>
+            <p>Your password has been changed. Sign in with the new password.</p>
+            {backToLogin}
+          </>
+        ) : (
+          <form onSubmit={handleReset}>
+            <p>Choose a new password</p>
+            <input
+              type="password"
+              placeholder="New password"
+              value={password}
+              onChange={(e) => setPassword(e.target.value)}
+              autoComplete="new-password"
+              required
+            />
+            {error && <div className="error">{error}</div>}
+            <div className="auth-buttons">
+              <button
+                type="submit"
+                className="login-btn"
+                disabled={status === "working"}
+              >
+                {status === "working" ? "Saving..." : "Set password"}
+              </button>
+            </div>
+          </form>
+        )}
+      </div>
+    </div>
+  );
+};
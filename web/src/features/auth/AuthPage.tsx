@@ -6,7 +6,7 @@ import { SvgIcon } from "../../../lib";
 import "./Auth.css";
 
 interface AuthPageProps {
-  onLogin: (token: string) => void;
+  onLogin: (token: string, refreshToken?: string) => void;
 }
 
 export const AuthPage: React.FC<AuthPageProps> = ({ onLogin }) => {
@@ -14,6 +14,10 @@ export const AuthPage: React.FC<AuthPageProps> = ({ onLogin }) => {
   const [password, setPassword] = useState("");
   const [authLoading, setAuthLoading] = useState(false);
   const [error, setError] = useState("");
+  const [challengeToken, setChallengeToken] = useState<string | null>(null);
+  const [twoFactorCode, setTwoFactorCode] = useState("");
+  const [resetEmail, setResetEmail] = useState<string | null>(null);
+  const [resetSent, setResetSent] = useState(false);
 
   // Background Image State
   const [bgImage, setBgImage] = useState("");
@@ -118,7 +122,12 @@ export const AuthPage: React.FC<AuthPageProps> = ({ onLogin }) => {
         password,
         ...captchaPayload,
       });
-      onLogin(res.token);
+      if (res.two_factor_required) {
+        setChallengeToken(res.challenge_token);
+        setTwoFactorCode("");
+        return;
+      }
+      onLogin(res.token, res.refresh_token);
     } catch (err: any) {
       setError(formatFriendlyError(err.message));
       captchaHook.fetchCaptcha();
@@ -127,6 +136,52 @@ export const AuthPage: React.FC<AuthPageProps> = ({ onLogin }) => {
     }
   };
 
+  const handleTwoFactor = async (e: React.FormEvent) => {
+    e.preventDefault();
+    if (!challengeToken) return;
+    setError("");
+    setAuthLoading(true);
+    try {
+      const res = await authService.loginTwoFactor(
+        challengeToken,
+        twoFactorCode.trim()
+      );
+      onLogin(res.token, res.refresh_token);
+    } catch (err: any) {
+      setError(formatFriendlyError(err.message));
+    } finally {
+      setAuthLoading(false);
+    }
+  };
+
+  const cancelTwoFactor = () => {
+    setChallengeToken(null);
+    setTwoFactorCode("");
+    setError("");
+    captchaHook.fetchCaptcha();
+  };
+
+  const handleForgotPassword = async (e: React.FormEvent) => {
+    e.preventDefault();
+    if (!resetEmail) return;
+    setError("");
+    setAuthLoading(true);
+    try {
+      await authService.requestPasswordReset(resetEmail.trim());
+      setResetSent(true);
+    } catch (err: any) {
+      setError(formatFriendlyError(err.message));
+    } finally {
+      setAuthLoading(false);
+    }
+  };
+
+  const closeForgotPassword = () => {
+    setResetEmail(null);
+    setResetSent(false);
+    setError("");
+  };
+
   const handleRegister = async (e: React.FormEvent) => {
     e.preventDefault();
     setError("");
@@ -148,7 +203,7 @@ export const AuthPage: React.FC<AuthPageProps> = ({ onLogin }) => {
         password,
         ...captchaPayload,
       });
-      onLogin(res.token);
+      onLogin(res.token, res.refresh_token);
     } catch (err: any) {
       setError(formatFriendlyError(err.message));
       captchaHook.fetchCaptcha();
@@ -177,51 +232,132 @@ export const AuthPage: React.FC<AuthPageProps> = ({ onLogin }) => {
           </span>
         </div>
         <p>Advanced Agentic File Management</p>
-        <form onSubmit={handleLogin}>
-          <input
-            type="text"
-            placeholder="Username"
-            value={username}
-            onChange={(e) => setUsername(e.target.value)}
-            required
-          />
-          <input
-            type="password"
-            placeholder="Password"
-            value={password}
-            onChange={(e) => setPassword(e.target.value)}
-            required
-          />
-
-          <CaptchaWidget
-            captcha={captchaHook.captcha}
-            captchaAnswer={captchaHook.captchaAnswer}
-            onAnswerChange={captchaHook.setCaptchaAnswer}
-            captchaLoading={captchaHook.captchaLoading}
-            captchaExpiry={captchaHook.captchaExpiry}
-            cooldownSeconds={captchaHook.cooldownSeconds}
-            onRefresh={captchaHook.fetchCaptcha}
-          />
-
-          {error && <div className="error">{error}</div>}
-          <div className="auth-buttons">
-            <button
-              type="submit"
-              className="login-btn"
-              disabled={authLoading || captchaHook.isDisabled}
-            >
-              {authLoading ? "Logging in..." : "Login"}
-            </button>
+        {resetEmail !== null ? (
+          <form onSubmit={handleForgotPassword}>
+            {resetSent ? (
+              <p>
+                If that address belongs to a verified account, a reset link is
+                on its way.
+              </p>
+            ) : (
+              <input
+                type="email"
+                placeholder="Verified email address"
+                value={resetEmail}
+                onChange={(e) => setResetEmail(e.target.value)}
+                autoFocus
+                required
+              />
+            )}
+            {error && <div className="error">{error}</div>}
+            <div className="auth-buttons">
+              {!resetSent && (
+                <button
+                  type="submit"
+                  className="login-btn"
+                  disabled={authLoading || !resetEmail.trim()}
+                >
+                  {authLoading ? "Sending..." : "Send reset link"}
+                </button>
+              )}
+              <button
+                type="button"
+                onClick={closeForgotPassword}
+                className="register-btn"
+                disabled={authLoading}
+              >
+                Back
+              </button>
+            </div>
+          </form>
+        ) : challengeToken ? (
+          <form onSubmit={handleTwoFactor}>
+            <input
+              type="text"
+              inputMode="numeric"
+              autoComplete="one-time-code"
+              placeholder="Authenticator or recovery code"
+              value={twoFactorCode}
+              onChange={(e) => setTwoFactorCode(e.target.value)}
+              autoFocus
+              required
+            />
+            {error && <div className="error">{error}</div>}
+            <div className="auth-buttons">
+              <button
+                type="submit"
+                className="login-btn"
+                disabled={authLoading || !twoFactorCode.trim()}
+              >
+                {authLoading ? "Verifying..." : "Verify"}
+              </button>
+              <button
+                type="button"
+                onClick={cancelTwoFactor}
+                className="register-btn"
+                disabled={authLoading}
+              >
+                Back
+              </button>
+            </div>
+          </form>
+        ) : (
+          <form onSubmit={handleLogin}>
+            <input
+              type="text"
+              placeholder="Username"
+              value={username}
+              onChange={(e) => setUsername(e.target.value)}
+              required
+            />
+            <input
+              type="password"
+              placeholder="Password"
+              value={password}
+              onChange={(e) => setPassword(e.target.value)}
+              required
+            />
+
+            <CaptchaWidget
+              captcha={captchaHook.captcha}
+              captchaAnswer={captchaHook.captchaAnswer}
+              onAnswerChange={captchaHook.setCaptchaAnswer}
+              captchaLoading={captchaHook.captchaLoading}
+              captchaExpiry={captchaHook.captchaExpiry}
+              cooldownSeconds={captchaHook.cooldownSeconds}
+              onRefresh={captchaHook.fetchCaptcha}
+            />
+
+            {error && <div className="error">{error}</div>}
+            <div className="auth-buttons">
+              <button
+                type="submit"
+                className="login-btn"
+                disabled={authLoading || captchaHook.isDisabled}
+              >
+                {authLoading ? "Logging in..." : "Login"}
+              </button>
+              <button
+                type="button"
+                onClick={handleRegister}
+                className="register-btn"
+                disabled={authLoading || captchaHook.isDisabled}
+              >
+                {authLoading ? <div className="spinner-small"></div> : "Register"}
+              </button>
+            </div>
             <button
               type="button"
-              onClick={handleRegister}
-              className="register-btn"
-              disabled={authLoading || captchaHook.isDisabled}
+              className="forgot-password-link"
+              onClick={() => {
+                setError("");
+                setResetEmail("");
+              }}
             >
-              {authLoading ? <div className="spinner-small"></div> : "Register"}
+              Forgot password?
             </button>
-          </div>
-        </form>
+          </form>
+        )}
       </div>
     </div>
   );
@@ -180,21 +180,46 @@ export const useFileUpload = (
       } catch (e) {}
     }
 
-    let preCheck = { exists: false, file_id: null };
+    let preCheck: any = { exists: false, file_id: null, challenge: null };
     if (hash) {
       try {
         preCheck = (await fileService.preCheck(hash, file.size)) as any;
       } catch (e) {}
     }
 
-    if (preCheck.exists && preCheck.file_id) {
-      await fileService.linkFile(
-        preCheck.file_id as string,
-        file.name,
-        folderId,
-      );
-      if (onProgress) onProgress(100);
-    } else {
+    let linked = false;
+    if (preCheck.exists && preCheck.file_id && preCheck.challenge) {
+      // Prove we hold the content: SHA-256 over the requested byte ranges
+      try {
+        const ranges: { offset: number; length: number }[] =
+          preCheck.challenge.ranges;
+        const parts = await Promise.all(
+          ranges.map((r) =>
+            file.slice(r.offset, r.offset + r.length).arrayBuffer(),
+          ),
+        );
+        const digest = await crypto.subtle.digest(
+          "SHA-256",
+          await new Blob(parts).arrayBuffer(),
+        );
+        const response = Array.from(new Uint8Array(digest))
+          .map((b) => b.toString(16).padStart(2, "0"))
+          .join("");
+        await fileService.linkFile(
+          preCheck.file_id as string,
+          file.name,
+          folderId,
+          preCheck.challenge.token,
+          response,
+        );
+        linked = true;
+        if (onProgress) onProgress(100);
+      } catch (e) {
+        console.warn(`[Dedup] Link failed for ${file.name}, uploading:`, e);
+      }
+    }
+
+    if (!linked) {
       const THRESHOLD = 90 * 1024 * 1024;
 
       // For large files (chunked), store in IndexedDB first for resumability
@@ -8,10 +8,35 @@ export const authService = {
       headers: { "Content-Type": "application/json" },
       body: JSON.stringify(body),
     }),
+  loginTwoFactor: (challengeToken: string, code: string) =>
+    request("/login/2fa", {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({ challenge_token: challengeToken, code }),
+    }),
   register: (body: any) =>
     request("/register", {
       method: "POST",
       headers: { "Content-Type": "application/json" },
       body: JSON.stringify(body),
     }),
+  logout: () => request("/auth/logout", { method: "POST" }),
+  verifyEmail: (token: string) =>
+    request("/auth/verify-email", {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({ token }),
+    }),
+  requestPasswordReset: (email: string) =>
+    request("/auth/password-reset", {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({ email }),
+    }),
+  confirmPasswordReset: (token: string, password: string) =>
+    request("/auth/password-reset/confirm", {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({ token, password }),
+    }),
 };
@@ -83,7 +83,13 @@ export const fileService = {
       body: JSON.stringify({ full_hash, size }),
     }),
 
-  linkFile: (storage_file_id: string, filename: string, parentId?: string) =>
+  linkFile: (
+    storage_file_id: string,
+    filename: string,
+    parentId: string | undefined,
+    challengeToken: string,
+    challengeResponse: string,
+  ) =>
     request("/files/link", {
       method: "POST",
       headers: { "Content-Type": "application/json" },
@@ -91,6 +97,8 @@ export const fileService = {
         storage_file_id,
         filename,
         parent_id: parentId === "0" ? null : parentId,
+        challenge_token: challengeToken,
+        challenge_response: challengeResponse,
       }),
     }),
 
@@ -1,9 +1,62 @@
 const API_URL = import.meta.env.VITE_API_URL || "/api";
 
 export const getAuthToken = () => localStorage.getItem("token");
-export const setAuthToken = (token: string) =>
+export const setAuthToken = (token: string, refreshToken?: string) => {
   localStorage.setItem("token", token);
-export const clearAuthToken = () => localStorage.removeItem("token");
+  if (refreshToken) localStorage.setItem("refreshToken", refreshToken);
+};
+export const clearAuthToken = () => {
+  localStorage.removeItem("token");
+  localStorage.removeItem("refreshToken");
+};
+
+// Access tokens are short-lived; refresh them shortly before they expire
+const REFRESH_MARGIN_MS = 60 * 1000;
+let refreshing: Promise<boolean> | null = null;
+
+const tokenExpiry = (token: string): number | null => {
+  try {
+    const payload = JSON.parse(
+      atob(token.split(".")[1].replace(/-/g, "+").replace(/_/g, "/")),
+    );
+    return typeof payload.exp === "number" ? payload.exp * 1000 : null;
+  } catch {
+    return null;
+  }
+};
+
+/** Swap the stored refresh token for new tokens; false if the session is gone */
+export const refreshAuthToken = (): Promise<boolean> => {
+  if (refreshing) return refreshing;
+  const refreshToken = localStorage.getItem("refreshToken");
+  if (!refreshToken) return Promise.resolve(false);
+
+  refreshing = fetch(`${API_URL}/auth/refresh`, {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ refresh_token: refreshToken }),
+  })
+    .then(async (res) => {
+      if (!res.ok) return false;
+      const data = await res.json();
+      setAuthToken(data.token, data.refresh_token);
+      return true;
+    })
+    .catch(() => false)
+    .finally(() => {
+      refreshing = null;
+    });
+  return refreshing;
+};
+
+/** Refresh the access token first if it is about to expire */
+export const ensureFreshToken = async () => {
+  const token = getAuthToken();
+  const expiry = token ? tokenExpiry(token) : null;
+  if (expiry !== null && expiry - Date.now() < REFRESH_MARGIN_MS) {
+    await refreshAuthToken();
+  }
+};
 
 interface RequestOptions extends RequestInit {
   headers?: Record<string, string>;
@@ -16,11 +69,16 @@ export const setOnRequestCallback = (callback: (() => void) | null) => {
   onRequestCallback = callback;
 };
 
-export async function request(endpoint: string, options: RequestOptions = {}) {
+export async function request(
+  endpoint: string,
+  options: RequestOptions = {},
+  retried = false,
+): Promise<any> {
   // Notify that a request is occurring
   if (onRequestCallback) {
     onRequestCallback();
   }
+  await ensureFreshToken();
   const token = getAuthToken();
   const headers = { ...options.headers };
 
@@ -33,7 +91,12 @@ export async function request(endpoint: string, options: RequestOptions = {}) {
     headers,
   });
 
-  if (res.status === 401) {
+  if (res.status === 401 && token && !retried && (await refreshAuthToken())) {
+    return request(endpoint, options, true);
+  }
+
+  // Only a rejected session signs out; a failed login step is just an error
+  if (res.status === 401 && token) {
     clearAuthToken();
     localStorage.removeItem("currentFolder");
     localStorage.removeItem("username");
@@ -1,4 +1,4 @@
-import { request, getAuthToken } from "./httpClient";
+import { request, getAuthToken, ensureFreshToken } from "./httpClient";
 import { getChunkData } from "./uploadDb";
 
 const BASE_URL = import.meta.env.VITE_API_URL || "/api";
@@ -20,6 +20,7 @@ export const uploadService = {
       return uploadService.uploadFileChunked(file, parentId, onProgress);
     }
 
+    await ensureFreshToken();
     return new Promise((resolve, reject) => {
       const xhr = new XMLHttpRequest();
       const formData = new FormData();
@@ -109,6 +110,7 @@ export const uploadService = {
       const MAX_RETRIES = 3;
 
       try {
+        await ensureFreshToken();
         return await new Promise((resolve, reject) => {
           const xhr = new XMLHttpRequest();
           xhr.open(
@@ -244,6 +246,7 @@ export const uploadService = {
       }
 
       try {
+        await ensureFreshToken();
         return await new Promise((resolve, reject) => {
           const xhr = new XMLHttpRequest();
           xhr.open(